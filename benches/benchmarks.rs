@@ -0,0 +1,257 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks of the performance sensitive parts of buffrs on synthetic large inputs
+//!
+//! Run with `cargo bench --features bench`, see `CONTRIBUTING.md` for comparing against a
+//! baseline.
+
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use buffrs::{
+    cache::Cache,
+    config::Config,
+    credentials::Credentials,
+    lock::{DigestAlgorithm, LockedPackage, Lockfile},
+    manifest::Manifest,
+    package::{Package, PackageStore},
+    progress::Progress,
+    resolver::DependencyGraph,
+};
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+/// Number of files of the synthetic package
+const FILES: usize = 200;
+/// Number of messages of every file of the synthetic package
+const MESSAGES: usize = 100;
+/// Number of packages of the synthetic lockfile and dependency graph
+const PACKAGES: usize = 500;
+/// Number of files of the synthetic package with many small files
+const SMALL_FILES: usize = 5000;
+
+fn runtime() -> Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+fn manifest(name: &str) -> Manifest {
+    format!("[package]\ntype = \"lib\"\nname = \"{name}\"\nversion = \"1.0.0\"\n")
+        .parse()
+        .unwrap()
+}
+
+/// Protos of a large package, about 1.5 MiB in total
+fn files() -> BTreeMap<PathBuf, Bytes> {
+    (0..FILES)
+        .map(|file| {
+            let messages: String = (0..MESSAGES)
+                .map(|message| {
+                    format!(
+                        "message Message{message} {{\n  string name = 1;\n  int64 value = 2;\n  repeated double samples = 3;\n}}\n\n"
+                    )
+                })
+                .collect();
+
+            (
+                PathBuf::from(format!("synthetic/v1/file{file}.proto")),
+                Bytes::from(format!(
+                    "syntax = \"proto3\";\n\npackage synthetic.v1;\n\n{messages}"
+                )),
+            )
+        })
+        .collect()
+}
+
+/// Protos of a package split into many small files, about 1 MiB in total
+fn small_files() -> BTreeMap<PathBuf, Bytes> {
+    (0..SMALL_FILES)
+        .map(|file| {
+            (
+                PathBuf::from(format!("synthetic/v1/file{file}.proto")),
+                Bytes::from(format!(
+                    "syntax = \"proto3\";\n\npackage synthetic.v1;\n\nmessage Message{file} {{\n  string name = 1;\n  int64 value = 2;\n  repeated double samples = 3;\n}}\n"
+                )),
+            )
+        })
+        .collect()
+}
+
+fn package() -> Package {
+    Package::create(manifest("synthetic"), files()).unwrap()
+}
+
+fn pack(c: &mut Criterion) {
+    let files = files();
+    let size: usize = files.values().map(Bytes::len).sum();
+
+    let mut group = c.benchmark_group("package");
+    group.throughput(Throughput::Bytes(size as u64));
+
+    group.bench_function("pack", |b| {
+        b.iter(|| Package::create(manifest("synthetic"), files.clone()).unwrap())
+    });
+
+    let package = package();
+    let runtime = runtime();
+
+    group.bench_function("unpack", |b| {
+        b.iter_batched(
+            || assert_fs::TempDir::new().unwrap(),
+            |dir| {
+                runtime
+                    .block_on(package.unpack(&dir.join("synthetic")))
+                    .unwrap()
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    let files = small_files();
+    let size: usize = files.values().map(Bytes::len).sum();
+
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function("pack-many", |b| {
+        b.iter(|| Package::create(manifest("synthetic"), files.clone()).unwrap())
+    });
+
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    for (path, contents) in &files {
+        let path = dir.join("proto").join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    let store = runtime.block_on(PackageStore::open(dir.path())).unwrap();
+    let manifest = manifest("synthetic");
+
+    group.bench_function("release-many", |b| {
+        b.iter(|| runtime.block_on(store.release(&manifest)).unwrap())
+    });
+
+    group.finish();
+}
+
+fn digest(c: &mut Criterion) {
+    let package = package();
+    let runtime = runtime();
+
+    let mut group = c.benchmark_group("digest");
+    group.throughput(Throughput::Bytes(package.tgz.len() as u64));
+
+    group.bench_function("single", |b| {
+        b.iter(|| DigestAlgorithm::SHA256.digest(&package.tgz))
+    });
+
+    let payloads = vec![package.tgz.clone(); 64];
+
+    group.throughput(Throughput::Bytes(64 * package.tgz.len() as u64));
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(DigestAlgorithm::SHA256.digest_all(payloads.clone()))
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+fn lockfile(c: &mut Criterion) {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let runtime = runtime();
+
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let lockfile: Lockfile = (0..PACKAGES)
+        .map(|index| {
+            let package = Package::create(
+                manifest(&format!("package{index}")),
+                BTreeMap::from([(
+                    PathBuf::from(format!("package{index}.proto")),
+                    Bytes::from(format!("package package{index};")),
+                )]),
+            )
+            .unwrap();
+
+            LockedPackage::lock(
+                &package,
+                "https://registry.example.com/artifactory".parse().unwrap(),
+                "protos".to_owned(),
+                1,
+            )
+        })
+        .collect();
+
+    runtime.block_on(lockfile.write()).unwrap();
+
+    c.bench_function("lockfile/read", |b| {
+        b.iter(|| runtime.block_on(Lockfile::read()).unwrap())
+    });
+}
+
+fn resolution(c: &mut Criterion) {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let runtime = runtime();
+
+    let mut root = String::from("[dependencies]\n");
+
+    for index in 0..PACKAGES {
+        let path = dir.join(format!("package{index}"));
+
+        std::fs::create_dir_all(path.join("proto")).unwrap();
+        runtime
+            .block_on(manifest(&format!("package{index}")).write_at(&path))
+            .unwrap();
+        std::fs::write(
+            path.join(format!("proto/package{index}.proto")),
+            format!("syntax = \"proto3\";\n\npackage package{index};\n"),
+        )
+        .unwrap();
+
+        root.push_str(&format!(
+            "package{index} = {{ path = {:?} }}\n",
+            path.display().to_string()
+        ));
+    }
+
+    let manifest: Manifest = root.parse().unwrap();
+    let lockfile = Lockfile::default();
+    let credentials = Arc::new(Credentials::default());
+    let config = Config::new(Some(dir.path())).unwrap();
+    let cache = runtime.block_on(Cache::new(dir.join("cache"))).unwrap();
+    let progress = Progress::hidden();
+
+    c.bench_function("resolution/local", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(DependencyGraph::from_manifest(
+                    &manifest,
+                    &lockfile,
+                    &credentials,
+                    &config,
+                    &cache,
+                    &progress,
+                ))
+                .unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, pack, digest, lockfile, resolution);
+criterion_main!(benches);
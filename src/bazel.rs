@@ -0,0 +1,216 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generation of Bazel `BUILD.bazel` files declaring `proto_library` targets for the local
+//! package and its installed dependencies
+
+use std::{
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+};
+
+/// Name of the generated build files
+pub const BUILD_FILE: &str = "BUILD.bazel";
+
+/// Files marking the root of a Bazel workspace
+const WORKSPACE_FILES: &[&str] = &["MODULE.bazel", "REPO.bazel", "WORKSPACE.bazel", "WORKSPACE"];
+
+/// A `proto_library` target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtoLibrary {
+    /// Name of the target
+    pub name: String,
+    /// Proto files, relative to the Bazel package
+    pub srcs: Vec<String>,
+    /// Labels of the libraries the protos import from
+    pub deps: Vec<String>,
+    /// Workspace relative directory the import paths of the protos start in
+    pub strip_import_prefix: String,
+    /// Directory prepended to the import paths, e.g. the name of the buffrs package
+    pub import_prefix: Option<String>,
+}
+
+impl ProtoLibrary {
+    /// Paths of proto files relative to the directory of their Bazel package, as used in `srcs`
+    pub fn sources(dir: &Path, files: &[PathBuf]) -> Vec<String> {
+        files
+            .iter()
+            .filter_map(|file| file.strip_prefix(dir).ok())
+            .map(|file| components(file).join("/"))
+            .collect()
+    }
+
+    /// Renders the target as a build file
+    pub fn render(&self) -> String {
+        let mut out = String::from("# Generated by buffrs, do not edit.\n\n");
+
+        out.push_str("load(\"@rules_proto//proto:defs.bzl\", \"proto_library\")\n\n");
+        out.push_str("proto_library(\n");
+
+        let _ = writeln!(out, "    name = \"{}\",", self.name);
+
+        render_list(&mut out, "srcs", &self.srcs);
+        render_list(&mut out, "deps", &self.deps);
+
+        let _ = writeln!(
+            out,
+            "    strip_import_prefix = \"{}\",",
+            self.strip_import_prefix
+        );
+
+        if let Some(prefix) = &self.import_prefix {
+            let _ = writeln!(out, "    import_prefix = \"{prefix}\",");
+        }
+
+        out.push_str("    visibility = [\"//visibility:public\"],\n)\n");
+        out
+    }
+}
+
+fn render_list(out: &mut String, attribute: &str, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(out, "    {attribute} = [");
+
+    for value in values {
+        let _ = writeln!(out, "        \"{value}\",");
+    }
+
+    out.push_str("    ],\n");
+}
+
+/// Location of a buffrs project within a Bazel workspace
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workspace {
+    /// Directory of the project, relative to the root of the workspace
+    prefix: Vec<String>,
+}
+
+impl Workspace {
+    /// Finds the workspace containing a project by looking for a `MODULE.bazel` or `WORKSPACE`
+    /// file in the project directory and its parents
+    pub fn locate(project: &Path) -> Option<Self> {
+        let root = project
+            .ancestors()
+            .find(|dir| WORKSPACE_FILES.iter().any(|file| dir.join(file).is_file()))?;
+
+        let prefix = project.strip_prefix(root).ok()?;
+
+        Some(Self {
+            prefix: components(prefix),
+        })
+    }
+
+    /// Label of a Bazel package, given by its directory relative to the project
+    pub fn package(&self, dir: &Path) -> String {
+        let path: Vec<_> = self.prefix.iter().cloned().chain(components(dir)).collect();
+
+        format!("//{}", path.join("/"))
+    }
+
+    /// Label of a target, omitting the name if it matches the directory of the package
+    pub fn label(&self, dir: &Path, name: &str) -> String {
+        let package = self.package(dir);
+
+        match dir.file_name() {
+            Some(last) if last == name => package,
+            _ => format!("{package}:{name}"),
+        }
+    }
+
+    /// Workspace relative path of a directory of the project, as used for import prefixes
+    pub fn path(&self, dir: &Path) -> String {
+        format!("/{}", self.package(dir).trim_start_matches('/'))
+    }
+}
+
+fn components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_proto_library() {
+        let target = ProtoLibrary {
+            name: "physics".to_owned(),
+            srcs: vec!["physics.proto".to_owned()],
+            deps: vec!["//proto/vendor/units".to_owned()],
+            strip_import_prefix: "/proto".to_owned(),
+            import_prefix: Some("physics".to_owned()),
+        };
+
+        let expected = r#"# Generated by buffrs, do not edit.
+
+load("@rules_proto//proto:defs.bzl", "proto_library")
+
+proto_library(
+    name = "physics",
+    srcs = [
+        "physics.proto",
+    ],
+    deps = [
+        "//proto/vendor/units",
+    ],
+    strip_import_prefix = "/proto",
+    import_prefix = "physics",
+    visibility = ["//visibility:public"],
+)
+"#;
+
+        assert_eq!(target.render(), expected);
+    }
+
+    #[test]
+    fn locates_workspace() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let project = dir.path().join("services").join("physics");
+
+        std::fs::create_dir_all(&project).unwrap();
+
+        assert_eq!(Workspace::locate(&project), None);
+
+        std::fs::write(dir.path().join("MODULE.bazel"), "").unwrap();
+
+        let workspace = Workspace::locate(&project).unwrap();
+        let vendor = Path::new("proto/vendor/units");
+
+        assert_eq!(
+            workspace.package(vendor),
+            "//services/physics/proto/vendor/units"
+        );
+        assert_eq!(
+            workspace.label(vendor, "units"),
+            "//services/physics/proto/vendor/units"
+        );
+        assert_eq!(
+            workspace.label(Path::new("proto"), "physics"),
+            "//services/physics/proto:physics"
+        );
+        assert_eq!(
+            workspace.path(Path::new("proto/vendor")),
+            "/services/physics/proto/vendor"
+        );
+        assert_eq!(Workspace::default().path(Path::new("proto")), "/proto");
+    }
+}
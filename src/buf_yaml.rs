@@ -1,11 +1,26 @@
 use miette::{miette, IntoDiagnostic};
 use pretty_yaml::config::FormatOptions;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, io::Write};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use crate::package::PackageStore;
 
 const BUF_YAML_FILE: &str = "buf.yaml";
+const BUF_LOCK_FILE: &str = "buf.lock";
+
+/// A module of a Buf workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufModule {
+    /// Directory of the module, relative to the workspace
+    pub path: PathBuf,
+    /// Name of the module on a Buf Schema Registry, e.g. `buf.build/acme/units`
+    pub name: Option<String>,
+}
 
 /// Representation of a Buf YAML file
 pub struct BufYamlFile {
@@ -48,12 +63,48 @@ impl BufYamlFile {
 
     /// Write `BufYamlFile` to a YAML file
     pub fn to_file(&self) -> miette::Result<()> {
+        self.write_to(Path::new(BUF_YAML_FILE))
+    }
+
+    /// Write `BufYamlFile` to the given path
+    pub fn write_to(&self, path: &Path) -> miette::Result<()> {
         let yaml_content = self.to_string()?;
-        let mut file = fs::File::create(BUF_YAML_FILE).into_diagnostic()?;
+        let mut file = fs::File::create(path).into_diagnostic()?;
         file.write_all(yaml_content.as_bytes()).into_diagnostic()?;
         Ok(())
     }
 
+    /// Modules of the workspace
+    pub fn modules(&self) -> Vec<BufModule> {
+        self.config
+            .modules
+            .iter()
+            .map(|module| BufModule {
+                path: PathBuf::from(&module.path),
+                name: module.name.clone(),
+            })
+            .collect()
+    }
+
+    /// Modules of a Buf Schema Registry the workspace depends on
+    pub fn deps(&self) -> &[String] {
+        &self.config.deps
+    }
+
+    /// Replace the modules of the Buf Schema Registry the workspace depends on
+    pub fn set_deps(&mut self, deps: Vec<String>) {
+        self.config.deps = deps;
+    }
+
+    /// Add a module published under the given name
+    pub fn add_named_module(&mut self, path: &str, name: &str) {
+        self.config.modules.push(Module {
+            path: path.to_owned(),
+            name: Some(name.to_owned()),
+            ..Default::default()
+        });
+    }
+
     /// Clear all modules from the Buf YAML file
     pub fn clear_modules(&mut self) {
         self.config.modules.clear();
@@ -91,6 +142,48 @@ impl Default for BufYamlFile {
     }
 }
 
+/// Representation of a Buf lock file, pinning the commits of dependencies
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BufLockFile {
+    /// Locked dependencies
+    #[serde(default)]
+    pub deps: Vec<BufLockedDep>,
+}
+
+/// A dependency pinned in a Buf lock file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BufLockedDep {
+    /// Name of the module, e.g. `buf.build/googleapis/googleapis`
+    pub name: String,
+    /// Commit of the module
+    pub commit: String,
+}
+
+impl BufLockFile {
+    /// Create a new `BufLockFile` from a string
+    pub fn new_from_str(s: &str) -> miette::Result<Self> {
+        serde_yml::from_str(s).into_diagnostic()
+    }
+
+    /// Load `BufLockFile` from the lock file of the workspace, if present
+    pub fn from_file() -> miette::Result<Option<Self>> {
+        if !Path::new(BUF_LOCK_FILE).exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(BUF_LOCK_FILE).into_diagnostic()?;
+        Self::new_from_str(&content).map(Some)
+    }
+
+    /// Commit a dependency is locked to
+    pub fn commit(&self, name: &str) -> Option<&str> {
+        self.deps
+            .iter()
+            .find(|dep| dep.name == name)
+            .map(|dep| dep.commit.as_str())
+    }
+}
+
 /// Default buf.yaml file
 const DEFAULT_YAML: &str = r#"
 version: v2
@@ -211,6 +304,37 @@ mod tests {
         writer.write_all(serialized.as_bytes()).unwrap();
     }
 
+    #[test]
+    fn test_modules_and_deps() {
+        let buf_yaml = BufYamlFile::new_from_str(SAMPLE_YAML).unwrap();
+        let modules = buf_yaml.modules();
+
+        assert_eq!(modules[0].path, PathBuf::from("proto/foo"));
+        assert_eq!(modules[0].name.as_deref(), Some("buf.build/acme/foo"));
+        assert_eq!(modules[2].name, None);
+        assert_eq!(buf_yaml.deps()[0], "buf.build/acme/paymentapis");
+    }
+
+    #[test]
+    fn test_buf_lock() {
+        let buf_lock = BufLockFile::new_from_str(
+            r#"
+version: v2
+deps:
+  - name: buf.build/googleapis/googleapis
+    commit: 61b203b9a9164be9a834f58c37be6f62
+    digest: b5:e2a2b5d5f55dc5dfb5ec4bd4b0a94d59a9f5f0d0
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            buf_lock.commit("buf.build/googleapis/googleapis"),
+            Some("61b203b9a9164be9a834f58c37be6f62")
+        );
+        assert_eq!(buf_lock.commit("buf.build/acme/units"), None);
+    }
+
     const SAMPLE_YAML: &str = r#"
 version: v2
 modules:
@@ -0,0 +1,470 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::{
+    cancel::{Cancel, Cancelled},
+    config::Config,
+    lock::LOCKFILE,
+    manifest::{Manifest, MANIFEST_FILE},
+    package::{PackageName, PackageStore, UnsupportedSyntax},
+};
+
+/// Errors that can occur while preparing build script inputs
+#[derive(Error, Diagnostic, Debug)]
+pub enum BuildError {
+    /// The project root could not be determined
+    #[error("could not determine the project root")]
+    Root(#[source] std::io::Error),
+    /// The manifest is missing in the project root
+    #[error("no manifest found in {}", .0.display())]
+    #[diagnostic(help("run `buffrs init` to set up the project"))]
+    ManifestNotFound(PathBuf),
+    /// The manifest could not be read
+    #[error("failed to read the manifest")]
+    Manifest(#[diagnostic_source] miette::Report),
+    /// The buffrs configuration could not be read
+    #[error("failed to read the buffrs configuration")]
+    Config(#[diagnostic_source] miette::Report),
+    /// The package store could not be opened or populated
+    #[error("failed to prepare the package store")]
+    Store(#[diagnostic_source] miette::Report),
+    /// A proto uses a syntax that is not allowed by the manifest
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    UnsupportedSyntax(UnsupportedSyntax),
+    /// A requested package is not installed
+    #[error("package `{0}` is not installed")]
+    #[diagnostic(help("run `buffrs install` before building"))]
+    NotInstalled(PackageName),
+    /// The async runtime could not be started
+    #[error("failed to start the async runtime")]
+    Runtime(#[source] std::io::Error),
+    /// The preparation was cancelled through [`BuildConfig::cancel`]
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Cancelled(Cancelled),
+}
+
+impl BuildError {
+    fn store(error: crate::Error) -> Self {
+        Self::Store(error.into_report())
+    }
+}
+
+/// Protocol buffer inputs for a code generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildInputs {
+    /// All `.proto` files that should be compiled, sorted
+    pub protos: Vec<PathBuf>,
+    /// Include paths the protos must be compiled with
+    pub includes: Vec<PathBuf>,
+}
+
+/// Configuration for preparing build script inputs
+///
+/// ```rust,no_run
+/// // build.rs
+/// let inputs = buffrs::build::BuildConfig::new()
+///     .emit_rerun_if_changed(true)
+///     .prepare_blocking()
+///     .unwrap();
+///
+/// // hand `inputs.protos` and `inputs.includes` to tonic-build / prost-build
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BuildConfig {
+    root: Option<PathBuf>,
+    packages: Option<Vec<PackageName>>,
+    exclude: Vec<PackageName>,
+    includes: Vec<PathBuf>,
+    emit_rerun_if_changed: bool,
+    cancel: Cancel,
+}
+
+impl BuildConfig {
+    /// Creates a configuration for the crate currently being built
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Overrides the project root
+    ///
+    /// Defaults to `CARGO_MANIFEST_DIR` or, if unset, the current directory.
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Restricts the inputs to a subset of the installed packages
+    pub fn packages(mut self, packages: impl IntoIterator<Item = PackageName>) -> Self {
+        self.packages = Some(packages.into_iter().collect());
+        self
+    }
+
+    /// Skips packages that are only needed to resolve imports
+    ///
+    /// Excluded packages stay on the include path, but their protos are not returned as
+    /// inputs, so no code is generated for them.
+    pub fn exclude(mut self, packages: impl IntoIterator<Item = PackageName>) -> Self {
+        self.exclude.extend(packages);
+        self
+    }
+
+    /// Adds an external proto root to the include paths
+    ///
+    /// Include paths configured in `[build.includes]` of the buffrs configuration are
+    /// added automatically.
+    pub fn include(mut self, path: impl Into<PathBuf>) -> Self {
+        self.includes.push(path.into());
+        self
+    }
+
+    /// Prints `cargo:rerun-if-changed` lines for every input
+    pub fn emit_rerun_if_changed(mut self, emit: bool) -> Self {
+        self.emit_rerun_if_changed = emit;
+        self
+    }
+
+    /// Stops preparing the inputs once `cancel` is cancelled, e.g. when an IDE discards a build
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    fn resolve_root(&self) -> Result<PathBuf, BuildError> {
+        if let Some(root) = &self.root {
+            return Ok(root.clone());
+        }
+
+        match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => env::current_dir().map_err(BuildError::Root),
+        }
+    }
+
+    /// Collects the protos and include paths of the project
+    pub async fn prepare(&self) -> crate::Result<BuildInputs> {
+        let inputs = self
+            .cancel
+            .run(self.collect())
+            .await
+            .map_err(BuildError::Cancelled)??;
+
+        Ok(inputs)
+    }
+
+    #[tracing::instrument(name = "build.prepare", skip_all)]
+    async fn collect(&self) -> Result<BuildInputs, BuildError> {
+        let root = self.resolve_root()?;
+
+        let manifest = Manifest::try_read_from(root.join(MANIFEST_FILE))
+            .await
+            .map_err(BuildError::Manifest)?
+            .ok_or_else(|| BuildError::ManifestNotFound(root.clone()))?;
+
+        let config = Config::new(Some(&root)).map_err(BuildError::Config)?;
+
+        let store = PackageStore::open(&root).await.map_err(BuildError::store)?;
+
+        if let Some(ref pkg) = manifest.package {
+            store.populate(pkg).await.map_err(BuildError::store)?;
+        }
+
+        if let Some(packages) = &self.packages {
+            if let Some(missing) = packages.iter().find(|p| !store.locate(p).is_dir()) {
+                return Err(BuildError::NotInstalled(missing.clone()));
+            }
+        }
+
+        let protos = store
+            .collect_packages(self.packages.as_deref(), &self.exclude)
+            .await
+            .map_err(BuildError::store)?;
+
+        let unsupported = store
+            .check_syntax(&protos, &manifest.lint.syntax)
+            .await
+            .map_err(BuildError::store)?;
+
+        if let Some(unsupported) = unsupported.into_iter().next() {
+            return Err(BuildError::UnsupportedSyntax(unsupported));
+        }
+
+        if self.emit_rerun_if_changed {
+            rerun_if_changed(&root, &store, &manifest, &protos).await;
+        }
+
+        let includes = std::iter::once(store.proto_vendor_path())
+            .chain(config.include_paths().iter().cloned())
+            .chain(self.includes.iter().cloned())
+            .collect();
+
+        Ok(BuildInputs { protos, includes })
+    }
+
+    /// Collects the protos and include paths of the project, blocking the current thread
+    ///
+    /// Called from within a multi-threaded runtime, the preparation runs on that runtime. A
+    /// single-threaded runtime cannot be blocked without stalling it, so the preparation runs on
+    /// a dedicated runtime in a separate thread then, like it does outside of any runtime.
+    pub fn prepare_blocking(&self) -> crate::Result<BuildInputs> {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return self.prepare_on_new_runtime();
+        };
+
+        match handle.runtime_flavor() {
+            tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| handle.block_on(self.prepare()))
+            }
+            _ => std::thread::scope(|scope| {
+                scope
+                    .spawn(|| self.prepare_on_new_runtime())
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            }),
+        }
+    }
+
+    fn prepare_on_new_runtime(&self) -> crate::Result<BuildInputs> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(BuildError::Runtime)?
+            .block_on(self.prepare())
+    }
+}
+
+async fn rerun_if_changed(
+    root: &Path,
+    store: &PackageStore,
+    manifest: &Manifest,
+    protos: &[PathBuf],
+) {
+    let emit = |path: &Path| println!("cargo:rerun-if-changed={}", path.display());
+
+    emit(&root.join(MANIFEST_FILE));
+    emit(&root.join(LOCKFILE));
+
+    // the populated copy of the local package is rewritten on every build, so
+    // its sources are tracked instead to avoid rerunning the build script forever
+    let local = manifest.package.as_ref().map(|pkg| store.locate(&pkg.name));
+
+    for proto in store.collect(&store.proto_path(), false).await {
+        emit(&proto);
+    }
+
+    for proto in protos {
+        if !local.as_ref().is_some_and(|local| proto.starts_with(local)) {
+            emit(proto);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::Syntax;
+    use assert_fs::TempDir;
+
+    #[tokio::test]
+    async fn collects_local_and_vendored_protos() {
+        let dir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dir.join(MANIFEST_FILE),
+            "[package]\ntype = \"lib\"\nname = \"units\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.join("proto/vendor/physics")).unwrap();
+        std::fs::write(dir.join("proto/units.proto"), "syntax = \"proto3\";").unwrap();
+        std::fs::write(
+            dir.join("proto/vendor/physics/mass.proto"),
+            "syntax = \"proto3\";",
+        )
+        .unwrap();
+
+        let inputs = BuildConfig::new().root(dir.path()).prepare().await.unwrap();
+
+        assert_eq!(
+            inputs.protos,
+            vec![
+                dir.join("proto/vendor/physics/mass.proto"),
+                dir.join("proto/vendor/units/units.proto"),
+            ]
+        );
+        assert_eq!(inputs.includes, vec![dir.join("proto/vendor")]);
+
+        let inputs = BuildConfig::new()
+            .root(dir.path())
+            .packages(["units".parse().unwrap()])
+            .prepare()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            inputs.protos,
+            vec![dir.join("proto/vendor/units/units.proto")]
+        );
+
+        let inputs = BuildConfig::new()
+            .root(dir.path())
+            .exclude(["physics".parse().unwrap()])
+            .prepare()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            inputs.protos,
+            vec![dir.join("proto/vendor/units/units.proto")]
+        );
+        assert_eq!(inputs.includes, vec![dir.join("proto/vendor")]);
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_packages() {
+        let dir = TempDir::new().unwrap();
+
+        std::fs::write(dir.join(MANIFEST_FILE), "[dependencies]\n").unwrap();
+
+        let result = BuildConfig::new()
+            .root(dir.path())
+            .packages(["physics".parse().unwrap()])
+            .prepare()
+            .await;
+
+        let Err(crate::Error::Resolution(report)) = result else {
+            panic!("expected a resolution error");
+        };
+
+        assert!(matches!(
+            report.downcast_ref::<BuildError>(),
+            Some(BuildError::NotInstalled(_))
+        ));
+    }
+
+    #[test]
+    fn prepares_blocking_within_runtimes() {
+        let dir = TempDir::new().unwrap();
+
+        std::fs::write(dir.join(MANIFEST_FILE), "[dependencies]\n").unwrap();
+
+        let config = BuildConfig::new().root(dir.path());
+
+        let expected = config.prepare_blocking().unwrap();
+
+        for mut builder in [
+            tokio::runtime::Builder::new_current_thread(),
+            tokio::runtime::Builder::new_multi_thread(),
+        ] {
+            let inputs = builder
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async { config.prepare_blocking() })
+                .unwrap();
+
+            assert_eq!(inputs, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn stops_when_cancelled() {
+        let dir = TempDir::new().unwrap();
+
+        std::fs::write(dir.join(MANIFEST_FILE), "[dependencies]\n").unwrap();
+
+        let cancel = Cancel::new();
+        cancel.cancel();
+
+        let result = BuildConfig::new()
+            .root(dir.path())
+            .cancel(cancel)
+            .prepare()
+            .await;
+
+        assert!(matches!(result, Err(crate::Error::Cancelled(_))));
+    }
+
+    #[tokio::test]
+    async fn rejects_unsupported_syntax() {
+        let dir = TempDir::new().unwrap();
+
+        std::fs::write(
+            dir.join(MANIFEST_FILE),
+            "[dependencies]\n\n[lint]\nsyntax = [\"proto3\"]\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.join("proto/vendor/physics")).unwrap();
+        std::fs::write(
+            dir.join("proto/vendor/physics/mass.proto"),
+            "edition = \"2023\";",
+        )
+        .unwrap();
+
+        let result = BuildConfig::new().root(dir.path()).prepare().await;
+
+        let Some(BuildError::UnsupportedSyntax(unsupported)) = result
+            .as_ref()
+            .err()
+            .and_then(|error| error.report().downcast_ref::<BuildError>())
+        else {
+            panic!("expected an unsupported syntax error");
+        };
+
+        assert_eq!(
+            *unsupported,
+            UnsupportedSyntax {
+                file: "proto/vendor/physics/mass.proto".into(),
+                syntax: Syntax::Edition("2023".into()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn adds_configured_include_paths() {
+        let dir = TempDir::new().unwrap();
+
+        std::fs::write(dir.join(MANIFEST_FILE), "[dependencies]\n").unwrap();
+        std::fs::create_dir(dir.join(".buffrs")).unwrap();
+        std::fs::write(
+            dir.join(".buffrs/config.toml"),
+            "[build]\nincludes = [\"third_party/googleapis\"]\n",
+        )
+        .unwrap();
+
+        let inputs = BuildConfig::new()
+            .root(dir.path())
+            .include("/opt/protos")
+            .prepare()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            inputs.includes,
+            vec![
+                dir.join("proto/vendor"),
+                dir.join("third_party/googleapis"),
+                PathBuf::from("/opt/protos"),
+            ]
+        );
+    }
+}
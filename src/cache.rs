@@ -16,6 +16,7 @@ use std::{
     io::ErrorKind,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
 use bytes::Bytes;
@@ -23,6 +24,8 @@ use miette::{miette, Context, IntoDiagnostic};
 use walkdir::WalkDir;
 
 use crate::{
+    cancel::Cancel,
+    flock::FileLock,
     lock::{Digest, DigestAlgorithm, FileRequirement},
     package::{Package, PackageName},
 };
@@ -31,6 +34,15 @@ use crate::{
 const CACHE_ENV_VAR: &str = "BUFFRS_CACHE";
 /// The default cache directory name
 const CACHE_DIRECTORY: &str = "cache";
+/// The subdirectory storing the chunks of packages published in chunks
+const CHUNKS_DIRECTORY: &str = "chunks";
+/// The subdirectory storing unpacked copies of packages, which installations link to
+const UNPACKED_DIRECTORY: &str = "unpacked";
+/// The subdirectory packages are downloaded into before they are put in the cache
+const DOWNLOADS_DIRECTORY: &str = "downloads";
+
+/// Distinguishes the downloads of a process
+static DOWNLOADS: AtomicU64 = AtomicU64::new(0);
 
 /// A instance of a cache
 pub struct Cache(PathBuf);
@@ -111,6 +123,8 @@ impl Cache {
     /// Please be cautions when calling this function on arbitrary directories as subcontents may
     /// be removed.
     pub async fn homogenize(&self) -> miette::Result<()> {
+        let _lock = FileLock::cache(self.path()).await?;
+
         let dir = WalkDir::new(self.path())
             .max_depth(1)
             .into_iter()
@@ -118,7 +132,12 @@ impl Cache {
 
         let (dirs, files): (Vec<_>, Vec<_>) = dir.partition(|e| e.path().is_dir());
 
-        let invalid_dirs = dirs.into_iter().filter(|d| d.path() != self.path());
+        let invalid_dirs = dirs.into_iter().filter(|d| {
+            d.path() != self.path()
+                && d.path() != self.chunks()
+                && d.path() != self.unpacked()
+                && d.path() != self.downloads()
+        });
 
         for dir in invalid_dirs {
             tracing::debug!("removing invalid cache entry: {}", dir.path().display());
@@ -206,6 +225,8 @@ impl Cache {
     pub async fn put(&self, entry: Entry, bytes: Bytes) -> miette::Result<()> {
         let file = self.path().join(entry.filename());
 
+        let _lock = FileLock::cache(self.path()).await?;
+
         tokio::fs::write(&file, bytes.as_ref())
             .await
             .into_diagnostic()
@@ -217,6 +238,157 @@ impl Cache {
         Ok(())
     }
 
+    /// Reserves a file to download a package into, which is later put in the cache with
+    /// [`Cache::adopt`]
+    ///
+    /// Downloading into the cache directory allows adopting the file by renaming it, without
+    /// holding the package in memory or writing it twice.
+    pub async fn download(&self) -> miette::Result<PathBuf> {
+        tokio::fs::create_dir_all(self.downloads())
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "failed to create the download directory of the cache"
+            ))?;
+
+        Ok(self.downloads().join(format!(
+            "{}-{}.part",
+            std::process::id(),
+            DOWNLOADS.fetch_add(1, Ordering::Relaxed)
+        )))
+    }
+
+    /// Put a downloaded package in the cache, moving the file reserved with [`Cache::download`]
+    pub async fn adopt(&self, entry: Entry, download: &Path) -> miette::Result<()> {
+        let file = self.path().join(entry.filename());
+
+        let _lock = FileLock::cache(self.path()).await?;
+
+        tokio::fs::rename(download, &file)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "failed to put package {} in the cache",
+                entry.filename().to_str().unwrap()
+            ))
+    }
+
+    /// Resolve a chunk of a package published in chunks from the cache
+    pub async fn get_chunk(&self, digest: &Digest) -> Option<Bytes> {
+        let chunk = tokio::fs::read(self.chunks().join(Self::chunk_filename(digest)))
+            .await
+            .ok()
+            .map(Bytes::from)?;
+
+        // chunks are addressed by their content, a corrupted chunk is simply downloaded again
+        (digest.algorithm().digest(&chunk) == *digest).then_some(chunk)
+    }
+
+    /// Put a chunk of a package published in chunks in the cache
+    pub async fn put_chunk(&self, digest: &Digest, bytes: Bytes) -> miette::Result<()> {
+        let _lock = FileLock::cache(self.path()).await?;
+
+        tokio::fs::create_dir_all(self.chunks())
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create the chunk cache"))?;
+
+        tokio::fs::write(
+            self.chunks().join(Self::chunk_filename(digest)),
+            bytes.as_ref(),
+        )
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to put chunk {digest} in the cache"))
+    }
+
+    fn chunks(&self) -> PathBuf {
+        self.path().join(CHUNKS_DIRECTORY)
+    }
+
+    /// Resolve the unpacked copy of a package, unpacking it into the cache if there is none yet
+    ///
+    /// Unpacked copies are addressed by the digest of the package like regular entries and can be
+    /// linked into any number of projects. Existing copies are verified against the package
+    /// first, and unpacked again if their files were modified, e.g. through a hardlink.
+    pub async fn unpack(&self, package: &Package, cancel: &Cancel) -> miette::Result<PathBuf> {
+        let entry = Entry::from(package);
+        let dir = self.unpacked().join(entry.filename().with_extension(""));
+
+        if Self::is_unpacked(package, &dir).await {
+            return Ok(dir);
+        }
+
+        let _lock = FileLock::cache(self.path()).await?;
+
+        // another process may have unpacked the package while we waited for the lock
+        if Self::is_unpacked(package, &dir).await {
+            return Ok(dir);
+        }
+
+        tokio::fs::remove_dir_all(&dir).await.ok();
+
+        // unpack next to the final location first, so that no partial copy is ever linked
+        let staging = self.unpacked().join(format!(".{}", package.name()));
+
+        package.unpack_cancellable(&staging, cancel).await?;
+
+        tokio::fs::rename(&staging, &dir)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "failed to put unpacked package {} in the cache",
+                package.name()
+            ))?;
+
+        Ok(dir)
+    }
+
+    /// Whether an unpacked copy exists and still holds exactly the files of its package
+    async fn is_unpacked(package: &Package, dir: &Path) -> bool {
+        matches!(package.verify_unpacked(dir).await, Ok(Ok(())))
+    }
+
+    fn downloads(&self) -> PathBuf {
+        self.path().join(DOWNLOADS_DIRECTORY)
+    }
+
+    fn unpacked(&self) -> PathBuf {
+        self.path().join(UNPACKED_DIRECTORY)
+    }
+
+    fn chunk_filename(digest: &Digest) -> String {
+        format!("{}.{}", digest.algorithm(), hex::encode(digest.as_bytes()))
+    }
+
+    /// Removes all packages and chunks from the cache, keeping the directory itself
+    pub async fn clear(&self) -> miette::Result<()> {
+        // only entries adhering to the cache specification are left after opening the cache
+        self.homogenize().await?;
+
+        let _lock = FileLock::cache(self.path()).await?;
+
+        let entries = WalkDir::new(self.path())
+            .min_depth(1)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok());
+
+        for entry in entries {
+            let removed = if entry.path().is_dir() {
+                tokio::fs::remove_dir_all(entry.path()).await
+            } else {
+                tokio::fs::remove_file(entry.path()).await
+            };
+
+            removed.into_diagnostic().wrap_err_with(|| {
+                miette!("failed to remove cache entry {}", entry.path().display())
+            })?;
+        }
+
+        Ok(())
+    }
+
     /// The directory in the filesystem used by this cache
     pub fn path(&self) -> &Path {
         self.0.as_path()
@@ -229,6 +401,19 @@ impl Cache {
 pub struct Entry(PathBuf);
 
 impl Entry {
+    /// Locates a package by its name and digest
+    pub fn new(name: &PackageName, digest: &Digest) -> Self {
+        Self(
+            format!(
+                "{}.{}.{}.tgz",
+                name,
+                digest.algorithm(),
+                hex::encode(digest.as_bytes())
+            )
+            .into(),
+        )
+    }
+
     /// The filename of the cache entry
     pub fn filename(&self) -> &Path {
         self.0.as_path()
@@ -243,16 +428,7 @@ impl From<Package> for Entry {
 
 impl From<&Package> for Entry {
     fn from(value: &Package) -> Self {
-        let digest = value.digest(DigestAlgorithm::SHA256);
-        Self(
-            format!(
-                "{}.{}.{}.tgz",
-                value.name(),
-                digest.algorithm(),
-                hex::encode(digest.as_bytes())
-            )
-            .into(),
-        )
+        Self::new(value.name(), &value.digest(DigestAlgorithm::SHA256))
     }
 }
 
@@ -264,14 +440,6 @@ impl From<FileRequirement> for Entry {
 
 impl From<&FileRequirement> for Entry {
     fn from(req: &FileRequirement) -> Entry {
-        Self(
-            format!(
-                "{}.{}.{}.tgz",
-                req.package,
-                req.digest.algorithm(),
-                hex::encode(req.digest.as_bytes())
-            )
-            .into(),
-        )
+        Self::new(&req.package, &req.digest)
     }
 }
@@ -0,0 +1,201 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+use tokio::sync::Notify;
+
+/// The operation was cancelled before it completed
+#[derive(Error, Diagnostic, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("the operation was cancelled")]
+#[diagnostic(code(E0011))]
+pub struct Cancelled;
+
+/// Handle to cancel long-running operations like installing, publishing or preparing code
+/// generation
+///
+/// Clones share their state, so one clone can be handed to an operation while another one
+/// cancels it, e.g. on Ctrl-C, or the handle cancels itself once its deadline passed.
+/// Operations stop at the next step and remove partially extracted packages before they fail
+/// with [`Cancelled`].
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<State>);
+
+#[derive(Debug, Default)]
+struct State {
+    cancelled: AtomicBool,
+    notify: Notify,
+    /// Point in time the handle cancels itself at
+    deadline: Option<Instant>,
+    /// Checks passing before the handle cancels itself, to cancel operations at a given step
+    #[cfg(test)]
+    countdown: std::sync::Mutex<Option<usize>>,
+}
+
+impl Cancel {
+    /// Creates a handle that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a handle that cancels itself once `timeout` elapsed
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self(Arc::new(State {
+            deadline: Instant::now().checked_add(timeout),
+            ..State::default()
+        }))
+    }
+
+    /// Creates a handle that cancels itself once the given number of checks passed
+    #[cfg(test)]
+    pub(crate) fn after(checks: usize) -> Self {
+        let cancel = Self::default();
+
+        *cancel.0.countdown.lock().unwrap() = Some(checks);
+
+        cancel
+    }
+
+    /// Cancels all operations using this handle or one of its clones
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.notify.notify_waiters();
+    }
+
+    /// Whether the handle was cancelled or its deadline passed
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+            || self
+                .0
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Fails if the handle was cancelled, for checks between the steps of an operation
+    pub fn check(&self) -> Result<(), Cancelled> {
+        #[cfg(test)]
+        if let Some(remaining) = self.0.countdown.lock().unwrap().as_mut() {
+            match remaining.checked_sub(1) {
+                Some(left) => *remaining = left,
+                None => self.cancel(),
+            }
+        }
+
+        match self.is_cancelled() {
+            true => Err(Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Waits until the handle is cancelled
+    pub async fn cancelled(&self) {
+        let notified = self.0.notify.notified();
+        tokio::pin!(notified);
+
+        // registered before checking the flag, so that a concurrent cancel is not missed
+        notified.as_mut().enable();
+
+        if self.is_cancelled() {
+            return;
+        }
+
+        match self.0.deadline {
+            Some(deadline) => {
+                tokio::select! {
+                    _ = notified => {}
+                    _ = tokio::time::sleep_until(deadline.into()) => {}
+                }
+            }
+            None => notified.await,
+        }
+    }
+
+    /// Runs a future to completion unless the handle is cancelled first, in which case the
+    /// future is dropped
+    pub async fn run<F: Future>(&self, future: F) -> Result<F::Output, Cancelled> {
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => Err(Cancelled),
+            output = future => Ok(output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cancels_clones() {
+        let cancel = Cancel::new();
+        let clone = cancel.clone();
+
+        assert_eq!(clone.check(), Ok(()));
+
+        cancel.cancel();
+
+        assert!(clone.is_cancelled());
+        assert_eq!(clone.check(), Err(Cancelled));
+    }
+
+    #[test]
+    fn cancels_after_checks() {
+        let cancel = Cancel::after(2);
+
+        assert_eq!(cancel.check(), Ok(()));
+        assert_eq!(cancel.check(), Ok(()));
+        assert_eq!(cancel.check(), Err(Cancelled));
+    }
+
+    #[tokio::test]
+    async fn drops_cancelled_futures() {
+        let cancel = Cancel::new();
+
+        assert_eq!(cancel.run(async { 42 }).await, Ok(42));
+
+        let pending = cancel.run(tokio::time::sleep(Duration::from_secs(3600)));
+        let canceller = async {
+            tokio::task::yield_now().await;
+            cancel.cancel();
+        };
+
+        let (result, ()) = tokio::join!(pending, canceller);
+
+        assert_eq!(result, Err(Cancelled));
+        assert_eq!(cancel.run(async { 42 }).await, Err(Cancelled));
+    }
+
+    #[tokio::test]
+    async fn cancels_at_deadline() {
+        let cancel = Cancel::with_timeout(Duration::from_millis(50));
+
+        assert_eq!(cancel.check(), Ok(()));
+
+        let pending = cancel.run(tokio::time::sleep(Duration::from_secs(3600)));
+
+        assert_eq!(pending.await, Err(Cancelled));
+        assert_eq!(cancel.check(), Err(Cancelled));
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Synchronization of the package version with the Cargo manifest of a Rust crate
+
+use miette::{bail, miette, Context, IntoDiagnostic};
+use semver::Version;
+
+/// Name of Cargo manifests
+pub const CARGO_MANIFEST: &str = "Cargo.toml";
+
+/// Tables of a Cargo manifest declaring a version, in order of precedence
+const VERSION_TABLES: &[&str] = &["package", "workspace.package"];
+
+/// Reads the version of the crate, or of the workspace if the manifest is a workspace root
+pub fn read_version(contents: &str) -> miette::Result<Version> {
+    let manifest: toml::Table = toml::from_str(contents)
+        .into_diagnostic()
+        .wrap_err(miette!("failed to parse {CARGO_MANIFEST}"))?;
+
+    for table in VERSION_TABLES {
+        let version = table
+            .split('.')
+            .try_fold(&manifest, |table, key| table.get(key)?.as_table())
+            .and_then(|table| table.get("version"));
+
+        match version {
+            Some(toml::Value::String(version)) => {
+                return Version::parse(version)
+                    .into_diagnostic()
+                    .wrap_err(miette!("invalid version in [{table}] of {CARGO_MANIFEST}"));
+            }
+            Some(_) => bail!(
+                help = "point [cargo] manifest at the workspace manifest declaring the version",
+                "the version in [{table}] of {CARGO_MANIFEST} is inherited"
+            ),
+            None => continue,
+        }
+    }
+
+    bail!("{CARGO_MANIFEST} declares no version in [package] or [workspace.package]")
+}
+
+/// Replaces the version of the crate or workspace, keeping the rest of the manifest as it is
+pub fn write_version(contents: &str, version: &Version) -> miette::Result<String> {
+    // the table the version is read from must be the one written to
+    let current = read_version(contents)?;
+
+    let mut table = None;
+    let mut replaced = false;
+    let mut out = String::with_capacity(contents.len());
+
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with('[') {
+            table = trimmed
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .map(|name| name.trim().to_owned());
+        }
+
+        let declares_version = trimmed
+            .strip_prefix("version")
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+
+        let in_version_table = table
+            .as_deref()
+            .is_some_and(|table| VERSION_TABLES.contains(&table));
+
+        if !replaced && in_version_table && declares_version {
+            let quoted = format!("\"{current}\"");
+
+            if let Some(position) = line.find(&quoted) {
+                out.push_str(&line[..position]);
+                out.push_str(&format!("\"{version}\""));
+                out.push_str(&line[position + quoted.len()..]);
+
+                replaced = true;
+                continue;
+            }
+        }
+
+        out.push_str(line);
+    }
+
+    if !replaced {
+        bail!("failed to locate the version {current} in {CARGO_MANIFEST}");
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"[package]
+name = "physics-service"
+# bumped by buffrs
+version = "1.2.3" # keep in sync
+
+[dependencies]
+version-compare = { version = "1.2.3" }
+"#;
+
+    #[test]
+    fn reads_versions() {
+        assert_eq!(read_version(MANIFEST).unwrap(), Version::new(1, 2, 3));
+
+        let workspace =
+            "[workspace]\nmembers = [\"a\"]\n\n[workspace.package]\nversion = \"0.4.0\"\n";
+
+        assert_eq!(read_version(workspace).unwrap(), Version::new(0, 4, 0));
+
+        let inherited = "[package]\nname = \"a\"\nversion.workspace = true\n";
+
+        assert!(read_version(inherited).is_err());
+    }
+
+    #[test]
+    fn writes_versions() {
+        let written = write_version(MANIFEST, &Version::new(1, 3, 0)).unwrap();
+
+        assert_eq!(
+            written,
+            MANIFEST.replacen("version = \"1.2.3\" #", "version = \"1.3.0\" #", 1)
+        );
+        assert_eq!(read_version(&written).unwrap(), Version::new(1, 3, 0));
+        assert!(written.contains("version-compare = { version = \"1.2.3\" }"));
+    }
+}
@@ -13,53 +13,92 @@
 // limitations under the License.
 
 use crate::{
+    bazel::{ProtoLibrary, Workspace, BUILD_FILE},
     buf_yaml::BufYamlFile,
     cache::Cache,
-    config::Config,
+    cancel::Cancel,
+    config::{Config, SyncDirection},
     credentials::Credentials,
-    lock::{LockedPackage, Lockfile},
+    doc::{Documentation, DOC_PATH},
+    errors::{ErrorCode, ExitCode, ERROR_CODES},
+    flock::FileLock,
+    generated::Generated,
+    lock::{Digest, DigestAlgorithm, LockedPackage, Lockfile, LOCKFILE},
     manifest::{Dependency, DependencyManifest, Manifest, PackageManifest, MANIFEST_FILE},
-    package::{PackageName, PackageStore, PackageType},
+    package::{LinkMode, Package, PackageName, PackageStore, PackageType, ProtoTree},
+    progress::{Event, EventSink, Progress},
     registry::{Artifactory, RegistryUri},
     resolver::{DependencyGraph, ResolvedDependency},
+    sbom::{Component, Sbom, SbomFormat},
+    signing::Signer,
+    template::Template,
+    vendor::{Vendor, VENDOR_DIR},
 };
 
 use async_recursion::async_recursion;
-use miette::{bail, ensure, miette, Context, IntoDiagnostic};
+use miette::{bail, ensure, miette, Context, Diagnostic, IntoDiagnostic, Severity};
 use semver::{Version, VersionReq};
+use serde_json::json;
 use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
     env,
+    io::IsTerminal,
     path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, SystemTime},
 };
+use strum::{Display, EnumString};
 use tokio::{
     fs,
-    io::{self, AsyncBufReadExt, BufReader},
+    io::{self, AsyncBufReadExt, AsyncReadExt, BufReader},
 };
 use walkdir::WalkDir;
 
+/// Commands on the lockfile
+pub mod lock;
+
+/// Settings of the configuration files
+pub mod config;
+
+/// Synchronization of the package version with a Cargo manifest
+pub mod cargo;
+
+/// Helpers for continuous integration
+pub mod ci;
+
+/// Interoperability with Buf workspaces and Buf Schema Registries
+pub mod buf;
+
+/// Git hooks checking the package before changes are committed or pushed
+#[cfg(all(feature = "git", feature = "validation"))]
+pub mod hooks;
+
+/// Mirroring of packages between registries
+pub mod mirror;
+
 const INITIAL_VERSION: Version = Version::new(0, 1, 0);
 const BUFFRS_TESTSUITE_VAR: &str = "BUFFRS_TESTSUITE";
 
+/// The name of the current directory as a package name
+fn curr_dir_name() -> miette::Result<PackageName> {
+    std::env::current_dir()
+        .into_diagnostic()?
+        .file_name()
+        // because the path originates from the current directory, this condition is never met
+        .ok_or(miette!(
+            "unexpected error: current directory path terminates in .."
+        ))?
+        .to_str()
+        .ok_or_else(|| miette!("current directory path is not valid utf-8"))?
+        .parse()
+}
+
 /// Initializes the project
 pub async fn init(kind: Option<PackageType>, name: Option<PackageName>) -> miette::Result<()> {
     if Manifest::exists().await? {
         bail!("a manifest file was found, project is already initialized");
     }
 
-    fn curr_dir_name() -> miette::Result<PackageName> {
-        std::env::current_dir()
-            .into_diagnostic()?
-            .file_name()
-            // because the path originates from the current directory, this condition is never met
-            .ok_or(miette!(
-                "unexpected error: current directory path terminates in .."
-            ))?
-            .to_str()
-            .ok_or_else(|| miette!("current directory path is not valid utf-8"))?
-            .parse()
-    }
-
     let package = kind
         .map(|kind| -> miette::Result<PackageManifest> {
             let name = name.map(Result::Ok).unwrap_or_else(curr_dir_name)?;
@@ -69,6 +108,8 @@ pub async fn init(kind: Option<PackageType>, name: Option<PackageName>) -> miett
                 name,
                 version: INITIAL_VERSION,
                 description: None,
+                license: None,
+                max_size: None,
             })
         })
         .transpose()?;
@@ -84,8 +125,180 @@ pub async fn init(kind: Option<PackageType>, name: Option<PackageName>) -> miett
     Ok(())
 }
 
+/// Initializes a package in the current directory from an existing directory of proto files
+///
+/// The files are moved into `proto/`, keeping their paths relative to `dir`. Dependencies are
+/// drafted as comments in the manifest for imports of files outside of `dir`.
+///
+/// # Arguments
+///  * `kind` - The package type, inferred from the files if omitted
+///  * `name` - The package name, inferred from the protobuf packages of the files if omitted
+///  * `dir` - The directory containing the proto files
+///  * `link` - Symlink the files into `proto/` instead of moving them
+pub async fn adopt(
+    kind: Option<PackageType>,
+    name: Option<PackageName>,
+    dir: &Path,
+    link: bool,
+) -> miette::Result<()> {
+    if Manifest::exists().await? {
+        bail!("a manifest file was found, project is already initialized");
+    }
+
+    let tree = ProtoTree::scan(dir)
+        .await
+        .wrap_err(miette!("failed to scan {}", dir.display()))?;
+
+    ensure!(
+        !tree.files.is_empty(),
+        "no proto files found in {}",
+        dir.display()
+    );
+
+    tracing::info!(
+        ":: found {} proto files declaring {}",
+        tree.files.len(),
+        tree.packages.iter().cloned().collect::<Vec<_>>().join(", ")
+    );
+
+    let name = match name.or_else(|| tree.name()) {
+        Some(name) => name,
+        None => curr_dir_name()?,
+    };
+
+    let kind = kind.unwrap_or_else(|| tree.kind());
+
+    let store = PackageStore::open(std::env::current_dir().unwrap_or_else(|_| ".".into()))
+        .await
+        .wrap_err(miette!("failed to create buffrs `proto` directories"))?;
+
+    let target = store.proto_path();
+    let canonical = fs::canonicalize(&target).await.into_diagnostic()?;
+
+    let mut transferred = 0;
+
+    for file in &tree.files {
+        let source = fs::canonicalize(dir.join(file)).await.into_diagnostic()?;
+        let destination = target.join(file);
+
+        // files already in place are kept
+        if source.starts_with(&canonical) {
+            continue;
+        }
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to create {}", parent.display()))?;
+        }
+
+        let result = if link {
+            #[cfg(unix)]
+            let result = fs::symlink(&source, &destination).await;
+            #[cfg(windows)]
+            let result = fs::symlink_file(&source, &destination).await;
+
+            result
+        } else {
+            fs::rename(&source, &destination).await
+        };
+
+        result.into_diagnostic().wrap_err(miette!(
+            "failed to {} {} to {}",
+            if link { "link" } else { "move" },
+            source.display(),
+            destination.display()
+        ))?;
+
+        transferred += 1;
+    }
+
+    if transferred > 0 {
+        tracing::info!(
+            ":: {} {transferred} files from {} into {}",
+            if link { "linked" } else { "moved" },
+            dir.display(),
+            PackageStore::PROTO_PATH
+        );
+    }
+
+    let manifest = Manifest::new(
+        Some(PackageManifest {
+            kind,
+            name: name.clone(),
+            version: INITIAL_VERSION,
+            description: None,
+            license: None,
+            max_size: None,
+        }),
+        vec![],
+    );
+
+    manifest.write().await?;
+
+    if !tree.dependencies.is_empty() {
+        // the dependencies are drafted as comments, as their registry and repository are unknown
+        let mut drafts = String::from(
+            "# drafted from imports of files outside of the package, fill in the registry, repository and version\n",
+        );
+
+        for package in tree.dependencies.keys() {
+            drafts.push_str(&format!(
+                "# {package} = {{ version = \"*\", registry = \"https://<domain>/artifactory\", repository = \"<repository>\" }}\n"
+            ));
+        }
+
+        let mut contents = fs::read_to_string(MANIFEST_FILE)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {MANIFEST_FILE}"))?;
+
+        contents.push_str(&drafts);
+
+        fs::write(MANIFEST_FILE, contents)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write {MANIFEST_FILE}"))?;
+
+        tracing::warn!(
+            ":: drafted dependencies on {} in {MANIFEST_FILE}, fill in their registry, repository and version",
+            tree.dependencies
+                .keys()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    for import in &tree.unresolved {
+        tracing::warn!(
+            ":: could not infer the package providing `{import}`, add it as a dependency manually"
+        );
+    }
+
+    tracing::info!(
+        ":: initialized {} package {name}",
+        kind.to_string().to_lowercase()
+    );
+
+    Ok(())
+}
+
 /// Initializes a project with the given name in the current directory
-pub async fn new(kind: Option<PackageType>, name: PackageName) -> miette::Result<()> {
+///
+/// # Arguments
+///  * `template` - The starter protocol buffers to scaffold
+///  * `build_script` - Whether to scaffold a `build.rs` compiling the protocol buffers
+pub async fn new(
+    kind: Option<PackageType>,
+    name: PackageName,
+    template: Template,
+    build_script: bool,
+) -> miette::Result<()> {
+    // validate the template before anything is created
+    let files = template.files(kind, &name, build_script)?;
+
     let package_dir = PathBuf::from(name.to_string());
     // create_dir fails if the folder already exists
     fs::create_dir(&package_dir)
@@ -103,6 +316,8 @@ pub async fn new(kind: Option<PackageType>, name: PackageName) -> miette::Result
                 name,
                 version: INITIAL_VERSION,
                 description: None,
+                license: None,
+                max_size: None,
             })
         })
         .transpose()?;
@@ -114,6 +329,19 @@ pub async fn new(kind: Option<PackageType>, name: PackageName) -> miette::Result
         .await
         .wrap_err(miette!("failed to create buffrs `proto` directories"))?;
 
+    for (path, contents) in files {
+        let path = package_dir.join(path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.into_diagnostic()?;
+        }
+
+        fs::write(&path, contents)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write {}", path.display()))?;
+    }
+
     Ok(())
 }
 
@@ -175,17 +403,51 @@ impl FromStr for DependencyLocator {
     }
 }
 
+/// Published version of a package, formatted as `<package>@<version>`
+struct PackageVersion {
+    package: PackageName,
+    version: Version,
+}
+
+impl FromStr for PackageVersion {
+    type Err = miette::Report;
+
+    fn from_str(input: &str) -> miette::Result<Self> {
+        let (package, version) = input
+            .trim()
+            .split_once('@')
+            .ok_or_else(|| miette!("\"{input}\" is missing a version (use <package>@<version>)"))?;
+
+        let package = package
+            .parse::<PackageName>()
+            .wrap_err(miette!("invalid package name: {package}"))?;
+
+        let version = Version::parse(version)
+            .into_diagnostic()
+            .wrap_err(miette!("not a valid version: {version}"))?;
+
+        Ok(Self { package, version })
+    }
+}
+
+impl std::fmt::Display for PackageVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.package, self.version)
+    }
+}
+
 /// Adds a dependency to this project
 pub async fn add(
     registry: &RegistryUri,
     resolved_registry: &RegistryUri,
     dependency: &str,
+    config: &Config,
 ) -> miette::Result<()> {
     let mut manifest = Manifest::read().await?;
 
     let DependencyLocator {
         repository,
-        package,
+        mut package,
         version,
     } = dependency.parse()?;
 
@@ -194,11 +456,24 @@ pub async fn add(
         DependencyLocatorVersion::Latest => {
             // query artifactory to retrieve the actual latest version
             let credentials = Credentials::load().await?;
-            let artifactory = Artifactory::new(resolved_registry, &credentials)?;
+            let artifactory = Artifactory::new(resolved_registry, &credentials)?
+                .with_network(config.network())?;
 
-            let latest_version = artifactory
+            let latest_version = match artifactory
                 .get_latest_version(repository.clone(), package.clone())
-                .await?;
+                .await
+            {
+                Ok(version) => version,
+                // a partial name, let the user pick from the matching packages
+                Err(_) if std::io::stdin().is_terminal() => {
+                    let (name, version) = pick(&artifactory, &repository, &package).await?;
+
+                    package = name;
+                    version
+                }
+                Err(error) => return Err(error),
+            };
+
             // Convert semver::Version to semver::VersionReq. It will default to operator `>`, which is what we want for Proto.toml
             VersionReq::parse(&latest_version.to_string())
                 .into_diagnostic()
@@ -216,6 +491,76 @@ pub async fn add(
         .wrap_err(miette!("failed to write `{MANIFEST_FILE}`"))
 }
 
+/// Lets the user pick a package and version among the packages of a repository whose name
+/// contains the query
+async fn pick(
+    artifactory: &Artifactory,
+    repository: &str,
+    query: &str,
+) -> miette::Result<(PackageName, Version)> {
+    let mut packages: Vec<_> = artifactory
+        .search(repository, query)
+        .await?
+        .into_iter()
+        .collect();
+
+    ensure!(
+        !packages.is_empty(),
+        "no packages matching `{query}` were found in {repository}"
+    );
+
+    tracing::info!(":: packages matching `{query}` in {repository}:");
+
+    for (index, (name, versions)) in packages.iter().enumerate() {
+        tracing::info!("   {}) {name} ({})", index + 1, versions[0]);
+    }
+
+    let (name, mut versions) = packages.swap_remove(prompt("package", packages.len()).await?);
+
+    if versions.len() > 1 {
+        tracing::info!(":: versions of {name}:");
+
+        for (index, version) in versions.iter().enumerate() {
+            tracing::info!("   {}) {version}", index + 1);
+        }
+    }
+
+    let version = versions.swap_remove(prompt("version", versions.len()).await?);
+
+    Ok((name, version))
+}
+
+/// Asks the user to choose one of `count` numbered entries, the first one being the default
+async fn prompt(entry: &str, count: usize) -> miette::Result<usize> {
+    if count == 1 {
+        return Ok(0);
+    }
+
+    let mut reader = BufReader::new(io::stdin());
+
+    loop {
+        tracing::info!(":: select a {entry} [1-{count}, default 1]:");
+
+        let mut raw = String::new();
+
+        let read = reader
+            .read_line(&mut raw)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read the selection from the user"))?;
+
+        ensure!(read > 0, "no {entry} was selected");
+
+        match raw.trim() {
+            "" => return Ok(0),
+            raw => match raw.parse::<usize>() {
+                Ok(choice) if (1..=count).contains(&choice) => return Ok(choice - 1),
+                _ => tracing::warn!(":: `{raw}` is not a number between 1 and {count}"),
+            },
+        }
+    }
+}
+
 /// Removes a dependency from this project
 pub async fn remove(package: PackageName) -> miette::Result<()> {
     let mut manifest = Manifest::read().await?;
@@ -229,14 +574,72 @@ pub async fn remove(package: PackageName) -> miette::Result<()> {
 
     let dependency = manifest.dependencies.remove(dependency);
 
-    store.uninstall(&dependency.package).await.ok();
+    if !Lockfile::exists().await? {
+        store.uninstall(&dependency.package).await.ok();
+
+        return manifest.write().await;
+    }
+
+    let mut lockfile = Lockfile::read().await?;
+
+    // packages locked for local dependencies are only reachable through their manifests
+    let mut roots: Vec<PackageName> = Vec::new();
+    let mut pending = manifest.dependencies.clone();
+
+    while let Some(dependency) = pending.pop() {
+        if roots.contains(&dependency.package) {
+            continue;
+        }
+
+        if let DependencyManifest::Local(ref local) = dependency.manifest {
+            let dir = local.path.clone();
+
+            if let Some(local) = Manifest::try_read_from(dir.join(MANIFEST_FILE)).await? {
+                // paths of nested local dependencies are relative to their dependant
+                pending.extend(local.dependencies.into_iter().map(|mut dependency| {
+                    if let DependencyManifest::Local(ref mut nested) = dependency.manifest {
+                        nested.path = dir.join(&nested.path);
+                    }
+
+                    dependency
+                }));
+            }
+        }
+
+        roots.push(dependency.package);
+    }
+
+    let removed = lockfile.prune(&roots);
+
+    // the package stays installed if another dependency still requires it
+    if lockfile.get(&dependency.package).is_none() {
+        store.uninstall(&dependency.package).await.ok();
+    }
+
+    for locked in removed
+        .iter()
+        .filter(|locked| locked.name != dependency.package)
+    {
+        store.uninstall(&locked.name).await.ok();
+
+        tracing::info!(
+            ":: removed unused dependency {}@{}",
+            locked.name,
+            locked.version
+        );
+    }
 
-    manifest.write().await
+    manifest.write().await?;
+    lockfile.write().await
 }
 
 /// Packages the api and writes it to the filesystem
+///
+/// The archive is written to `output` if given, and named after the package inside of
+/// `directory` otherwise.
 pub async fn package(
     directory: impl AsRef<Path>,
+    output: Option<&Path>,
     dry_run: bool,
     version: Option<Version>,
 ) -> miette::Result<()> {
@@ -261,29 +664,109 @@ pub async fn package(
         return Ok(());
     }
 
-    let path = {
-        let file = format!("{}-{}.tgz", package.name(), package.version());
+    let path = match output {
+        Some(output) => {
+            if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .await
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to create {}", parent.display()))?;
+            }
+
+            output.to_path_buf()
+        }
+        None => {
+            let file = format!("{}-{}.tgz", package.name(), package.version());
 
-        directory.as_ref().join(file)
+            directory.as_ref().join(file)
+        }
     };
 
-    fs::write(path, package.tgz)
+    fs::write(&path, package.tgz)
         .await
         .into_diagnostic()
         .wrap_err(miette!(
-            "failed to write package release to the current directory"
+            "failed to write package release to {}",
+            path.display()
         ))
 }
 
 /// Publishes the api package to the registry
+#[allow(clippy::too_many_arguments)]
 pub async fn publish(
     registry: &RegistryUri,
     repository: String,
     #[cfg(feature = "git")] allow_dirty: bool,
+    #[cfg(feature = "validation")] skip_verify: bool,
+    #[cfg(feature = "validation")] enforce_semver: bool,
     dry_run: bool,
+    chunked: bool,
     version: Option<Version>,
-) -> miette::Result<()> {
+    signer: Option<Signer<'_>>,
+    format: OutputFormat,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<serde_json::Value> {
     #[cfg(feature = "git")]
+    ensure_clean(allow_dirty).await?;
+
+    let mut manifest = Manifest::read().await?;
+    let credentials = Credentials::load().await?;
+    let store = PackageStore::current().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?
+        .with_network(config.network())?
+        .with_encryption(config.encryption(registry));
+
+    if let Some(version) = version {
+        if let Some(ref mut package) = manifest.package {
+            tracing::info!(":: modified version in published manifest to {version}");
+
+            package.version = version;
+        }
+    }
+
+    let package = prepare_release(
+        &store,
+        &manifest,
+        &artifactory,
+        registry,
+        &repository,
+        #[cfg(feature = "validation")]
+        skip_verify,
+        #[cfg(feature = "validation")]
+        enforce_semver,
+        config,
+    )
+    .await?;
+
+    if dry_run {
+        report_size(&package)?;
+    }
+
+    check_size(&package)?;
+
+    if dry_run {
+        tracing::warn!(":: aborting upload due to dry run");
+    } else {
+        // incompatible schemas abort the publish before the package is uploaded
+        #[cfg(feature = "validation")]
+        register_schemas(&store, &manifest, config).await?;
+
+        upload(&artifactory, &package, &repository, chunked, signer, cancel).await?;
+    }
+
+    let release = release_json(&package, registry, &repository, dry_run)?;
+
+    if let OutputFormat::Json = format {
+        print_json(&release);
+    }
+
+    Ok(release)
+}
+
+/// Aborts publishing if the git working tree contains uncommitted changes, unless allowed
+#[cfg(feature = "git")]
+async fn ensure_clean(allow_dirty: bool) -> miette::Result<()> {
     async fn git_statuses() -> miette::Result<Vec<String>> {
         use std::process::Stdio;
 
@@ -322,7 +805,6 @@ pub async fn publish(
         Ok(lines.unwrap_or_default())
     }
 
-    #[cfg(feature = "git")]
     if let Ok(statuses) = git_statuses().await {
         if !allow_dirty && !statuses.is_empty() {
             tracing::error!("{} files in the working directory contain changes that were not yet committed into git:\n", statuses.len());
@@ -335,281 +817,4395 @@ pub async fn publish(
         }
     }
 
-    let mut manifest = Manifest::read().await?;
-    let credentials = Credentials::load().await?;
-    let store = PackageStore::current().await?;
-    let artifactory = Artifactory::new(registry, &credentials)?;
+    Ok(())
+}
 
-    if let Some(version) = version {
-        if let Some(ref mut package) = manifest.package {
-            tracing::info!(":: modified version in published manifest to {version}");
+/// Checks the imports of a package, compiles it unless skipped and packages it for publishing
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(feature = "validation"), allow(unused_variables))]
+async fn prepare_release(
+    store: &PackageStore,
+    manifest: &Manifest,
+    artifactory: &Artifactory,
+    registry: &RegistryUri,
+    repository: &str,
+    #[cfg(feature = "validation")] skip_verify: bool,
+    #[cfg(feature = "validation")] enforce_semver: bool,
+    config: &Config,
+) -> miette::Result<Package> {
+    let imports = store
+        .check_imports(manifest, config.include_paths())
+        .await?;
 
-            package.version = version;
-        }
+    for dependency in &imports.unused {
+        tracing::warn!(":: dependency `{dependency}` is declared but never imported");
+    }
+
+    if !imports.undeclared.is_empty() {
+        imports
+            .undeclared
+            .iter()
+            .for_each(|import| tracing::error!("{import}"));
+
+        bail!("attempted to publish a package importing undeclared dependencies");
     }
 
     if let Some(ref pkg) = manifest.package {
         store.populate(pkg).await?;
-    }
 
-    let package = store.release(&manifest).await?;
+        #[cfg(feature = "validation")]
+        if !skip_verify {
+            store
+                .verify(pkg, config.include_paths())
+                .await
+                .wrap_err(miette!(
+                    help = "pass `--skip-verify` to publish without compiling the package",
+                    "failed to compile the protocol buffers of `{}`",
+                    pkg.name
+                ))?;
+        }
 
-    if dry_run {
-        tracing::warn!(":: aborting upload due to dry run");
-        return Ok(());
+        #[cfg(feature = "validation")]
+        check_semver(
+            store,
+            artifactory,
+            registry,
+            repository,
+            pkg,
+            enforce_semver,
+            config,
+        )
+        .await?;
     }
 
-    artifactory.publish(package, repository).await
-}
-
-/// Install mode for dependencies
-pub enum InstallMode {
-    /// Only install dependencies, not the package itself
-    DependenciesOnly,
-
-    /// Install the package and its dependencies
-    All,
+    Ok(store.release(manifest).await?)
 }
 
-bitflags::bitflags! {
-    /// Flags for generation
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    pub struct GenerationFlags: u8 {
-        /// Flag for generating buf.yaml files
-        const BUF_YAML = 0b0001;
-    }
+/// A package of a multi-package repository, found by following local dependencies
+struct Member {
+    /// Directory holding the manifest of the package
+    dir: PathBuf,
+    /// Manifest of the package
+    manifest: Manifest,
 }
 
-/// Installs dependencies
+/// Collects the packages reachable through local dependencies from the current directory
 ///
-/// # Arguments
-/// * `mode` - The install mode (dependencies only or all)
-/// * `generation` - Flags for generation of files
-/// * `config` - The configuration
-pub async fn install(
-    mode: InstallMode,
-    generation: GenerationFlags,
-    config: &Config,
-) -> miette::Result<()> {
-    let manifest = {
-        let mut manifest = Manifest::read().await?;
+/// The current directory does not need to declare a package itself, e.g. in repositories with
+/// a top level manifest depending on every package.
+async fn collect_members() -> miette::Result<BTreeMap<PackageName, Member>> {
+    let mut members: BTreeMap<PackageName, Member> = BTreeMap::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![PathBuf::from(".")];
+
+    while let Some(dir) = pending.pop() {
+        let canonical = fs::canonicalize(&dir)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to locate `{}`", dir.display()))?;
 
-        // resolve all alias URLs in the manifest
+        if !visited.insert(canonical) {
+            continue;
+        }
+
+        let manifest = Manifest::try_read_from(dir.join(MANIFEST_FILE))
+            .await?
+            .ok_or(miette!(
+                "`{}` does not exist",
+                dir.join(MANIFEST_FILE).display()
+            ))?;
+
+        for dependency in &manifest.dependencies {
+            if let DependencyManifest::Local(ref local) = dependency.manifest {
+                pending.push(dir.join(&local.path));
+            }
+        }
+
+        let Some(ref package) = manifest.package else {
+            continue;
+        };
+
+        if let Some(other) = members.get(&package.name) {
+            bail!(
+                "the package {} is declared both in `{}` and `{}`",
+                package.name,
+                other.dir.display(),
+                dir.display()
+            );
+        }
+
+        members.insert(package.name.clone(), Member { dir, manifest });
+    }
+
+    Ok(members)
+}
+
+/// Maps every member to the packages it depends on through local dependencies
+fn local_dependencies(
+    members: &BTreeMap<PackageName, Member>,
+) -> BTreeMap<PackageName, Vec<PackageName>> {
+    members
+        .iter()
+        .map(|(name, member)| {
+            let dependencies = member
+                .manifest
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.manifest.is_local())
+                .map(|dependency| dependency.package.clone())
+                .collect();
+
+            (name.clone(), dependencies)
+        })
+        .collect()
+}
+
+/// Extends a set of packages by every package reaching one of them through its dependencies
+#[cfg(feature = "git")]
+fn with_dependents(
+    dependencies: &BTreeMap<PackageName, Vec<PackageName>>,
+    mut packages: BTreeSet<PackageName>,
+) -> BTreeSet<PackageName> {
+    loop {
+        let dependents: Vec<PackageName> = dependencies
+            .iter()
+            .filter(|(name, requires)| {
+                !packages.contains(*name) && requires.iter().any(|dep| packages.contains(dep))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        if dependents.is_empty() {
+            return packages;
+        }
+
+        packages.extend(dependents);
+    }
+}
+
+/// Orders packages so that every package comes after the packages it depends on
+///
+/// Dependencies on packages that are not part of the map are ignored. Among packages whose
+/// dependencies are satisfied, the alphabetically first one comes first.
+fn publish_order(
+    dependencies: &BTreeMap<PackageName, Vec<PackageName>>,
+) -> miette::Result<Vec<PackageName>> {
+    let mut order: Vec<PackageName> = Vec::new();
+
+    while order.len() < dependencies.len() {
+        let next = dependencies.iter().find(|(name, requires)| {
+            !order.contains(name)
+                && requires
+                    .iter()
+                    .all(|dep| order.contains(dep) || !dependencies.contains_key(dep))
+        });
+
+        let Some((name, _)) = next else {
+            let cycle: Vec<String> = dependencies
+                .keys()
+                .filter(|name| !order.contains(name))
+                .map(ToString::to_string)
+                .collect();
+
+            bail!(
+                "the local dependencies of {} depend on each other",
+                cycle.join(", ")
+            );
+        };
+
+        order.push(name.clone());
+    }
+
+    Ok(order)
+}
+
+/// Waits until a freshly published version is visible in the registry
+///
+/// Registries may index uploads asynchronously, so packages depending on the version can only be
+/// published once it can be resolved.
+async fn await_visibility(
+    artifactory: &Artifactory,
+    repository: &str,
+    package: &Package,
+) -> miette::Result<()> {
+    const ATTEMPTS: u32 = 8;
+
+    for attempt in 0..ATTEMPTS {
+        if artifactory
+            .is_published(repository, package.name(), package.version())
+            .await?
+        {
+            return Ok(());
+        }
+
+        let delay = Duration::from_millis(500 << attempt.min(4));
+
+        tracing::debug!(
+            "waiting {delay:?} for {}@{} to become visible",
+            package.name(),
+            package.version()
+        );
+
+        tokio::time::sleep(delay).await;
+    }
+
+    bail!(
+        "{}@{} did not become visible in the registry after publishing",
+        package.name(),
+        package.version()
+    )
+}
+
+/// Publishes the current package and all packages it reaches through local dependencies
+///
+/// Packages are published in dependency order, with their local dependencies replaced by the
+/// published versions. Versions that are already published are skipped, so that an interrupted
+/// run can be resumed.
+#[allow(clippy::too_many_arguments)]
+pub async fn publish_all(
+    registry: &RegistryUri,
+    repository: String,
+    #[cfg(feature = "git")] allow_dirty: bool,
+    #[cfg(feature = "validation")] skip_verify: bool,
+    #[cfg(feature = "validation")] enforce_semver: bool,
+    dry_run: bool,
+    chunked: bool,
+    signer: Option<Signer<'_>>,
+    format: OutputFormat,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<serde_json::Value> {
+    #[cfg(feature = "git")]
+    ensure_clean(allow_dirty).await?;
+
+    let credentials = Credentials::load().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?
+        .with_network(config.network())?
+        .with_encryption(config.encryption(registry));
+
+    let members = collect_members().await?;
+
+    ensure!(
+        !members.is_empty(),
+        "no packages found in the current directory or its local dependencies"
+    );
+
+    let order = publish_order(&local_dependencies(&members))?;
+
+    tracing::info!(
+        ":: publishing {} in order",
+        order
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let mut releases = Vec::new();
+
+    for (index, name) in order.iter().enumerate() {
+        let member = &members[name];
+        let mut manifest = member.manifest.clone();
+
+        // depend on the versions published in the previous steps instead of the local packages
         for dependency in manifest.dependencies.iter_mut() {
-            if let DependencyManifest::Remote(ref mut manifest) = &mut dependency.manifest {
-                manifest.registry = config.resolve_registry_uri(&manifest.registry)?;
+            let Some(published) = members
+                .get(&dependency.package)
+                .filter(|_| dependency.manifest.is_local())
+                .and_then(|member| member.manifest.package.as_ref())
+            else {
+                continue;
+            };
+
+            *dependency = Dependency::new(
+                registry,
+                repository.clone(),
+                dependency.package.clone(),
+                VersionReq::STAR,
+            )
+            .with_version(&published.version);
+        }
+
+        let store = PackageStore::open(&member.dir).await?;
+
+        let package = prepare_release(
+            &store,
+            &manifest,
+            &artifactory,
+            registry,
+            &repository,
+            #[cfg(feature = "validation")]
+            skip_verify,
+            #[cfg(feature = "validation")]
+            enforce_semver,
+            config,
+        )
+        .await
+        .wrap_err(miette!("failed to package `{}`", member.dir.display()))?;
+
+        check_size(&package)?;
+
+        let step = format!("({}/{})", index + 1, order.len());
+
+        if artifactory
+            .is_published(&repository, package.name(), package.version())
+            .await?
+        {
+            tracing::info!(
+                ":: {}@{} is already published, skipping {step}",
+                package.name(),
+                package.version()
+            );
+
+            continue;
+        }
+
+        if dry_run {
+            tracing::info!(
+                ":: would publish {}@{} {step}",
+                package.name(),
+                package.version()
+            );
+        } else {
+            upload(&artifactory, &package, &repository, chunked, signer, cancel).await?;
+            await_visibility(&artifactory, &repository, &package).await?;
+
+            tracing::info!(
+                ":: published {}@{} {step}",
+                package.name(),
+                package.version()
+            );
+        }
+
+        releases.push(release_json(&package, registry, &repository, dry_run)?);
+    }
+
+    if dry_run {
+        tracing::warn!(":: aborting upload due to dry run");
+    }
+
+    let result = json!({ "packages": releases, "dry_run": dry_run });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+    }
+
+    Ok(result)
+}
+
+/// Determines the packages affected by changes since a git ref
+///
+/// Packages are collected like in [`publish_all`]. A package is affected if its manifest,
+/// lockfile or protos changed between the merge base of `since` and the working tree, including
+/// untracked files, or if it reaches an affected package through local dependencies. Affected
+/// packages are returned in publish order.
+///
+/// # Arguments
+/// * `since` - The git ref to compare against, e.g. `origin/main`
+/// * `format` - The output format
+#[cfg(feature = "git")]
+pub async fn changed(since: &str, format: OutputFormat) -> miette::Result<serde_json::Value> {
+    let members = collect_members().await?;
+
+    let root = git_output(&["rev-parse", "--show-toplevel"])
+        .await
+        .wrap_err(miette!("failed to locate the git repository"))?;
+
+    let root = fs::canonicalize(&root)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to locate `{root}`"))?;
+
+    let base = git_output(&["merge-base", since, "HEAD"])
+        .await
+        .wrap_err(miette!(
+            "failed to find the merge base of `{since}` and HEAD"
+        ))?;
+
+    let diff = git_output(&["diff", "--name-only", "--no-renames", &base]).await?;
+    let untracked =
+        git_output(&["ls-files", "--others", "--exclude-standard", "--full-name"]).await?;
+
+    let paths: Vec<PathBuf> = diff
+        .lines()
+        .chain(untracked.lines())
+        .map(PathBuf::from)
+        .collect();
+
+    let mut affected = BTreeSet::new();
+
+    for (name, member) in &members {
+        let dir = fs::canonicalize(&member.dir)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to locate `{}`", member.dir.display()))?;
+
+        let Ok(dir) = dir.strip_prefix(&root) else {
+            tracing::warn!(
+                ":: {name} is located outside of the git repository, assuming it is unchanged"
+            );
+
+            continue;
+        };
+
+        let protos = dir.join(PackageStore::PROTO_PATH);
+        let generated = [
+            dir.join(PackageStore::PROTO_VENDOR_PATH),
+            dir.join(PackageStore::PROTO_BUILD_PATH),
+        ];
+
+        let modified = paths.iter().any(|path| {
+            *path == dir.join(MANIFEST_FILE)
+                || *path == dir.join(LOCKFILE)
+                || (path.starts_with(&protos)
+                    && path.extension().is_some_and(|ext| ext == "proto")
+                    && !generated.iter().any(|prefix| path.starts_with(prefix)))
+        });
+
+        if modified {
+            tracing::debug!(":: {name} was modified since {since}");
+            affected.insert(name.clone());
+        }
+    }
+
+    let dependencies = local_dependencies(&members);
+    let affected = with_dependents(&dependencies, affected);
+
+    let order = publish_order(
+        &dependencies
+            .into_iter()
+            .filter(|(name, _)| affected.contains(name))
+            .collect(),
+    )?;
+
+    let packages: Vec<serde_json::Value> = order
+        .iter()
+        .map(|name| {
+            let member = &members[name];
+            let version = member.manifest.package.as_ref().map(|p| &p.version);
+
+            json!({ "name": name, "version": version, "path": member.dir })
+        })
+        .collect();
+
+    let result = json!({ "since": since, "packages": packages });
+
+    match format {
+        OutputFormat::Human => order.iter().for_each(|name| println!("{name}")),
+        OutputFormat::Json => print_json(&result),
+    }
+
+    Ok(result)
+}
+
+/// Publishes a previously packaged archive to the registry
+///
+/// The archive has to contain a manifest declaring a package without local dependencies. If an
+/// expected `digest` or `version` is given, the archive has to match them.
+#[allow(clippy::too_many_arguments)]
+pub async fn publish_file(
+    registry: &RegistryUri,
+    repository: String,
+    path: &Path,
+    digest: Option<Digest>,
+    version: Option<Version>,
+    dry_run: bool,
+    chunked: bool,
+    signer: Option<Signer<'_>>,
+    format: OutputFormat,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<serde_json::Value> {
+    let package = Package::read(path).await?;
+
+    ensure!(
+        package.manifest.package.is_some(),
+        "the manifest of {} does not declare a package",
+        path.display()
+    );
+
+    if let Some(expected) = digest {
+        let actual = package.digest(expected.algorithm());
+
+        ensure!(
+            actual == expected,
+            "the digest of {} is {actual}, expected {expected}",
+            path.display()
+        );
+    }
+
+    if let Some(version) = version {
+        if package.version() != &version {
+            return Err(miette!(
+                help = format!("packaged archives can not be modified, use `buffrs package --set-version {version}` to build the release"),
+                "{} contains {}@{}, expected version {version}",
+                path.display(),
+                package.name(),
+                package.version()
+            ));
+        }
+    }
+
+    tracing::info!(
+        ":: validated {}@{} ({})",
+        package.name(),
+        package.version(),
+        package.digest(DigestAlgorithm::SHA256)
+    );
+
+    if dry_run {
+        report_size(&package)?;
+    }
+
+    check_size(&package)?;
+
+    if dry_run {
+        tracing::warn!(":: aborting upload due to dry run");
+    } else {
+        let credentials = Credentials::load().await?;
+        let artifactory = Artifactory::new(registry, &credentials)?
+            .with_network(config.network())?
+            .with_encryption(config.encryption(registry));
+
+        upload(&artifactory, &package, &repository, chunked, signer, cancel).await?;
+    }
+
+    let release = release_json(&package, registry, &repository, dry_run)?;
+
+    if let OutputFormat::Json = format {
+        print_json(&release);
+    }
+
+    Ok(release)
+}
+
+/// Uploads a release to the registry, together with its signature if a signer is given
+async fn upload(
+    artifactory: &Artifactory,
+    package: &Package,
+    repository: &str,
+    chunked: bool,
+    signer: Option<Signer<'_>>,
+    cancel: &Cancel,
+) -> miette::Result<()> {
+    // sign before uploading so that a failure does not leave an unsigned release behind
+    let signature = match signer {
+        Some(signer) => Some((
+            signer.extension(),
+            signer
+                .sign(package)
+                .await
+                .wrap_err(miette!("failed to sign {}", package.name()))?,
+        )),
+        None => None,
+    };
+
+    // an interrupted upload leaves no release behind, unlike one interrupted after the package
+    // was published but before its signature was
+    let published = async {
+        match chunked {
+            true => artifactory.publish_chunks(package, repository).await,
+            false => {
+                artifactory
+                    .publish(package.clone(), repository.to_owned())
+                    .await
+            }
+        }
+    };
+
+    cancel.run(published).await??;
+
+    if let Some((extension, signature)) = signature {
+        artifactory
+            .publish_signature(package, repository, extension, signature)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Registers the messages configured in `[schema-registry]` with the Confluent schema registry
+///
+/// Each message is registered as the file declaring it, under the subject named by the
+/// configured template. Requires the package to be populated.
+#[cfg(feature = "validation")]
+async fn register_schemas(
+    store: &PackageStore,
+    manifest: &Manifest,
+    config: &Config,
+) -> miette::Result<()> {
+    use crate::{
+        registry::confluent::{self, SchemaFile, SchemaRegistry},
+        validation::Validator,
+    };
+
+    let (Some(settings), Some(package)) = (config.schema_registry(), &manifest.package) else {
+        return Ok(());
+    };
+
+    if settings.messages.is_empty() {
+        return Ok(());
+    }
+
+    let vendor = store.proto_vendor_path();
+    let mut validator = Validator::new(&vendor, package);
+
+    for file in store.populated_files(package).await {
+        validator.input(&file);
+    }
+
+    let descriptors = validator
+        .descriptors()
+        .wrap_err(miette!("failed to compile `{}`", package.name))?;
+
+    let mut files = BTreeMap::new();
+
+    for descriptor in descriptors {
+        let name = descriptor.name().to_owned();
+
+        // well-known types are provided by the parser, not installed
+        let Ok(contents) = fs::read_to_string(vendor.join(&name)).await else {
+            continue;
+        };
+
+        let file = SchemaFile {
+            contents,
+            package: descriptor.package().to_owned(),
+            messages: descriptor
+                .message_type
+                .iter()
+                .map(|message| message.name().to_owned())
+                .collect(),
+            imports: descriptor.dependency.clone(),
+        };
+
+        files.insert(name, file);
+    }
+
+    let registry = SchemaRegistry::new(&settings.url, config.network())?;
+
+    for message in &settings.messages {
+        let subject = confluent::subject(&settings.subject, message, &package.name);
+
+        let version = registry
+            .register(&files, message, &subject)
+            .await
+            .wrap_err(miette!(
+                "failed to register {message} with the schema registry {}",
+                settings.url
+            ))?;
+
+        tracing::info!(":: registered {message} as {subject} version {version}");
+    }
+
+    Ok(())
+}
+
+/// Describes a published release in the JSON output of `publish`
+fn release_json(
+    package: &Package,
+    registry: &RegistryUri,
+    repository: &str,
+    dry_run: bool,
+) -> miette::Result<serde_json::Value> {
+    let files: Vec<_> = package
+        .files()?
+        .into_iter()
+        .map(|(path, size)| json!({ "path": path, "size": size }))
+        .collect();
+
+    Ok(json!({
+        "name": package.name().to_string(),
+        "version": package.version().to_string(),
+        "registry": registry.to_string(),
+        "repository": repository,
+        "digest": package.digest(DigestAlgorithm::SHA256).to_string(),
+        "size": package.tgz.len(),
+        "files": files,
+        "dry_run": dry_run,
+    }))
+}
+
+/// Reports the size of a release together with a breakdown of its files
+fn report_size(package: &Package) -> miette::Result<()> {
+    let mut files = package.files()?;
+
+    files.sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then_with(|| a.cmp(b)));
+
+    let unpacked: u64 = files.iter().map(|(_, size)| size).sum();
+    let size = package.tgz.len() as u64;
+
+    tracing::info!(
+        ":: {} files, {} compressed, {} unpacked",
+        files.len(),
+        format_size(size),
+        format_size(unpacked)
+    );
+
+    for (file, size) in &files {
+        tracing::info!("   {:>10}  {}", format_size(*size), file.display());
+    }
+
+    Ok(())
+}
+
+/// Enforces the `max-size` declared in the manifest of a release
+fn check_size(package: &Package) -> miette::Result<()> {
+    let size = package.tgz.len() as u64;
+
+    let max_size = package
+        .manifest
+        .package
+        .as_ref()
+        .and_then(|package| package.max_size);
+
+    if let Some(max_size) = max_size.filter(|max_size| size > *max_size) {
+        return Err(miette!(
+            help = "remove files from the package or raise `max-size` in the manifest",
+            "the package archive is {} but may be at most {}",
+            format_size(size),
+            format_size(max_size)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Formats a size in bytes with a binary unit
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Prints the JSON output of a command to stdout
+fn print_json(value: &serde_json::Value) {
+    // hint: always ok, as per serde_json doc
+    if let Ok(json) = serde_json::to_string_pretty(value) {
+        println!("{json}");
+    }
+}
+
+/// Describes a diagnostic in the JSON output of a command
+fn diagnostic_json(diagnostic: &dyn Diagnostic) -> serde_json::Value {
+    let severity = match diagnostic.severity().unwrap_or(Severity::Error) {
+        Severity::Advice => "advice",
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    };
+
+    json!({
+        "code": diagnostic.code().map(|code| code.to_string()),
+        "severity": severity,
+        "message": diagnostic.to_string(),
+        "help": diagnostic.help().map(|help| help.to_string()),
+    })
+}
+
+/// Install mode for dependencies
+pub enum InstallMode {
+    /// Only install dependencies, not the package itself
+    DependenciesOnly,
+
+    /// Install the package and its dependencies
+    All,
+}
+
+bitflags::bitflags! {
+    /// Flags for generation
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct GenerationFlags: u8 {
+        /// Flag for generating buf.yaml files
+        const BUF_YAML = 0b0001;
+        /// Flag for generating the configuration of proto language servers
+        const LSP = 0b0010;
+    }
+}
+
+bitflags::bitflags! {
+    /// Targets removed by `clean`
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct CleanTargets: u8 {
+        /// Installed dependencies in `proto/vendor`
+        const DEPS = 0b0001;
+        /// Build output in `proto/build`
+        const BUILD = 0b0010;
+        /// Packages cached in the buffrs home
+        const CACHE = 0b0100;
+    }
+}
+
+/// Lowest severity of the diagnostics that fails a check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum FailOn {
+    /// Fail on warnings and errors
+    Warn,
+    /// Fail on errors only
+    Error,
+}
+
+impl FailOn {
+    /// Whether a diagnostic of the given severity fails the check
+    pub fn fails(self, severity: Severity) -> bool {
+        match severity {
+            Severity::Error => true,
+            Severity::Warning => self == Self::Warn,
+            Severity::Advice => false,
+        }
+    }
+}
+
+/// Format of the output of commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable text
+    #[default]
+    Human,
+    /// A single JSON document on stdout, while logs are written to stderr
+    Json,
+}
+
+/// Installs dependencies
+///
+/// # Arguments
+/// * `mode` - The install mode (dependencies only or all)
+/// * `generation` - Flags for generation of files
+/// * `dry_run` - Only report what would be installed and locked
+/// * `vendored` - Install exclusively from the `vendor` directory, see [`vendor`]
+/// * `format` - The output format
+/// * `config` - The configuration
+/// * `cancel` - Aborts the installation, leaving the lockfile untouched
+pub async fn install(
+    mode: InstallMode,
+    generation: GenerationFlags,
+    dry_run: bool,
+    vendored: bool,
+    format: OutputFormat,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<serde_json::Value> {
+    let manifest = {
+        let mut manifest = Manifest::read().await?;
+
+        // resolve all alias URLs in the manifest
+        for dependency in manifest.dependencies.iter_mut() {
+            if let DependencyManifest::Remote(ref mut manifest) = &mut dependency.manifest {
+                manifest.registry = config.resolve_registry_uri(&manifest.registry)?;
+            }
+        }
+
+        manifest
+    };
+    let lockfile = Lockfile::read_or_default().await?;
+    let store = PackageStore::current().await?;
+
+    let source = if vendored {
+        Source::Vendor(Vendor::open(VENDOR_DIR).await?)
+    } else {
+        Source::Registry(Credentials::load().await?, Cache::open().await?)
+    };
+
+    // the bar replaces the per package logs on terminals
+    let progress = match format {
+        OutputFormat::Human if !dry_run && config.output().profile.is_rich() => Progress::new(),
+        _ => Progress::hidden(),
+    };
+
+    if let (InstallMode::All, false) = (&mode, dry_run) {
+        if let Some(ref pkg) = manifest.package {
+            progress.event(Event::Resolved {
+                name: &pkg.name,
+                version: &pkg.version,
+            });
+
+            store.populate(pkg).await?;
+
+            progress.event(Event::Installed {
+                name: &pkg.name,
+                version: &pkg.version,
+            });
+
+            if !progress.is_visible() {
+                tracing::info!(":: installed {}@{}", pkg.name, pkg.version);
+            }
+        }
+    }
+
+    let resolution = async {
+        match &source {
+            Source::Registry(credentials, cache) => {
+                DependencyGraph::from_manifest(
+                    &manifest,
+                    &lockfile,
+                    &credentials.clone().into(),
+                    config,
+                    cache,
+                    &progress,
+                )
+                .await
+            }
+            Source::Vendor(vendor) => {
+                DependencyGraph::from_vendor(&manifest, &lockfile, vendor, &progress).await
+            }
+        }
+    };
+
+    let dependency_graph = cancel
+        .run(resolution)
+        .await?
+        .wrap_err(miette!("dependency resolution failed"))?;
+
+    let remote = dependency_graph
+        .get_package_names()
+        .into_iter()
+        .filter_map(|name| match dependency_graph.get(&name) {
+            Some(ResolvedDependency::Remote { package, .. }) => Some(package),
+            _ => None,
+        });
+
+    lockfile.validate_all(remote).await?;
+
+    // vendored packages had their signatures verified when they were vendored
+    let credentials = match &source {
+        Source::Registry(credentials, _) => Some(credentials),
+        Source::Vendor(_) => None,
+    };
+
+    for name in dependency_graph.get_package_names() {
+        let Some(ResolvedDependency::Remote {
+            package,
+            registry,
+            repository,
+            ..
+        }) = dependency_graph.get(&name)
+        else {
+            continue;
+        };
+
+        let (Some(credentials), Some(policy)) = (credentials, config.trust_policy(registry)) else {
+            continue;
+        };
+
+        let signature = Artifactory::new(registry, credentials)?
+            .with_network(config.network())?
+            .download_signature(
+                repository,
+                package.name(),
+                package.version(),
+                policy.extension(),
+            )
+            .await
+            .wrap_err(miette!("failed to download the signature of {name}"))?;
+
+        policy.verify(package, signature).await?;
+    }
+
+    let mut installed = Vec::new();
+
+    if let InstallMode::All = mode {
+        if let Some(ref pkg) = manifest.package {
+            installed.push(json!({
+                "name": pkg.name.to_string(),
+                "version": pkg.version.to_string(),
+            }));
+        }
+    }
+
+    for name in dependency_graph.get_package_names() {
+        let Some(resolved) = dependency_graph.get(&name) else {
+            continue;
+        };
+
+        let mut entry = json!({
+            "name": name.to_string(),
+            "version": resolved.package().version().to_string(),
+        });
+
+        if let ResolvedDependency::Remote {
+            package,
+            registry,
+            repository,
+            ..
+        } = resolved
+        {
+            entry["registry"] = json!(registry.to_string());
+            entry["repository"] = json!(repository);
+            entry["digest"] = json!(package.digest(DigestAlgorithm::SHA256).to_string());
+        }
+
+        installed.push(entry);
+    }
+
+    if dry_run {
+        report_install(&manifest, &mode, &dependency_graph, &lockfile);
+
+        let result = json!({ "packages": installed, "dry_run": true });
+
+        if let OutputFormat::Json = format {
+            print_json(&result);
+        }
+
+        return Ok(result);
+    }
+
+    let mut locked = Vec::new();
+
+    #[async_recursion]
+    #[allow(clippy::too_many_arguments)]
+    async fn traverse_and_install(
+        name: &PackageName,
+        graph: &DependencyGraph,
+        store: &PackageStore,
+        source: &Source,
+        link: LinkMode,
+        locked: &mut Vec<LockedPackage>,
+        progress: &Progress,
+        cancel: &Cancel,
+        prefix: String,
+    ) -> miette::Result<()> {
+        cancel.check()?;
+
+        let resolved = graph.get(name).ok_or(miette!(
+            "unexpected error: missing dependency in dependency graph"
+        ))?;
+
+        // remote packages are unpacked once into the cache and linked into every project from
+        // there, local packages change too often for that
+        let unpacked = match resolved {
+            // installed by a previous run or through another dependant
+            _ if store.is_installed(resolved.package()).await => Ok(()),
+            ResolvedDependency::Remote { package, .. } => {
+                async {
+                    let unpacked = match source {
+                        Source::Registry(_, cache) => cache.unpack(package, cancel).await?,
+                        Source::Vendor(vendor) => vendor.unpacked(package.name()),
+                    };
+
+                    store.link(package, &unpacked, link).await
+                }
+                .await
+            }
+            ResolvedDependency::Local { package, .. } => store.unpack(package, cancel).await,
+        };
+
+        // cancellation is reported as such rather than as a failure of the package
+        cancel.check()?;
+
+        unpacked.wrap_err(miette!(
+            "failed to unpack package {}",
+            &resolved.package().name()
+        ))?;
+
+        progress.event(Event::Installed {
+            name,
+            version: resolved.package().version(),
+        });
+
+        if !progress.is_visible() {
+            tracing::info!(
+                "{} installed {}@{}",
+                if prefix.is_empty() { "::" } else { &prefix },
+                name,
+                resolved.package().version()
+            );
+        }
+
+        if let ResolvedDependency::Remote {
+            package,
+            registry,
+            repository,
+            dependants,
+            ..
+        } = &resolved
+        {
+            locked.push(package.lock(registry.clone(), repository.clone(), dependants.len()));
+        }
+
+        for (index, dependency) in resolved.depends_on().iter().enumerate() {
+            let tree_char = if index + 1 == resolved.depends_on().len() {
+                '┗'
+            } else {
+                '┣'
+            };
+
+            let new_prefix = format!(
+                "{} {tree_char}",
+                if prefix.is_empty() { "  " } else { &prefix }
+            );
+
+            traverse_and_install(
+                dependency, graph, store, source, link, locked, progress, cancel, new_prefix,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    for dependency in manifest.dependencies {
+        traverse_and_install(
+            &dependency.package,
+            &dependency_graph,
+            &store,
+            &source,
+            config.install().link,
+            &mut locked,
+            &progress,
+            cancel,
+            String::new(),
+        )
+        .await?;
+    }
+
+    // packages no longer depended upon are left over from previous runs
+    let retained: HashSet<_> = dependency_graph
+        .get_package_names()
+        .into_iter()
+        .chain(match mode {
+            InstallMode::All => manifest.package.as_ref().map(|pkg| pkg.name.clone()),
+            _ => None,
+        })
+        .collect();
+
+    store.retain(&retained).await?;
+
+    let cycles = store.check_cycles().await?;
+
+    if !cycles.is_empty() {
+        let count = cycles.len();
+
+        cycles
+            .into_iter()
+            .map(miette::Report::new)
+            .for_each(|r| eprintln!("{r:?}"));
+
+        bail!("the installed packages contain {count} circular import(s)");
+    }
+
+    if generation.contains(GenerationFlags::BUF_YAML) {
+        // Check if a buf.yaml file already exists in cwd
+        let mut buf_yaml = if Path::new("buf.yaml").exists() {
+            BufYamlFile::from_file().wrap_err(miette!("failed to read buf.yaml file"))?
+        } else {
+            BufYamlFile::default()
+        };
+
+        // Add vendor modules to the Buf YAML file
+        let mut vendor_modules: Vec<String> = dependency_graph
+            .get_package_names()
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        vendor_modules.sort();
+
+        buf_yaml.clear_modules();
+
+        if manifest.package.is_some() {
+            // double-check that the package really contains proto files
+            // under proto/** (but not under proto/vendor/**)
+            let vendor_path = store.proto_vendor_path();
+            let mut has_protos = false;
+            for entry in WalkDir::new(store.proto_path()).into_iter().flatten() {
+                if entry.path().is_file() {
+                    let path = entry.path();
+                    if path.starts_with(&vendor_path) {
+                        continue;
+                    }
+
+                    if path.extension().map_or(false, |ext| ext == "proto") {
+                        has_protos = true;
+                        break;
+                    }
+                }
+            }
+
+            if has_protos {
+                buf_yaml.add_module();
+            }
+        }
+
+        buf_yaml.set_vendor_modules(vendor_modules);
+
+        // Write the Buf YAML file
+        buf_yaml
+            .to_file()
+            .wrap_err(miette!("failed to write buf.yaml file"))?;
+    }
+
+    if generation.contains(GenerationFlags::LSP) {
+        configure_lsp(config).await?;
+    }
+
+    Lockfile::from_iter(locked.into_iter()).write().await?;
+
+    if progress.is_visible() {
+        tracing::info!(":: installed {}", progress.finish());
+    }
+
+    let result = json!({ "packages": installed, "dry_run": false });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+    }
+
+    Ok(result)
+}
+
+/// Where an installation takes remote packages from
+enum Source {
+    /// The registries, through the package cache
+    Registry(Credentials, Cache),
+    /// A directory created by [`vendor`]
+    Vendor(Vendor),
+}
+
+/// Vendors the locked dependencies into the `vendor` directory
+///
+/// Every locked package is taken from the cache or downloaded, checked against the lockfile and
+/// the trust policy of its registry, and stored as an archive together with its extracted protos.
+/// Packages that are no longer locked are removed from the directory.
+pub async fn vendor(config: &Config, cancel: &Cancel) -> miette::Result<()> {
+    let lockfile = Lockfile::read().await.wrap_err(miette!(
+        help = "run `buffrs install` to lock the dependencies",
+        "a lockfile is required to vendor the dependencies"
+    ))?;
+    let credentials = Credentials::load().await?;
+    let cache = Cache::open().await?;
+    let mut vendor = Vendor::open(VENDOR_DIR).await?;
+
+    let mut packages: Vec<_> = lockfile.packages().collect();
+    packages.sort();
+
+    for locked in packages {
+        cancel.check()?;
+
+        if vendor.get(locked).await.is_ok() {
+            tracing::debug!("{}@{} is already vendored", locked.name, locked.version);
+            continue;
+        }
+
+        let artifactory = Artifactory::new(&locked.registry, &credentials)?
+            .with_network(config.network())?
+            .with_encryption(config.encryption(&locked.registry));
+
+        let package = match cache.get(locked.into()).await? {
+            Some(package) => package,
+            None => {
+                let dependency = Dependency::new(
+                    &locked.registry,
+                    locked.repository.clone(),
+                    locked.name.clone(),
+                    VersionReq::STAR,
+                )
+                .with_version(&locked.version);
+
+                cancel.run(artifactory.download(dependency)).await??
+            }
+        };
+
+        locked.validate(&package)?;
+
+        if let Some(policy) = config.trust_policy(&locked.registry) {
+            let signature = artifactory
+                .download_signature(
+                    &locked.repository,
+                    &locked.name,
+                    &locked.version,
+                    policy.extension(),
+                )
+                .await
+                .wrap_err(miette!(
+                    "failed to download the signature of {}",
+                    locked.name
+                ))?;
+
+            policy.verify(&package, signature).await?;
+        }
+
+        vendor.add(&package).await?;
+
+        tracing::info!(":: vendored {}@{}", locked.name, locked.version);
+    }
+
+    vendor
+        .retain(|vendored| {
+            lockfile
+                .get(&vendored.name)
+                .is_some_and(|locked| locked.version == vendored.version)
+        })
+        .await?;
+
+    vendor.write().await
+}
+
+/// Logs the packages an installation would unpack and the changes it would make to the lockfile
+fn report_install(
+    manifest: &Manifest,
+    mode: &InstallMode,
+    graph: &DependencyGraph,
+    lockfile: &Lockfile,
+) {
+    if let (InstallMode::All, Some(pkg)) = (mode, &manifest.package) {
+        tracing::info!(":: would install {}@{}", pkg.name, pkg.version);
+    }
+
+    let mut names = graph.get_package_names();
+    names.sort();
+
+    for name in &names {
+        if let Some(resolved) = graph.get(name) {
+            tracing::info!(":: would install {name}@{}", resolved.package().version());
+        }
+    }
+
+    for name in &names {
+        let Some(ResolvedDependency::Remote { package, .. }) = graph.get(name) else {
+            continue;
+        };
+
+        match lockfile.get(name) {
+            None => tracing::info!(":: would lock {name}@{}", package.version()),
+            Some(locked) if &locked.version != package.version() => tracing::info!(
+                ":: would update {name} from {} to {}",
+                locked.version,
+                package.version()
+            ),
+            Some(_) => (),
+        }
+    }
+
+    let mut unlocked: Vec<_> = lockfile
+        .packages()
+        .filter(|locked| graph.get(&locked.name).is_none())
+        .collect();
+    unlocked.sort();
+
+    for locked in unlocked {
+        tracing::info!(":: would unlock {}@{}", locked.name, locked.version);
+    }
+
+    tracing::warn!(":: aborting installation due to dry run");
+}
+
+/// Points proto language servers at the installed packages
+///
+/// Writes the include paths into `protols.toml`, and into the settings of Visual Studio Code if
+/// the project has a `.vscode` directory. Other settings in these files are kept.
+async fn configure_lsp(config: &Config) -> miette::Result<()> {
+    use crate::lsp::{self, PROTOLS_FILE, VSCODE_SETTINGS};
+
+    let include_paths: Vec<String> = std::iter::once(PackageStore::PROTO_VENDOR_PATH.to_owned())
+        .chain(
+            config
+                .include_paths()
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned()),
+        )
+        .collect();
+
+    let existing = fs::read_to_string(PROTOLS_FILE).await.ok();
+
+    fs::write(
+        PROTOLS_FILE,
+        lsp::protols(existing.as_deref(), &include_paths)?,
+    )
+    .await
+    .into_diagnostic()
+    .wrap_err(miette!("failed to write {PROTOLS_FILE}"))?;
+
+    if Path::new(".vscode").is_dir() {
+        let existing = fs::read_to_string(VSCODE_SETTINGS).await.ok();
+
+        fs::write(
+            VSCODE_SETTINGS,
+            lsp::vscode(existing.as_deref(), &include_paths)?,
+        )
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to write {VSCODE_SETTINGS}"))?;
+    }
+
+    Ok(())
+}
+
+/// Uninstalls dependencies
+pub async fn uninstall() -> miette::Result<()> {
+    Ok(PackageStore::current().await?.clear().await?)
+}
+
+/// Removes installed dependencies, build output and cached packages
+///
+/// # Arguments
+/// * `targets` - What to remove, the dependencies and the build output if empty
+/// * `dry_run` - Only report what would be removed
+pub async fn clean(targets: CleanTargets, dry_run: bool) -> miette::Result<()> {
+    let targets = if targets.is_empty() {
+        CleanTargets::DEPS | CleanTargets::BUILD
+    } else {
+        targets
+    };
+
+    if targets.intersects(CleanTargets::DEPS | CleanTargets::BUILD) {
+        // never remove directories of something that is not a buffrs project
+        ensure!(
+            Manifest::exists().await?,
+            "no `{MANIFEST_FILE}` found in the current directory"
+        );
+    }
+
+    if targets.contains(CleanTargets::DEPS) {
+        if dry_run {
+            tracing::info!(":: would remove {}", PackageStore::PROTO_VENDOR_PATH);
+        } else {
+            PackageStore::current().await?.clear().await?;
+
+            tracing::info!(":: removed {}", PackageStore::PROTO_VENDOR_PATH);
+        }
+    }
+
+    let path = Path::new(PackageStore::PROTO_BUILD_PATH);
+
+    if targets.contains(CleanTargets::BUILD) && fs::try_exists(path).await.into_diagnostic()? {
+        if dry_run {
+            tracing::info!(":: would remove {}", path.display());
+        } else {
+            fs::remove_dir_all(path)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to remove {}", path.display()))?;
+
+            tracing::info!(":: removed {}", path.display());
+        }
+    }
+
+    if targets.contains(CleanTargets::CACHE) {
+        let cache = Cache::open().await?;
+
+        if dry_run {
+            tracing::info!(":: would clear the cache in {}", cache.path().display());
+        } else {
+            cache.clear().await?;
+
+            tracing::info!(":: cleared the cache in {}", cache.path().display());
+        }
+    }
+
+    if dry_run {
+        tracing::warn!(":: aborting clean due to dry run");
+    }
+
+    Ok(())
+}
+
+/// Generates markdown documentation for the package and its dependencies
+///
+/// The documentation is written to `proto/build/doc`, replacing previous output.
+pub async fn doc() -> miette::Result<()> {
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+
+    // the local package is documented through its populated copy in the vendor directory
+    let files = match manifest.package {
+        Some(ref pkg) => {
+            store.populate(pkg).await?;
+            store.collect(&store.proto_vendor_path(), true).await
+        }
+        None => store.collect(&store.proto_path(), true).await,
+    };
+
+    let mut docs = Documentation::default();
+
+    for file in &files {
+        let contents = fs::read_to_string(file)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {}", file.display()))?;
+
+        docs.add(&contents);
+    }
+
+    docs.write(Path::new(DOC_PATH)).await?;
+
+    tracing::info!(":: generated documentation in {DOC_PATH}");
+
+    Ok(())
+}
+
+/// Kind of files generated by `buffrs generate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Emit {
+    /// `BUILD.bazel` files declaring `proto_library` targets
+    Bazel,
+    /// A Maven project packaging the protos and descriptor set of the package
+    #[cfg(feature = "validation")]
+    Maven,
+    /// An npm package compiling TypeScript bindings of the package to ES modules and CommonJS
+    Npm,
+    /// A Kubernetes ConfigMap or Secret holding the descriptor set of the package
+    #[cfg(feature = "validation")]
+    Kubernetes,
+}
+
+/// Settings of `buffrs generate` for specific kinds of files
+#[derive(Debug, Clone, Default)]
+pub struct GenerateOptions {
+    /// Maven group of the package and its dependencies
+    pub group: String,
+    /// npm scope of the package and its dependencies, without the leading `@`
+    pub scope: Option<String>,
+    /// Directory of the TypeScript bindings packaged for npm
+    pub bindings: Option<PathBuf>,
+    /// Name of the Kubernetes resource, defaults to `<package>-descriptors`
+    pub name: Option<String>,
+    /// Namespace of the Kubernetes resource
+    pub namespace: Option<String>,
+    /// Emit a Kubernetes Secret instead of a ConfigMap
+    pub secret: bool,
+    /// Only check that the files on disk are up to date instead of writing them
+    pub check: bool,
+}
+
+/// Generates files integrating the package and its installed dependencies into other build
+/// systems
+///
+/// # Arguments
+/// * `emit` - The kind of files to generate
+/// * `options` - Settings for the kind of files
+///
+/// The generated files are described by a build manifest in
+/// [`BUILD_MANIFEST_PATH`](crate::generated::BUILD_MANIFEST_PATH). With `check` set, nothing is
+/// written and the command fails if the files on disk are stale.
+pub async fn generate(emit: Emit, options: &GenerateOptions) -> miette::Result<()> {
+    let project = env::current_dir().into_diagnostic()?;
+
+    let generated = match emit {
+        Emit::Bazel => generate_bazel(&project).await?,
+        #[cfg(feature = "validation")]
+        Emit::Maven => generate_maven(&project, &options.group).await?,
+        Emit::Npm => {
+            generate_npm(
+                &project,
+                options.scope.as_deref(),
+                options.bindings.as_deref(),
+            )
+            .await?
+        }
+        #[cfg(feature = "validation")]
+        Emit::Kubernetes => generate_kubernetes(&project, options).await?,
+    };
+
+    if options.check {
+        let stale = generated.stale().await?;
+
+        for path in &stale {
+            tracing::info!(":: stale {}", path.display());
+        }
+
+        ensure!(
+            stale.is_empty(),
+            help = format!("run `buffrs generate --emit {emit}` to update them"),
+            "{} generated file(s) are out of date",
+            stale.len()
+        );
+
+        tracing::info!(":: generated {emit} files are up to date");
+
+        return Ok(());
+    }
+
+    generated.write().await?;
+
+    match emit {
+        #[cfg(feature = "validation")]
+        Emit::Maven => {
+            tracing::info!(":: generated Maven project in {}", crate::maven::MAVEN_PATH)
+        }
+        Emit::Npm => tracing::info!(":: generated npm package in {}", crate::npm::NPM_PATH),
+        _ => {
+            for file in generated.files() {
+                tracing::info!(":: generated {}", file.path.display());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Versions of the dependencies of a package, as locked or, for local dependencies, installed
+async fn installed_versions(
+    manifest: &Manifest,
+    store: &PackageStore,
+) -> miette::Result<Vec<(PackageName, Version)>> {
+    let lockfile = Lockfile::read_or_default().await?;
+    let mut versions = Vec::new();
+
+    for dependency in &manifest.dependencies {
+        let name = &dependency.package;
+
+        let version = match lockfile.get(name) {
+            Some(locked) => locked.version.clone(),
+            None => {
+                let installed = store.resolve(name).await.wrap_err(miette!(
+                    help = "run `buffrs install` to install the dependencies",
+                    "`{name}` is not installed"
+                ))?;
+
+                installed
+                    .package
+                    .ok_or(miette!(
+                        "the local dependency `{name}` does not declare a package"
+                    ))?
+                    .version
+            }
+        };
+
+        versions.push((name.clone(), version));
+    }
+
+    Ok(versions)
+}
+
+/// Copies files into a directory, keeping their paths relative to `base`
+async fn copy_files(files: &[PathBuf], base: &Path, target: &Path) -> miette::Result<()> {
+    for file in files {
+        let destination = target.join(file.strip_prefix(base).into_diagnostic()?);
+
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to create {}", parent.display()))?;
+        }
+
+        fs::copy(file, &destination)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to copy {}", file.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Generates a `BUILD.bazel` file for the local package and every installed dependency
+///
+/// Labels and import prefixes are relative to the Bazel workspace containing the project, which
+/// is found by its `MODULE.bazel` or `WORKSPACE` file.
+async fn generate_bazel(project: &Path) -> miette::Result<Generated> {
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+
+    let workspace = Workspace::locate(project).unwrap_or_else(|| {
+        tracing::warn!(":: no Bazel workspace found, assuming the project is its root");
+        Workspace::default()
+    });
+
+    let vendor = Path::new(PackageStore::PROTO_VENDOR_PATH);
+    let label = |name: &PackageName| workspace.label(&vendor.join(&**name), name);
+    let labels = |dependencies: &[Dependency]| {
+        let mut labels: Vec<_> = dependencies.iter().map(|d| label(&d.package)).collect();
+        labels.sort();
+        labels.dedup();
+        labels
+    };
+
+    let mut generated = Generated::new(project, &Emit::Bazel.to_string());
+
+    let local = store.collect(&store.proto_path(), false).await;
+
+    if !local.is_empty() {
+        let dir = Path::new(PackageStore::PROTO_PATH);
+        let package = manifest.package.as_ref().map(|package| &package.name);
+
+        let target = ProtoLibrary {
+            name: package
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| PackageStore::PROTO_PATH.to_owned()),
+            srcs: ProtoLibrary::sources(&store.proto_path(), &local),
+            deps: labels(&manifest.dependencies),
+            strip_import_prefix: workspace.path(dir),
+            import_prefix: package.map(|name| name.to_string()),
+        };
+
+        generated.add(dir.join(BUILD_FILE), target.render(), package, &local);
+    }
+
+    let mut pending: Vec<_> = manifest
+        .dependencies
+        .iter()
+        .map(|dependency| dependency.package.clone())
+        .collect();
+    let mut visited = HashSet::new();
+
+    while let Some(name) = pending.pop() {
+        if !visited.insert(name.clone()) {
+            continue;
+        }
+
+        let installed = store.resolve(&name).await.wrap_err(miette!(
+            help = "run `buffrs install` to install the dependencies",
+            "`{name}` is not installed"
+        ))?;
+
+        let path = store.locate(&name);
+        let protos = store.collect(&path, true).await;
+
+        let target = ProtoLibrary {
+            name: name.to_string(),
+            srcs: ProtoLibrary::sources(&path, &protos),
+            deps: labels(&installed.dependencies),
+            strip_import_prefix: workspace.path(vendor),
+            import_prefix: None,
+        };
+
+        generated.add(
+            vendor.join(&*name).join(BUILD_FILE),
+            target.render(),
+            Some(&name),
+            &protos,
+        );
+
+        pending.extend(
+            installed
+                .dependencies
+                .into_iter()
+                .map(|dependency| dependency.package),
+        );
+    }
+
+    Ok(generated)
+}
+
+/// Generates a Maven project for the local package into [`MAVEN_PATH`]
+///
+/// The project contains the protos of the package, its descriptor set including all imports and
+/// a POM declaring the installed versions of its dependencies as artifacts of the same group.
+#[cfg(feature = "validation")]
+async fn generate_maven(project: &Path, group: &str) -> miette::Result<Generated> {
+    use crate::{
+        maven::{Pom, MAVEN_PATH, PROTO_DIR, RESOURCES_DIR},
+        validation::Validator,
+    };
+
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+
+    let package = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to generate a Maven project"
+    ))?;
+
+    let dependencies = installed_versions(&manifest, &store).await?;
+
+    store.populate(package).await?;
+
+    let vendor = store.proto_vendor_path();
+    let files = store.populated_files(package).await;
+
+    let mut validator = Validator::new(&vendor, package);
+
+    for file in &files {
+        validator.input(file);
+    }
+
+    let descriptors = validator
+        .descriptor_set()
+        .wrap_err(miette!("failed to compile `{}`", package.name))?;
+
+    let root = Path::new(MAVEN_PATH);
+    let name = Some(&package.name);
+    let mut generated = Generated::owning(project, &Emit::Maven.to_string(), root);
+
+    generated
+        .copy(&files, &vendor, &root.join(PROTO_DIR), name)
+        .await?;
+
+    generated.add(
+        root.join(RESOURCES_DIR)
+            .join(format!("{}.desc", package.name)),
+        descriptors,
+        name,
+        &files,
+    );
+
+    let pom = Pom {
+        group: group.to_owned(),
+        package: package.clone(),
+        dependencies,
+    };
+
+    generated.add(root.join("pom.xml"), pom.render(), name, &files);
+
+    Ok(generated)
+}
+
+/// Generates a Kubernetes resource holding the descriptor set of the local package into
+/// [`KUBERNETES_PATH`]
+///
+/// The descriptor set includes all imports, so the resource can be mounted directly into
+/// proxies such as the gRPC-JSON transcoder of Envoy.
+#[cfg(feature = "validation")]
+async fn generate_kubernetes(
+    project: &Path,
+    options: &GenerateOptions,
+) -> miette::Result<Generated> {
+    use crate::{
+        kubernetes::{DescriptorResource, KUBERNETES_PATH},
+        validation::Validator,
+    };
+
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+
+    let package = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to generate Kubernetes resources"
+    ))?;
+
+    store.populate(package).await?;
+
+    let vendor = store.proto_vendor_path();
+    let files = store.populated_files(package).await;
+
+    let mut validator = Validator::new(&vendor, package);
+
+    for file in &files {
+        validator.input(file);
+    }
+
+    let descriptors = validator
+        .descriptor_set()
+        .wrap_err(miette!("failed to compile `{}`", package.name))?;
+
+    let resource = DescriptorResource {
+        name: options.name.clone(),
+        namespace: options.namespace.clone(),
+        secret: options.secret,
+        package: package.clone(),
+        descriptors,
+    };
+
+    let root = Path::new(KUBERNETES_PATH);
+    let mut generated = Generated::new(project, &Emit::Kubernetes.to_string());
+
+    generated.add(
+        root.join(format!("{}.yaml", resource.name())),
+        resource.render()?,
+        Some(&package.name),
+        &files,
+    );
+
+    Ok(generated)
+}
+
+/// Generates an npm package wrapping TypeScript bindings of the local package into [`NPM_PATH`]
+///
+/// The bindings are generated by other tools, e.g. `protoc-gen-es`, into the `bindings`
+/// directory. They are packaged together with the protos, the npm packages they import become
+/// peer dependencies and the dependencies of the package become dependencies at their installed
+/// versions.
+async fn generate_npm(
+    project: &Path,
+    scope: Option<&str>,
+    bindings: Option<&Path>,
+) -> miette::Result<Generated> {
+    use crate::npm::{self, PackageJson, NPM_PATH, PROTO_DIR, SOURCE_DIR};
+
+    let bindings = bindings.ok_or(miette!(
+        help = "generate TypeScript bindings, e.g. with protoc-gen-es, and pass their directory",
+        "`--bindings` is required to generate an npm package"
+    ))?;
+
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+
+    let package = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to generate an npm package"
+    ))?;
+
+    let dependencies = installed_versions(&manifest, &store).await?;
+
+    let sources: Vec<PathBuf> = WalkDir::new(bindings)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ts"))
+        .collect();
+
+    ensure!(
+        !sources.is_empty(),
+        "no TypeScript bindings were found in {}",
+        bindings.display()
+    );
+
+    let mut runtime = BTreeSet::new();
+
+    for source in &sources {
+        let contents = fs::read_to_string(source)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {}", source.display()))?;
+
+        runtime.extend(npm::imports(&contents));
+    }
+
+    let mut manifest = PackageJson {
+        scope: scope.map(str::to_owned),
+        package: package.clone(),
+        dependencies,
+        runtime: BTreeSet::new(),
+    };
+
+    // bindings importing those of the dependencies are served by the regular dependencies
+    let provided: Vec<_> = manifest
+        .dependencies
+        .iter()
+        .map(|(name, _)| manifest.name(name))
+        .collect();
+
+    manifest.runtime = runtime
+        .into_iter()
+        .filter(|name| !provided.contains(name))
+        .collect();
+
+    store.populate(package).await?;
+
+    let vendor = store.proto_vendor_path();
+    let protos = store.populated_files(package).await;
+
+    let root = Path::new(NPM_PATH);
+    let name = Some(&package.name);
+    let mut generated = Generated::owning(project, &Emit::Npm.to_string(), root);
+
+    // the bindings are not generated from the protos by buffrs, but stem from them
+    for source in &sources {
+        let path = root
+            .join(SOURCE_DIR)
+            .join(source.strip_prefix(bindings).into_diagnostic()?);
+
+        let contents = fs::read(source)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {}", source.display()))?;
+
+        generated.add(path, contents, name, &protos);
+    }
+
+    generated
+        .copy(&protos, &vendor, &root.join(PROTO_DIR), name)
+        .await?;
+
+    // node treats the `.js` files of the ES module build as such because of this marker
+    let files = [
+        (root.join("package.json"), manifest.render()),
+        (root.join("tsconfig.esm.json"), npm::tsconfig_esm()),
+        (root.join("tsconfig.cjs.json"), npm::tsconfig_cjs()),
+        (
+            root.join("dist").join("esm").join("package.json"),
+            json!({ "type": "module" }),
+        ),
+    ];
+
+    for (path, contents) in files {
+        let contents = serde_json::to_string_pretty(&contents).into_diagnostic()?;
+
+        generated.add(path, contents + "\n", name, &protos);
+    }
+
+    Ok(generated)
+}
+
+/// How often `buffrs watch` looks for changes
+#[cfg(feature = "validation")]
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Modification times and sizes of the files watched by `buffrs watch`
+#[cfg(feature = "validation")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Snapshot(BTreeMap<PathBuf, (Option<SystemTime>, u64)>);
+
+impl Snapshot {
+    /// Records the local protos, the manifest and the custom lint rules of a project
+    fn take(root: &Path) -> Self {
+        let vendor = root.join(PackageStore::PROTO_VENDOR_PATH);
+
+        let protos = WalkDir::new(root.join(PackageStore::PROTO_PATH))
+            .into_iter()
+            .filter_entry(|entry| entry.path() != vendor)
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.path().extension() == Some("proto".as_ref()))
+            .map(|entry| entry.into_path());
+
+        let files = [MANIFEST_FILE, crate::validation::LINT_FILE]
+            .into_iter()
+            .map(|file| root.join(file))
+            .filter(|file| file.is_file());
+
+        Self(
+            protos
+                .chain(files)
+                .filter_map(|file| {
+                    let metadata = file.metadata().ok()?;
+                    Some((file, (metadata.modified().ok(), metadata.len())))
+                })
+                .collect(),
+        )
+    }
+
+    /// The files added, modified or removed since an earlier snapshot
+    fn changes<'a>(&'a self, earlier: &'a Self) -> BTreeSet<&'a Path> {
+        let modified = self
+            .0
+            .iter()
+            .filter(|(file, stamp)| earlier.0.get(*file) != Some(stamp))
+            .map(|(file, _)| file.as_path());
+
+        let removed = earlier
+            .0
+            .keys()
+            .filter(|file| !self.0.contains_key(*file))
+            .map(PathBuf::as_path);
+
+        modified.chain(removed).collect()
+    }
+}
+
+/// Re-runs lint and regenerates the installed protos whenever the package changes
+///
+/// Changes are debounced, so that saving many files at once triggers a single run. Only the steps
+/// affected by a change are repeated: a changed manifest reinstalls all dependencies, while
+/// changed protos only refresh the vendored copy of the package itself.
+///
+/// # Arguments
+///  * `doc` - Also regenerate the documentation on every change
+///  * `debounce` - How long the files have to stay unchanged before running
+///  * `config` - The configuration
+#[cfg(feature = "validation")]
+pub async fn watch(
+    doc: bool,
+    debounce: Duration,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<()> {
+    let root = env::current_dir().into_diagnostic()?;
+    let manifest_path = root.join(MANIFEST_FILE);
+
+    let mut snapshot = Snapshot::take(&root);
+    // the first run installs everything
+    let mut changes: BTreeSet<PathBuf> = [manifest_path.clone()].into();
+
+    loop {
+        // wait until the files settled, so that a single save or checkout triggers a single run
+        loop {
+            tokio::time::sleep(debounce).await;
+
+            // stopping is the regular way to end watching
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+
+            let settled = Snapshot::take(&root);
+
+            if settled == snapshot {
+                break;
+            }
+
+            changes.extend(
+                settled
+                    .changes(&snapshot)
+                    .into_iter()
+                    .map(Path::to_path_buf),
+            );
+            snapshot = settled;
+        }
+
+        if !changes.is_empty() {
+            let reinstall = changes.contains(&manifest_path);
+
+            changes.clear();
+
+            if let Err(report) = rerun(reinstall, doc, config, cancel).await {
+                eprintln!("{report:?}");
+            }
+
+            tracing::info!(":: watching for changes, press ctrl-c to stop");
+        }
+
+        // no need to debounce until the first change
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            if cancel.is_cancelled() {
+                return Ok(());
+            }
+
+            let current = Snapshot::take(&root);
+
+            if current != snapshot {
+                changes.extend(
+                    current
+                        .changes(&snapshot)
+                        .into_iter()
+                        .map(Path::to_path_buf),
+                );
+                snapshot = current;
+                break;
+            }
+        }
+    }
+}
+
+/// Runs the steps of `buffrs watch` affected by a change
+#[cfg(feature = "validation")]
+async fn rerun(reinstall: bool, doc: bool, config: &Config, cancel: &Cancel) -> miette::Result<()> {
+    let _lock = FileLock::project(&env::current_dir().into_diagnostic()?).await?;
+
+    let manifest = Manifest::read().await?;
+
+    if reinstall {
+        install(
+            InstallMode::All,
+            GenerationFlags::empty(),
+            false,
+            false,
+            OutputFormat::Human,
+            config,
+            cancel,
+        )
+        .await?;
+    } else if let Some(ref pkg) = manifest.package {
+        PackageStore::current().await?.populate(pkg).await?;
+    }
+
+    let result = lint(false, None, OutputFormat::Human, config).await?;
+
+    match result["diagnostics"].as_array().map(Vec::len).unwrap_or(0) {
+        0 => tracing::info!(":: no lint diagnostics"),
+        count => tracing::warn!(":: found {count} lint diagnostic(s)"),
+    }
+
+    if doc {
+        self::doc().await?;
+    }
+
+    Ok(())
+}
+
+/// Lists all protobuf files managed by Buffrs to stdout
+///
+/// # Arguments
+/// * `only` - Restrict the output to these packages (all packages if empty)
+/// * `exclude` - Packages whose files are left out
+/// * `format` - The output format
+pub async fn list(
+    only: &[PackageName],
+    exclude: &[PackageName],
+    format: OutputFormat,
+) -> miette::Result<serde_json::Value> {
+    let store = PackageStore::current().await?;
+    let manifest = Manifest::read().await?;
+
+    if let Some(ref pkg) = manifest.package {
+        store.populate(pkg).await?;
+    }
+
+    let only = (!only.is_empty()).then_some(only);
+    let protos = store.collect_packages(only, exclude).await?;
+
+    // Canonicalize the protos
+    let protos = protos
+        .into_iter()
+        .map(|proto| {
+            proto
+                .canonicalize()
+                .into_diagnostic()
+                .wrap_err(miette!("failed to canonicalize proto path"))
+        })
+        .collect::<miette::Result<Vec<_>>>()?;
+
+    let cwd = {
+        let cwd = std::env::current_dir()
+            .into_diagnostic()
+            .wrap_err(miette!("failed to get current directory"))?;
+
+        fs::canonicalize(cwd)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to canonicalize current directory"))?
+    };
+
+    let protos = protos
+        .iter()
+        .map(|proto| {
+            proto
+                .strip_prefix(&cwd)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to transform protobuf path"))
+        })
+        .collect::<miette::Result<Vec<_>>>()?;
+
+    let result = json!({ "files": protos });
+
+    match format {
+        OutputFormat::Human => protos.iter().for_each(|rel| print!("{} ", rel.display())),
+        OutputFormat::Json => print_json(&result),
+    }
+
+    Ok(result)
+}
+
+/// State of an installed dependency relative to the lockfile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum InstallStatus {
+    /// Installed at the locked version, with the files of the locked package
+    Verified,
+    /// Installed at the locked version, but the locked package is not cached to compare with
+    Unverified,
+    /// Installed at the locked version, but files were modified, added or removed since
+    Modified,
+    /// Installed at another version than the locked one
+    Outdated,
+    /// Locked, but not installed
+    Missing,
+    /// Installed, but neither locked nor a local dependency
+    Orphaned,
+    /// The package itself or a local dependency, which are installed without being locked
+    Local,
+}
+
+impl InstallStatus {
+    /// Whether the installation matches the lockfile
+    pub fn is_consistent(&self) -> bool {
+        matches!(self, Self::Verified | Self::Unverified | Self::Local)
+    }
+}
+
+/// Lists the installed dependencies with their state relative to the lockfile
+///
+/// # Arguments
+/// * `only` - Restrict the output to these packages (all packages if empty)
+/// * `exclude` - Packages that are left out
+/// * `format` - The output format
+pub async fn list_installed(
+    only: &[PackageName],
+    exclude: &[PackageName],
+    format: OutputFormat,
+) -> miette::Result<serde_json::Value> {
+    let store = PackageStore::current().await?;
+    let manifest = Manifest::read().await?;
+    let lockfile = Lockfile::read_or_default().await?;
+    let cache = Cache::open().await?;
+
+    // the package itself and local dependencies are vendored, but never locked
+    let local: Vec<&PackageName> = manifest
+        .package
+        .iter()
+        .map(|package| &package.name)
+        .chain(
+            manifest
+                .dependencies
+                .iter()
+                .filter(|dependency| matches!(dependency.manifest, DependencyManifest::Local(_)))
+                .map(|dependency| &dependency.package),
+        )
+        .collect();
+
+    let mut installed = BTreeMap::new();
+
+    if let Ok(mut entries) = fs::read_dir(store.proto_vendor_path()).await {
+        while let Some(entry) = entries.next_entry().await.into_diagnostic()? {
+            let Ok(name) = PackageName::new(entry.file_name().to_string_lossy()) else {
+                continue;
+            };
+
+            let version = store
+                .resolve(&name)
+                .await
+                .ok()
+                .and_then(|manifest| manifest.package)
+                .map(|package| package.version);
+
+            installed.insert(name, version);
+        }
+    }
+
+    let names: BTreeSet<PackageName> = installed
+        .keys()
+        .cloned()
+        .chain(lockfile.packages().map(|locked| locked.name.clone()))
+        .filter(|name| only.is_empty() || only.contains(name))
+        .filter(|name| !exclude.contains(name))
+        .collect();
+
+    let mut packages = Vec::new();
+    let mut verifications = Vec::new();
+
+    for name in names {
+        let locked = lockfile.get(&name);
+        let version = installed.get(&name);
+
+        let status = match (locked, version) {
+            (Some(_), None) => InstallStatus::Missing,
+            (None, Some(_)) if local.contains(&&name) => InstallStatus::Local,
+            (None, _) => InstallStatus::Orphaned,
+            (Some(locked), Some(version)) if version.as_ref() != Some(&locked.version) => {
+                InstallStatus::Outdated
+            }
+            (Some(locked), Some(_)) => match cache.get(locked.into()).await? {
+                None => InstallStatus::Unverified,
+                Some(package) => {
+                    // awaited below, so that all packages are verified in parallel
+                    let verification = package.verify_unpacked(&store.locate(&name));
+                    verifications.push((packages.len(), verification));
+
+                    InstallStatus::Verified
+                }
+            },
+        };
+
+        packages.push((name, version.cloned().flatten(), locked, status, Vec::new()));
+    }
+
+    for (index, verification) in verifications {
+        if let Err(mismatch) = verification.await? {
+            let (_, _, _, status, files) = &mut packages[index];
+
+            *status = InstallStatus::Modified;
+            *files = mismatch.files;
+        }
+    }
+
+    let result = json!({
+        "packages": packages
+            .iter()
+            .map(|(name, version, locked, status, files)| {
+                json!({
+                    "name": name.to_string(),
+                    "version": version.as_ref().map(ToString::to_string),
+                    "locked_version": locked.map(|locked| locked.version.to_string()),
+                    "status": status.to_string(),
+                    "files": files,
+                })
+            })
+            .collect::<Vec<_>>(),
+    });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+
+        return Ok(result);
+    }
+
+    let width = packages
+        .iter()
+        .map(|(name, ..)| name.len())
+        .max()
+        .unwrap_or_default();
+
+    for (name, version, locked, status, files) in &packages {
+        let version = version
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "-".to_owned());
+
+        let details = match status {
+            InstallStatus::Outdated => locked.map(|locked| format!(" (locked {})", locked.version)),
+            InstallStatus::Modified => Some(format!(" ({})", files.join(", "))),
+            _ => None,
+        };
+
+        println!(
+            "{name:<width$}  {version:<10}  {status}{}",
+            details.unwrap_or_default()
+        );
+    }
+
+    if packages
+        .iter()
+        .any(|(.., status, _)| !status.is_consistent())
+    {
+        tracing::warn!(
+            ":: the installed dependencies do not match {LOCKFILE}, run `buffrs install` to restore them"
+        );
+    }
+
+    Ok(result)
+}
+
+/// Number of files, lines and bytes of a set of files
+#[derive(Debug, Default, Clone, Copy)]
+struct Tally {
+    files: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+impl Tally {
+    /// Counts the lines and bytes of the given files
+    async fn of(files: &[PathBuf]) -> miette::Result<Self> {
+        let mut tally = Self::default();
+
+        for file in files {
+            let contents = fs::read(file)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", file.display()))?;
+
+            tally.files += 1;
+            tally.lines += String::from_utf8_lossy(&contents).lines().count();
+            tally.bytes += contents.len() as u64;
+        }
+
+        Ok(tally)
+    }
+
+    /// Counts the files and bytes below a directory, which may not exist
+    fn of_dir(dir: &Path) -> Self {
+        WalkDir::new(dir)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.metadata().ok())
+            .fold(Self::default(), |tally, metadata| Self {
+                files: tally.files + 1,
+                lines: tally.lines,
+                bytes: tally.bytes + metadata.len(),
+            })
+    }
+}
+
+/// Summarizes the size of the project, its dependencies and the cache
+///
+/// # Arguments
+///  * `published` - Also query the registries for the publish dates of the locked packages
+///  * `format` - The output format
+///  * `config` - The configuration providing the network settings
+pub async fn stats(
+    published: bool,
+    format: OutputFormat,
+    config: &Config,
+) -> miette::Result<serde_json::Value> {
+    let store = PackageStore::current().await?;
+    let manifest = Manifest::read().await?;
+    let lockfile = Lockfile::read_or_default().await?;
+    let cache = Cache::open().await?;
+
+    let protos = Tally::of(&store.collect(&store.proto_path(), false).await).await?;
+    let vendored = Tally::of(&store.collect(&store.proto_vendor_path(), true).await).await?;
+    let cached = Tally::of_dir(cache.path());
+
+    let credentials = if published {
+        Some(Credentials::load().await?)
+    } else {
+        None
+    };
+
+    let mut locked: Vec<&LockedPackage> = lockfile.packages().collect();
+    locked.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut packages = Vec::new();
+
+    for package in locked {
+        let dir = store.locate(&package.name);
+
+        let size = fs::try_exists(&dir)
+            .await
+            .unwrap_or(false)
+            .then(|| Tally::of_dir(&dir).bytes);
+
+        let date = match &credentials {
+            Some(credentials) => {
+                let date = async {
+                    Artifactory::new(&package.registry, credentials)?
+                        .with_network(config.network())?
+                        .published(&package.repository, &package.name, &package.version)
+                        .await
+                }
+                .await;
+
+                // a single unreachable registry should not hide the other statistics
+                match date {
+                    Ok(date) => Some(date),
+                    Err(error) => {
+                        tracing::warn!(
+                            ":: failed to retrieve the publish date of {}@{}: {error}",
+                            package.name,
+                            package.version
+                        );
+
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        packages.push((package, size, date));
+    }
+
+    let tally =
+        |tally: &Tally| json!({ "files": tally.files, "lines": tally.lines, "bytes": tally.bytes });
+
+    let result = json!({
+        "dependencies": manifest.dependencies.len(),
+        "locked": packages.len(),
+        "protos": tally(&protos),
+        "vendored": tally(&vendored),
+        "cache": { "files": cached.files, "bytes": cached.bytes },
+        "packages": packages
+            .iter()
+            .map(|(package, size, date)| {
+                json!({
+                    "name": package.name.to_string(),
+                    "version": package.version.to_string(),
+                    "size": size,
+                    "published": date,
+                })
+            })
+            .collect::<Vec<_>>(),
+    });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+
+        return Ok(result);
+    }
+
+    let files = |tally: &Tally| {
+        format!(
+            "{} ({} lines, {})",
+            tally.files,
+            tally.lines,
+            format_size(tally.bytes)
+        )
+    };
+
+    println!("dependencies      {}", manifest.dependencies.len());
+    println!("locked packages   {}", packages.len());
+    println!("proto files       {}", files(&protos));
+    println!("vendored files    {}", files(&vendored));
+    println!(
+        "cache             {} in {} files",
+        format_size(cached.bytes),
+        cached.files
+    );
+
+    if !packages.is_empty() {
+        println!();
+    }
+
+    let width = packages
+        .iter()
+        .map(|(package, ..)| package.name.len())
+        .max()
+        .unwrap_or_default();
+
+    for (package, size, date) in &packages {
+        let size = size.map(format_size).unwrap_or_else(|| "-".to_owned());
+
+        println!(
+            "{:<width$}  {:<10}  {size:<10}  {}",
+            package.name,
+            package.version.to_string(),
+            date.as_deref().unwrap_or("-")
+        );
+    }
+
+    Ok(result)
+}
+
+/// Outcome of a single check of `buffrs doctor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+enum Health {
+    /// Nothing to do
+    Ok,
+    /// Buffrs works, but some commands may fail or behave unexpectedly
+    Warn,
+    /// Buffrs does not work until this is fixed
+    Fail,
+}
+
+/// Result of a single check of `buffrs doctor`, with an actionable fix if it did not pass
+#[derive(Debug, Clone)]
+struct Checkup {
+    check: &'static str,
+    health: Health,
+    message: String,
+    fix: Option<String>,
+}
+
+impl Checkup {
+    fn ok(check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            health: Health::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(check: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            check,
+            health: Health::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(check: &'static str, message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            health: Health::Fail,
+            ..Self::warn(check, message, fix)
+        }
+    }
+}
+
+/// Oldest protoc release supporting `optional` fields in proto3 without experimental flags
+const PROTOC_MINIMUM: (u64, u64) = (3, 15);
+
+/// Checks that protoc, which code generators like `tonic-build` invoke, is installed
+async fn check_protoc() -> Checkup {
+    const CHECK: &str = "protoc";
+
+    let protoc = env::var("PROTOC").unwrap_or_else(|_| "protoc".to_owned());
+
+    let output = tokio::process::Command::new(&protoc)
+        .arg("--version")
+        .output()
+        .await;
+
+    let version = match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().to_owned()
+        }
+        _ => {
+            return Checkup::warn(
+                CHECK,
+                format!("`{protoc}` could not be run, code generation will fail"),
+                "install protoc from https://github.com/protocolbuffers/protobuf/releases or set PROTOC to its path",
+            )
+        }
+    };
+
+    // e.g. `libprotoc 3.21.12` or `libprotoc 25.1`
+    let mut numbers = version
+        .rsplit(' ')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|number| number.parse::<u64>().ok());
+
+    match (numbers.next().flatten(), numbers.next().flatten()) {
+        (Some(major), Some(minor)) if (major, minor) < PROTOC_MINIMUM => Checkup::warn(
+            CHECK,
+            format!("{version} does not support optional fields in proto3"),
+            format!(
+                "upgrade protoc to {}.{} or later",
+                PROTOC_MINIMUM.0, PROTOC_MINIMUM.1
+            ),
+        ),
+        _ => Checkup::ok(CHECK, version),
+    }
+}
+
+/// Whether files can be created in a directory
+async fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(".buffrs-doctor");
+
+    let writable = fs::write(&probe, b"").await.is_ok();
+
+    fs::remove_file(&probe).await.ok();
+
+    writable
+}
+
+/// Checks that the cache can be opened and written to
+async fn check_cache() -> Checkup {
+    const CHECK: &str = "cache";
+
+    let cache = match Cache::open().await {
+        Ok(cache) => cache,
+        Err(error) => {
+            return Checkup::fail(
+                CHECK,
+                format!("failed to open the cache: {error}"),
+                "point BUFFRS_CACHE to a writable directory",
+            )
+        }
+    };
+
+    if !is_writable(cache.path()).await {
+        return Checkup::fail(
+            CHECK,
+            format!("{} is not writable", cache.path().display()),
+            "fix the permissions of the cache or point BUFFRS_CACHE to a writable directory",
+        );
+    }
+
+    let tally = Tally::of_dir(cache.path());
+
+    Checkup::ok(
+        CHECK,
+        format!("{} in {} files", format_size(tally.bytes), tally.files),
+    )
+}
+
+/// Checks the stored credentials against the registries of the project
+async fn check_credentials(mut registries: BTreeSet<RegistryUri>, config: &Config) -> Vec<Checkup> {
+    const CHECK: &str = "credentials";
+
+    let credentials = match Credentials::read().await {
+        Ok(credentials) => credentials.unwrap_or_default(),
+        Err(error) => {
+            return vec![Checkup::fail(
+                CHECK,
+                format!("{error:?}").trim().to_owned(),
+                "fix the credentials file or log in again with `buffrs login`",
+            )]
+        }
+    };
+
+    registries.extend(credentials.registry_tokens.keys().cloned());
+
+    if registries.is_empty() {
+        return vec![Checkup::ok(CHECK, "no registries in use")];
+    }
+
+    let mut checkups = Vec::new();
+
+    for registry in registries {
+        if !credentials.registry_tokens.contains_key(&registry) {
+            checkups.push(Checkup::warn(
+                CHECK,
+                format!("no token for {registry}, requests are anonymous"),
+                format!("run `buffrs login --registry {registry}` if the registry is private"),
+            ));
+
+            continue;
+        }
+
+        if env::var(BUFFRS_TESTSUITE_VAR).is_ok() {
+            checkups.push(Checkup::ok(CHECK, format!("token for {registry}")));
+
+            continue;
+        }
+
+        let ping = async {
+            Artifactory::new(&registry, &credentials)?
+                .with_network(config.network())?
+                .ping()
+                .await
+        }
+        .await;
+
+        checkups.push(match ping {
+            Ok(()) => Checkup::ok(CHECK, format!("{registry} accepts the token")),
+            Err(error) if ExitCode::of(&error) == ExitCode::Network => Checkup::fail(
+                CHECK,
+                format!("{registry} could not be reached"),
+                "check the registry url, your network connection and proxy settings",
+            ),
+            Err(error) => Checkup::fail(
+                CHECK,
+                format!("{registry} rejected the token: {error}"),
+                format!("run `buffrs login --registry {registry}` with a valid token"),
+            ),
+        });
+    }
+
+    checkups
+}
+
+/// Differences between the dependencies of a manifest and the packages locked for them
+fn lock_drift(manifest: &Manifest, lockfile: &Lockfile) -> Vec<String> {
+    manifest
+        .dependencies
+        .iter()
+        .filter_map(|dependency| match &dependency.manifest {
+            DependencyManifest::Remote(remote) => Some((&dependency.package, remote)),
+            DependencyManifest::Local(_) => None,
+        })
+        .filter_map(
+            |(name, remote)| match lockfile.get(name).map(|locked| &locked.version) {
+                None => Some(format!("{name} is not locked")),
+                Some(version) if !remote.version.matches(version) => Some(format!(
+                    "{name} is locked at {version}, which does not satisfy {}",
+                    remote.version
+                )),
+                Some(_) => None,
+            },
+        )
+        .collect()
+}
+
+/// Checks that the lockfile covers the dependencies of the manifest
+async fn check_lockfile(manifest: &Manifest) -> Checkup {
+    const CHECK: &str = "lockfile";
+
+    let lockfile = match Lockfile::read_or_default().await {
+        Ok(lockfile) => lockfile,
+        Err(error) => {
+            return Checkup::fail(
+                CHECK,
+                format!("{error:?}").trim().to_owned(),
+                format!("fix or delete {LOCKFILE} and run `buffrs install`"),
+            )
+        }
+    };
+
+    let drift = lock_drift(manifest, &lockfile);
+
+    if drift.is_empty() {
+        return Checkup::ok(
+            CHECK,
+            format!("{} packages locked", lockfile.packages().count()),
+        );
+    }
+
+    Checkup::warn(
+        CHECK,
+        drift.join(", "),
+        "run `buffrs install` to update the lockfile",
+    )
+}
+
+/// Checks that buffrs can write to its home and the project
+async fn check_permissions(cwd: &Path, project: bool) -> Vec<Checkup> {
+    const CHECK: &str = "permissions";
+
+    let mut checkups = Vec::new();
+
+    match crate::home() {
+        Ok(home) => {
+            fs::create_dir_all(&home).await.ok();
+
+            checkups.push(if is_writable(&home).await {
+                Checkup::ok(CHECK, format!("{} is writable", home.display()))
+            } else {
+                Checkup::fail(
+                    CHECK,
+                    format!("{} is not writable, logging in will fail", home.display()),
+                    "fix the permissions of the directory or point BUFFRS_HOME to a writable one",
+                )
+            });
+        }
+        Err(error) => checkups.push(Checkup::fail(
+            CHECK,
+            error.0.to_string(),
+            "set BUFFRS_HOME to a writable directory",
+        )),
+    }
+
+    if project {
+        let vendor = cwd.join(PackageStore::PROTO_VENDOR_PATH);
+
+        let dir = if fs::try_exists(&vendor).await.unwrap_or(false) {
+            vendor
+        } else {
+            cwd.to_path_buf()
+        };
+
+        if !is_writable(&dir).await {
+            checkups.push(Checkup::fail(
+                CHECK,
+                format!("{} is not writable, installing will fail", dir.display()),
+                "fix the permissions of the project directory",
+            ));
+        }
+    }
+
+    checkups
+}
+
+/// Diagnoses the environment and the current project, printing fixes for the detected problems
+///
+/// # Arguments
+///  * `format` - The output format
+///  * `config` - The configuration providing the network settings
+pub async fn doctor(format: OutputFormat, config: &Config) -> miette::Result<serde_json::Value> {
+    let cwd = env::current_dir().into_diagnostic()?;
+
+    let manifest = if Manifest::exists().await? {
+        Some(Manifest::read().await)
+    } else {
+        None
+    };
+
+    let mut checkups = vec![check_protoc().await, check_cache().await];
+
+    let registries = match &manifest {
+        Some(Ok(manifest)) => manifest
+            .dependencies
+            .iter()
+            .filter_map(|dependency| match &dependency.manifest {
+                DependencyManifest::Remote(remote) => Some(remote.registry.clone()),
+                DependencyManifest::Local(_) => None,
+            })
+            .collect(),
+        _ => BTreeSet::new(),
+    };
+
+    checkups.extend(check_credentials(registries, config).await);
+
+    match &manifest {
+        Some(Ok(manifest)) => {
+            checkups.push(Checkup::ok(
+                "manifest",
+                format!("{} dependencies declared", manifest.dependencies.len()),
+            ));
+            checkups.push(check_lockfile(manifest).await);
+        }
+        Some(Err(error)) => checkups.push(Checkup::fail(
+            "manifest",
+            format!("{error:?}").trim().to_owned(),
+            format!("fix the syntax of {MANIFEST_FILE}"),
+        )),
+        None => checkups.push(Checkup::ok(
+            "manifest",
+            format!("no {MANIFEST_FILE} in the current directory, skipping project checks"),
+        )),
+    }
+
+    checkups.extend(check_permissions(&cwd, manifest.is_some()).await);
+
+    let result = json!({
+        "checks": checkups
+            .iter()
+            .map(|checkup| {
+                json!({
+                    "check": checkup.check,
+                    "status": checkup.health.to_string(),
+                    "message": checkup.message,
+                    "fix": checkup.fix,
+                })
+            })
+            .collect::<Vec<_>>(),
+    });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+    } else {
+        for checkup in &checkups {
+            println!(
+                "{:<4}  {:<11}  {}",
+                checkup.health.to_string(),
+                checkup.check,
+                checkup.message
+            );
+
+            if let Some(fix) = &checkup.fix {
+                println!("{:<4}  {:<11}  fix: {fix}", "", "");
+            }
+        }
+    }
+
+    let failed = checkups
+        .iter()
+        .filter(|checkup| checkup.health == Health::Fail)
+        .count();
+
+    ensure!(failed == 0, "{failed} check(s) failed");
+
+    Ok(result)
+}
+
+/// Parses current package and validates rules.
+///
+/// # Arguments
+///  * `unused` - Also report definitions that are not used by any service
+///  * `fail_on` - Fail if diagnostics of this or a higher severity are found
+///  * `format` - The output format
+///  * `config` - The configuration providing additional include paths
+#[cfg(feature = "validation")]
+pub async fn lint(
+    unused: bool,
+    fail_on: Option<FailOn>,
+    format: OutputFormat,
+    config: &Config,
+) -> miette::Result<serde_json::Value> {
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+
+    let pkg = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared run the linter"
+    ))?;
+
+    store.populate(pkg).await?;
+
+    let custom = crate::validation::CustomRules::read().await?;
+
+    let violations = store
+        .validate(&manifest, config.include_paths(), &custom, unused)
+        .await?;
+
+    let mut diagnostics = Vec::new();
+    let mut severities = Vec::new();
+
+    let mut report = |report: miette::Report| {
+        if let OutputFormat::Human = format {
+            eprintln!("{report:?}");
+        }
+
+        severities.push(report.severity().unwrap_or(Severity::Error));
+        diagnostics.push(diagnostic_json(&*report));
+    };
+
+    violations
+        .into_iter()
+        .map(miette::Report::new)
+        .for_each(&mut report);
+
+    let imports = store
+        .check_imports(&manifest, config.include_paths())
+        .await?;
+
+    if let OutputFormat::Human = format {
+        for dependency in &imports.unused {
+            tracing::warn!(":: dependency `{dependency}` is declared but never imported");
+        }
+    }
+
+    imports
+        .undeclared
+        .into_iter()
+        .map(miette::Report::new)
+        .for_each(&mut report);
+
+    store
+        .check_cycles()
+        .await?
+        .into_iter()
+        .map(miette::Report::new)
+        .for_each(&mut report);
+
+    let protos = store.collect(&store.proto_path(), true).await;
+
+    store
+        .check_syntax(&protos, &manifest.lint.syntax)
+        .await?
+        .into_iter()
+        .map(miette::Report::new)
+        .for_each(&mut report);
+
+    let unused: Vec<_> = imports.unused.iter().map(ToString::to_string).collect();
+
+    let result = json!({
+        "diagnostics": diagnostics,
+        "unused_dependencies": unused,
+    });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+    }
+
+    if let Some(fail_on) = fail_on {
+        // unused dependencies are reported as warnings
+        let failing = severities
+            .into_iter()
+            .chain(unused.iter().map(|_| Severity::Warning))
+            .filter(|severity| fail_on.fails(*severity))
+            .count();
+
+        ensure!(
+            failing == 0,
+            code = "E0009",
+            "found {failing} diagnostic(s) at or above the `{fail_on}` severity"
+        );
+    }
+
+    Ok(result)
+}
+
+/// Prints the API changes between two versions of a package
+///
+/// # Arguments
+///  * `registry` - The registry to download published versions from
+///  * `repository` - The repository the package is published to
+///  * `from` - The baseline version, formatted as `<package>@<version>`
+///  * `to` - The version to compare with, defaults to the local package
+///  * `config` - The configuration providing additional include paths
+#[cfg(feature = "validation")]
+pub async fn diff(
+    registry: &RegistryUri,
+    repository: String,
+    from: &str,
+    to: Option<&str>,
+    config: &Config,
+) -> miette::Result<()> {
+    let from: PackageVersion = from.parse()?;
+    let to = to.map(str::parse::<PackageVersion>).transpose()?;
+
+    let credentials = Credentials::load().await?;
+    let store = PackageStore::current().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?.with_network(config.network())?;
+
+    // published versions are unpacked like vendored packages so that their imports resolve
+    let root = tempfile::Builder::new()
+        .prefix("buffrs-diff-")
+        .tempdir()
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create a temporary directory"))?;
+
+    let fetch = |version: &PackageVersion, path: PathBuf| {
+        let dependency = Dependency::new(
+            registry,
+            repository.clone(),
+            version.package.clone(),
+            VersionReq::STAR,
+        )
+        .with_version(&version.version);
+
+        let artifactory = &artifactory;
+
+        async move {
+            artifactory
+                .download(dependency)
+                .await?
+                .unpack(&path)
+                .await?;
+
+            Ok::<_, miette::Report>(path)
+        }
+    };
+
+    let baseline = fetch(&from, root.path().join("baseline").join(&*from.package)).await?;
+
+    let (current, label) = match &to {
+        Some(to) => (
+            fetch(to, root.path().join("current").join(&*to.package)).await?,
+            to.to_string(),
+        ),
+        None => {
+            let manifest = Manifest::read().await?;
+
+            let pkg = manifest.package.ok_or(miette!(
+                "a [package] section must be declared to compare against the local package"
+            ))?;
+
+            store.populate(&pkg).await?;
+
+            (
+                store.proto_vendor_path().join(&*pkg.name),
+                format!("the local {}", pkg.name),
+            )
+        }
+    };
+
+    let diff = store
+        .diff(&baseline, &current, config.include_paths())
+        .await?;
+
+    if diff.is_empty() {
+        tracing::info!(":: no API changes between {from} and {label}");
+    } else {
+        print!("{diff}");
+    }
+
+    Ok(())
+}
+
+/// Detects breaking changes of the populated local package against a published version
+#[cfg(feature = "validation")]
+#[allow(clippy::too_many_arguments)]
+async fn breaking_changes(
+    store: &PackageStore,
+    artifactory: &Artifactory,
+    registry: &RegistryUri,
+    repository: &str,
+    pkg: &PackageManifest,
+    version: &Version,
+    clients: bool,
+    config: &Config,
+) -> miette::Result<Vec<crate::validation::BreakingChange>> {
+    let dependency = Dependency::new(
+        registry,
+        repository.to_owned(),
+        pkg.name.clone(),
+        VersionReq::STAR,
+    )
+    .with_version(version);
+
+    let baseline = artifactory.download(dependency).await?;
+
+    // the baseline is unpacked like a vendored package so that its imports resolve
+    let root = tempfile::Builder::new()
+        .prefix("buffrs-breaking-")
+        .tempdir()
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create a temporary directory"))?;
+
+    baseline.unpack(&root.path().join(&*pkg.name)).await?;
+
+    Ok(store
+        .breaking(pkg, root.path(), config.include_paths(), clients)
+        .await?)
+}
+
+/// The lowest version that may contain breaking changes against `version`
+///
+/// Before 1.0.0 the left-most non-zero component is treated as the major version.
+#[cfg(feature = "validation")]
+fn next_breaking(version: &Version) -> Version {
+    match (version.major, version.minor) {
+        (0, 0) => Version::new(0, 0, version.patch + 1),
+        (0, minor) => Version::new(0, minor + 1, 0),
+        (major, _) => Version::new(major + 1, 0, 0),
+    }
+}
+
+/// Checks that a package containing breaking changes is published as a new major version
+///
+/// The package is compared against the latest published version. Violations are reported as a
+/// warning, or as an error if `enforce` is set. Packages that were never published are skipped,
+/// and so are packages whose published versions can not be looked up unless `enforce` is set.
+#[cfg(feature = "validation")]
+async fn check_semver(
+    store: &PackageStore,
+    artifactory: &Artifactory,
+    registry: &RegistryUri,
+    repository: &str,
+    pkg: &PackageManifest,
+    enforce: bool,
+    config: &Config,
+) -> miette::Result<()> {
+    let latest = match artifactory.latest_version(repository, &pkg.name).await {
+        Ok(latest) => latest,
+        Err(error) if enforce => {
+            return Err(error.wrap_err(miette!(
+                "failed to look up the published versions of {}",
+                pkg.name
+            )))
+        }
+        Err(error) => {
+            tracing::warn!(
+                ":: skipping the semver check, failed to look up the published versions of {}: {error}",
+                pkg.name
+            );
+            return Ok(());
+        }
+    };
+
+    let Some(latest) = latest else {
+        tracing::debug!("skipping semver check, {} was never published", pkg.name);
+        return Ok(());
+    };
+
+    let required = next_breaking(&latest);
+
+    if pkg.version <= latest || pkg.version >= required {
+        return Ok(());
+    }
+
+    let changes = breaking_changes(
+        store,
+        artifactory,
+        registry,
+        repository,
+        pkg,
+        &latest,
+        false,
+        config,
+    )
+    .await
+    .wrap_err(miette!(
+        "failed to check {}@{} for breaking changes against {latest}",
+        pkg.name,
+        pkg.version
+    ))?;
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let count = changes.len();
+
+    changes
+        .into_iter()
+        .map(miette::Report::new)
+        .for_each(|r| eprintln!("{r:?}"));
+
+    if enforce {
+        return Err(miette!(
+            help = format!("publish this release as {required} instead"),
+            "{}@{} contains {count} breaking change(s) against {latest} without increasing the major version",
+            pkg.name,
+            pkg.version
+        ));
+    }
+
+    tracing::warn!(
+        ":: {}@{} contains {count} breaking change(s) against {latest}, consider publishing it as {required}",
+        pkg.name,
+        pkg.version
+    );
+
+    Ok(())
+}
+
+/// Checks the local package for breaking changes against a published version
+///
+/// # Arguments
+///  * `registry` - The registry to download the baseline from
+///  * `repository` - The repository the package is published to
+///  * `against` - The baseline version, defaults to the latest published version
+///  * `clients` - Only report service changes, grouped by service for planning rollouts
+///  * `fail_on` - Fail if changes of this or a higher severity are found, source incompatible
+///    changes are warnings and wire incompatible changes errors
+///  * `config` - The configuration providing additional include paths
+#[cfg(feature = "validation")]
+pub async fn breaking(
+    registry: &RegistryUri,
+    repository: String,
+    against: Option<Version>,
+    clients: bool,
+    fail_on: FailOn,
+    config: &Config,
+) -> miette::Result<()> {
+    let manifest = Manifest::read().await?;
+    let credentials = Credentials::load().await?;
+    let store = PackageStore::current().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?.with_network(config.network())?;
+
+    let pkg = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to check for breaking changes"
+    ))?;
+
+    let version = match against {
+        Some(version) => version,
+        None => {
+            artifactory
+                .get_latest_version(repository.clone(), pkg.name.clone())
+                .await?
+        }
+    };
+
+    store.populate(pkg).await?;
+
+    let changes = breaking_changes(
+        &store,
+        &artifactory,
+        registry,
+        &repository,
+        pkg,
+        &version,
+        clients,
+        config,
+    )
+    .await?;
+
+    let count = changes.len();
+
+    let failing = changes
+        .iter()
+        .filter(|change| fail_on.fails(change.severity().unwrap_or(Severity::Error)))
+        .count();
+
+    if clients {
+        let mut services: BTreeMap<&str, Vec<&str>> = Default::default();
+
+        for change in &changes {
+            services
+                .entry(&change.path)
+                .or_default()
+                .push(&change.message);
+        }
+
+        for (service, changes) in &services {
+            println!("service {service}");
+
+            for change in changes {
+                println!("  - {change}");
             }
         }
 
-        manifest
-    };
-    let lockfile = Lockfile::read_or_default().await?;
-    let store = PackageStore::current().await?;
+        ensure!(
+            failing == 0,
+            code = "E0010",
+            "{} service(s) of {}@{version} changed incompatibly for existing clients, update all clients before rolling out this version",
+            services.len(),
+            pkg.name
+        );
+
+        tracing::info!(
+            ":: existing clients of {}@{version} are compatible",
+            pkg.name
+        );
+
+        return Ok(());
+    }
+
+    changes
+        .into_iter()
+        .map(miette::Report::new)
+        .for_each(|r| eprintln!("{r:?}"));
+
+    ensure!(
+        failing == 0,
+        code = "E0010",
+        "found {failing} breaking change(s) against {}@{version}",
+        pkg.name
+    );
+
+    if count > 0 {
+        tracing::warn!(
+            ":: found {count} breaking change(s) against {}@{version} below the `{fail_on}` severity",
+            pkg.name
+        );
+
+        return Ok(());
+    }
+
+    tracing::info!(":: no breaking changes against {}@{version}", pkg.name);
+
+    Ok(())
+}
+
+/// Reserves the numbers and names of fields removed since a published version
+///
+/// # Arguments
+///  * `registry` - The registry to download the baseline from
+///  * `repository` - The repository the package is published to
+///  * `against` - The baseline version, defaults to the latest published version
+///  * `config` - The configuration providing additional include paths
+#[cfg(feature = "validation")]
+pub async fn fix(
+    registry: &RegistryUri,
+    repository: String,
+    against: Option<Version>,
+    config: &Config,
+) -> miette::Result<()> {
+    let manifest = Manifest::read().await?;
     let credentials = Credentials::load().await?;
-    let cache = Cache::open().await?;
+    let store = PackageStore::current().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?.with_network(config.network())?;
 
-    store.clear().await?;
+    let pkg = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to fix the package"
+    ))?;
+
+    let version = match against {
+        Some(version) => version,
+        None => {
+            artifactory
+                .get_latest_version(repository.clone(), pkg.name.clone())
+                .await?
+        }
+    };
+
+    store.populate(pkg).await?;
+
+    let mut reservations: Vec<_> = breaking_changes(
+        &store,
+        &artifactory,
+        registry,
+        &repository,
+        pkg,
+        &version,
+        false,
+        config,
+    )
+    .await?
+    .into_iter()
+    .filter_map(|change| change.reservation)
+    .collect();
+
+    if reservations.is_empty() {
+        tracing::info!(
+            ":: no removed fields to reserve since {}@{version}",
+            pkg.name
+        );
+        return Ok(());
+    }
+
+    for file in store.collect(&store.proto_path(), false).await {
+        let original = fs::read_to_string(&file)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {}", file.display()))?;
+
+        let mut contents = original.clone();
+
+        reservations.retain(|reservation| match reservation.apply(&contents) {
+            Some(fixed) => {
+                tracing::info!(":: reserved {reservation} in {}", file.display());
+                contents = fixed;
+                false
+            }
+            None => true,
+        });
+
+        if contents != original {
+            fs::write(&file, contents)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", file.display()))?;
+        }
+    }
+
+    for reservation in reservations {
+        tracing::warn!(":: could not find the declaration to reserve {reservation}");
+    }
+
+    Ok(())
+}
+
+/// Component of a version to increase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum BumpLevel {
+    /// Increase the patch version, e.g. 1.2.3 to 1.2.4
+    Patch,
+    /// Increase the minor version, e.g. 1.2.3 to 1.3.0
+    Minor,
+    /// Increase the major version, e.g. 1.2.3 to 2.0.0
+    Major,
+}
+
+impl BumpLevel {
+    /// Increases the component of the version, resetting all lower components
+    ///
+    /// Pre-release and build metadata are dropped.
+    pub fn apply(self, version: &Version) -> Version {
+        match self {
+            Self::Patch => Version::new(version.major, version.minor, version.patch + 1),
+            Self::Minor => Version::new(version.major, version.minor + 1, 0),
+            Self::Major => Version::new(version.major + 1, 0, 0),
+        }
+    }
+
+    /// The level required by the changes since `version`
+    ///
+    /// Before 1.0.0 the left-most non-zero component is treated as the major version, so breaking
+    /// changes increase the minor version of 0.x releases and additions only the patch version.
+    pub fn required(version: &Version, breaking: bool, additions: bool) -> Self {
+        match (version.major, version.minor) {
+            _ if !breaking && !additions => Self::Patch,
+            (0, 0) => Self::Patch,
+            (0, _) if breaking => Self::Minor,
+            (0, _) => Self::Patch,
+            _ if breaking => Self::Major,
+            _ => Self::Minor,
+        }
+    }
+}
+
+/// Suggests the level to increase the local package by from its API changes since a version
+#[cfg(feature = "validation")]
+async fn suggest_bump(
+    store: &PackageStore,
+    artifactory: &Artifactory,
+    registry: &RegistryUri,
+    repository: &str,
+    pkg: &PackageManifest,
+    version: &Version,
+    config: &Config,
+) -> miette::Result<BumpLevel> {
+    let dependency = Dependency::new(
+        registry,
+        repository.to_owned(),
+        pkg.name.clone(),
+        VersionReq::STAR,
+    )
+    .with_version(version);
+
+    let baseline = artifactory.download(dependency).await?;
+
+    // the baseline is unpacked like a vendored package so that its imports resolve
+    let root = tempfile::Builder::new()
+        .prefix("buffrs-version-")
+        .tempdir()
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create a temporary directory"))?;
+
+    baseline.unpack(&root.path().join(&*pkg.name)).await?;
+
+    let breaking = !store
+        .breaking(pkg, root.path(), config.include_paths(), false)
+        .await?
+        .is_empty();
+
+    let diff = store
+        .diff(
+            &root.path().join(&*pkg.name),
+            &store.proto_vendor_path().join(&*pkg.name),
+            config.include_paths(),
+        )
+        .await?;
+
+    let additions = diff
+        .changes
+        .values()
+        .flatten()
+        .any(|change| change.kind == crate::validation::ChangeKind::Added);
+
+    Ok(BumpLevel::required(version, breaking, additions))
+}
+
+/// Runs git with the given arguments in the current directory
+#[cfg(feature = "git")]
+async fn git(args: &[&str]) -> miette::Result<()> {
+    let status = tokio::process::Command::new("git")
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to run `git {}`", args.join(" ")))?;
+
+    ensure!(
+        status.success(),
+        "`git {}` exited with {status}",
+        args.join(" ")
+    );
+
+    Ok(())
+}
+
+/// Runs git with the given arguments in the current directory and returns its output
+#[cfg(feature = "git")]
+async fn git_output(args: &[&str]) -> miette::Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to run `git {}`", args.join(" ")))?;
+
+    ensure!(
+        output.status.success(),
+        "`git {}` exited with {}",
+        args.join(" "),
+        output.status
+    );
+
+    let stdout = String::from_utf8(output.stdout)
+        .into_diagnostic()
+        .wrap_err(miette!(
+            "invalid utf-8 character in the output of `git {}`",
+            args.join(" ")
+        ))?;
+
+    Ok(stdout.trim().to_owned())
+}
+
+/// Writes a new version of the package into the Cargo manifest, if `[cargo]` synchronizes
+/// versions into it, and returns the path of the written manifest
+async fn write_cargo_version(
+    version: &Version,
+    config: &Config,
+) -> miette::Result<Option<PathBuf>> {
+    let settings = config.cargo();
+
+    if settings.sync_version != Some(SyncDirection::Write) {
+        return Ok(None);
+    }
+
+    let path = &settings.manifest;
+
+    let contents = fs::read_to_string(path)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to read {}", path.display()))?;
+
+    fs::write(path, crate::cargo::write_version(&contents, version)?)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to write {}", path.display()))?;
+
+    tracing::info!(":: set the version in {} to {version}", path.display());
+
+    Ok(Some(path.clone()))
+}
+
+/// Increases the version of the local package and returns the new version
+///
+/// # Arguments
+///  * `level` - The component to increase, suggested from the API changes against the latest
+///    published version if omitted
+///  * `baseline` - The registry and repository the package is published to, used to check the
+///    level against the API changes
+///  * `commit` - Commit the manifest with the new version
+///  * `tag` - Commit the manifest and tag the commit with the new version
+///  * `dry_run` - Only report the new version
+///  * `config` - The configuration providing additional include paths
+#[cfg_attr(not(feature = "validation"), allow(unused_variables))]
+pub async fn version(
+    level: Option<BumpLevel>,
+    baseline: Option<(&RegistryUri, String)>,
+    #[cfg(feature = "git")] commit: bool,
+    #[cfg(feature = "git")] tag: bool,
+    dry_run: bool,
+    config: &Config,
+) -> miette::Result<Version> {
+    ensure!(
+        config.cargo().sync_version != Some(SyncDirection::Read),
+        help = format!(
+            "increase the version in {} and run `buffrs cargo sync`",
+            config.cargo().manifest.display()
+        ),
+        "the version of the package is taken from its Cargo manifest"
+    );
+
+    let mut manifest = Manifest::read().await?;
+
+    let pkg = manifest.package.as_mut().ok_or(miette!(
+        "a [package] section must be declared to increase the version"
+    ))?;
+
+    #[cfg(feature = "validation")]
+    let suggested = match baseline {
+        Some((registry, repository)) => {
+            let credentials = Credentials::load().await?;
+            let store = PackageStore::current().await?;
+            let artifactory =
+                Artifactory::new(registry, &credentials)?.with_network(config.network())?;
+
+            let latest = artifactory
+                .get_latest_version(repository.clone(), pkg.name.clone())
+                .await?;
 
-    if let InstallMode::All = mode {
-        if let Some(ref pkg) = manifest.package {
             store.populate(pkg).await?;
 
-            tracing::info!(":: installed {}@{}", pkg.name, pkg.version);
+            let suggested = suggest_bump(
+                &store,
+                &artifactory,
+                registry,
+                &repository,
+                pkg,
+                &latest,
+                config,
+            )
+            .await
+            .wrap_err(miette!(
+                "failed to compare {} against {}@{latest}",
+                pkg.name,
+                pkg.name
+            ))?;
+
+            tracing::info!(
+                ":: the changes since {}@{latest} require a {suggested} release",
+                pkg.name
+            );
+
+            Some(suggested)
+        }
+        None => None,
+    };
+
+    #[cfg(not(feature = "validation"))]
+    let suggested: Option<BumpLevel> = None;
+
+    let level = match (level, suggested) {
+        (Some(level), Some(suggested)) if level < suggested => {
+            tracing::warn!(
+                ":: a {level} release of {} does not match its API changes, consider a {suggested} release instead",
+                pkg.name
+            );
+
+            level
         }
+        (Some(level), _) => level,
+        (None, Some(suggested)) => suggested,
+        (None, None) => bail!(
+            help = "pass --repository to suggest the level from the changes since the latest published version",
+            "no version level (patch, minor or major) given"
+        ),
+    };
+
+    let previous = pkg.version.clone();
+
+    pkg.version = level.apply(&previous);
+
+    let name = pkg.name.clone();
+    let version = pkg.version.clone();
+
+    if dry_run {
+        tracing::info!(":: would bump {name} from {previous} to {version}");
+
+        return Ok(version);
     }
 
-    let dependency_graph =
-        DependencyGraph::from_manifest(&manifest, &lockfile, &credentials.into(), &cache)
+    manifest.write().await?;
+
+    tracing::info!(":: bumped {name} from {previous} to {version}");
+
+    #[cfg_attr(not(feature = "git"), allow(unused_variables))]
+    let cargo = write_cargo_version(&version, config).await?;
+
+    #[cfg(feature = "git")]
+    if commit || tag {
+        let message = format!("Release {name}@{version}");
+        let cargo = cargo.map(|path| path.to_string_lossy().into_owned());
+        let files: Vec<&str> = std::iter::once(MANIFEST_FILE)
+            .chain(cargo.as_deref())
+            .collect();
+
+        let mut args = vec!["commit", "--message", &message, "--"];
+
+        args.extend(&files);
+
+        git(&args)
             .await
-            .wrap_err(miette!("dependency resolution failed"))?;
+            .wrap_err(miette!("failed to commit {}", files.join(" and ")))?;
 
-    let mut locked = Vec::new();
+        tracing::info!(":: committed {}", files.join(" and "));
 
-    #[async_recursion]
-    async fn traverse_and_install(
-        name: &PackageName,
-        graph: &DependencyGraph,
-        store: &PackageStore,
-        locked: &mut Vec<LockedPackage>,
-        prefix: String,
-    ) -> miette::Result<()> {
-        let resolved = graph.get(name).ok_or(miette!(
-            "unexpected error: missing dependency in dependency graph"
-        ))?;
+        if tag {
+            let tag = format!("{name}@{version}");
+
+            git(&["tag", "--annotate", "--message", &message, &tag])
+                .await
+                .wrap_err(miette!("failed to create the tag {tag}"))?;
+
+            tracing::info!(":: tagged the release as {tag}");
+        }
+    }
+
+    Ok(version)
+}
+
+/// Directory the archives built by `release` are written to
+pub const RELEASE_PATH: &str = "proto/build/release";
+
+/// A code forge hosting the releases of a repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Forge {
+    /// GitHub, releases are created with the `gh` CLI
+    GitHub,
+    /// GitLab, releases are created with the `glab` CLI
+    GitLab,
+}
+
+impl Forge {
+    /// Creates a release of a pushed tag with the archive attached
+    async fn release(self, tag: &str, notes: &str, archive: &Path) -> miette::Result<()> {
+        let archive = archive.to_string_lossy().into_owned();
+
+        let (program, args) = match self {
+            Self::GitHub => (
+                "gh",
+                vec![
+                    "release",
+                    "create",
+                    tag,
+                    archive.as_str(),
+                    "--title",
+                    tag,
+                    "--notes",
+                    notes,
+                    "--verify-tag",
+                ],
+            ),
+            Self::GitLab => (
+                "glab",
+                vec![
+                    "release",
+                    "create",
+                    tag,
+                    archive.as_str(),
+                    "--name",
+                    tag,
+                    "--notes",
+                    notes,
+                ],
+            ),
+        };
+
+        let status = tokio::process::Command::new(program)
+            .args(&args)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!(
+                help = format!("install the `{program}` CLI and log in to {self}"),
+                "failed to run `{program}`"
+            ))?;
+
+        ensure!(
+            status.success(),
+            "`{program} release create` exited with {status}"
+        );
+
+        Ok(())
+    }
+}
+
+/// Releases a new version of the local package
+///
+/// Bumps the version like [`version`], regenerates the lockfile, builds and verifies the package
+/// and writes the archive to [`RELEASE_PATH`]. The manifest and lockfile are then committed and
+/// tagged, the package published and, if requested, the commit and tag pushed and a release
+/// created on the forge with the archive attached.
+///
+/// # Arguments
+///  * `level` - The component to increase, suggested from the API changes since the latest
+///    published version if omitted
+///  * `registry` - The registry to publish to
+///  * `repository` - The repository to publish to
+///  * `push` - Push the release commit and tag to the upstream of the current branch
+///  * `forge` - Create a release on this forge, requires `push`
+///  * `dry_run` - Only report the new version and build the package
+///  * `signer` - Sign the published package
+///  * `format` - The output format
+///  * `config` - The configuration
+///  * `cancel` - Aborts the upload
+#[cfg(feature = "git")]
+#[allow(clippy::too_many_arguments)]
+pub async fn release(
+    level: Option<BumpLevel>,
+    registry: &RegistryUri,
+    repository: String,
+    push: bool,
+    forge: Option<Forge>,
+    dry_run: bool,
+    signer: Option<Signer<'_>>,
+    format: OutputFormat,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<serde_json::Value> {
+    ensure!(
+        push || forge.is_none(),
+        "creating a release on {} requires pushing the tag",
+        forge.map(|forge| forge.to_string()).unwrap_or_default()
+    );
+
+    ensure_clean(false).await?;
+
+    // without a level, it is suggested from the changes since the latest published version
+    let baseline = level.is_none().then(|| (registry, repository.clone()));
+
+    let version = version(level, baseline, false, false, dry_run, config).await?;
+
+    install(
+        InstallMode::All,
+        GenerationFlags::empty(),
+        dry_run,
+        false,
+        OutputFormat::Human,
+        config,
+        cancel,
+    )
+    .await
+    .wrap_err(miette!("failed to regenerate `{LOCKFILE}`"))?;
+
+    let mut manifest = Manifest::read().await?;
+    let credentials = Credentials::load().await?;
+    let store = PackageStore::current().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?
+        .with_network(config.network())?
+        .with_encryption(config.encryption(registry));
+
+    // the manifest is only bumped on disk outside of dry runs
+    if let Some(ref mut package) = manifest.package {
+        package.version = version;
+    }
+
+    let package = prepare_release(
+        &store,
+        &manifest,
+        &artifactory,
+        registry,
+        &repository,
+        #[cfg(feature = "validation")]
+        false,
+        #[cfg(feature = "validation")]
+        false,
+        config,
+    )
+    .await?;
+
+    check_size(&package)?;
+
+    let tag = format!("{}@{}", package.name(), package.version());
+    let message = format!("Release {tag}");
+
+    let mut release = release_json(&package, registry, &repository, dry_run)?;
+
+    release["tag"] = json!(tag);
+
+    if dry_run {
+        report_size(&package)?;
+
+        tracing::warn!(":: aborting the release of {tag} due to dry run");
+
+        if let OutputFormat::Json = format {
+            print_json(&release);
+        }
+
+        return Ok(release);
+    }
+
+    let archive =
+        Path::new(RELEASE_PATH).join(format!("{}-{}.tgz", package.name(), package.version()));
+
+    fs::create_dir_all(RELEASE_PATH)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create {RELEASE_PATH}"))?;
+
+    fs::write(&archive, &package.tgz)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to write {}", archive.display()))?;
+
+    let cargo = config.cargo();
+    let cargo_manifest = cargo.manifest.to_string_lossy();
+
+    let mut add = vec!["add", "--", MANIFEST_FILE];
+
+    if Path::new(LOCKFILE).exists() {
+        add.push(LOCKFILE);
+    }
+
+    if cargo.sync_version == Some(SyncDirection::Write) {
+        add.push(&cargo_manifest);
+    }
+
+    let files = add[2..].join(" and ");
+
+    git(&add)
+        .await
+        .wrap_err(miette!("failed to stage {files}"))?;
+
+    git(&["commit", "--message", &message])
+        .await
+        .wrap_err(miette!("failed to commit {files}"))?;
+
+    git(&["tag", "--annotate", "--message", &message, &tag])
+        .await
+        .wrap_err(miette!("failed to create the tag {tag}"))?;
+
+    tracing::info!(":: committed and tagged the release as {tag}");
+
+    #[cfg(feature = "validation")]
+    register_schemas(&store, &manifest, config).await?;
+
+    upload(&artifactory, &package, &repository, false, signer, cancel).await?;
+
+    tracing::info!(":: published {tag} to {registry}:{repository}");
+
+    if push {
+        git(&["push", "--follow-tags"])
+            .await
+            .wrap_err(miette!("failed to push the release commit and {tag}"))?;
+
+        tracing::info!(":: pushed the release commit and {tag}");
+    }
+
+    if let Some(forge) = forge {
+        forge
+            .release(&tag, &message, &archive)
+            .await
+            .wrap_err(miette!("failed to create the {forge} release {tag}"))?;
 
-        store.unpack(resolved.package()).await.wrap_err(miette!(
-            "failed to unpack package {}",
-            &resolved.package().name()
-        ))?;
+        tracing::info!(":: created the {forge} release {tag}");
+    }
 
-        tracing::info!(
-            "{} installed {}@{}",
-            if prefix.is_empty() { "::" } else { &prefix },
-            name,
-            resolved.package().version()
-        );
+    if let OutputFormat::Json = format {
+        print_json(&release);
+    }
 
-        if let ResolvedDependency::Remote {
-            package,
-            registry,
-            repository,
-            dependants,
-            ..
-        } = &resolved
-        {
-            locked.push(package.lock(registry.clone(), repository.clone(), dependants.len()));
-        }
+    Ok(release)
+}
 
-        for (index, dependency) in resolved.depends_on().iter().enumerate() {
-            let tree_char = if index + 1 == resolved.depends_on().len() {
-                '┗'
-            } else {
-                '┣'
-            };
+/// Prints the metadata and contents of a package
+///
+/// # Arguments
+///  * `package` - Either `<package>[@<version>]` of a published package, defaulting to the latest
+///    version, or the path to a local `.tgz` archive
+///  * `registry` - The registry to download published packages from
+///  * `repository` - The repository the package is published to
+pub async fn info(
+    package: &str,
+    registry: Option<&RegistryUri>,
+    repository: Option<String>,
+    format: OutputFormat,
+    config: &Config,
+) -> miette::Result<serde_json::Value> {
+    let (package, published) = load_package(package, registry, repository, config).await?;
 
-            let new_prefix = format!(
-                "{} {tree_char}",
-                if prefix.is_empty() { "  " } else { &prefix }
-            );
+    let manifest = package.manifest.package.as_ref().ok_or(miette!(
+        "the package manifest is missing a [package] section"
+    ))?;
 
-            traverse_and_install(dependency, graph, store, locked, new_prefix).await?;
+    let published = match published {
+        Some((artifactory, repository)) => {
+            let (name, version) = (package.name(), package.version());
+
+            match artifactory.published(&repository, name, version).await {
+                Ok(published) => Some(published),
+                Err(error) => {
+                    tracing::warn!(
+                        ":: failed to retrieve the publish date of {name}@{version}: {error}"
+                    );
+                    None
+                }
+            }
         }
+        None => None,
+    };
 
-        Ok(())
-    }
+    let files = package.files()?;
 
-    for dependency in manifest.dependencies {
-        traverse_and_install(
-            &dependency.package,
-            &dependency_graph,
-            &store,
-            &mut locked,
-            String::new(),
-        )
-        .await?;
+    let dependencies: Vec<_> = package
+        .manifest
+        .dependencies
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    let result = json!({
+        "name": manifest.name.to_string(),
+        "version": manifest.version.to_string(),
+        "type": manifest.kind.to_string().to_lowercase(),
+        "description": manifest.description,
+        "published": published,
+        "edition": <&str>::from(package.manifest.edition.clone()),
+        "digest": package.digest(DigestAlgorithm::SHA256).to_string(),
+        "size": package.tgz.len(),
+        "dependencies": dependencies,
+        "files": files
+            .iter()
+            .map(|(path, size)| json!({ "path": path, "size": size }))
+            .collect::<Vec<_>>(),
+    });
+
+    if let OutputFormat::Json = format {
+        print_json(&result);
+
+        return Ok(result);
     }
 
-    if let GenerationFlags::BUF_YAML = generation {
-        // Check if a buf.yaml file already exists in cwd
-        let mut buf_yaml = if Path::new("buf.yaml").exists() {
-            BufYamlFile::from_file().wrap_err(miette!("failed to read buf.yaml file"))?
-        } else {
-            BufYamlFile::default()
-        };
+    println!(
+        "{}@{} ({})",
+        manifest.name,
+        manifest.version,
+        manifest.kind.to_string().to_lowercase()
+    );
 
-        // Add vendor modules to the Buf YAML file
-        let mut vendor_modules: Vec<String> = dependency_graph
-            .get_package_names()
-            .iter()
-            .map(|p| p.to_string())
-            .collect();
-        vendor_modules.sort();
+    if let Some(description) = &manifest.description {
+        println!("description: {description}");
+    }
 
-        buf_yaml.clear_modules();
+    if let Some(published) = published {
+        println!("published: {published}");
+    }
 
-        if manifest.package.is_some() {
-            // double-check that the package really contains proto files
-            // under proto/** (but not under proto/vendor/**)
-            let vendor_path = store.proto_vendor_path();
-            let mut has_protos = false;
-            for entry in WalkDir::new(store.proto_path()).into_iter().flatten() {
-                if entry.path().is_file() {
-                    let path = entry.path();
-                    if path.starts_with(&vendor_path) {
-                        continue;
-                    }
+    println!(
+        "edition: {}",
+        <&str>::from(package.manifest.edition.clone())
+    );
+    println!("digest: {}", package.digest(DigestAlgorithm::SHA256));
+    println!("size: {} bytes", package.tgz.len());
 
-                    if path.extension().map_or(false, |ext| ext == "proto") {
-                        has_protos = true;
-                        break;
-                    }
-                }
-            }
+    println!("dependencies:");
 
-            if has_protos {
-                buf_yaml.add_module();
-            }
-        }
+    if package.manifest.dependencies.is_empty() {
+        println!("  (none)");
+    }
 
-        buf_yaml.set_vendor_modules(vendor_modules);
+    for dependency in &package.manifest.dependencies {
+        println!("  {dependency}");
+    }
 
-        // Write the Buf YAML file
-        buf_yaml
-            .to_file()
-            .wrap_err(miette!("failed to write buf.yaml file"))?;
+    println!("files ({}):", files.len());
+
+    for (file, size) in files {
+        println!("  {size:>8}  {}", file.display());
     }
 
-    Lockfile::from_iter(locked.into_iter()).write().await
+    Ok(result)
 }
 
-/// Uninstalls dependencies
-pub async fn uninstall() -> miette::Result<()> {
-    PackageStore::current().await?.clear().await
+/// Extracts the files of a package into a directory
+///
+/// # Arguments
+///  * `package` - Either `<package>[@<version>]` of a published package, defaulting to the latest
+///    version, or the path to a local `.tgz` archive
+///  * `directory` - The directory to extract to, has to be empty or not exist yet
+///  * `registry` - The registry to download published packages from
+///  * `repository` - The repository the package is published to
+pub async fn extract(
+    package: &str,
+    directory: &Path,
+    registry: Option<&RegistryUri>,
+    repository: Option<String>,
+    config: &Config,
+) -> miette::Result<()> {
+    // unpacking replaces the directory, so never touch existing contents
+    if let Ok(mut entries) = fs::read_dir(directory).await {
+        ensure!(
+            entries.next_entry().await.into_diagnostic()?.is_none(),
+            "{} is not empty",
+            directory.display()
+        );
+    }
+
+    let (package, _) = load_package(package, registry, repository, config).await?;
+
+    ensure!(
+        package.manifest.package.is_some(),
+        "the package manifest is missing a [package] section"
+    );
+
+    package.unpack(directory).await?;
+
+    tracing::info!(
+        ":: extracted {}@{} to {}",
+        package.name(),
+        package.version(),
+        directory.display()
+    );
+
+    Ok(())
 }
 
-/// Lists all protobuf files managed by Buffrs to stdout
-pub async fn list() -> miette::Result<()> {
-    let store = PackageStore::current().await?;
-    let manifest = Manifest::read().await?;
+/// Reads a local `.tgz` archive or downloads a published package given as
+/// `<package>[@<version>]`, together with the registry and repository it was downloaded from
+async fn load_package(
+    package: &str,
+    registry: Option<&RegistryUri>,
+    repository: Option<String>,
+    config: &Config,
+) -> miette::Result<(Package, Option<(Artifactory, String)>)> {
+    let path = Path::new(package);
 
-    if let Some(ref pkg) = manifest.package {
-        store.populate(pkg).await?;
+    if path.extension().is_some_and(|ext| ext == "tgz") {
+        return Ok((Package::read(path).await?, None));
     }
 
-    let protos = store.collect(&store.proto_vendor_path(), true).await;
+    let registry = registry.ok_or(miette!(
+        help = "pass --registry or configure a default registry",
+        "a registry is required to fetch published packages"
+    ))?;
 
-    // Canonicalize the protos
-    let protos = protos
-        .into_iter()
-        .map(|proto| {
-            proto
-                .canonicalize()
-                .into_diagnostic()
-                .wrap_err(miette!("failed to canonicalize proto path"))
-        })
-        .collect::<miette::Result<Vec<_>>>()?;
+    let repository = repository.ok_or(miette!(
+        help = "pass --repository",
+        "a repository is required to fetch published packages"
+    ))?;
 
-    let cwd = {
-        let cwd = std::env::current_dir()
-            .into_diagnostic()
-            .wrap_err(miette!("failed to get current directory"))?;
+    let (name, version) = match package.trim().split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (package.trim(), None),
+    };
 
-        fs::canonicalize(cwd)
-            .await
+    let name = name
+        .parse::<PackageName>()
+        .wrap_err(miette!("invalid package name: {name}"))?;
+
+    let credentials = Credentials::load().await?;
+    let artifactory = Artifactory::new(registry, &credentials)?.with_network(config.network())?;
+
+    let version = match version {
+        Some(version) => Version::parse(version)
             .into_diagnostic()
-            .wrap_err(miette!("failed to canonicalize current directory"))?
+            .wrap_err(miette!("not a valid version: {version}"))?,
+        None => {
+            artifactory
+                .get_latest_version(repository.clone(), name.clone())
+                .await?
+        }
     };
 
-    for proto in protos.iter() {
-        let rel = proto
-            .strip_prefix(&cwd)
-            .into_diagnostic()
-            .wrap_err(miette!("failed to transform protobuf path"))?;
+    let dependency = Dependency::new(registry, repository.clone(), name.clone(), VersionReq::STAR)
+        .with_version(&version);
 
-        print!("{} ", rel.display())
-    }
+    let package = artifactory.download(dependency).await?;
 
-    Ok(())
+    Ok((package, Some((artifactory, repository))))
 }
 
-/// Parses current package and validates rules.
-#[cfg(feature = "validation")]
-pub async fn lint() -> miette::Result<()> {
+/// Prints a software bill of materials for the package and its installed dependencies
+///
+/// # Arguments
+///  * `format` - The format of the bill of materials
+pub async fn sbom(format: SbomFormat) -> miette::Result<()> {
+    async fn installed(store: &PackageStore, name: &PackageName) -> miette::Result<Manifest> {
+        store.resolve(name).await.wrap_err(miette!(
+            help = "run `buffrs install` to install the dependencies",
+            "`{name}` is not installed"
+        ))
+    }
+
     let manifest = Manifest::read().await?;
     let store = PackageStore::current().await?;
+    let lockfile = Lockfile::read().await?;
 
-    let pkg = manifest.package.ok_or(miette!(
-        "a [package] section must be declared run the linter"
+    let package = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to generate a bill of materials"
     ))?;
 
-    store.populate(&pkg).await?;
+    let mut components = Vec::new();
 
-    let violations = store.validate(&pkg).await?;
+    for locked in lockfile.packages() {
+        let installed = installed(&store, &locked.name).await?;
+        components.push(Component::locked(locked, installed.package.as_ref()));
+    }
 
-    violations
-        .into_iter()
-        .map(miette::Report::new)
-        .for_each(|r| eprintln!("{r:?}"));
+    for dependency in manifest.dependencies.iter() {
+        if !dependency.manifest.is_local() || lockfile.get(&dependency.package).is_some() {
+            continue;
+        }
+
+        let installed = installed(&store, &dependency.package).await?;
+
+        let local = installed.package.ok_or(miette!(
+            "the local dependency `{}` does not declare a package",
+            dependency.package
+        ))?;
+
+        components.push(Component {
+            name: local.name,
+            version: local.version,
+            digest: None,
+            url: None,
+            license: local.license,
+            dependencies: installed
+                .dependencies
+                .into_iter()
+                .map(|dependency| dependency.package)
+                .collect(),
+        });
+    }
+
+    let root = Component {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        digest: None,
+        url: None,
+        license: package.license.clone(),
+        dependencies: manifest
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.package.clone())
+            .collect(),
+    };
+
+    let sbom = Sbom::new(root, components).render(format);
+
+    // hint: always ok, as per serde_json doc
+    if let Ok(json) = serde_json::to_string_pretty(&sbom) {
+        println!("{json}");
+    }
 
     Ok(())
 }
@@ -618,14 +5214,20 @@ pub async fn lint() -> miette::Result<()> {
 ///
 /// # Arguments
 ///  * `registry` - The registry to log in to
-///  * `token` - An optional token to use, if not provided, the user will be prompted for one
-pub async fn login(registry: &RegistryUri, token: Option<String>) -> miette::Result<()> {
+///  * `token` - An optional token to use, if not provided, it is read from stdin, prompting the
+///    user if stdin is a terminal
+///  * `config` - The configuration providing the network settings
+pub async fn login(
+    registry: &RegistryUri,
+    token: Option<String>,
+    config: &Config,
+) -> miette::Result<()> {
     let mut credentials = Credentials::load().await?;
 
     let token = match token {
         Some(token) => token,
-        None => {
-            tracing::info!(":: please enter your artifactory token:");
+        None if std::io::stdin().is_terminal() => {
+            tracing::info!(":: please enter your token for {registry}:");
 
             let mut raw = String::new();
             let mut reader = BufReader::new(io::stdin());
@@ -636,20 +5238,43 @@ pub async fn login(registry: &RegistryUri, token: Option<String>) -> miette::Res
                 .into_diagnostic()
                 .wrap_err(miette!("failed to read the token from the user"))?;
 
-            raw.trim().into()
+            raw
+        }
+        None => {
+            let mut raw = String::new();
+
+            io::stdin()
+                .read_to_string(&mut raw)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read the token from stdin"))?;
+
+            raw
         }
     };
 
-    credentials.registry_tokens.insert(registry.clone(), token);
+    let token = token.trim();
+
+    ensure!(!token.is_empty(), "no token provided for {registry}");
 
+    credentials
+        .registry_tokens
+        .insert(registry.clone(), token.to_owned());
+
+    // only persist tokens the registry accepts
     if env::var(BUFFRS_TESTSUITE_VAR).is_err() {
         Artifactory::new(registry, &credentials)?
+            .with_network(config.network())?
             .ping()
             .await
-            .wrap_err(miette!("failed to validate token"))?;
+            .wrap_err(miette!("failed to validate the token for {registry}"))?;
     }
 
-    credentials.write().await
+    credentials.write().await?;
+
+    tracing::info!(":: logged in to {registry}");
+
+    Ok(())
 }
 
 /// Logs you out from a registry
@@ -659,29 +5284,129 @@ pub async fn logout(registry: &RegistryUri) -> miette::Result<()> {
     credentials.write().await
 }
 
-/// Commands on the lockfile
-pub mod lock {
-    use super::*;
-    use crate::lock::FileRequirement;
+/// Prints the extended explanation of an error code, or lists all codes if none is given
+pub async fn explain(code: Option<&str>) -> miette::Result<()> {
+    let Some(code) = code else {
+        for error in ERROR_CODES {
+            println!("{}  {}", error.code, error.summary);
+        }
 
-    /// Prints the file requirements serialized as JSON
-    pub async fn print_files() -> miette::Result<()> {
-        let lock = Lockfile::read().await?;
+        return Ok(());
+    };
 
-        let requirements: Vec<FileRequirement> = lock.into();
+    let error = ErrorCode::lookup(code).ok_or(miette!(
+        help = "run `buffrs explain` to list all error codes",
+        "`{code}` is not a buffrs error code"
+    ))?;
 
-        // hint: always ok, as per serde_json doc
-        if let Ok(json) = serde_json::to_string_pretty(&requirements) {
-            println!("{json}");
-        }
+    print!("{}", error.explanation);
 
-        Ok(())
-    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::DependencyLocator;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use semver::Version;
+
+    #[cfg(feature = "git")]
+    use super::with_dependents;
+    use super::{format_size, publish_order, BumpLevel, DependencyLocator, PackageVersion};
+    use crate::package::PackageName;
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn bumps_versions() {
+        let version = Version::parse("1.2.3-rc.1").unwrap();
+
+        assert_eq!(BumpLevel::Patch.apply(&version), Version::new(1, 2, 4));
+        assert_eq!(BumpLevel::Minor.apply(&version), Version::new(1, 3, 0));
+        assert_eq!(BumpLevel::Major.apply(&version), Version::new(2, 0, 0));
+    }
+
+    #[test]
+    fn requires_levels_by_changes() {
+        let stable = Version::new(1, 2, 3);
+        let unstable = Version::new(0, 2, 3);
+
+        assert_eq!(BumpLevel::required(&stable, true, true), BumpLevel::Major);
+        assert_eq!(BumpLevel::required(&stable, false, true), BumpLevel::Minor);
+        assert_eq!(BumpLevel::required(&stable, false, false), BumpLevel::Patch);
+        assert_eq!(
+            BumpLevel::required(&unstable, true, false),
+            BumpLevel::Minor
+        );
+        assert_eq!(
+            BumpLevel::required(&unstable, false, true),
+            BumpLevel::Patch
+        );
+        assert_eq!(
+            BumpLevel::required(&Version::new(0, 0, 3), true, false),
+            BumpLevel::Patch
+        );
+    }
+
+    #[test]
+    fn orders_packages_by_dependencies() {
+        let name = |name: &str| name.parse::<PackageName>().unwrap();
+
+        let dependencies = BTreeMap::from([
+            (name("api"), vec![name("units"), name("physics")]),
+            (name("physics"), vec![name("units"), name("third-party")]),
+            (name("units"), vec![]),
+        ]);
+
+        assert_eq!(
+            publish_order(&dependencies).unwrap(),
+            vec![name("units"), name("physics"), name("api")]
+        );
+
+        let cyclic = BTreeMap::from([
+            (name("physics"), vec![name("units")]),
+            (name("units"), vec![name("physics")]),
+        ]);
+
+        assert!(publish_order(&cyclic).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "git")]
+    fn extends_packages_by_dependents() {
+        let name = |name: &str| name.parse::<PackageName>().unwrap();
+
+        let dependencies = BTreeMap::from([
+            (name("api"), vec![name("physics")]),
+            (name("physics"), vec![name("units")]),
+            (name("chemistry"), vec![name("units")]),
+            (name("units"), vec![]),
+            (name("docs"), vec![]),
+        ]);
+
+        assert_eq!(
+            with_dependents(&dependencies, BTreeSet::from([name("units")])),
+            BTreeSet::from([
+                name("api"),
+                name("chemistry"),
+                name("physics"),
+                name("units")
+            ])
+        );
+
+        assert_eq!(
+            with_dependents(&dependencies, BTreeSet::from([name("physics")])),
+            BTreeSet::from([name("api"), name("physics")])
+        );
+
+        assert!(with_dependents(&dependencies, BTreeSet::new()).is_empty());
+    }
 
     #[test]
     fn valid_dependency_locator() {
@@ -711,4 +5436,111 @@ mod tests {
         assert!("repo/pkg@=1#meta".parse::<DependencyLocator>().is_err());
         assert!("repo/PKG@=1.0".parse::<DependencyLocator>().is_err());
     }
+
+    #[test]
+    fn package_versions() {
+        assert!("pkg@1.0.0".parse::<PackageVersion>().is_ok());
+        assert!("pkg-with-dash@1.0.0-rc.1".parse::<PackageVersion>().is_ok());
+        assert!("pkg".parse::<PackageVersion>().is_err());
+        assert!("pkg@=1.0".parse::<PackageVersion>().is_err());
+        assert!("PKG@1.0.0".parse::<PackageVersion>().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "validation")]
+    fn breaking_versions() {
+        use super::next_breaking;
+        use semver::Version;
+
+        let next = |version: &str| next_breaking(&Version::parse(version).unwrap()).to_string();
+
+        assert_eq!(next("0.0.3"), "0.0.4");
+        assert_eq!(next("0.4.2"), "0.5.0");
+        assert_eq!(next("1.2.3"), "2.0.0");
+        assert_eq!(next("2.0.0-rc.1"), "3.0.0");
+    }
+
+    #[test]
+    fn detects_lock_drift() {
+        use super::lock_drift;
+        use crate::{
+            lock::{DigestAlgorithm, LockedPackage, Lockfile},
+            manifest::{Dependency, Manifest},
+            registry::RegistryUri,
+        };
+
+        let registry: RegistryUri = "https://registry.example.com/artifactory".parse().unwrap();
+
+        let dependency = |name: &str, version: &str| {
+            Dependency::new(
+                &registry,
+                "protos".into(),
+                name.parse().unwrap(),
+                version.parse().unwrap(),
+            )
+        };
+
+        let locked = |name: &str, version: &str| LockedPackage {
+            name: name.parse().unwrap(),
+            digest: DigestAlgorithm::SHA256.digest(name.as_bytes()),
+            registry: registry.clone(),
+            repository: "protos".into(),
+            version: version.parse().unwrap(),
+            dependencies: vec![],
+            dependants: 1,
+        };
+
+        let manifest = Manifest::new(
+            None,
+            vec![
+                dependency("physics", "^1.0"),
+                dependency("units", "=2.0.0"),
+                dependency("chemistry", "^0.3"),
+            ],
+        );
+
+        let lockfile = Lockfile::from_iter([locked("physics", "1.4.0"), locked("units", "2.1.0")]);
+
+        assert_eq!(
+            lock_drift(&manifest, &lockfile),
+            vec![
+                "units is locked at 2.1.0, which does not satisfy =2.0.0",
+                "chemistry is not locked"
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "validation")]
+    fn detects_changed_files() {
+        use super::Snapshot;
+        use std::{
+            path::{Path, PathBuf},
+            time::{Duration, SystemTime},
+        };
+
+        let stamp = |secs| (Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)), 10);
+
+        let earlier = Snapshot(BTreeMap::from([
+            (PathBuf::from("Proto.toml"), stamp(1)),
+            (PathBuf::from("proto/mass.proto"), stamp(1)),
+            (PathBuf::from("proto/units.proto"), stamp(1)),
+        ]));
+
+        let later = Snapshot(BTreeMap::from([
+            (PathBuf::from("Proto.toml"), stamp(1)),
+            (PathBuf::from("proto/mass.proto"), stamp(2)),
+            (PathBuf::from("proto/weight.proto"), stamp(2)),
+        ]));
+
+        assert_eq!(
+            later.changes(&earlier).into_iter().collect::<Vec<_>>(),
+            vec![
+                Path::new("proto/mass.proto"),
+                Path::new("proto/units.proto"),
+                Path::new("proto/weight.proto"),
+            ]
+        );
+        assert!(later.changes(&later).is_empty());
+    }
 }
@@ -0,0 +1,226 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeSet, path::Path};
+
+use miette::{bail, miette, Context, IntoDiagnostic};
+use semver::{BuildMetadata, Version};
+use tokio::fs;
+
+use super::{adopt, copy_files};
+use crate::{
+    buf_yaml::{BufLockFile, BufYamlFile},
+    config::Config,
+    manifest::{Dependency, LocalDependencyManifest, Manifest, PackageManifest, MANIFEST_FILE},
+    package::{name_of, PackageStore, ProtoTree},
+    registry::bsr::{Bsr, ModuleRef, PulledPackage, PulledPackages, BSR_DIR},
+};
+
+/// Directory the Buf module of the package is exported to
+pub const EXPORT_PATH: &str = "proto/build/buf";
+
+/// Pulls a module from a Buf Schema Registry and adds it as a dependency
+///
+/// Every top-level directory of the module becomes a package in the `bsr` directory, which is
+/// added as a local dependency. The packages are versioned `0.0.0+<commit>` and depend on
+/// each other as their imports require.
+pub async fn add(module: &ModuleRef, config: &Config) -> miette::Result<()> {
+    let mut manifest = Manifest::read().await?;
+
+    let contents = Bsr::new(config.network())?
+        .download(module)
+        .await
+        .wrap_err(miette!("failed to download {module}"))?;
+
+    let root = Path::new(BSR_DIR);
+    let mut pulled = PulledPackages::read(root).await?;
+    let packages = contents.packages()?;
+
+    let commit: String = contents.commit.chars().take(12).collect();
+    let version = Version {
+        build: BuildMetadata::new(&commit).into_diagnostic()?,
+        ..Version::new(0, 0, 0)
+    };
+
+    for (name, files) in &packages {
+        let dir = root.join(&**name);
+        let protos = dir.join(PackageStore::PROTO_PATH);
+
+        fs::remove_dir_all(&dir).await.ok();
+
+        for (file, data) in files {
+            let path = protos.join(file);
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .await
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to create {}", parent.display()))?;
+            }
+
+            fs::write(&path, data)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", path.display()))?;
+        }
+
+        let tree = ProtoTree::scan(&protos).await?;
+
+        // imports of other directories of the module are served by their packages
+        let dependencies = tree
+            .dependencies
+            .keys()
+            .filter(|dependency| packages.contains_key(*dependency))
+            .map(|dependency| Dependency {
+                package: dependency.clone(),
+                manifest: LocalDependencyManifest {
+                    path: Path::new("..").join(&**dependency),
+                }
+                .into(),
+            })
+            .collect();
+
+        let package = PackageManifest {
+            kind: tree.kind(),
+            name: name.clone(),
+            version: version.clone(),
+            description: Some(format!("{} at commit {}", module.name(), contents.commit)),
+            license: None,
+            max_size: None,
+        };
+
+        Manifest::new(Some(package), dependencies)
+            .write_at(&dir)
+            .await?;
+
+        manifest.dependencies.retain(|other| &other.package != name);
+        manifest.dependencies.push(Dependency {
+            package: name.clone(),
+            manifest: LocalDependencyManifest { path: dir.clone() }.into(),
+        });
+
+        pulled.insert(PulledPackage {
+            package: name.clone(),
+            module: module.name(),
+            commit: contents.commit.clone(),
+        });
+
+        tracing::info!(":: pulled {name} from {module} at commit {commit}");
+    }
+
+    pulled.write(root).await?;
+
+    manifest
+        .write()
+        .await
+        .wrap_err(miette!("failed to write `{MANIFEST_FILE}`"))
+}
+
+/// Turns a module of the Buf workspace in the current directory into a buffrs package
+///
+/// The protos are adopted like with `buffrs init --from-protos`, the package is named after
+/// the module. Dependencies on modules of a Buf Schema Registry are pulled at the commits
+/// locked in `buf.lock`.
+pub async fn import(module: Option<&Path>, config: &Config) -> miette::Result<()> {
+    let buf_yaml = BufYamlFile::from_file().wrap_err(miette!("failed to read buf.yaml"))?;
+    let modules = buf_yaml.modules();
+
+    let selected = match (module, modules.as_slice()) {
+        (Some(path), modules) => modules
+            .iter()
+            .find(|module| module.path == path)
+            .ok_or(miette!("buf.yaml declares no module in {}", path.display()))?,
+        (None, [module]) => module,
+        (None, []) => bail!("buf.yaml declares no modules"),
+        (None, _) => bail!(
+            help = "select one with `--module <path>`",
+            "buf.yaml declares several modules"
+        ),
+    };
+
+    let name = selected
+        .name
+        .as_deref()
+        .and_then(|name| name.parse::<ModuleRef>().ok())
+        .and_then(|module| name_of(&module.module));
+
+    adopt(None, name, &selected.path, false).await?;
+
+    let buf_lock = BufLockFile::from_file().wrap_err(miette!("failed to read buf.lock"))?;
+
+    for dep in buf_yaml.deps() {
+        let mut module: ModuleRef = dep.parse()?;
+
+        let locked = buf_lock
+            .as_ref()
+            .and_then(|buf_lock| buf_lock.commit(&module.name()));
+
+        if let (None, Some(commit)) = (&module.reference, locked) {
+            module = module.at(commit);
+        }
+
+        add(&module, config).await?;
+    }
+
+    Ok(())
+}
+
+/// Exports the package as a Buf module into [`EXPORT_PATH`], ready for `buf push`
+///
+/// The protos keep the import paths used by buffrs. Dependencies pulled from a Buf Schema
+/// Registry are declared at their pulled commits, all other dependencies are expected to be
+/// exported to the same owner as the package.
+pub async fn export(module: &ModuleRef) -> miette::Result<()> {
+    let manifest = Manifest::read().await?;
+    let store = PackageStore::current().await?;
+    let pulled = PulledPackages::read(Path::new(BSR_DIR)).await?;
+
+    let package = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to export a Buf module"
+    ))?;
+
+    let deps = manifest
+        .dependencies
+        .iter()
+        .map(|dependency| match pulled.get(&dependency.package) {
+            Some(pulled) => format!("{}:{}", pulled.module, pulled.commit),
+            None => format!("{}/{}/{}", module.remote, module.owner, dependency.package),
+        })
+        .collect::<BTreeSet<_>>();
+
+    store.populate(package).await?;
+
+    let vendor = store.proto_vendor_path();
+    let files = store.populated_files(package).await;
+    let root = Path::new(EXPORT_PATH);
+
+    fs::remove_dir_all(root).await.ok();
+    fs::create_dir_all(root)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create {EXPORT_PATH}"))?;
+
+    copy_files(&files, &vendor, root).await?;
+
+    let mut buf_yaml = BufYamlFile::new();
+
+    buf_yaml.clear_modules();
+    buf_yaml.add_named_module(".", &module.name());
+    buf_yaml.set_deps(deps.into_iter().collect());
+    buf_yaml.write_to(&root.join("buf.yaml"))?;
+
+    tracing::info!(":: exported {} to {EXPORT_PATH}", module.name());
+
+    Ok(())
+}
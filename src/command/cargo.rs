@@ -0,0 +1,95 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use miette::{ensure, miette, Context, IntoDiagnostic};
+use semver::Version;
+use tokio::fs;
+
+use super::write_cargo_version;
+use crate::{
+    cargo::read_version,
+    config::{Config, SyncDirection},
+    manifest::{Manifest, MANIFEST_FILE},
+};
+
+/// Reads the versions of the package and the Cargo manifest
+async fn versions(config: &Config) -> miette::Result<(Manifest, Version, String)> {
+    let manifest = Manifest::read().await?;
+    let path = &config.cargo().manifest;
+
+    let package = manifest.package.as_ref().ok_or(miette!(
+        "a [package] section must be declared to synchronize its version"
+    ))?;
+
+    let version = package.version.clone();
+
+    let contents = fs::read_to_string(path)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to read {}", path.display()))?;
+
+    Ok((manifest, version, contents))
+}
+
+/// Synchronizes the versions in the direction configured in `[cargo]`
+pub async fn sync(config: &Config) -> miette::Result<()> {
+    let settings = config.cargo();
+
+    let direction = settings.sync_version.ok_or(miette!(
+        help = "set `sync-version` in [cargo] to `read` or `write`",
+        "no direction to synchronize versions in is configured"
+    ))?;
+
+    let (mut manifest, version, contents) = versions(config).await?;
+
+    match direction {
+        SyncDirection::Read => {
+            let cargo = read_version(&contents)?;
+
+            if let Some(ref mut package) = manifest.package {
+                package.version = cargo.clone();
+            }
+
+            manifest.write().await?;
+
+            tracing::info!(":: set the version in {MANIFEST_FILE} to {cargo}");
+        }
+        SyncDirection::Write => {
+            write_cargo_version(&version, config).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Fails if the versions of the package and the Cargo manifest differ
+pub async fn check(config: &Config) -> miette::Result<()> {
+    let (_, version, contents) = versions(config).await?;
+    let cargo = read_version(&contents)?;
+    let path = &config.cargo().manifest;
+
+    ensure!(
+        version == cargo,
+        help = "run `buffrs cargo sync` to synchronize the versions",
+        "{MANIFEST_FILE} declares version {version}, but {} declares {cargo}",
+        path.display()
+    );
+
+    tracing::info!(
+        ":: {MANIFEST_FILE} and {} agree on version {version}",
+        path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,79 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use miette::{miette, Context, IntoDiagnostic};
+use semver::Version;
+use tokio::fs;
+
+use crate::{
+    config::Config,
+    image::{ImageSpec, DEVCONTAINER_DIR},
+    manifest::Manifest,
+};
+
+/// Emits a container image pinning buffrs, protoc and the plugins configured in `[ci]`
+///
+/// The Dockerfile is printed to stdout, or written into [`DEVCONTAINER_DIR`] together with a
+/// `devcontainer.json` using it.
+pub async fn image_spec(devcontainer: bool, config: &Config) -> miette::Result<()> {
+    let settings = config.ci();
+
+    let spec = ImageSpec {
+        buffrs: Version::parse(env!("CARGO_PKG_VERSION")).into_diagnostic()?,
+        protoc: settings.protoc.clone(),
+        plugins: settings.plugins.clone(),
+        run: settings.run.clone(),
+    };
+
+    if !devcontainer {
+        print!("{}", spec.dockerfile());
+
+        return Ok(());
+    }
+
+    let manifest = match Manifest::exists().await? {
+        true => Some(Manifest::read().await?),
+        false => None,
+    };
+
+    let name = manifest
+        .and_then(|manifest| manifest.package)
+        .map(|package| package.name.to_string())
+        .unwrap_or_else(|| "buffrs".to_owned());
+
+    let root = Path::new(DEVCONTAINER_DIR);
+
+    fs::create_dir_all(root)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create {DEVCONTAINER_DIR}"))?;
+
+    fs::write(root.join("Dockerfile"), spec.dockerfile())
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to write the Dockerfile"))?;
+
+    let devcontainer = serde_json::to_string_pretty(&spec.devcontainer(&name)).into_diagnostic()?;
+
+    fs::write(root.join("devcontainer.json"), devcontainer + "\n")
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to write devcontainer.json"))?;
+
+    tracing::info!(":: wrote the development container to {DEVCONTAINER_DIR}");
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::Path;
+
+use miette::{miette, IntoDiagnostic};
+use tokio::fs;
+
+use crate::config::Config;
+
+/// Prints all effective settings as `key = value` lines
+pub async fn list(config: &Config) -> miette::Result<()> {
+    for (key, value) in config.list() {
+        println!("{key} = {value}");
+    }
+
+    Ok(())
+}
+
+/// Prints the effective value of a setting
+pub async fn get(config: &Config, key: &str) -> miette::Result<()> {
+    let value = config
+        .get(key)
+        .ok_or(miette!("the setting `{key}` is not configured"))?;
+
+    match value {
+        toml::Value::String(value) => println!("{value}"),
+        value => println!("{value}"),
+    }
+
+    Ok(())
+}
+
+/// Writes a setting to the project or the global configuration file
+///
+/// The configuration is reloaded afterwards, and the file restored if the setting turns out
+/// to be invalid.
+pub async fn set(cwd: &Path, global: bool, key: &str, value: &str) -> miette::Result<()> {
+    let path = if global {
+        Config::global_config_path().ok_or(miette!(
+            "failed to locate the global configuration directory"
+        ))?
+    } else {
+        Config::project_config_path(cwd)
+    };
+
+    let previous = fs::read(&path).await.ok();
+
+    Config::set(&path, key, value)?;
+
+    if let Err(error) = Config::new(Some(cwd)) {
+        match previous {
+            Some(contents) => fs::write(&path, contents).await.into_diagnostic()?,
+            None => fs::remove_file(&path).await.into_diagnostic()?,
+        }
+
+        return Err(error.wrap_err(miette!("invalid value for `{key}`: {value}")));
+    }
+
+    tracing::info!(":: set {key} in {}", path.display());
+
+    Ok(())
+}
@@ -0,0 +1,222 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use miette::{bail, ensure, miette, Context, IntoDiagnostic};
+use strum::{Display, EnumString};
+use tokio::{
+    fs,
+    io::{self, AsyncBufReadExt, BufReader},
+};
+
+use super::{breaking, git_output, lint, FailOn, OutputFormat};
+use crate::{
+    config::{Config, GitCheck},
+    manifest::{Manifest, MANIFEST_FILE},
+};
+
+/// Comment marking hooks written by buffrs, which may be replaced without `--force`
+const MARKER: &str = "# installed by buffrs hooks install";
+
+/// A git hook installed by buffrs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum GitHook {
+    /// Runs before a commit is created
+    PreCommit,
+    /// Runs before refs are pushed to a remote
+    PrePush,
+}
+
+impl GitHook {
+    const ALL: [Self; 2] = [Self::PreCommit, Self::PrePush];
+
+    /// The shell script git runs for this hook
+    ///
+    /// The script changes into the package directory, which may be nested in the repository,
+    /// and passes on the arguments and standard input git provides.
+    fn script(&self, prefix: &str) -> String {
+        format!(
+            "#!/bin/sh\n{MARKER}\ncd \"$(git rev-parse --show-toplevel)/{prefix}\" || exit 1\nexec buffrs hooks run {self} \"$@\"\n"
+        )
+    }
+}
+
+/// Installs the pre-commit and pre-push hooks into the git repository of the package
+///
+/// # Arguments
+///  * `force` - Replace hooks that were not installed by buffrs
+pub async fn install(force: bool) -> miette::Result<()> {
+    ensure!(
+        Manifest::exists().await?,
+        "no `{MANIFEST_FILE}` found in the current directory"
+    );
+
+    let hooks = PathBuf::from(git_output(&["rev-parse", "--git-path", "hooks"]).await?);
+    let prefix = git_output(&["rev-parse", "--show-prefix"]).await?;
+
+    for hook in GitHook::ALL {
+        let path = hooks.join(hook.to_string());
+
+        let Ok(existing) = fs::read_to_string(&path).await else {
+            continue;
+        };
+
+        if !force && !existing.contains(MARKER) {
+            bail!(
+                help = "pass `--force` to replace it",
+                "{} was not installed by buffrs",
+                path.display()
+            );
+        }
+    }
+
+    fs::create_dir_all(&hooks)
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to create {}", hooks.display()))?;
+
+    for hook in GitHook::ALL {
+        let path = hooks.join(hook.to_string());
+
+        fs::write(&path, hook.script(&prefix))
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write {}", path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to make {} executable", path.display()))?;
+        }
+
+        tracing::info!(":: installed the {hook} hook to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Runs the checks configured for a hook in `[git-hooks]`
+///
+/// The checks are skipped if the commit or push does not change any proto file or the
+/// manifest of the package.
+pub async fn run(hook: GitHook, config: &Config) -> miette::Result<()> {
+    let settings = config.git_hooks();
+
+    let checks = match hook {
+        GitHook::PreCommit => &settings.pre_commit,
+        GitHook::PrePush => &settings.pre_push,
+    };
+
+    if checks.is_empty() {
+        return Ok(());
+    }
+
+    let changed = match hook {
+        GitHook::PreCommit => {
+            Some(changed_files(&["diff", "--cached", "--name-only", "--relative"]).await?)
+        }
+        GitHook::PrePush => pushed_files().await?,
+    };
+
+    // without a baseline, e.g. for new branches, the changes are unknown
+    if let Some(changed) = changed {
+        let relevant = changed
+            .iter()
+            .any(|file| file.ends_with(".proto") || file == MANIFEST_FILE);
+
+        if !relevant {
+            tracing::debug!(":: no changes to the package, skipping the {hook} checks");
+            return Ok(());
+        }
+    }
+
+    for check in checks {
+        match check {
+            GitCheck::Lint => {
+                lint(false, Some(FailOn::Error), OutputFormat::Human, config).await?;
+            }
+            GitCheck::Breaking => {
+                let Some(repository) = &settings.repository else {
+                    tracing::warn!(
+                        ":: skipping the breaking check, `git-hooks.repository` is not configured"
+                    );
+                    continue;
+                };
+
+                let registry = config.resolve_registry_string(&settings.registry)?;
+
+                breaking(
+                    &registry,
+                    repository.clone(),
+                    None,
+                    false,
+                    FailOn::Error,
+                    config,
+                )
+                .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the files printed by a git command, relative to the current directory
+async fn changed_files(args: &[&str]) -> miette::Result<Vec<String>> {
+    let output = git_output(args).await?;
+
+    Ok(output.lines().map(String::from).collect())
+}
+
+/// Lists the files changed by the pushed refs, which git passes on the standard input
+///
+/// Returns `None` if a ref has no known baseline, e.g. because a new branch is pushed.
+async fn pushed_files() -> miette::Result<Option<Vec<String>>> {
+    let mut lines = BufReader::new(io::stdin()).lines();
+    let mut files = Vec::new();
+
+    let unset = |sha: &str| sha.bytes().all(|byte| byte == b'0');
+
+    while let Some(line) = lines.next_line().await.into_diagnostic()? {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let [_, local, _, remote] = fields[..] else {
+            continue;
+        };
+
+        // deleted refs push no changes
+        if unset(local) {
+            continue;
+        }
+
+        if unset(remote) {
+            return Ok(None);
+        }
+
+        let range = format!("{remote}..{local}");
+
+        match changed_files(&["diff", "--name-only", "--relative", &range]).await {
+            Ok(changed) => files.extend(changed),
+            Err(_) => return Ok(None),
+        }
+    }
+
+    Ok(Some(files))
+}
@@ -0,0 +1,29 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::lock::{FileRequirement, Lockfile};
+
+/// Prints the file requirements serialized as JSON
+pub async fn print_files() -> miette::Result<()> {
+    let lock = Lockfile::read().await?;
+
+    let requirements: Vec<FileRequirement> = lock.into();
+
+    // hint: always ok, as per serde_json doc
+    if let Ok(json) = serde_json::to_string_pretty(&requirements) {
+        println!("{json}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+
+use miette::{ensure, miette, Context};
+use semver::{Version, VersionReq};
+use serde_json::json;
+use strum::Display;
+
+use super::{print_json, OutputFormat};
+use crate::{
+    cancel::Cancel,
+    config::Config,
+    credentials::Credentials,
+    lock::DigestAlgorithm,
+    manifest::Dependency,
+    package::PackageName,
+    registry::{Artifactory, RegistryUri},
+    signing::{GPG_EXTENSION, SIGSTORE_EXTENSION},
+};
+
+/// A registry and repository packages are mirrored from or to
+pub struct Endpoint<'a> {
+    /// The registry
+    pub registry: &'a RegistryUri,
+    /// The repository within the registry
+    pub repository: &'a str,
+}
+
+/// Outcome of mirroring a package version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "kebab-case")]
+enum Outcome {
+    /// The version was copied to the mirror
+    Copied,
+    /// The mirror already stores the version
+    Present,
+    /// The version would be copied, but this is a dry run
+    Missing,
+}
+
+/// Clients of the source and the mirror
+struct Mirror<'a> {
+    source: Artifactory,
+    from: Endpoint<'a>,
+    mirror: Artifactory,
+    to: Endpoint<'a>,
+    /// Whether the digests stored by the source can be compared to the archives, which is not
+    /// the case for encrypted registries
+    verify_source: bool,
+    /// Whether the digests stored by the mirror can be compared to the archives
+    verify_mirror: bool,
+}
+
+/// Copies the packages of a repository to another registry or repository
+///
+/// Versions the mirror already stores are skipped after comparing their digests, so that
+/// interrupted runs can be resumed. Copied archives are verified against the digest reported
+/// by the source before they are uploaded and against the digest reported by the mirror
+/// afterwards. Detached signatures are copied ahead of their packages.
+///
+/// # Arguments
+/// * `from` - The registry and repository to copy from
+/// * `to` - The registry and repository to copy to
+/// * `packages` - The packages to copy (all packages of the repository if empty)
+/// * `versions` - Only copy the versions matching this requirement
+/// * `dry_run` - Only report the versions that would be copied
+/// * `format` - The output format
+#[allow(clippy::too_many_arguments)]
+pub async fn sync(
+    from: Endpoint<'_>,
+    to: Endpoint<'_>,
+    packages: &[PackageName],
+    versions: Option<&VersionReq>,
+    dry_run: bool,
+    format: OutputFormat,
+    config: &Config,
+    cancel: &Cancel,
+) -> miette::Result<serde_json::Value> {
+    ensure!(
+        from.registry != to.registry || from.repository != to.repository,
+        "the source and the mirror are the same repository"
+    );
+
+    ensure!(
+        config.encryption(from.registry).is_none() || config.encryption(to.registry).is_some(),
+        help = "configure a key for the mirror in an `[encryption.<alias>]` section to keep the packages encrypted",
+        "{} is encrypted, its packages would be mirrored in plaintext",
+        from.registry
+    );
+
+    let credentials = Credentials::load().await?;
+
+    let mirror = Mirror {
+        source: Artifactory::new(from.registry, &credentials)?
+            .with_network(config.network())?
+            .with_encryption(config.encryption(from.registry)),
+        mirror: Artifactory::new(to.registry, &credentials)?
+            .with_network(config.network())?
+            .with_encryption(config.encryption(to.registry)),
+        verify_source: config.encryption(from.registry).is_none(),
+        verify_mirror: config.encryption(to.registry).is_none(),
+        from,
+        to,
+    };
+
+    let mut published = mirror.source.search(mirror.from.repository, "").await?;
+
+    if !packages.is_empty() {
+        published.retain(|name, _| packages.contains(name));
+
+        for name in packages.iter().filter(|name| !published.contains_key(name)) {
+            tracing::warn!(":: {name} is not published to {}", mirror.from.repository);
+        }
+    }
+
+    let mut mirrored = Vec::new();
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+
+    for (name, mut available) in published {
+        available.retain(|version| versions.is_none_or(|req| req.matches(version)));
+        // copy the oldest versions first, so that the mirror never skips a version
+        available.reverse();
+
+        for version in available {
+            cancel.check()?;
+
+            let outcome = mirror
+                .copy(&name, &version, dry_run, cancel)
+                .await
+                .wrap_err(miette!("failed to mirror {name}@{version}"))?;
+
+            match outcome {
+                Outcome::Copied => tracing::info!(":: copied {name}@{version}"),
+                Outcome::Present => tracing::debug!(":: {name}@{version} is already present"),
+                Outcome::Missing => tracing::info!(":: would copy {name}@{version}"),
+            }
+
+            *counts.entry(outcome.to_string()).or_default() += 1;
+
+            mirrored.push(json!({
+                "name": name,
+                "version": version.to_string(),
+                "status": outcome.to_string(),
+            }));
+        }
+    }
+
+    let result = json!({
+        "from": format!("{}:{}", mirror.from.registry, mirror.from.repository),
+        "to": format!("{}:{}", mirror.to.registry, mirror.to.repository),
+        "packages": mirrored,
+        "dry_run": dry_run,
+    });
+
+    let count = |outcome: Outcome| counts.get(&outcome.to_string()).copied().unwrap_or(0);
+
+    match (format, dry_run) {
+        (OutputFormat::Human, true) => tracing::info!(
+            ":: {} versions would be copied, {} are already present",
+            count(Outcome::Missing),
+            count(Outcome::Present)
+        ),
+        (OutputFormat::Human, false) => tracing::info!(
+            ":: copied {} versions, {} were already present",
+            count(Outcome::Copied),
+            count(Outcome::Present)
+        ),
+        (OutputFormat::Json, _) => print_json(&result),
+    }
+
+    Ok(result)
+}
+
+impl Mirror<'_> {
+    /// Copies a single package version unless the mirror already stores it
+    async fn copy(
+        &self,
+        name: &PackageName,
+        version: &Version,
+        dry_run: bool,
+        cancel: &Cancel,
+    ) -> miette::Result<Outcome> {
+        let (from, to) = (&self.from, &self.to);
+
+        if self
+            .mirror
+            .is_published(to.repository, name, version)
+            .await?
+        {
+            if self.verify_source && self.verify_mirror {
+                let expected = self.source.checksum(from.repository, name, version).await?;
+                let actual = self.mirror.checksum(to.repository, name, version).await?;
+
+                if let (Some(expected), Some(actual)) = (expected, actual) {
+                    ensure!(
+                        expected == actual,
+                        code = "E0003",
+                        help = "the version was published to the mirror independently, remove it from the mirror to copy it again",
+                        "the mirror stores the digest {actual}, the source {expected}"
+                    );
+                }
+            }
+
+            return Ok(Outcome::Present);
+        }
+
+        if dry_run {
+            return Ok(Outcome::Missing);
+        }
+
+        let dependency = Dependency::new(
+            from.registry,
+            from.repository.to_owned(),
+            name.clone(),
+            VersionReq::STAR,
+        )
+        .with_version(version);
+
+        // chunk indices carry the digest of the archive, which assembling verifies
+        let package = match self.source.download_chunk_index(&dependency).await? {
+            Some(index) => {
+                let mut chunks = Vec::with_capacity(index.chunks.len());
+
+                for digest in &index.chunks {
+                    cancel.check()?;
+
+                    chunks.push(
+                        self.source
+                            .download_chunk(from.repository, name, digest)
+                            .await?,
+                    );
+                }
+
+                index.assemble(&chunks)?
+            }
+            None => {
+                let package = cancel.run(self.source.download(dependency)).await??;
+
+                if self.verify_source {
+                    if let Some(expected) =
+                        self.source.checksum(from.repository, name, version).await?
+                    {
+                        let actual = expected.algorithm().digest(&package.tgz);
+
+                        ensure!(
+                            actual == expected,
+                            code = "E0003",
+                            "the downloaded archive has the digest {actual}, the source reports {expected}"
+                        );
+                    }
+                }
+
+                package
+            }
+        };
+
+        let digest = DigestAlgorithm::SHA256.digest(&package.tgz);
+
+        // signatures go first, the archive marks the version as present for resumed runs
+        for extension in [SIGSTORE_EXTENSION, GPG_EXTENSION] {
+            let signature = self
+                .source
+                .download_signature(from.repository, name, version, extension)
+                .await?;
+
+            if let Some(signature) = signature {
+                self.mirror
+                    .publish_signature(&package, to.repository, extension, signature)
+                    .await?;
+            }
+        }
+
+        self.mirror
+            .publish(package.clone(), to.repository.to_owned())
+            .await?;
+
+        if !self.verify_mirror {
+            return Ok(Outcome::Copied);
+        }
+
+        match self.mirror.checksum(to.repository, name, version).await? {
+            Some(stored) => ensure!(
+                stored == digest,
+                code = "E0003",
+                "the mirror stores the digest {stored}, the uploaded archive has {digest}"
+            ),
+            None => tracing::warn!(
+                ":: the mirror does not report a digest for {name}@{version}, it was not verified"
+            ),
+        }
+
+        Ok(Outcome::Copied)
+    }
+}
@@ -12,19 +12,46 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::registry::RegistryUri;
+use crate::{
+    encryption::Encryption, output::OutputProfile, package::LinkMode, registry::RegistryUri,
+    signing::TrustPolicy,
+};
 use miette::{bail, ensure, miette, Context, IntoDiagnostic};
+use semver::Version;
+use serde::Deserialize;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     path::{Path, PathBuf},
     str::FromStr,
 };
+use url::Url;
 
 // Location of the configuration file
 const CONFIG_FILE: &str = ".buffrs/config.toml";
 
+// Location of the global configuration file within the user's configuration directory
+const GLOBAL_CONFIG_FILE: &str = "buffrs/config.toml";
+
+/// Environment variables overriding single settings of the configuration files
+pub const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("BUFFRS_REGISTRY", "registry.default"),
+    ("BUFFRS_PROXY", "network.proxy"),
+    ("BUFFRS_RETRIES", "network.retries"),
+    ("BUFFRS_COLOR", "output.color"),
+    ("BUFFRS_OUTPUT_PROFILE", "output.profile"),
+    ("BUFFRS_LINK", "install.link"),
+    ("BUFFRS_WORKER_THREADS", "runtime.worker-threads"),
+    (
+        "BUFFRS_MAX_BLOCKING_THREADS",
+        "runtime.max-blocking-threads",
+    ),
+];
+
 /// Representation of the .config/buffrs/config.toml configuration file
 ///
+/// The configuration is layered: the global `~/.config/buffrs/config.toml` is overridden by the
+/// `.buffrs/config.toml` of the project, which in turn is overridden by [`ENV_OVERRIDES`].
+///
 /// # Example
 ///
 /// ```toml
@@ -36,13 +63,47 @@ const CONFIG_FILE: &str = ".buffrs/config.toml";
 ///
 /// [commands.install]
 /// default_args = ["--buf-yaml"]
+///
+/// [alias]
+/// i = "install --buf-yaml"
+///
+/// [hooks]
+/// post-install = "buffrs generate --lang rust"
+///
+/// [build]
+/// includes = ["third_party/googleapis"]
+///
+/// [install]
+/// link = "hardlink"
+///
+/// [network]
+/// proxy = "http://proxy.example.com:3128"
+/// retries = 3
+///
+/// [output]
+/// color = "never"
+/// profile = "ci"
+///
+/// [runtime]
+/// worker-threads = 4
+///
+/// [signing.some_org]
+/// require-signatures = true
+/// key = "keys/cosign.pub"
+///
+/// [encryption.some_org]
+/// recipients = ["age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"]
+/// identity = "keys/age.txt"
 /// ```
 ///
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     /// Path to the configuration file
     config_path: Option<PathBuf>,
 
+    /// Effective settings of all layers
+    settings: toml::Table,
+
     /// Default registry to use if none is specified
     default_registry: Option<String>,
 
@@ -51,6 +112,229 @@ pub struct Config {
 
     /// Default arguments for commands
     command_defaults: HashMap<String, Vec<String>>,
+
+    /// Shortcuts expanding into commands and their arguments
+    aliases: HashMap<String, Vec<String>>,
+
+    /// Shell commands run after buffrs commands, keyed by `post-<command>`
+    hooks: HashMap<String, String>,
+
+    /// Additional include paths for compiling protocol buffers
+    include_paths: Vec<PathBuf>,
+
+    /// Proxy and retry settings for registry requests
+    network: Network,
+
+    /// How packages are installed into projects
+    install: Install,
+
+    /// Preferences for the terminal output
+    output: Output,
+
+    /// Sizing of the async runtime
+    runtime: Runtime,
+
+    /// Checks run by the installed git hooks
+    git_hooks: GitHooks,
+
+    /// Confluent schema registry messages are registered in when publishing
+    schema_registry: Option<SchemaRegistry>,
+
+    /// Toolchain of the CI images
+    ci: Ci,
+
+    /// Synchronization of the package version with a Cargo manifest
+    cargo: Cargo,
+
+    /// Signature requirements keyed by registry alias
+    trust_policies: HashMap<String, TrustPolicy>,
+
+    /// Encryption keys keyed by registry alias
+    encryption: HashMap<String, Encryption>,
+}
+
+/// Proxy and retry settings for registry requests, read from `[network]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Network {
+    /// Proxy all registry requests are sent through
+    pub proxy: Option<String>,
+    /// How often requests failing with a connection or server error are retried
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// How packages are installed into projects, read from `[install]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Install {
+    /// How installed packages are created from their unpacked copies in the cache
+    #[serde(default)]
+    pub link: LinkMode,
+}
+
+/// Preferences for the terminal output, read from `[output]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Output {
+    /// Whether to color the output
+    #[serde(default)]
+    pub color: ColorChoice,
+    /// How log messages are rendered
+    #[serde(default)]
+    pub profile: OutputProfile,
+}
+
+/// Sizing of the async runtime buffrs runs on, read from `[runtime]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Runtime {
+    /// Threads running async tasks, a single-threaded runtime is used if unset
+    pub worker_threads: Option<usize>,
+    /// Upper bound of the threads running blocking file system and archive operations
+    pub max_blocking_threads: Option<usize>,
+}
+
+impl Runtime {
+    /// Builds an async runtime with these settings
+    pub fn build(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = match self.worker_threads {
+            Some(threads) => {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                builder.worker_threads(threads.max(1));
+                builder
+            }
+            None => tokio::runtime::Builder::new_current_thread(),
+        };
+
+        if let Some(threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(threads.max(1));
+        }
+
+        builder.enable_all().build()
+    }
+}
+
+/// Checks run by the git hooks installed with `buffrs hooks install`, read from `[git-hooks]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct GitHooks {
+    /// Checks run before commits changing protos or the manifest
+    pub pre_commit: Vec<GitCheck>,
+    /// Checks run before pushes changing protos or the manifest
+    pub pre_push: Vec<GitCheck>,
+    /// Registry the package is published to, the default registry if unset
+    pub registry: Option<String>,
+    /// Repository the package is published to, required by the `breaking` check
+    pub repository: Option<String>,
+}
+
+impl Default for GitHooks {
+    fn default() -> Self {
+        Self {
+            pre_commit: vec![GitCheck::Lint],
+            pre_push: vec![GitCheck::Lint, GitCheck::Breaking],
+            registry: None,
+            repository: None,
+        }
+    }
+}
+
+/// A check run by a git hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitCheck {
+    /// `buffrs lint`
+    Lint,
+    /// `buffrs breaking` against the latest published version
+    Breaking,
+}
+
+/// Confluent schema registry the messages of published packages are registered in, read from
+/// `[schema-registry]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SchemaRegistry {
+    /// Base URL of the schema registry
+    pub url: Url,
+    /// Fully qualified names of the messages to register, e.g. `physics.units.Mass`
+    #[serde(default)]
+    pub messages: Vec<String>,
+    /// Template of the subject names, see [`subject`](crate::registry::confluent::subject)
+    #[serde(default = "SchemaRegistry::default_subject")]
+    pub subject: String,
+}
+
+impl SchemaRegistry {
+    /// Names subjects after the fully qualified message names, like the `RecordNameStrategy`
+    fn default_subject() -> String {
+        "{message}".to_owned()
+    }
+}
+
+/// Toolchain of the images emitted by `buffrs ci image-spec`, read from `[ci]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct Ci {
+    /// Release of protoc installed in the image
+    pub protoc: String,
+    /// protoc plugins installed with `cargo install`, by crate name
+    pub plugins: BTreeMap<String, Version>,
+    /// Further commands run while building the image, e.g. to install plugins of other ecosystems
+    pub run: Vec<String>,
+}
+
+impl Default for Ci {
+    fn default() -> Self {
+        Self {
+            protoc: "25.1".to_owned(),
+            plugins: BTreeMap::new(),
+            run: Vec::new(),
+        }
+    }
+}
+
+/// Synchronization of the package version with the Cargo manifest of a Rust crate, read from
+/// `[cargo]`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct Cargo {
+    /// Which manifest the version is taken from, versions are not synchronized if unset
+    pub sync_version: Option<SyncDirection>,
+    /// Path of the Cargo manifest, relative to the project
+    pub manifest: PathBuf,
+}
+
+impl Default for Cargo {
+    fn default() -> Self {
+        Self {
+            sync_version: None,
+            manifest: PathBuf::from(crate::cargo::CARGO_MANIFEST),
+        }
+    }
+}
+
+/// Direction versions are synchronized in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncDirection {
+    /// The package takes the version of the Cargo manifest
+    Read,
+    /// The Cargo manifest takes the version of the package
+    Write,
+}
+
+/// Whether to color the terminal output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color the output if it is written to a terminal
+    #[default]
+    Auto,
+    /// Always color the output
+    Always,
+    /// Never color the output
+    Never,
 }
 
 impl Config {
@@ -59,15 +343,111 @@ impl Config {
     /// * `cwd` - Starting directory to search for the configuration file
     ///
     pub fn new(cwd: Option<&Path>) -> miette::Result<Self> {
-        match Self::locate_config(cwd) {
-            Some(config_path) => Self::new_from_config_file(&config_path),
-            None => Ok(Self {
-                config_path: None,
-                default_registry: None,
-                registries: HashMap::new(),
-                command_defaults: HashMap::new(),
-            }),
+        let config_path = Self::locate_config(cwd);
+
+        let mut settings = toml::Table::new();
+
+        let layers = Self::global_config_path()
+            .filter(|path| path.exists())
+            .into_iter()
+            .chain(config_path.clone());
+
+        for path in layers {
+            merge(&mut settings, Self::read_layer(&path)?);
         }
+
+        for (var, key) in ENV_OVERRIDES {
+            if let Ok(value) = std::env::var(var) {
+                insert(&mut settings, key, parse_value(&value))
+                    .wrap_err(miette!("invalid value of {var}"))?;
+            }
+        }
+
+        Self::from_settings(settings, config_path)
+    }
+
+    /// Location of the global configuration file, `~/.config/buffrs/config.toml` by default
+    ///
+    /// Honors `XDG_CONFIG_HOME` if it is set.
+    pub fn global_config_path() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home::home_dir().map(|home| home.join(".config")))
+            .map(|dir| dir.join(GLOBAL_CONFIG_FILE))
+    }
+
+    /// Location of the configuration file of the project in a directory
+    pub fn project_config_path(dir: &Path) -> PathBuf {
+        dir.join(CONFIG_FILE)
+    }
+
+    /// Path of the project configuration file in use, if any
+    pub fn config_path(&self) -> Option<&Path> {
+        self.config_path.as_deref()
+    }
+
+    /// Looks up the effective value of a setting by its dotted key, e.g. `network.retries`
+    pub fn get(&self, key: &str) -> Option<&toml::Value> {
+        let mut parts = key.split('.');
+        let mut value = self.settings.get(parts.next()?)?;
+
+        for part in parts {
+            value = value.get(part)?;
+        }
+
+        Some(value)
+    }
+
+    /// All effective settings as pairs of dotted keys and values, sorted by key
+    pub fn list(&self) -> Vec<(String, toml::Value)> {
+        fn flatten(prefix: &str, table: &toml::Table, settings: &mut Vec<(String, toml::Value)>) {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.to_owned()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                match value {
+                    toml::Value::Table(table) => flatten(&key, table, settings),
+                    value => settings.push((key, value.clone())),
+                }
+            }
+        }
+
+        let mut settings = Vec::new();
+
+        flatten("", &self.settings, &mut settings);
+
+        settings.sort_by(|(a, _), (b, _)| a.cmp(b));
+        settings
+    }
+
+    /// Writes a setting to a configuration file, creating the file if needed
+    ///
+    /// The value is parsed as a TOML value, e.g. `3` or `["--buf-yaml"]`, and stored as a string
+    /// otherwise.
+    pub fn set(path: &Path, key: &str, value: &str) -> miette::Result<()> {
+        let mut settings = Self::read_table(path)?;
+
+        insert(&mut settings, key, parse_value(value))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to create {}", parent.display()))?;
+        }
+
+        let contents = toml::to_string(&settings)
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "failed to serialize config file: {}",
+                path.display()
+            ))?;
+
+        std::fs::write(path, contents)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write config file: {}", path.display()))
     }
 
     /// Resolve the registry URI from the configuration
@@ -159,6 +539,86 @@ impl Config {
             .unwrap_or_default()
     }
 
+    /// Expansion of a command alias declared in the `[alias]` section, if any
+    pub fn alias(&self, name: &str) -> Option<&[String]> {
+        self.aliases.get(name).map(Vec::as_slice)
+    }
+
+    /// Shell command declared in the `[hooks]` section to run after a command, if any
+    pub fn hook(&self, command: &str) -> Option<&str> {
+        self.hooks
+            .get(&format!("post-{command}"))
+            .map(String::as_str)
+    }
+
+    /// Additional include paths for compiling protocol buffers
+    ///
+    /// These are read from `[build.includes]` and are resolved relative to the directory
+    /// containing the `.buffrs` configuration directory.
+    pub fn include_paths(&self) -> &[PathBuf] {
+        &self.include_paths
+    }
+
+    /// Proxy and retry settings for registry requests
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    /// How packages are installed into projects
+    pub fn install(&self) -> &Install {
+        &self.install
+    }
+
+    /// Preferences for the terminal output
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Sizing of the async runtime
+    pub fn runtime(&self) -> &Runtime {
+        &self.runtime
+    }
+
+    /// Checks run by the installed git hooks
+    pub fn git_hooks(&self) -> &GitHooks {
+        &self.git_hooks
+    }
+
+    /// Schema registry messages are registered in when publishing, if configured
+    pub fn schema_registry(&self) -> Option<&SchemaRegistry> {
+        self.schema_registry.as_ref()
+    }
+
+    /// Toolchain of the CI images
+    pub fn ci(&self) -> &Ci {
+        &self.ci
+    }
+
+    /// Synchronization of the package version with a Cargo manifest
+    pub fn cargo(&self) -> &Cargo {
+        &self.cargo
+    }
+
+    /// Signature requirements for packages installed from a registry
+    ///
+    /// These are read from `[signing.<alias>]` and apply to the registry the alias resolves to.
+    pub fn trust_policy(&self, registry: &RegistryUri) -> Option<&TrustPolicy> {
+        self.registries
+            .iter()
+            .find(|(_, uri)| *uri == registry)
+            .and_then(|(alias, _)| self.trust_policies.get(alias))
+    }
+
+    /// Keys encrypting the packages published to and installed from a registry
+    ///
+    /// These are read from `[encryption.<alias>]` and apply to the registry the alias resolves to.
+    pub fn encryption(&self, registry: &RegistryUri) -> Option<&Encryption> {
+        self.registries
+            .iter()
+            .find(|(_, uri)| *uri == registry)
+            .and_then(|(alias, _)| self.encryption.get(alias))
+    }
+
     /// Locate the configuration file in the current directory or any parent directories
     ///
     /// # Arguments
@@ -185,21 +645,86 @@ impl Config {
         None
     }
 
-    /// Create configuration from a TOML file
-    ///
-    /// # Arguments
-    /// * `config_path` - Path to the configuration file
-    fn new_from_config_file(config_path: &Path) -> miette::Result<Self> {
+    /// Read the settings of a configuration file, which are empty if the file does not exist
+    fn read_table(config_path: &Path) -> miette::Result<toml::Table> {
+        if !config_path.exists() {
+            return Ok(toml::Table::new());
+        }
+
         let config = std::fs::read_to_string(config_path)
             .into_diagnostic()
             .wrap_err(miette!(
                 "failed to read config file: {}",
                 config_path.display()
             ))?;
-        let config: toml::Value = toml::from_str(&config).into_diagnostic().wrap_err(miette!(
+
+        toml::from_str(&config).into_diagnostic().wrap_err(miette!(
             "failed to parse config file: {}",
             config_path.display()
-        ))?;
+        ))
+    }
+
+    /// Read a layer of the configuration, resolving the paths it contains
+    ///
+    /// Paths in the configuration of a project are relative to the project root, paths in the
+    /// global configuration relative to the directory of the file.
+    fn read_layer(config_path: &Path) -> miette::Result<toml::Table> {
+        let mut settings = Self::read_table(config_path)?;
+
+        let dir = config_path.parent().unwrap_or(Path::new("."));
+        let root = match dir.file_name() {
+            Some(name) if name == crate::BUFFRS_HOME => dir.parent().unwrap_or(Path::new(".")),
+            _ => dir,
+        };
+
+        let resolve = |value: &mut toml::Value| {
+            if let toml::Value::String(path) = value {
+                *path = root.join(&*path).to_string_lossy().into_owned();
+            }
+        };
+
+        if let Some(includes) = settings
+            .get_mut("build")
+            .and_then(|build| build.get_mut("includes"))
+            .and_then(|includes| includes.as_array_mut())
+        {
+            includes.iter_mut().for_each(resolve);
+        }
+
+        for (section, keys) in [
+            ("signing", &["key", "gpg-keyring"][..]),
+            ("encryption", &["identity"][..]),
+        ] {
+            let Some(aliases) = settings
+                .get_mut(section)
+                .and_then(|aliases| aliases.as_table_mut())
+            else {
+                continue;
+            };
+
+            for (_, table) in aliases.iter_mut() {
+                for key in keys {
+                    if let Some(path) = table.get_mut(*key) {
+                        resolve(path);
+                    }
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Create configuration from the merged settings of all layers
+    ///
+    /// # Arguments
+    /// * `config` - The effective settings
+    /// * `config_path` - Path to the configuration file of the project, if any
+    fn from_settings(config: toml::Table, config_path: Option<PathBuf>) -> miette::Result<Self> {
+        let source = config_path
+            .as_deref()
+            .unwrap_or(Path::new("configuration"))
+            .display()
+            .to_string();
 
         // Load registries from [registries] section
         let registries = config
@@ -213,15 +738,14 @@ impl Config {
                             .as_str()
                             .ok_or_else(|| miette!("registry URI must be a string"))
                             .wrap_err(miette!("invalid URI for registry '{}'", name))
-                            .wrap_err(miette!("in config file: {}", config_path.display()))?;
+                            .wrap_err(miette!("in config file: {source}"))?;
                         Ok((name.to_string(), RegistryUri::from_str(uri)?))
                     })
                     .collect::<miette::Result<HashMap<String, RegistryUri>>>()
             })
             .unwrap_or_else(|| Ok(HashMap::new()))
             .wrap_err(miette!(
-                "failed to load registries from config file: {}",
-                config_path.display()
+                "failed to load registries from config file: {source}"
             ))?;
 
         // Locate default registry from [registry.default]
@@ -263,15 +787,426 @@ impl Config {
             })
             .unwrap_or_else(|| Ok(HashMap::new()))
             .wrap_err(miette!(
-                "failed to load command defaults from config file: {}",
-                config_path.display()
+                "failed to load command defaults from config file: {source}"
             ))?;
 
+        // Load command aliases from [alias], either a string split at whitespace or an array
+        let aliases = config
+            .get("alias")
+            .and_then(|aliases| aliases.as_table())
+            .map(|aliases| {
+                aliases
+                    .iter()
+                    .map(|(alias, expansion)| {
+                        let args: Vec<String> = match expansion {
+                            toml::Value::String(expansion) => {
+                                expansion.split_whitespace().map(String::from).collect()
+                            }
+                            toml::Value::Array(args) => args
+                                .iter()
+                                .map(|arg| {
+                                    arg.as_str()
+                                        .map(String::from)
+                                        .ok_or_else(|| miette!("alias arguments must be strings"))
+                                })
+                                .collect::<miette::Result<_>>()?,
+                            _ => bail!("alias must be a string or an array of strings"),
+                        };
+
+                        ensure!(!args.is_empty(), "alias '{alias}' is empty");
+
+                        Ok((alias.to_string(), args))
+                    })
+                    .collect::<miette::Result<HashMap<String, Vec<String>>>>()
+            })
+            .unwrap_or_else(|| Ok(HashMap::new()))
+            .wrap_err(miette!("failed to load aliases from config file: {source}"))?;
+
+        // Load post-command hooks from [hooks]
+        let hooks = config
+            .get("hooks")
+            .and_then(|hooks| hooks.as_table())
+            .map(|hooks| {
+                hooks
+                    .iter()
+                    .map(|(hook, command)| {
+                        ensure!(
+                            hook.starts_with("post-"),
+                            "hook '{hook}' must be named after the command it follows, e.g. post-install"
+                        );
+
+                        let command = command
+                            .as_str()
+                            .ok_or_else(|| miette!("hook '{hook}' must be a string"))?;
+
+                        Ok((hook.to_string(), command.to_string()))
+                    })
+                    .collect::<miette::Result<HashMap<String, String>>>()
+            })
+            .unwrap_or_else(|| Ok(HashMap::new()))
+            .wrap_err(miette!("failed to load hooks from config file: {source}"))?;
+
+        // Load extra include paths from [build.includes]
+        let include_paths = config
+            .get("build")
+            .and_then(|build| build.get("includes"))
+            .and_then(|includes| includes.as_array())
+            .map(|includes| {
+                includes
+                    .iter()
+                    .map(|include| {
+                        include
+                            .as_str()
+                            .map(PathBuf::from)
+                            .ok_or_else(|| miette!("include paths must be strings"))
+                    })
+                    .collect::<miette::Result<Vec<PathBuf>>>()
+            })
+            .unwrap_or_else(|| Ok(Vec::new()))
+            .wrap_err(miette!(
+                "failed to load include paths from config file: {source}"
+            ))?;
+
+        // Load signature requirements from [signing.<alias>]
+        let trust_policies = config
+            .get("signing")
+            .and_then(|signing| signing.as_table())
+            .map(|signing| {
+                signing
+                    .iter()
+                    .map(|(alias, policy)| {
+                        ensure!(
+                            registries.contains_key(alias),
+                            "signing policy for unknown registry '{}'",
+                            alias
+                        );
+
+                        let policy: TrustPolicy = policy
+                            .clone()
+                            .try_into()
+                            .into_diagnostic()
+                            .wrap_err(miette!("invalid signing policy for registry '{}'", alias))?;
+
+                        Ok((alias.to_string(), policy))
+                    })
+                    .collect::<miette::Result<HashMap<String, TrustPolicy>>>()
+            })
+            .unwrap_or_else(|| Ok(HashMap::new()))
+            .wrap_err(miette!(
+                "failed to load signing policies from config file: {source}"
+            ))?;
+
+        // Load encryption keys from [encryption.<alias>]
+        let encryption = config
+            .get("encryption")
+            .and_then(|encryption| encryption.as_table())
+            .map(|encryption| {
+                encryption
+                    .iter()
+                    .map(|(alias, keys)| {
+                        ensure!(
+                            registries.contains_key(alias),
+                            "encryption keys for unknown registry '{}'",
+                            alias
+                        );
+
+                        let keys: Encryption = keys.clone().try_into().into_diagnostic().wrap_err(
+                            miette!("invalid encryption keys for registry '{}'", alias),
+                        )?;
+
+                        Ok((alias.to_string(), keys))
+                    })
+                    .collect::<miette::Result<HashMap<String, Encryption>>>()
+            })
+            .unwrap_or_else(|| Ok(HashMap::new()))
+            .wrap_err(miette!(
+                "failed to load encryption keys from config file: {source}"
+            ))?;
+
+        // Load proxy and retry settings from [network]
+        let network = Self::section(&config, "network", &source)?;
+
+        // Load installation preferences from [install]
+        let install = Self::section(&config, "install", &source)?;
+
+        // Load output preferences from [output]
+        let output = Self::section(&config, "output", &source)?;
+
+        // Load runtime sizing from [runtime]
+        let runtime = Self::section(&config, "runtime", &source)?;
+
+        // Load the checks of git hooks from [git-hooks]
+        let git_hooks = Self::section(&config, "git-hooks", &source)?;
+
+        // Load the schema registry from [schema-registry]
+        let schema_registry = Self::section(&config, "schema-registry", &source)?;
+
+        // Load the toolchain of CI images from [ci]
+        let ci = Self::section(&config, "ci", &source)?;
+
+        // Load the version synchronization with Cargo from [cargo]
+        let cargo = Self::section(&config, "cargo", &source)?;
+
         Ok(Self {
-            config_path: Some(config_path.to_owned()),
+            config_path,
+            settings: config,
             default_registry,
             registries,
             command_defaults,
+            aliases,
+            hooks,
+            include_paths,
+            network,
+            install,
+            output,
+            runtime,
+            git_hooks,
+            schema_registry,
+            ci,
+            cargo,
+            trust_policies,
+            encryption,
         })
     }
+
+    /// Deserialize an optional section of the configuration
+    fn section<T>(config: &toml::Table, name: &str, source: &str) -> miette::Result<T>
+    where
+        T: Default + serde::de::DeserializeOwned,
+    {
+        config
+            .get(name)
+            .cloned()
+            .map(|section| section.try_into())
+            .transpose()
+            .into_diagnostic()
+            .wrap_err(miette!("invalid [{name}] section in config file: {source}"))
+            .map(Option::unwrap_or_default)
+    }
+}
+
+/// Merges a layer of settings into the settings of the layers below, tables are merged key by key
+fn merge(settings: &mut toml::Table, layer: toml::Table) {
+    for (key, value) in layer {
+        match (settings.get_mut(&key), value) {
+            (Some(toml::Value::Table(below)), toml::Value::Table(table)) => merge(below, table),
+            (_, value) => {
+                settings.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Sets a setting by its dotted key, creating the tables along the way
+fn insert(settings: &mut toml::Table, key: &str, value: toml::Value) -> miette::Result<()> {
+    let (path, name) = match key.rsplit_once('.') {
+        Some((path, name)) => (Some(path), name),
+        None => (None, key),
+    };
+
+    let mut table = settings;
+
+    for part in path.into_iter().flat_map(|path| path.split('.')) {
+        table = table
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or(miette!("{key} is not a table: {part} already has a value"))?;
+    }
+
+    ensure!(!name.is_empty(), "invalid setting: {key}");
+
+    table.insert(name.to_owned(), value);
+
+    Ok(())
+}
+
+/// Parses a value given on the command line or in an environment variable as a TOML value,
+/// falling back to a string
+fn parse_value(value: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(value.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> toml::Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn merges_layers() {
+        let mut settings = table(
+            "[registries]\nglobal = \"https://global.example.com\"\n[network]\nretries = 1\n",
+        );
+
+        merge(
+            &mut settings,
+            table("[registries]\nlocal = \"https://local.example.com\"\n[network]\nretries = 3\n"),
+        );
+
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert!(config.lookup_registry("global").is_ok());
+        assert!(config.lookup_registry("local").is_ok());
+        assert_eq!(config.network().retries, 3);
+        assert_eq!(
+            config.get("network.retries"),
+            Some(&toml::Value::Integer(3))
+        );
+    }
+
+    #[test]
+    fn inserts_dotted_keys() {
+        let mut settings = toml::Table::new();
+
+        insert(
+            &mut settings,
+            "network.proxy",
+            parse_value("http://proxy:3128"),
+        )
+        .unwrap();
+        insert(&mut settings, "network.retries", parse_value("2")).unwrap();
+
+        assert_eq!(
+            settings,
+            table("[network]\nproxy = \"http://proxy:3128\"\nretries = 2\n")
+        );
+
+        assert!(insert(&mut settings, "network.proxy.host", parse_value("x")).is_err());
+    }
+
+    #[test]
+    fn parses_output_profile() {
+        let settings = table("[output]\nprofile = \"ci\"\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.output().profile, OutputProfile::Ci);
+        assert_eq!(config.output().color, ColorChoice::Auto);
+    }
+
+    #[test]
+    fn parses_link_mode() {
+        let settings = table("[install]\nlink = \"hardlink\"\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.install().link, LinkMode::Hardlink);
+
+        let config = Config::from_settings(toml::Table::new(), None).unwrap();
+
+        assert_eq!(config.install().link, LinkMode::Copy);
+    }
+
+    #[test]
+    fn parses_runtime() {
+        let settings = table("[runtime]\nworker-threads = 4\nmax-blocking-threads = 8\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.runtime().worker_threads, Some(4));
+        assert_eq!(config.runtime().max_blocking_threads, Some(8));
+
+        let config = Config::from_settings(toml::Table::new(), None).unwrap();
+
+        assert_eq!(config.runtime(), &Runtime::default());
+    }
+
+    #[test]
+    fn parses_git_hooks() {
+        let settings = table("[git-hooks]\npre-push = [\"breaking\"]\nrepository = \"protos\"\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.git_hooks().pre_commit, vec![GitCheck::Lint]);
+        assert_eq!(config.git_hooks().pre_push, vec![GitCheck::Breaking]);
+        assert_eq!(config.git_hooks().repository.as_deref(), Some("protos"));
+
+        let settings = table("[git-hooks]\npre-commit = [\"fmt\"]\n");
+
+        assert!(Config::from_settings(settings, None).is_err());
+    }
+
+    #[test]
+    fn parses_schema_registry() {
+        let settings = table(
+            "[schema-registry]\nurl = \"https://schemas.example.com\"\nmessages = [\"physics.Mass\"]\n",
+        );
+        let config = Config::from_settings(settings, None).unwrap();
+        let registry = config.schema_registry().unwrap();
+
+        assert_eq!(registry.url.as_str(), "https://schemas.example.com/");
+        assert_eq!(registry.messages, vec!["physics.Mass"]);
+        assert_eq!(registry.subject, "{message}");
+
+        let config = Config::from_settings(toml::Table::new(), None).unwrap();
+
+        assert_eq!(config.schema_registry(), None);
+    }
+
+    #[test]
+    fn parses_ci() {
+        let settings = table("[ci]\nplugins = { protoc-gen-prost = \"0.4.0\" }\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.ci().protoc, "25.1");
+        assert_eq!(
+            config.ci().plugins["protoc-gen-prost"],
+            Version::new(0, 4, 0)
+        );
+
+        let settings = table("[ci]\nplugins = { protoc-gen-prost = \"^0.4\" }\n");
+
+        assert!(Config::from_settings(settings, None).is_err());
+    }
+
+    #[test]
+    fn parses_cargo() {
+        let settings = table("[cargo]\nsync-version = \"write\"\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.cargo().sync_version, Some(SyncDirection::Write));
+        assert_eq!(config.cargo().manifest, Path::new("Cargo.toml"));
+
+        let config = Config::from_settings(toml::Table::new(), None).unwrap();
+
+        assert_eq!(config.cargo().sync_version, None);
+    }
+
+    #[test]
+    fn rejects_invalid_sections() {
+        let settings = table("[output]\ncolor = \"sometimes\"\n");
+
+        assert!(Config::from_settings(settings, None).is_err());
+    }
+
+    #[test]
+    fn parses_aliases() {
+        let settings = table("[alias]\ni = \"install --buf-yaml\"\nl = [\"lint\", \"--unused\"]\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(
+            config.alias("i"),
+            Some(&["install".into(), "--buf-yaml".into()][..])
+        );
+        assert_eq!(
+            config.alias("l"),
+            Some(&["lint".into(), "--unused".into()][..])
+        );
+        assert_eq!(config.alias("x"), None);
+
+        assert!(Config::from_settings(table("[alias]\ni = \"\"\n"), None).is_err());
+    }
+
+    #[test]
+    fn parses_hooks() {
+        let settings = table("[hooks]\npost-install = \"buffrs generate --lang rust\"\n");
+        let config = Config::from_settings(settings, None).unwrap();
+
+        assert_eq!(config.hook("install"), Some("buffrs generate --lang rust"));
+        assert_eq!(config.hook("publish"), None);
+
+        assert!(Config::from_settings(table("[hooks]\ninstall = \"make\"\n"), None).is_err());
+    }
 }
@@ -0,0 +1,849 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, fmt::Write, iter::Peekable, path::Path, vec::IntoIter};
+
+use miette::{miette, Context, IntoDiagnostic};
+use tokio::fs;
+
+/// Output directory of the generated documentation
+pub const DOC_PATH: &str = "proto/build/doc";
+
+/// Kind of a documented definition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefinitionKind {
+    /// Message definition
+    Message,
+    /// Enumeration
+    Enum,
+    /// Service definition
+    Service,
+}
+
+/// Signature of a documented member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signature {
+    /// Field of a message
+    Field {
+        /// Type of the field, including its label
+        type_: String,
+        /// Field number
+        number: String,
+    },
+    /// Value of an enum
+    Value {
+        /// Value number
+        number: String,
+    },
+    /// Method of a service
+    Method {
+        /// Request message
+        request: String,
+        /// Whether requests are streamed
+        client_streaming: bool,
+        /// Response message
+        response: String,
+        /// Whether responses are streamed
+        server_streaming: bool,
+    },
+}
+
+/// Documented member of a definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// Name of the member
+    pub name: String,
+    /// Signature of the member
+    pub signature: Signature,
+    /// Comment attached to the member
+    pub comment: String,
+}
+
+/// Documented message, enum or service
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// Kind of definition
+    pub kind: DefinitionKind,
+    /// Name relative to the package, nested definitions are separated by dots
+    pub name: String,
+    /// Comment attached to the definition
+    pub comment: String,
+    /// Fields, values or methods of the definition
+    pub members: Vec<Member>,
+}
+
+/// Documentation of a protocol buffer package
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageDoc {
+    /// Definitions of all files declaring the package
+    pub definitions: Vec<Definition>,
+}
+
+/// Documentation of a set of protocol buffer packages
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Documentation {
+    /// Documented packages by name
+    pub packages: BTreeMap<String, PackageDoc>,
+}
+
+impl Documentation {
+    /// Add the definitions of a proto file
+    pub fn add(&mut self, contents: &str) {
+        let mut parser = Parser {
+            tokens: tokenize(contents).into_iter().peekable(),
+            definitions: Vec::new(),
+        };
+
+        let package = parser.parse_file();
+
+        self.packages
+            .entry(package)
+            .or_default()
+            .definitions
+            .append(&mut parser.definitions);
+    }
+
+    /// Render the documentation as markdown files, keyed by file name
+    pub fn render(&self) -> BTreeMap<String, String> {
+        let mut files = BTreeMap::new();
+
+        let mut index = String::from("# Protocol Buffer Documentation\n\n");
+
+        for (name, package) in &self.packages {
+            let title = display_package(name);
+            let _ = writeln!(index, "- [`{title}`]({})", file_name(name));
+            files.insert(file_name(name), self.render_package(name, package));
+        }
+
+        files.insert("index.md".into(), index);
+
+        files
+    }
+
+    /// Write the rendered documentation into a directory, replacing its contents
+    pub async fn write(&self, dir: &Path) -> miette::Result<()> {
+        fs::remove_dir_all(dir).await.ok();
+
+        fs::create_dir_all(dir)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create {}", dir.display()))?;
+
+        for (name, contents) in self.render() {
+            fs::write(dir.join(&name), contents)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {name}"))?;
+        }
+
+        Ok(())
+    }
+
+    fn render_package(&self, name: &str, package: &PackageDoc) -> String {
+        let mut out = format!("# `{}`\n", display_package(name));
+
+        for definition in &package.definitions {
+            let kind = match definition.kind {
+                DefinitionKind::Message => "message",
+                DefinitionKind::Enum => "enum",
+                DefinitionKind::Service => "service",
+            };
+
+            let _ = write!(
+                out,
+                "\n<a id=\"{0}\"></a>\n## {kind} `{0}`\n\n",
+                definition.name
+            );
+
+            if !definition.comment.is_empty() {
+                let _ = write!(out, "{}\n\n", definition.comment);
+            }
+
+            if definition.members.is_empty() {
+                continue;
+            }
+
+            let header = match definition.kind {
+                DefinitionKind::Message => {
+                    "| Field | Type | Number | Description |\n| --- | --- | --- | --- |"
+                }
+                DefinitionKind::Enum => "| Value | Number | Description |\n| --- | --- | --- |",
+                DefinitionKind::Service => {
+                    "| Method | Request | Response | Description |\n| --- | --- | --- | --- |"
+                }
+            };
+
+            let _ = writeln!(out, "{header}");
+
+            for member in &definition.members {
+                let columns = match &member.signature {
+                    Signature::Field { type_, number } => {
+                        vec![self.link(type_, name, &definition.name), number.clone()]
+                    }
+                    Signature::Value { number } => vec![number.clone()],
+                    Signature::Method {
+                        request,
+                        client_streaming,
+                        response,
+                        server_streaming,
+                    } => vec![
+                        stream(*client_streaming) + &self.link(request, name, ""),
+                        stream(*server_streaming) + &self.link(response, name, ""),
+                    ],
+                };
+
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {} | {} |",
+                    member.name,
+                    columns.join(" | "),
+                    cell(&member.comment)
+                );
+            }
+        }
+
+        out
+    }
+
+    /// Render a type reference, linking to its definition if it is documented
+    fn link(&self, type_: &str, package: &str, scope: &str) -> String {
+        // labels and map types are kept as is, only the referenced type is linked
+        if let Some(inner) = type_
+            .strip_prefix("map<")
+            .and_then(|inner| inner.strip_suffix('>'))
+        {
+            let (key, value) = inner.split_once(',').unwrap_or((inner, ""));
+            return format!(
+                "map<`{}`, {}>",
+                key.trim(),
+                self.link(value.trim(), package, scope)
+            );
+        }
+
+        if let Some((label, inner)) = type_.split_once(' ') {
+            return format!("{label} {}", self.link(inner, package, scope));
+        }
+
+        match self.resolve(type_, package, scope) {
+            Some((package, name)) => format!("[`{type_}`]({}#{name})", file_name(&package)),
+            None => format!("`{type_}`"),
+        }
+    }
+
+    /// Resolve a type reference using the scoping rules of protocol buffers
+    fn resolve(&self, type_: &str, package: &str, scope: &str) -> Option<(String, String)> {
+        if let Some(absolute) = type_.strip_prefix('.') {
+            return self.lookup(absolute);
+        }
+
+        let mut parts: Vec<&str> = package
+            .split('.')
+            .chain(scope.split('.'))
+            .filter(|part| !part.is_empty())
+            .collect();
+
+        loop {
+            let candidate = match parts.is_empty() {
+                true => type_.to_string(),
+                false => format!("{}.{type_}", parts.join(".")),
+            };
+
+            if let Some(found) = self.lookup(&candidate) {
+                return Some(found);
+            }
+
+            parts.pop()?;
+        }
+    }
+
+    fn lookup(&self, full_name: &str) -> Option<(String, String)> {
+        self.packages.iter().find_map(|(package, doc)| {
+            let name = match package.is_empty() {
+                true => full_name,
+                false => full_name.strip_prefix(package)?.strip_prefix('.')?,
+            };
+
+            doc.definitions
+                .iter()
+                .any(|definition| definition.name == name)
+                .then(|| (package.clone(), name.to_string()))
+        })
+    }
+}
+
+fn display_package(name: &str) -> &str {
+    match name.is_empty() {
+        true => "(default package)",
+        false => name,
+    }
+}
+
+fn file_name(package: &str) -> String {
+    match package.is_empty() {
+        true => "_.md".into(),
+        false => format!("{package}.md"),
+    }
+}
+
+fn stream(streaming: bool) -> String {
+    match streaming {
+        true => "stream ".into(),
+        false => String::new(),
+    }
+}
+
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    text: String,
+    comment: Vec<String>,
+    trailing: Option<String>,
+}
+
+/// Splits a proto file into tokens, attaching comments to the token they document
+fn tokenize(contents: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut comment = Vec::new();
+    let mut chars = contents.chars().peekable();
+
+    // whether the last token is followed by a newline, trailing comments are only
+    // attached to tokens on the same line
+    let mut newline = true;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => {
+                newline = true;
+            }
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                chars.next();
+
+                let mut line = String::new();
+
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    line.push(c);
+                }
+
+                let line = line.trim_start_matches('/').trim().to_string();
+
+                match tokens.last_mut() {
+                    Some(last) if !newline && last.text == ";" => {
+                        last.trailing = Some(line);
+                    }
+                    _ => comment.push(line),
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                let mut block = String::new();
+                let mut prev = None;
+
+                for c in chars.by_ref() {
+                    if prev == Some('*') && c == '/' {
+                        block.pop();
+                        break;
+                    }
+
+                    block.push(c);
+                    prev = Some(c);
+                }
+
+                comment.extend(
+                    block
+                        .lines()
+                        .map(|line| line.trim().trim_start_matches('*').trim().to_string()),
+                );
+            }
+            '"' | '\'' => {
+                let mut text = String::from(c);
+
+                while let Some(next) = chars.next() {
+                    text.push(next);
+
+                    if next == '\\' {
+                        text.extend(chars.next());
+                    } else if next == c {
+                        break;
+                    }
+                }
+
+                tokens.push(token(text, &mut comment));
+                newline = false;
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                let mut text = String::from(c);
+
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '.')
+                {
+                    text.push(c);
+                }
+
+                tokens.push(token(text, &mut comment));
+                newline = false;
+            }
+            c => {
+                tokens.push(token(c.to_string(), &mut comment));
+                newline = false;
+            }
+        }
+    }
+
+    tokens
+}
+
+fn token(text: String, comment: &mut Vec<String>) -> Token {
+    Token {
+        text,
+        comment: std::mem::take(comment),
+        trailing: None,
+    }
+}
+
+/// Lenient parser that only extracts what is needed for documentation
+struct Parser {
+    tokens: Peekable<IntoIter<Token>>,
+    definitions: Vec<Definition>,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        self.tokens.next()
+    }
+
+    fn next_text(&mut self) -> String {
+        self.next().map(|token| token.text).unwrap_or_default()
+    }
+
+    fn peek_is(&mut self, text: &str) -> bool {
+        self.tokens.peek().is_some_and(|token| token.text == text)
+    }
+
+    /// Skip to the end of the current statement, returning its trailing comment
+    fn skip_statement(&mut self) -> Option<String> {
+        let mut depth = 0;
+
+        while let Some(token) = self.next() {
+            match token.text.as_str() {
+                "{" | "[" | "(" => depth += 1,
+                "}" | "]" | ")" => depth -= 1,
+                ";" if depth == 0 => return token.trailing,
+                _ => {}
+            }
+
+            if depth == 0 && token.text == "}" {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Skip a balanced block, expecting the opening brace to be next
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+
+        while let Some(token) = self.next() {
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    fn parse_file(&mut self) -> String {
+        let mut package = String::new();
+
+        while let Some(token) = self.next() {
+            let comment = token.comment.join("\n");
+
+            match token.text.as_str() {
+                "package" => {
+                    package = self.next_text();
+                    self.skip_statement();
+                }
+                "message" => self.parse_message("", comment),
+                "enum" => self.parse_enum("", comment),
+                "service" => self.parse_service(comment),
+                "extend" => {
+                    self.next();
+                    self.skip_block();
+                }
+                ";" => {}
+                _ => {
+                    self.skip_statement();
+                }
+            }
+        }
+
+        package
+    }
+
+    fn parse_message(&mut self, scope: &str, comment: String) {
+        let name = scoped(scope, &self.next_text());
+        let index = self.definitions.len();
+
+        self.definitions.push(Definition {
+            kind: DefinitionKind::Message,
+            name: name.clone(),
+            comment,
+            members: Vec::new(),
+        });
+
+        if !self.peek_is("{") {
+            return;
+        }
+
+        self.next();
+
+        let mut oneof = 0;
+
+        while let Some(token) = self.next() {
+            let comment = token.comment.join("\n");
+
+            match token.text.as_str() {
+                "}" if oneof > 0 => oneof -= 1,
+                "}" => return,
+                ";" => {}
+                "message" => self.parse_message(&name, comment),
+                "enum" => self.parse_enum(&name, comment),
+                "oneof" => {
+                    self.next();
+
+                    if self.peek_is("{") {
+                        self.next();
+                        oneof += 1;
+                    }
+                }
+                "extend" => {
+                    self.next();
+                    self.skip_block();
+                }
+                "option" | "reserved" | "extensions" => {
+                    self.skip_statement();
+                }
+                "map" => {
+                    let mut type_ = String::from("map");
+
+                    while let Some(token) = self.next() {
+                        type_.push_str(&token.text);
+
+                        if token.text == ">" {
+                            break;
+                        }
+                    }
+
+                    let type_ = type_.replace(',', ", ");
+                    self.parse_field(index, type_, comment);
+                }
+                label @ ("optional" | "repeated" | "required") => {
+                    let type_ = format!("{label} {}", self.next_text());
+                    self.parse_field(index, type_, comment);
+                }
+                type_ => {
+                    let type_ = type_.to_string();
+                    self.parse_field(index, type_, comment);
+                }
+            }
+        }
+    }
+
+    fn parse_field(&mut self, index: usize, type_: String, comment: String) {
+        let name = self.next_text();
+
+        if !self.peek_is("=") {
+            self.skip_statement();
+            return;
+        }
+
+        self.next();
+
+        let number = self.next_text();
+        let trailing = self.skip_statement();
+
+        self.definitions[index].members.push(Member {
+            name,
+            signature: Signature::Field { type_, number },
+            comment: member_comment(comment, trailing),
+        });
+    }
+
+    fn parse_enum(&mut self, scope: &str, comment: String) {
+        let name = scoped(scope, &self.next_text());
+        let mut definition = Definition {
+            kind: DefinitionKind::Enum,
+            name,
+            comment,
+            members: Vec::new(),
+        };
+
+        if self.peek_is("{") {
+            self.next();
+
+            while let Some(token) = self.next() {
+                match token.text.as_str() {
+                    "}" => break,
+                    ";" => {}
+                    "option" | "reserved" => {
+                        self.skip_statement();
+                    }
+                    name => {
+                        let name = name.to_string();
+                        let comment = token.comment.join("\n");
+
+                        // `=` followed by the number, which may be negative
+                        self.next();
+                        let mut number = self.next_text();
+
+                        if number == "-" {
+                            number.push_str(&self.next_text());
+                        }
+
+                        let trailing = self.skip_statement();
+
+                        definition.members.push(Member {
+                            name,
+                            signature: Signature::Value { number },
+                            comment: member_comment(comment, trailing),
+                        });
+                    }
+                }
+            }
+        }
+
+        self.definitions.push(definition);
+    }
+
+    fn parse_service(&mut self, comment: String) {
+        let mut definition = Definition {
+            kind: DefinitionKind::Service,
+            name: self.next_text(),
+            comment,
+            members: Vec::new(),
+        };
+
+        if self.peek_is("{") {
+            self.next();
+
+            while let Some(token) = self.next() {
+                match token.text.as_str() {
+                    "}" => break,
+                    ";" => {}
+                    "rpc" => {
+                        let comment = token.comment.join("\n");
+                        let name = self.next_text();
+                        let (request, client_streaming) = self.parse_method_type();
+
+                        // `returns`
+                        self.next();
+
+                        let (response, server_streaming) = self.parse_method_type();
+
+                        let trailing = match self.peek_is("{") {
+                            true => {
+                                self.skip_block();
+                                None
+                            }
+                            false => self.skip_statement(),
+                        };
+
+                        definition.members.push(Member {
+                            name,
+                            signature: Signature::Method {
+                                request,
+                                client_streaming,
+                                response,
+                                server_streaming,
+                            },
+                            comment: member_comment(comment, trailing),
+                        });
+                    }
+                    _ => {
+                        self.skip_statement();
+                    }
+                }
+            }
+        }
+
+        self.definitions.push(definition);
+    }
+
+    /// Parse `(stream Type)` of a method
+    fn parse_method_type(&mut self) -> (String, bool) {
+        // `(`
+        self.next();
+
+        let mut streaming = false;
+        let mut type_ = self.next_text();
+
+        if type_ == "stream" && !self.peek_is(")") {
+            streaming = true;
+            type_ = self.next_text();
+        }
+
+        // `)`
+        self.next();
+
+        (type_, streaming)
+    }
+}
+
+fn scoped(scope: &str, name: &str) -> String {
+    match scope.is_empty() {
+        true => name.to_string(),
+        false => format!("{scope}.{name}"),
+    }
+}
+
+fn member_comment(leading: String, trailing: Option<String>) -> String {
+    match (leading.is_empty(), trailing) {
+        (true, Some(trailing)) => trailing,
+        (_, _) => leading,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTO: &str = r#"
+syntax = "proto3";
+
+package physics.units;
+
+import "google/protobuf/timestamp.proto";
+
+// A temperature reading.
+message Temperature {
+  // The measured value
+  double value = 1;
+  Unit unit = 2; // unit of the value
+  repeated Reading history = 3 [deprecated = true];
+  map<string, Unit> aliases = 4;
+
+  oneof source {
+    string sensor = 5;
+  }
+
+  /* Nested reading */
+  message Reading {
+    google.protobuf.Timestamp at = 1;
+  }
+}
+
+enum Unit {
+  UNIT_UNSPECIFIED = 0;
+  // degrees celsius
+  UNIT_CELSIUS = 1;
+}
+
+// Converts temperatures.
+service Converter {
+  rpc Convert(Temperature) returns (stream Temperature) {
+    option deprecated = true;
+  }
+}
+"#;
+
+    fn field(name: &str, type_: &str, number: &str, comment: &str) -> Member {
+        Member {
+            name: name.into(),
+            signature: Signature::Field {
+                type_: type_.into(),
+                number: number.into(),
+            },
+            comment: comment.into(),
+        }
+    }
+
+    #[test]
+    fn extracts_definitions() {
+        let mut docs = Documentation::default();
+        docs.add(PROTO);
+
+        let package = &docs.packages["physics.units"];
+
+        assert_eq!(
+            package.definitions[0],
+            Definition {
+                kind: DefinitionKind::Message,
+                name: "Temperature".into(),
+                comment: "A temperature reading.".into(),
+                members: vec![
+                    field("value", "double", "1", "The measured value"),
+                    field("unit", "Unit", "2", "unit of the value"),
+                    field("history", "repeated Reading", "3", ""),
+                    field("aliases", "map<string, Unit>", "4", ""),
+                    field("sensor", "string", "5", ""),
+                ],
+            }
+        );
+
+        assert_eq!(package.definitions[1].name, "Temperature.Reading");
+        assert_eq!(package.definitions[1].comment, "Nested reading");
+
+        assert_eq!(
+            package.definitions[2].members[1],
+            Member {
+                name: "UNIT_CELSIUS".into(),
+                signature: Signature::Value { number: "1".into() },
+                comment: "degrees celsius".into(),
+            }
+        );
+
+        assert_eq!(
+            package.definitions[3].members,
+            vec![Member {
+                name: "Convert".into(),
+                signature: Signature::Method {
+                    request: "Temperature".into(),
+                    client_streaming: false,
+                    response: "Temperature".into(),
+                    server_streaming: true,
+                },
+                comment: "".into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn links_referenced_types() {
+        let mut docs = Documentation::default();
+        docs.add(PROTO);
+
+        let files = docs.render();
+        let package = &files["physics.units.md"];
+
+        assert!(files["index.md"].contains("- [`physics.units`](physics.units.md)"));
+        assert!(package.contains("## message `Temperature`"));
+        assert!(package.contains(
+            "| `history` | repeated [`Reading`](physics.units.md#Temperature.Reading) | 3 |  |"
+        ));
+        assert!(package
+            .contains("| `aliases` | map<`string`, [`Unit`](physics.units.md#Unit)> | 4 |  |"));
+        assert!(package.contains("| `at` | `google.protobuf.Timestamp` | 1 |  |"));
+        assert!(package.contains(
+            "| `Convert` | [`Temperature`](physics.units.md#Temperature) | stream [`Temperature`](physics.units.md#Temperature) |  |"
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{path::PathBuf, process::Stdio};
+
+use bytes::Bytes;
+use miette::{ensure, miette, Context, IntoDiagnostic};
+use serde::Deserialize;
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Header every age encrypted file starts with
+const AGE_HEADER: &[u8] = b"age-encryption.org/v1";
+
+/// Keys encrypting the packages of a registry with [age](https://age-encryption.org)
+///
+/// Configured per registry alias in the `[encryption.<alias>]` section of the configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Encryption {
+    /// Public keys of the recipients published packages are encrypted to
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    /// Identity file holding the private key that decrypts installed packages
+    pub identity: Option<PathBuf>,
+}
+
+impl Encryption {
+    /// Whether a payload downloaded from a registry is encrypted
+    pub fn is_encrypted(payload: &[u8]) -> bool {
+        payload.starts_with(AGE_HEADER)
+    }
+
+    /// Encrypts a package archive to the configured recipients
+    pub async fn encrypt(&self, tgz: Bytes) -> miette::Result<Bytes> {
+        ensure!(
+            !self.recipients.is_empty(),
+            "no recipients are configured to encrypt packages to"
+        );
+
+        let mut command = Command::new("age");
+
+        command.arg("--encrypt");
+
+        for recipient in &self.recipients {
+            command.arg("--recipient").arg(recipient);
+        }
+
+        pipe(&mut command, tgz)
+            .await
+            .wrap_err(miette!("failed to encrypt the package"))
+    }
+
+    /// Decrypts a payload downloaded from a registry with the configured identity
+    pub async fn decrypt(&self, payload: Bytes) -> miette::Result<Bytes> {
+        let identity = self.identity.as_ref().ok_or(miette!(
+            help = "configure an `identity` in the `[encryption]` section of the registry",
+            "no identity is configured to decrypt packages with"
+        ))?;
+
+        let mut command = Command::new("age");
+
+        command.arg("--decrypt").arg("--identity").arg(identity);
+
+        pipe(&mut command, payload)
+            .await
+            .wrap_err(miette!("failed to decrypt the package"))
+    }
+}
+
+/// Runs age with the given input on stdin and returns its output
+async fn pipe(command: &mut Command, input: Bytes) -> miette::Result<Bytes> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .into_diagnostic()
+        .wrap_err(miette!(
+            help = "install age from https://github.com/FiloSottile/age",
+            "failed to run age"
+        ))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or(miette!("failed to open the input of age"))?;
+
+    // write concurrently, age may start writing its output before consuming all input
+    let writer = tokio::spawn(async move {
+        stdin.write_all(&input).await?;
+        stdin.shutdown().await
+    });
+
+    let output = child.wait_with_output().await.into_diagnostic()?;
+
+    // a failing age closes its input early, so its own error is the more helpful one
+    ensure!(
+        output.status.success(),
+        "age failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    writer
+        .await
+        .into_diagnostic()?
+        .into_diagnostic()
+        .wrap_err(miette!("failed to pass the package to age"))?;
+
+    Ok(output.stdout.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_encrypted_payloads() {
+        assert!(Encryption::is_encrypted(
+            b"age-encryption.org/v1\n-> X25519 ..."
+        ));
+        assert!(!Encryption::is_encrypted(&[0x1f, 0x8b, 0x08, 0x00]));
+    }
+}
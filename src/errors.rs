@@ -1,15 +1,18 @@
-use miette::Diagnostic;
+use miette::{Diagnostic, NamedSource, SourceSpan};
 
-use crate::ManagedFile;
+use crate::{cancel::Cancelled, package::FileListMismatch, ManagedFile};
 
+#[cfg(feature = "native")]
 #[derive(thiserror::Error, Diagnostic, Debug)]
 #[error("failed to determine if {0} file exists")]
 pub(crate) struct FileExistsError(pub &'static str);
 
+#[cfg(feature = "native")]
 #[derive(thiserror::Error, Diagnostic, Debug)]
 #[error("could not write to {0} file")]
 pub(crate) struct WriteError(pub &'static str);
 
+#[cfg(feature = "native")]
 #[derive(thiserror::Error, Diagnostic, Debug)]
 #[error("could not read from {0} file")]
 pub(crate) struct ReadError(pub &'static str);
@@ -22,6 +25,639 @@ pub(crate) struct DeserializationError(pub ManagedFile);
 #[error("could not serialize {0}")]
 pub(crate) struct SerializationError(pub ManagedFile);
 
+#[cfg(feature = "native")]
 #[derive(thiserror::Error, Diagnostic, Debug)]
 #[error("file `{0}` is missing")]
 pub(crate) struct FileNotFound(pub String);
+
+/// The manifest is not valid TOML or does not match the manifest format
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{message}")]
+#[diagnostic(code(E0004))]
+pub(crate) struct MalformedManifest {
+    message: String,
+    #[source_code]
+    contents: NamedSource<String>,
+    #[label]
+    span: Option<SourceSpan>,
+}
+
+impl MalformedManifest {
+    /// Points at the location of a parse error in the contents of the manifest at `path`
+    pub fn new(path: impl AsRef<str>, contents: &str, error: toml::de::Error) -> Self {
+        Self {
+            message: error.message().to_owned(),
+            contents: NamedSource::new(path, contents.to_owned()),
+            span: error.span().map(SourceSpan::from),
+        }
+    }
+}
+
+/// The lockfile is not valid TOML or does not match the lockfile format
+#[cfg(feature = "native")]
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{message}")]
+pub(crate) struct MalformedLockfile {
+    message: String,
+    #[source_code]
+    contents: NamedSource<String>,
+    #[label]
+    span: Option<SourceSpan>,
+}
+
+#[cfg(feature = "native")]
+impl MalformedLockfile {
+    /// Points at the location of a parse error in the contents of the lockfile at `path`
+    pub fn new(path: impl AsRef<str>, contents: &str, error: toml::de::Error) -> Self {
+        Self {
+            message: error.message().to_owned(),
+            contents: NamedSource::new(path, contents.to_owned()),
+            span: error.span().map(SourceSpan::from),
+        }
+    }
+}
+
+/// The registry rejected the credentials
+#[cfg(feature = "native")]
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{message}")]
+#[diagnostic(code(E0001))]
+pub(crate) struct Unauthorized {
+    pub message: String,
+    #[help]
+    pub help: Option<String>,
+}
+
+/// The requirements on a dependency conflict with each other or with the lockfile
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{0}")]
+#[diagnostic(code(E0002))]
+pub(crate) struct Conflict(pub String);
+
+/// A package does not match the digest it is expected to have
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{0}")]
+#[diagnostic(code(E0003))]
+pub(crate) struct DigestMismatch(pub String);
+
+/// A package, version or repository does not exist in a registry
+#[cfg(feature = "native")]
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{0}")]
+#[diagnostic(code(E0005))]
+pub(crate) struct NotFound(pub String);
+
+/// The signature of a package is missing or invalid
+#[cfg(feature = "native")]
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("{0}")]
+#[diagnostic(code(E0007))]
+pub(crate) struct SignatureRejected(pub String);
+
+/// A registry could not be reached, e.g. because of a connection error or a timeout
+#[cfg(feature = "native")]
+#[derive(thiserror::Error, Diagnostic, Debug)]
+#[error("failed to reach the registry")]
+#[diagnostic(code(E0008))]
+pub(crate) struct RegistryUnreachable(#[from] pub reqwest::Error);
+
+/// Failure of a buffrs operation, categorized so library consumers can react to kinds of failures
+///
+/// The entry points of the library, [`Registry`](crate::Registry),
+/// [`Resolver`](crate::Resolver), [`PackageStore`](crate::PackageStore) and
+/// [`BuildConfig`](crate::BuildConfig), fail with this error. The category follows from the step
+/// that failed and the types of its causes.
+///
+/// Every variant keeps the original report, which carries the message, the [`ErrorCode`] and the
+/// chain of causes. The error is transparent: displaying it or converting it back into a
+/// [`miette::Report`] renders exactly the original diagnostic.
+///
+/// ```rust,no_run
+/// # async fn example(config: buffrs::config::Config) -> buffrs::Result<()> {
+/// use buffrs::{lock::Lockfile, manifest::Manifest, Resolver};
+///
+/// let manifest = Manifest::read().await?;
+/// let lockfile = Lockfile::read_or_default().await?;
+///
+/// match Resolver::new(config).resolve(&manifest, &lockfile).await {
+///     Err(buffrs::Error::Network(_)) => eprintln!("the registry is unavailable, try again later"),
+///     result => drop(result?),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The registry rejected the credentials
+    Auth(miette::Report),
+    /// A registry could not be reached
+    Network(miette::Report),
+    /// The dependencies could not be resolved, e.g. because of conflicting requirements or a
+    /// missing package
+    Resolution(miette::Report),
+    /// A package failed an integrity check: its digest, file list or signature
+    Integrity(miette::Report),
+    /// Reading or writing a file failed
+    Io(miette::Report),
+    /// The manifest or the lockfile is malformed
+    Manifest(miette::Report),
+    /// The operation was cancelled
+    Cancelled(miette::Report),
+    /// Any other failure
+    Other(miette::Report),
+}
+
+/// Result of a buffrs operation
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    /// The report describing the failure
+    pub fn report(&self) -> &miette::Report {
+        match self {
+            Self::Auth(report)
+            | Self::Network(report)
+            | Self::Resolution(report)
+            | Self::Integrity(report)
+            | Self::Io(report)
+            | Self::Manifest(report)
+            | Self::Cancelled(report)
+            | Self::Other(report) => report,
+        }
+    }
+
+    /// Converts the error back into the report describing the failure
+    pub fn into_report(self) -> miette::Report {
+        match self {
+            Self::Auth(report)
+            | Self::Network(report)
+            | Self::Resolution(report)
+            | Self::Integrity(report)
+            | Self::Io(report)
+            | Self::Manifest(report)
+            | Self::Cancelled(report)
+            | Self::Other(report) => report,
+        }
+    }
+
+    /// Categorizes a report by the types of its causes, `None` if none of them is known
+    fn categorize(report: &miette::Report) -> Option<Category> {
+        report.chain().find_map(Self::category)
+    }
+
+    /// Category of a single cause, looking into the reports kept by the errors of the entry points
+    fn category(cause: &(dyn std::error::Error + 'static)) -> Option<Category> {
+        #[cfg(feature = "native")]
+        {
+            if let Some(error) = cause.downcast_ref::<crate::registry::RegistryError>() {
+                return Some(Self::registry(error));
+            }
+
+            if let Some(error) = cause.downcast_ref::<crate::resolver::ResolveError>() {
+                return Some(Self::resolve(error));
+            }
+
+            if let Some(error) = cause.downcast_ref::<crate::build::BuildError>() {
+                return Some(Self::build(error));
+            }
+
+            if cause.is::<reqwest::Error>() || cause.is::<RegistryUnreachable>() {
+                return Some(Self::Network);
+            }
+
+            if cause.is::<Unauthorized>() {
+                return Some(Self::Auth);
+            }
+
+            if cause.is::<NotFound>() {
+                return Some(Self::Resolution);
+            }
+
+            if cause.is::<SignatureRejected>() {
+                return Some(Self::Integrity);
+            }
+
+            if cause.is::<MalformedLockfile>() {
+                return Some(Self::Manifest);
+            }
+        }
+
+        if cause.is::<Conflict>() {
+            return Some(Self::Resolution);
+        }
+
+        if cause.is::<DigestMismatch>() || cause.is::<FileListMismatch>() {
+            return Some(Self::Integrity);
+        }
+
+        if cause.is::<MalformedManifest>() || cause.is::<toml::de::Error>() {
+            return Some(Self::Manifest);
+        }
+
+        if cause.is::<Cancelled>() {
+            return Some(Self::Cancelled);
+        }
+
+        if cause.is::<std::io::Error>() {
+            return Some(Self::Io);
+        }
+
+        None
+    }
+
+    /// Category of a failed registry operation
+    #[cfg(feature = "native")]
+    fn registry(error: &crate::registry::RegistryError) -> Category {
+        use crate::registry::RegistryError;
+
+        match error {
+            RegistryError::Signature(..) => Self::Integrity,
+            RegistryError::Setup(_, cause)
+            | RegistryError::Download(_, _, cause)
+            | RegistryError::Publish(_, _, cause)
+            | RegistryError::Versions(_, cause) => Self::categorize(cause).unwrap_or(Self::Other),
+        }
+    }
+
+    /// Category of a failed resolution
+    #[cfg(feature = "native")]
+    fn resolve(error: &crate::resolver::ResolveError) -> Category {
+        use crate::resolver::ResolveError;
+
+        match error {
+            ResolveError::Registry(_, cause) => Self::categorize(cause).unwrap_or(Self::Other),
+            ResolveError::Credentials(cause) | ResolveError::Cache(cause) => {
+                Self::categorize(cause).unwrap_or(Self::Io)
+            }
+            ResolveError::Resolution(cause) => Self::categorize(cause).unwrap_or(Self::Resolution),
+            ResolveError::Cancelled(_) => Self::Cancelled,
+        }
+    }
+
+    /// Category of a failed build preparation
+    #[cfg(feature = "native")]
+    fn build(error: &crate::build::BuildError) -> Category {
+        use crate::build::BuildError;
+
+        match error {
+            BuildError::Root(_) | BuildError::Runtime(_) => Self::Io,
+            BuildError::ManifestNotFound(_) => Self::Manifest,
+            BuildError::Manifest(cause) => Self::categorize(cause).unwrap_or(Self::Manifest),
+            BuildError::Config(cause) => Self::categorize(cause).unwrap_or(Self::Other),
+            BuildError::Store(cause) => Self::categorize(cause).unwrap_or(Self::Io),
+            BuildError::UnsupportedSyntax(_) => Self::Other,
+            BuildError::NotInstalled(_) => Self::Resolution,
+            BuildError::Cancelled(_) => Self::Cancelled,
+        }
+    }
+}
+
+/// Constructor of a variant of [`Error`]
+type Category = fn(miette::Report) -> Error;
+
+impl From<miette::Report> for Error {
+    fn from(report: miette::Report) -> Self {
+        Self::categorize(&report).unwrap_or(Self::Other)(report)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<crate::registry::RegistryError> for Error {
+    fn from(error: crate::registry::RegistryError) -> Self {
+        Self::registry(&error)(miette::Report::new(error))
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<crate::resolver::ResolveError> for Error {
+    fn from(error: crate::resolver::ResolveError) -> Self {
+        Self::resolve(&error)(miette::Report::new(error))
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<crate::build::BuildError> for Error {
+    fn from(error: crate::build::BuildError) -> Self {
+        Self::build(&error)(miette::Report::new(error))
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self.report(), f)
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.report().source()
+    }
+}
+
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.report().code()
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        self.report().severity()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.report().help()
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        self.report().url()
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.report().source_code()
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        self.report().labels()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        self.report().related()
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.report().diagnostic_source()
+    }
+}
+
+/// Status the process exits with, distinguishing kinds of failures for scripts and CI pipelines
+///
+/// Failures are classified by their [`ErrorCode`], failures without a code exit with
+/// [`ExitCode::Failure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// The command succeeded
+    Success = 0,
+    /// The command failed, e.g. because of an invalid manifest or argument
+    Failure = 1,
+    /// The command line could not be parsed
+    Usage = 2,
+    /// The dependencies could not be resolved
+    Resolution = 3,
+    /// A registry could not be reached
+    Network = 4,
+    /// Packages or protocol buffers failed a check, e.g. digests, signatures, lints or breaking
+    /// changes
+    Verification = 5,
+    /// The command was cancelled, e.g. by Ctrl-C, following the convention of shells for
+    /// interrupted processes
+    Cancelled = 130,
+}
+
+impl ExitCode {
+    /// The status to exit with after a command failed with `report`
+    pub fn of(report: &miette::Report) -> Self {
+        report
+            .code()
+            .and_then(|code| ErrorCode::lookup(&code.to_string()))
+            .map_or(Self::Failure, |error| error.exit)
+    }
+}
+
+/// Stable code of a common failure, explained in detail by `buffrs explain`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorCode {
+    /// The code, e.g. `E0001`
+    pub code: &'static str,
+    /// One line summary of the failure
+    pub summary: &'static str,
+    /// Extended guidance in markdown
+    pub explanation: &'static str,
+    /// Status the process exits with on this failure
+    pub exit: ExitCode,
+}
+
+/// Every code emitted in diagnostics, in ascending order
+///
+/// Codes are never reused or renumbered, so that they can be searched for and linked to.
+pub const ERROR_CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "E0001",
+        summary: "the registry rejected the credentials",
+        explanation: include_str!("errors/E0001.md"),
+        exit: ExitCode::Failure,
+    },
+    ErrorCode {
+        code: "E0002",
+        summary: "conflicting dependency requirements",
+        explanation: include_str!("errors/E0002.md"),
+        exit: ExitCode::Resolution,
+    },
+    ErrorCode {
+        code: "E0003",
+        summary: "a package does not match its locked digest",
+        explanation: include_str!("errors/E0003.md"),
+        exit: ExitCode::Verification,
+    },
+    ErrorCode {
+        code: "E0004",
+        summary: "malformed manifest",
+        explanation: include_str!("errors/E0004.md"),
+        exit: ExitCode::Failure,
+    },
+    ErrorCode {
+        code: "E0005",
+        summary: "package, version or repository not found",
+        explanation: include_str!("errors/E0005.md"),
+        exit: ExitCode::Resolution,
+    },
+    ErrorCode {
+        code: "E0006",
+        summary: "package contents do not match the file list",
+        explanation: include_str!("errors/E0006.md"),
+        exit: ExitCode::Verification,
+    },
+    ErrorCode {
+        code: "E0007",
+        summary: "package signature verification failed",
+        explanation: include_str!("errors/E0007.md"),
+        exit: ExitCode::Verification,
+    },
+    ErrorCode {
+        code: "E0008",
+        summary: "the registry could not be reached",
+        explanation: include_str!("errors/E0008.md"),
+        exit: ExitCode::Network,
+    },
+    ErrorCode {
+        code: "E0009",
+        summary: "lint diagnostics at or above the --fail-on severity",
+        explanation: include_str!("errors/E0009.md"),
+        exit: ExitCode::Verification,
+    },
+    ErrorCode {
+        code: "E0010",
+        summary: "breaking changes against a published version",
+        explanation: include_str!("errors/E0010.md"),
+        exit: ExitCode::Verification,
+    },
+    ErrorCode {
+        code: "E0011",
+        summary: "the operation was cancelled",
+        explanation: include_str!("errors/E0011.md"),
+        exit: ExitCode::Cancelled,
+    },
+];
+
+impl ErrorCode {
+    /// Looks up a code, ignoring case, e.g. `E0007` or `e0007`
+    pub fn lookup(code: &str) -> Option<&'static Self> {
+        ERROR_CODES
+            .iter()
+            .find(|error| error.code.eq_ignore_ascii_case(code.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_up_codes() {
+        assert_eq!(ErrorCode::lookup("E0007").unwrap().code, "E0007");
+        assert_eq!(ErrorCode::lookup("e0001").unwrap().code, "E0001");
+        assert_eq!(ErrorCode::lookup("E9999"), None);
+    }
+
+    #[test]
+    fn points_at_malformed_toml() {
+        let contents = "[package]\nname = \"physics\"\nversion = 1.0.0\n";
+        let error = toml::from_str::<toml::Table>(contents).unwrap_err();
+
+        let malformed = MalformedManifest::new("Proto.toml", contents, error);
+        let span = malformed.span.expect("parse errors have a span");
+
+        assert!(span.offset() >= contents.find("1.0.0").unwrap());
+        assert!(span.offset() < contents.len());
+    }
+
+    #[test]
+    fn classifies_exit_codes() {
+        let report = |code: &str| miette::miette!(code = code, "failed");
+
+        assert_eq!(ExitCode::of(&report("E0002")), ExitCode::Resolution);
+        assert_eq!(ExitCode::of(&report("E0008")), ExitCode::Network);
+        assert_eq!(ExitCode::of(&report("E0007")), ExitCode::Verification);
+        assert_eq!(ExitCode::of(&report("lint::unused")), ExitCode::Failure);
+        assert_eq!(
+            ExitCode::of(&miette::miette!("failed").wrap_err("context")),
+            ExitCode::Failure
+        );
+    }
+
+    #[test]
+    fn categorizes_errors() {
+        #[derive(thiserror::Error, Diagnostic, Debug)]
+        #[error("could not read")]
+        struct Unreadable(#[source] std::io::Error);
+
+        let malformed = MalformedManifest::new(
+            "Proto.toml",
+            "=",
+            toml::from_str::<toml::Table>("=").unwrap_err(),
+        );
+
+        assert!(matches!(
+            Error::from(miette::Report::new(FileListMismatch { files: vec![] })),
+            Error::Integrity(_)
+        ));
+        assert!(matches!(
+            Error::from(miette::Report::new(malformed)),
+            Error::Manifest(_)
+        ));
+        assert!(matches!(
+            Error::from(
+                miette::Report::new(Conflict("conflict".to_owned()))
+                    .wrap_err("dependency resolution failed")
+            ),
+            Error::Resolution(_)
+        ));
+        assert!(matches!(
+            Error::from(miette::Report::new(Unreadable(
+                std::io::ErrorKind::NotFound.into()
+            ))),
+            Error::Io(_)
+        ));
+        assert!(matches!(
+            Error::from(miette::Report::new(Cancelled)),
+            Error::Cancelled(_)
+        ));
+        assert!(matches!(
+            Error::from(miette::miette!(code = "E0001", "failed")),
+            Error::Other(_)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn categorizes_errors_of_entry_points() {
+        use crate::{package::PackageName, registry::RegistryError, resolver::ResolveError};
+
+        let mismatch = miette::Report::new(DigestMismatch("digest mismatch".to_owned()));
+        let physics = PackageName::new("physics").unwrap();
+        let version = semver::Version::new(1, 0, 0);
+        let unauthorized = Unauthorized {
+            message: "unauthorized".to_owned(),
+            help: None,
+        };
+
+        assert!(matches!(
+            Error::from(miette::Report::new(unauthorized)),
+            Error::Auth(_)
+        ));
+        assert!(matches!(
+            Error::from(miette::Report::new(NotFound("not found".to_owned()))),
+            Error::Resolution(_)
+        ));
+        assert!(matches!(
+            Error::from(ResolveError::Resolution(mismatch)),
+            Error::Integrity(_)
+        ));
+        assert!(matches!(
+            Error::from(ResolveError::Resolution(miette::miette!("failed"))),
+            Error::Resolution(_)
+        ));
+        assert!(matches!(
+            Error::from(RegistryError::Signature(
+                physics,
+                version,
+                miette::miette!("invalid signature")
+            )),
+            Error::Integrity(_)
+        ));
+        assert!(matches!(
+            Error::from(
+                miette::Report::new(ResolveError::Cancelled(Cancelled))
+                    .wrap_err("failed to install")
+            ),
+            Error::Cancelled(_)
+        ));
+    }
+
+    #[test]
+    fn is_transparent() {
+        let error = Error::from(miette::miette!(code = "E0008", "failed to reach"));
+
+        assert_eq!(error.to_string(), "failed to reach");
+
+        let report = miette::Report::new(error);
+
+        assert_eq!(report.to_string(), "failed to reach");
+        assert_eq!(ExitCode::of(&report), ExitCode::Network);
+    }
+
+    #[test]
+    fn codes_are_ascending() {
+        for (index, error) in ERROR_CODES.iter().enumerate() {
+            assert_eq!(error.code, format!("E{:04}", index + 1));
+        }
+    }
+}
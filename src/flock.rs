@@ -0,0 +1,114 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+};
+
+use miette::{miette, Context, IntoDiagnostic};
+
+use crate::lock::DigestAlgorithm;
+
+/// The directory in the buffrs home storing the lock files
+const LOCKS_DIRECTORY: &str = "locks";
+
+/// An exclusive advisory lock serializing buffrs processes, released when dropped
+///
+/// Lock files are kept in the buffrs home instead of the locked directories, so that they
+/// neither show up in projects nor get removed together with the directories they guard.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Locks a project, guarding its installed dependencies and lockfile
+    pub async fn project(dir: &Path) -> miette::Result<Self> {
+        Self::acquire(dir, "the project").await
+    }
+
+    /// Locks a cache directory
+    pub async fn cache(dir: &Path) -> miette::Result<Self> {
+        Self::acquire(dir, "the cache").await
+    }
+
+    /// Locks a directory, waiting for other processes holding its lock to release it
+    async fn acquire(dir: &Path, what: &'static str) -> miette::Result<Self> {
+        let path = Self::location(dir)?;
+
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to create {}", parent.display()))?;
+            }
+
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&path)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to open the lock file {}", path.display()))?;
+
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) => {
+                    tracing::info!(":: blocked on another buffrs process using {what}, waiting");
+                }
+                Err(TryLockError::Error(error)) => {
+                    return Err(error)
+                        .into_diagnostic()
+                        .wrap_err(miette!("failed to lock {what}"))
+                }
+            }
+
+            file.lock()
+                .into_diagnostic()
+                .wrap_err(miette!("failed to lock {what}"))?;
+
+            Ok(Self { _file: file })
+        })
+        .await
+        .into_diagnostic()?
+    }
+
+    /// The lock file of a directory, named after the digest of its canonical path
+    fn location(dir: &Path) -> miette::Result<PathBuf> {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+
+        let digest = DigestAlgorithm::SHA256.digest(dir.as_os_str().as_encoded_bytes());
+
+        Ok(crate::home()?
+            .join(LOCKS_DIRECTORY)
+            .join(format!("{}.lock", hex::encode(&digest.as_bytes()[..8]))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileLock;
+
+    #[test]
+    fn locates_locks_by_directory() {
+        let here = FileLock::location(".".as_ref()).unwrap();
+        let cwd = FileLock::location(&std::env::current_dir().unwrap()).unwrap();
+        let other = FileLock::location("/some/other/project".as_ref()).unwrap();
+
+        assert_eq!(here, cwd);
+        assert_ne!(here, other);
+        assert_eq!(here.extension().unwrap(), "lock");
+    }
+}
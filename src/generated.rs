@@ -0,0 +1,334 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Files written by `buffrs generate` and the build manifests describing them to other build
+//! systems
+
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use miette::{miette, Context, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use walkdir::WalkDir;
+
+use crate::{
+    lock::{Digest, DigestAlgorithm},
+    package::PackageName,
+};
+
+/// Directory the build manifests are written to, one per kind of generated files
+pub const BUILD_MANIFEST_PATH: &str = "proto/build/manifest";
+
+/// A file produced by `buffrs generate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedFile {
+    /// Path of the file, relative to the project
+    pub path: PathBuf,
+    /// Contents of the file
+    pub contents: Vec<u8>,
+    /// Package the file was generated for, if the project declares one
+    pub package: Option<PackageName>,
+    /// Protos the file was generated from, relative to the project
+    pub sources: Vec<PathBuf>,
+}
+
+/// Machine-readable description of the files produced by `buffrs generate`
+///
+/// Build systems like Bazel or Buck consume it to declare the generated files as outputs of
+/// their protos, and detect stale outputs by their digests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    /// The generated files, ordered by their path
+    pub files: Vec<BuildManifestEntry>,
+}
+
+/// A generated file as described by the build manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifestEntry {
+    /// Path of the file, relative to the project
+    pub path: PathBuf,
+    /// Package the file was generated for
+    pub package: Option<PackageName>,
+    /// Protos the file was generated from, relative to the project
+    pub sources: Vec<PathBuf>,
+    /// Digest of the contents of the file
+    pub digest: Digest,
+}
+
+/// The files produced by one run of `buffrs generate`, held in memory until they are written
+#[derive(Debug, Clone)]
+pub struct Generated {
+    /// The project the files are generated in
+    project: PathBuf,
+    /// Kind of the files, naming their build manifest
+    kind: String,
+    /// Directory holding nothing but the files, replaced as a whole
+    owned: Option<PathBuf>,
+    files: Vec<GeneratedFile>,
+}
+
+impl Generated {
+    /// Collects files of a kind, written next to other files
+    pub fn new(project: &Path, kind: &str) -> Self {
+        Self {
+            project: project.to_owned(),
+            kind: kind.to_owned(),
+            owned: None,
+            files: Vec::new(),
+        }
+    }
+
+    /// Collects files of a kind into `dir`, which is cleared before they are written
+    pub fn owning(project: &Path, kind: &str, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            owned: Some(dir.into()),
+            ..Self::new(project, kind)
+        }
+    }
+
+    /// Adds a file, with its path and sources relative to the project or absolute
+    pub fn add(
+        &mut self,
+        path: impl AsRef<Path>,
+        contents: impl Into<Vec<u8>>,
+        package: Option<&PackageName>,
+        sources: &[PathBuf],
+    ) {
+        let path = self.relative(path.as_ref());
+        let sources = sources.iter().map(|source| self.relative(source)).collect();
+
+        self.files.push(GeneratedFile {
+            path,
+            contents: contents.into(),
+            package: package.cloned(),
+            sources,
+        });
+    }
+
+    /// Adds copies of files, keeping their paths relative to `base` below `target`
+    pub async fn copy(
+        &mut self,
+        files: &[PathBuf],
+        base: &Path,
+        target: &Path,
+        package: Option<&PackageName>,
+    ) -> miette::Result<()> {
+        for file in files {
+            let contents = fs::read(file)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", file.display()))?;
+
+            let path = target.join(file.strip_prefix(base).into_diagnostic()?);
+
+            self.add(path, contents, package, std::slice::from_ref(file));
+        }
+
+        Ok(())
+    }
+
+    /// The generated files, in the order they were added
+    pub fn files(&self) -> &[GeneratedFile] {
+        &self.files
+    }
+
+    /// Path of the build manifest, relative to the project
+    pub fn manifest_path(&self) -> PathBuf {
+        Path::new(BUILD_MANIFEST_PATH).join(format!("{}.json", self.kind))
+    }
+
+    /// Describes the generated files
+    pub fn manifest(&self) -> BuildManifest {
+        let mut files: Vec<_> = self
+            .files
+            .iter()
+            .map(|file| BuildManifestEntry {
+                path: file.path.clone(),
+                package: file.package.clone(),
+                sources: file.sources.clone(),
+                digest: DigestAlgorithm::SHA256.digest(&file.contents),
+            })
+            .collect();
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        BuildManifest { files }
+    }
+
+    /// Renders the build manifest
+    fn render_manifest(&self) -> miette::Result<Vec<u8>> {
+        let manifest = serde_json::to_string_pretty(&self.manifest()).into_diagnostic()?;
+
+        Ok((manifest + "\n").into_bytes())
+    }
+
+    /// Writes the generated files and their build manifest
+    pub async fn write(&self) -> miette::Result<()> {
+        if let Some(dir) = &self.owned {
+            fs::remove_dir_all(self.project.join(dir)).await.ok();
+        }
+
+        let manifest = self.render_manifest()?;
+        let manifest_path = self.manifest_path();
+
+        let files = self
+            .files
+            .iter()
+            .map(|file| (&file.path, &file.contents))
+            .chain([(&manifest_path, &manifest)]);
+
+        for (path, contents) in files {
+            let path = self.project.join(path);
+
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .await
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to create {}", parent.display()))?;
+            }
+
+            fs::write(&path, contents)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares the generated files and their build manifest with the ones on disk
+    ///
+    /// Returns the paths of files that are missing or differ, and of files in an owned directory
+    /// that would be removed, ordered by their path.
+    pub async fn stale(&self) -> miette::Result<Vec<PathBuf>> {
+        let manifest = self.render_manifest()?;
+        let manifest_path = self.manifest_path();
+
+        let files = self
+            .files
+            .iter()
+            .map(|file| (&file.path, &file.contents))
+            .chain([(&manifest_path, &manifest)]);
+
+        let mut stale = BTreeSet::new();
+
+        for (path, contents) in files {
+            if fs::read(self.project.join(path)).await.ok().as_ref() != Some(contents) {
+                stale.insert(path.clone());
+            }
+        }
+
+        if let Some(dir) = &self.owned {
+            let expected: BTreeSet<_> = self.files.iter().map(|file| &file.path).collect();
+
+            let extra = WalkDir::new(self.project.join(dir))
+                .into_iter()
+                .flatten()
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| self.relative(entry.path()))
+                .filter(|path| !expected.contains(path));
+
+            stale.extend(extra);
+        }
+
+        Ok(stale.into_iter().collect())
+    }
+
+    /// Path relative to the project, unless it lies outside of it
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.project).unwrap_or(path).to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated(project: &Path) -> Generated {
+        let physics: PackageName = "physics".parse().unwrap();
+        let mut generated = Generated::owning(project, "test", "proto/build/test");
+
+        generated.add(
+            project.join("proto/build/test/physics.txt"),
+            "generated",
+            Some(&physics),
+            &[project.join("proto/physics.proto")],
+        );
+
+        generated
+    }
+
+    #[tokio::test]
+    async fn writes_build_manifest() {
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        std::fs::create_dir_all(dir.join("proto/build/test")).unwrap();
+        std::fs::write(dir.join("proto/build/test/stale.txt"), "stale").unwrap();
+
+        generated(dir.path()).write().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("proto/build/test/physics.txt")).unwrap(),
+            "generated"
+        );
+        assert!(!dir.join("proto/build/test/stale.txt").exists());
+
+        let manifest: BuildManifest = serde_json::from_slice(
+            &std::fs::read(dir.join(BUILD_MANIFEST_PATH).join("test.json")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            manifest.files,
+            vec![BuildManifestEntry {
+                path: PathBuf::from("proto/build/test/physics.txt"),
+                package: Some("physics".parse().unwrap()),
+                sources: vec![PathBuf::from("proto/physics.proto")],
+                digest: DigestAlgorithm::SHA256.digest(b"generated"),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn detects_stale_files() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let generated = generated(dir.path());
+
+        assert_eq!(
+            generated.stale().await.unwrap(),
+            vec![
+                PathBuf::from(BUILD_MANIFEST_PATH).join("test.json"),
+                PathBuf::from("proto/build/test/physics.txt"),
+            ]
+        );
+
+        generated.write().await.unwrap();
+
+        assert!(generated.stale().await.unwrap().is_empty());
+
+        std::fs::write(dir.join("proto/build/test/physics.txt"), "edited").unwrap();
+        std::fs::write(dir.join("proto/build/test/stale.txt"), "stale").unwrap();
+
+        assert_eq!(
+            generated.stale().await.unwrap(),
+            vec![
+                PathBuf::from("proto/build/test/physics.txt"),
+                PathBuf::from("proto/build/test/stale.txt"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{io::ErrorKind, process::Stdio};
+
+use miette::{ensure, miette, Context, IntoDiagnostic};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Environment variable holding the name of the command a hook runs after
+pub const COMMAND_VAR: &str = "BUFFRS_COMMAND";
+
+/// Runs the hook configured for a command through the shell
+///
+/// The hook receives the JSON result of the command on stdin, the same document printed with
+/// `--format json`, or `null` for commands without a JSON result. Its output is passed through.
+pub async fn run(hook: &str, command: &str, result: &serde_json::Value) -> miette::Result<()> {
+    tracing::debug!("running post-{command} hook: {hook}");
+
+    let mut child = shell(hook)
+        .env(COMMAND_VAR, command)
+        .stdin(Stdio::piped())
+        .spawn()
+        .into_diagnostic()
+        .wrap_err(miette!("failed to run the post-{command} hook"))?;
+
+    let mut stdin = child.stdin.take().ok_or(miette!(
+        "failed to open the input of the post-{command} hook"
+    ))?;
+
+    // hint: always ok, as per serde_json doc
+    let input = serde_json::to_vec(result).unwrap_or_default();
+
+    // write concurrently, the hook may produce output before consuming all input
+    let writer = tokio::spawn(async move {
+        stdin.write_all(&input).await?;
+        stdin.shutdown().await
+    });
+
+    let status = child.wait().await.into_diagnostic()?;
+
+    ensure!(
+        status.success(),
+        "the post-{command} hook failed with {status}"
+    );
+
+    // hooks are free to ignore their input and exit before it was written
+    match writer.await.into_diagnostic()? {
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => {
+            Err(error).into_diagnostic().wrap_err(miette!(
+                "failed to pass the result to the post-{command} hook"
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Command running a command line through the platform shell
+fn shell(command_line: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    command.arg(command_line);
+    command
+}
@@ -0,0 +1,128 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Container images pinning the toolchain of a project, for reproducible code generation
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use semver::Version;
+use serde_json::{json, Value};
+
+/// Directory of the development container configuration
+pub const DEVCONTAINER_DIR: &str = ".devcontainer";
+
+/// Image buffrs and the Rust protoc plugins are compiled in
+const BUILDER_IMAGE: &str = "rust:1-slim-bookworm";
+
+/// Image the toolchain is installed into, matching the C library of [`BUILDER_IMAGE`]
+const BASE_IMAGE: &str = "debian:bookworm-slim";
+
+/// The toolchain installed into an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageSpec {
+    /// Version of buffrs
+    pub buffrs: Version,
+    /// Release of protoc, e.g. `25.1`
+    pub protoc: String,
+    /// protoc plugins installed with `cargo install`, by crate name
+    pub plugins: BTreeMap<String, Version>,
+    /// Further commands run while building the image
+    pub run: Vec<String>,
+}
+
+impl ImageSpec {
+    /// Renders a multi-stage Dockerfile
+    ///
+    /// buffrs and the plugins are compiled in a builder stage and copied into the final image,
+    /// protoc is downloaded from its GitHub releases for the architecture of the image.
+    pub fn dockerfile(&self) -> String {
+        let mut out = String::from("# Generated by buffrs ci image-spec, do not edit.\n\n");
+
+        let _ = writeln!(out, "FROM {BUILDER_IMAGE} AS builder\n");
+        let _ = writeln!(
+            out,
+            "RUN cargo install --locked --root /opt/buffrs buffrs@{}",
+            self.buffrs
+        );
+
+        for (plugin, version) in &self.plugins {
+            let _ = writeln!(
+                out,
+                "RUN cargo install --locked --root /opt/buffrs {plugin}@{version}"
+            );
+        }
+
+        let _ = writeln!(out, "\nFROM {BASE_IMAGE}\n");
+        out.push_str("ARG TARGETARCH\n");
+        let _ = writeln!(out, "ARG PROTOC_VERSION={}\n", self.protoc);
+        out.push_str(
+            "RUN apt-get update \\\n    && apt-get install -y --no-install-recommends ca-certificates curl unzip git \\\n    && rm -rf /var/lib/apt/lists/*\n\n",
+        );
+        out.push_str(
+            "RUN case \"$TARGETARCH\" in arm64) arch=aarch_64 ;; *) arch=x86_64 ;; esac \\\n    && curl -fsSL -o /tmp/protoc.zip \"https://github.com/protocolbuffers/protobuf/releases/download/v${PROTOC_VERSION}/protoc-${PROTOC_VERSION}-linux-${arch}.zip\" \\\n    && unzip -q /tmp/protoc.zip -d /usr/local \\\n    && rm /tmp/protoc.zip\n\n",
+        );
+        out.push_str("COPY --from=builder /opt/buffrs/bin/ /usr/local/bin/\n");
+
+        if !self.run.is_empty() {
+            out.push('\n');
+        }
+
+        for command in &self.run {
+            let _ = writeln!(out, "RUN {command}");
+        }
+
+        out
+    }
+
+    /// Renders a `devcontainer.json` building the image from the Dockerfile next to it
+    pub fn devcontainer(&self, name: &str) -> Value {
+        json!({
+            "name": name,
+            "build": { "dockerfile": "Dockerfile" },
+            "containerEnv": { "PROTOC": "/usr/local/bin/protoc" }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ImageSpec {
+        ImageSpec {
+            buffrs: Version::new(0, 9, 0),
+            protoc: "25.1".to_owned(),
+            plugins: BTreeMap::from([("protoc-gen-prost".to_owned(), Version::new(0, 4, 0))]),
+            run: vec!["npm install -g @bufbuild/protoc-gen-es@1.10.0".to_owned()],
+        }
+    }
+
+    #[test]
+    fn renders_dockerfiles() {
+        let dockerfile = spec().dockerfile();
+
+        assert!(dockerfile.contains("buffrs@0.9.0"));
+        assert!(dockerfile.contains("protoc-gen-prost@0.4.0"));
+        assert!(dockerfile.contains("ARG PROTOC_VERSION=25.1"));
+        assert!(dockerfile.ends_with("RUN npm install -g @bufbuild/protoc-gen-es@1.10.0\n"));
+    }
+
+    #[test]
+    fn renders_devcontainers() {
+        let devcontainer = spec().devcontainer("physics");
+
+        assert_eq!(devcontainer["name"], "physics");
+        assert_eq!(devcontainer["build"]["dockerfile"], "Dockerfile");
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kubernetes resources carrying the descriptor set of a package, e.g. for the gRPC-JSON
+//! transcoder of Envoy
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use miette::IntoDiagnostic;
+use serde_json::{json, Value};
+
+use crate::manifest::PackageManifest;
+
+/// Directory the resources are written to
+pub const KUBERNETES_PATH: &str = "proto/build/kubernetes";
+
+/// A ConfigMap or Secret holding the descriptor set of a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorResource {
+    /// Name of the resource, defaults to `<package>-descriptors`
+    pub name: Option<String>,
+    /// Namespace of the resource, the namespace of the context it is applied in if unset
+    pub namespace: Option<String>,
+    /// Create a Secret instead of a ConfigMap
+    pub secret: bool,
+    /// The package
+    pub package: PackageManifest,
+    /// Serialized `FileDescriptorSet` of the package and its imports
+    pub descriptors: Vec<u8>,
+}
+
+impl DescriptorResource {
+    /// Name of the resource
+    pub fn name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{}-descriptors", self.package.name))
+    }
+
+    /// Key of the descriptor set within the resource, the file name it is mounted as
+    pub fn key(&self) -> String {
+        format!("{}.pb", self.package.name)
+    }
+
+    /// Renders the resource as a manifest
+    pub fn render(&self) -> miette::Result<String> {
+        let mut metadata = json!({
+            "name": self.name(),
+            "labels": {
+                "app.kubernetes.io/name": self.package.name.to_string(),
+                "app.kubernetes.io/version": self.package.version.to_string(),
+                "app.kubernetes.io/managed-by": "buffrs"
+            }
+        });
+
+        if let Some(namespace) = &self.namespace {
+            metadata["namespace"] = json!(namespace);
+        }
+
+        let data = json!({ self.key(): STANDARD.encode(&self.descriptors) });
+
+        let resource: Value = match self.secret {
+            true => json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": metadata,
+                "type": "Opaque",
+                "data": data
+            }),
+            false => json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": metadata,
+                "binaryData": data
+            }),
+        };
+
+        serde_yml::to_string(&resource).into_diagnostic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use super::*;
+    use crate::package::PackageType;
+
+    fn resource(secret: bool) -> DescriptorResource {
+        DescriptorResource {
+            name: None,
+            namespace: Some("gateway".to_owned()),
+            secret,
+            package: PackageManifest {
+                kind: PackageType::Api,
+                name: "physics".parse().unwrap(),
+                version: Version::new(1, 2, 0),
+                description: None,
+                license: None,
+                max_size: None,
+            },
+            descriptors: b"descriptors".to_vec(),
+        }
+    }
+
+    #[test]
+    fn renders_config_maps() {
+        let manifest: Value = serde_yml::from_str(&resource(false).render().unwrap()).unwrap();
+
+        assert_eq!(manifest["kind"], "ConfigMap");
+        assert_eq!(manifest["metadata"]["name"], "physics-descriptors");
+        assert_eq!(manifest["metadata"]["namespace"], "gateway");
+        assert_eq!(
+            manifest["binaryData"]["physics.pb"],
+            STANDARD.encode(b"descriptors")
+        );
+    }
+
+    #[test]
+    fn renders_secrets() {
+        let manifest: Value = serde_yml::from_str(&resource(true).render().unwrap()).unwrap();
+
+        assert_eq!(manifest["kind"], "Secret");
+        assert_eq!(manifest["type"], "Opaque");
+        assert!(manifest.get("binaryData").is_none());
+        assert_eq!(
+            manifest["data"]["physics.pb"],
+            STANDARD.encode(b"descriptors")
+        );
+    }
+}
@@ -15,46 +15,127 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "native")]
 use miette::Diagnostic;
+#[cfg(feature = "native")]
 use std::{env, path::PathBuf};
+#[cfg(feature = "native")]
 use thiserror::Error;
 
+/// Bazel build file generation
+#[cfg(feature = "native")]
+pub mod bazel;
+/// Build script integration
+#[cfg(feature = "native")]
+pub mod build;
 /// Caching implementation
+#[cfg(feature = "native")]
 pub mod cache;
+/// Cancellation of long-running operations
+pub mod cancel;
+/// Version synchronization with Cargo manifests
+#[cfg(feature = "native")]
+pub mod cargo;
 /// CLI command implementations
+#[cfg(feature = "cli")]
 pub mod command;
 /// Configuration file (.buffrs/config.toml) handling
+#[cfg(feature = "native")]
 pub mod config;
 /// Credential management
+#[cfg(feature = "native")]
 pub mod credentials;
+/// Documentation generation
+#[cfg(feature = "native")]
+pub mod doc;
+/// Package encryption for restricted registries
+#[cfg(feature = "native")]
+pub mod encryption;
 /// Common error types
 pub mod errors;
+/// Advisory locks serializing concurrent buffrs processes
+#[cfg(feature = "native")]
+pub mod flock;
+/// Files written by `buffrs generate` and their build manifest
+#[cfg(feature = "native")]
+pub mod generated;
+/// Post-command hooks
+#[cfg(feature = "native")]
+pub mod hook;
+/// Container images pinning the toolchain of a project
+#[cfg(feature = "native")]
+pub mod image;
+/// Kubernetes resources carrying descriptor sets
+#[cfg(feature = "native")]
+pub mod kubernetes;
 /// Lockfile implementation
 pub mod lock;
+/// Configuration of proto language servers
+#[cfg(feature = "native")]
+pub mod lsp;
 /// Manifest format and IO
 pub mod manifest;
+/// Maven project generation for JVM consumers
+#[cfg(feature = "native")]
+pub mod maven;
+/// npm package generation for TypeScript consumers
+#[cfg(feature = "native")]
+pub mod npm;
+/// Rendering of log messages
+pub mod output;
 /// Packages formats and utilities
 pub mod package;
+/// Order-preserving parallel processing
+mod parallel;
+/// Progress reporting of installations
+pub mod progress;
 /// Supported registries
 pub mod registry;
 /// Resolve package dependencies.
 pub mod resolver;
+/// Software bill of materials generation
+pub mod sbom;
+/// Package signing and signature verification
+#[cfg(feature = "native")]
+pub mod signing;
+/// Export of tracing spans to OpenTelemetry collectors
+#[cfg(feature = "otel")]
+pub mod telemetry;
+/// Starter contents of new packages
+pub mod template;
 /// Validation for buffrs packages.
 #[cfg(feature = "validation")]
 pub mod validation;
+/// Vendored copies of the locked dependencies
+#[cfg(feature = "native")]
+pub mod vendor;
 
 /// buf.yaml generation
+#[cfg(feature = "native")]
 pub mod buf_yaml;
 
+#[cfg(feature = "native")]
+pub use build::BuildConfig;
+pub use errors::{Error, Result};
+#[cfg(feature = "native")]
+pub use package::PackageStore;
+#[cfg(feature = "native")]
+pub use registry::{Registry, RegistryError};
+#[cfg(feature = "native")]
+pub use resolver::{ResolveError, Resolver};
+
 /// Managed directory for `buffrs`
 pub const BUFFRS_HOME: &str = ".buffrs";
 
+#[cfg(feature = "native")]
 pub(crate) const BUFFRS_HOME_VAR: &str = "BUFFRS_HOME";
 
+#[cfg(feature = "native")]
 #[derive(Error, Diagnostic, Debug)]
 #[error("could not determine buffrs home location")]
 struct HomeError(#[diagnostic_source] miette::Report);
 
+#[cfg(feature = "native")]
 fn home() -> Result<PathBuf, HomeError> {
     env::var(BUFFRS_HOME_VAR)
         .map(PathBuf::from)
@@ -68,20 +149,26 @@ fn home() -> Result<PathBuf, HomeError> {
 
 #[derive(Debug)]
 pub(crate) enum ManagedFile {
+    #[cfg(feature = "native")]
     Credentials,
     Manifest,
+    #[cfg(feature = "native")]
     Lock,
 }
 
 impl ManagedFile {
     fn name(&self) -> &str {
+        #[cfg(feature = "native")]
         use credentials::CREDENTIALS_FILE;
+        #[cfg(feature = "native")]
         use lock::LOCKFILE;
         use manifest::MANIFEST_FILE;
 
         match self {
             ManagedFile::Manifest => MANIFEST_FILE,
+            #[cfg(feature = "native")]
             ManagedFile::Lock => LOCKFILE,
+            #[cfg(feature = "native")]
             ManagedFile::Credentials => CREDENTIALS_FILE,
         }
     }
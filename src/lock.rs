@@ -12,24 +12,34 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use miette::{ensure, Context, IntoDiagnostic};
+#[cfg(feature = "native")]
+use miette::IntoDiagnostic;
+use miette::{ensure, miette, Context};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+#[cfg(feature = "native")]
 use tokio::fs;
 use url::Url;
 
 use crate::{
-    errors::{DeserializationError, FileExistsError, FileNotFound, SerializationError, WriteError},
+    errors::DigestMismatch,
     package::{Package, PackageName},
     registry::RegistryUri,
+};
+#[cfg(feature = "native")]
+use crate::{
+    errors::{
+        DeserializationError, FileExistsError, FileNotFound, MalformedLockfile, SerializationError,
+        WriteError,
+    },
     ManagedFile,
 };
 
 mod digest;
-pub use digest::{Digest, DigestAlgorithm};
+pub use digest::{Digest, DigestAlgorithm, Hasher};
 
 /// File name of the lockfile
 pub const LOCKFILE: &str = "Proto.lock";
@@ -83,8 +93,11 @@ impl LockedPackage {
 
     /// Validates if another LockedPackage matches this one
     pub fn validate(&self, package: &Package) -> miette::Result<()> {
-        let digest: Digest = DigestAlgorithm::SHA256.digest(&package.tgz);
+        self.validate_digest(package, &DigestAlgorithm::SHA256.digest(&package.tgz))
+    }
 
+    /// Validates if another LockedPackage matches this one, given its precomputed digest
+    fn validate_digest(&self, package: &Package, digest: &Digest) -> miette::Result<()> {
         #[derive(Error, Debug)]
         #[error("{property} mismatch - expected {expected}, actual {actual}")]
         struct ValidationError {
@@ -112,18 +125,18 @@ impl LockedPackage {
         );
 
         ensure!(
-            self.digest == digest,
-            ValidationError {
-                property: "digest",
-                expected: self.digest.to_string(),
-                actual: digest.to_string(),
-            }
+            self.digest == *digest,
+            DigestMismatch(format!(
+                "digest mismatch - expected {}, actual {digest}",
+                self.digest
+            ))
         );
 
         Ok(())
     }
 }
 
+#[cfg(feature = "native")]
 #[derive(Serialize, Deserialize)]
 struct RawLockfile {
     version: u16,
@@ -140,6 +153,7 @@ pub struct Lockfile {
 
 impl Lockfile {
     /// Checks if the Lockfile currently exists in the filesystem
+    #[cfg(feature = "native")]
     pub async fn exists() -> miette::Result<bool> {
         fs::try_exists(LOCKFILE)
             .await
@@ -148,11 +162,12 @@ impl Lockfile {
     }
 
     /// Loads the Lockfile from the current directory
+    #[cfg(feature = "native")]
     pub async fn read() -> miette::Result<Self> {
         match fs::read_to_string(LOCKFILE).await {
             Ok(contents) => {
                 let raw: RawLockfile = toml::from_str(&contents)
-                    .into_diagnostic()
+                    .map_err(|error| MalformedLockfile::new(LOCKFILE, &contents, error))
                     .wrap_err(DeserializationError(ManagedFile::Lock))?;
                 Ok(Self::from_iter(raw.packages.into_iter()))
             }
@@ -164,6 +179,7 @@ impl Lockfile {
     }
 
     /// Loads the Lockfile from the current directory, if it exists, otherwise returns an empty one
+    #[cfg(feature = "native")]
     pub async fn read_or_default() -> miette::Result<Self> {
         if Lockfile::exists().await? {
             Lockfile::read().await
@@ -173,6 +189,7 @@ impl Lockfile {
     }
 
     /// Persists a Lockfile to the filesystem
+    #[cfg(feature = "native")]
     pub async fn write(&self) -> miette::Result<()> {
         let mut packages: Vec<_> = self
             .packages
@@ -207,6 +224,106 @@ impl Lockfile {
     pub fn get(&self, name: &PackageName) -> Option<&LockedPackage> {
         self.packages.get(name)
     }
+
+    /// Iterates over all locked packages
+    pub fn packages(&self) -> impl Iterator<Item = &LockedPackage> {
+        self.packages.values()
+    }
+
+    /// Validates packages against their locked versions, hashing them in parallel
+    ///
+    /// Packages that are not locked, or locked at another version which the next write of the
+    /// lockfile replaces, are skipped.
+    pub async fn validate_all<'a>(
+        &self,
+        packages: impl IntoIterator<Item = &'a Package>,
+    ) -> miette::Result<()> {
+        let locked: Vec<_> = packages
+            .into_iter()
+            .filter_map(|package| {
+                self.get(package.name())
+                    .filter(|locked| &locked.version == package.version())
+                    .map(|locked| (locked, package))
+            })
+            .collect();
+
+        let digests = DigestAlgorithm::SHA256
+            .digest_all(
+                locked
+                    .iter()
+                    .map(|(_, package)| package.tgz.clone())
+                    .collect(),
+            )
+            .await?;
+
+        for ((locked, package), digest) in locked.into_iter().zip(digests) {
+            locked
+                .validate_digest(package, &digest)
+                .wrap_err(miette!("failed to validate package {}", locked.name))?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the packages that are no longer reachable from the given direct dependencies
+    ///
+    /// The dependant counts of the remaining packages are updated, the removed packages are
+    /// returned sorted by name.
+    pub fn prune<'a>(
+        &mut self,
+        dependencies: impl IntoIterator<Item = &'a PackageName>,
+    ) -> Vec<LockedPackage> {
+        let roots: HashSet<&PackageName> = dependencies.into_iter().collect();
+
+        let mut reachable: HashSet<PackageName> = HashSet::new();
+        let mut pending: Vec<&PackageName> = roots.iter().copied().collect();
+
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+
+            if let Some(locked) = self.packages.get(name) {
+                pending.extend(&locked.dependencies);
+            }
+        }
+
+        let unreachable: Vec<PackageName> = self
+            .packages
+            .keys()
+            .filter(|name| !reachable.contains(*name))
+            .cloned()
+            .collect();
+
+        let mut removed: Vec<LockedPackage> = unreachable
+            .iter()
+            .filter_map(|name| self.packages.remove(name))
+            .collect();
+
+        removed.sort();
+
+        let dependants: Vec<(PackageName, usize)> = self
+            .packages
+            .keys()
+            .map(|name| {
+                let count = self
+                    .packages
+                    .values()
+                    .filter(|locked| locked.dependencies.contains(name))
+                    .count();
+
+                (name.clone(), count + usize::from(roots.contains(name)))
+            })
+            .collect();
+
+        for (name, count) in dependants {
+            if let Some(locked) = self.packages.get_mut(&name) {
+                locked.dependants = count;
+            }
+        }
+
+        removed
+    }
 }
 
 impl FromIterator<LockedPackage> for Lockfile {
@@ -293,3 +410,46 @@ impl From<&LockedPackage> for FileRequirement {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(name: &str, dependencies: &[&str], dependants: usize) -> LockedPackage {
+        LockedPackage {
+            name: name.parse().unwrap(),
+            digest: DigestAlgorithm::SHA256.digest(name.as_bytes()),
+            registry: "https://registry.example.com/artifactory".parse().unwrap(),
+            repository: "protos".into(),
+            version: Version::new(1, 0, 0),
+            dependencies: dependencies.iter().map(|d| d.parse().unwrap()).collect(),
+            dependants,
+        }
+    }
+
+    #[test]
+    fn prunes_unreachable_packages() {
+        let mut lockfile = Lockfile::from_iter([
+            locked("physics", &["units"], 1),
+            locked("chemistry", &["units", "elements"], 1),
+            locked("units", &[], 2),
+            locked("elements", &[], 1),
+        ]);
+
+        let physics: PackageName = "physics".parse().unwrap();
+        let removed = lockfile.prune([&physics]);
+
+        assert_eq!(
+            removed,
+            vec![
+                locked("chemistry", &["units", "elements"], 1),
+                locked("elements", &[], 1)
+            ]
+        );
+
+        let units: PackageName = "units".parse().unwrap();
+
+        assert_eq!(lockfile.get(&units), Some(&locked("units", &[], 1)));
+        assert_eq!(lockfile.packages().count(), 2);
+    }
+}
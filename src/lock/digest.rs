@@ -12,8 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{fmt, str::FromStr};
+use std::{fmt, io, num::NonZeroUsize, str::FromStr, thread};
 
+use bytes::Bytes;
+use miette::IntoDiagnostic;
 use serde::{de::Visitor, Deserialize, Serialize};
 use sha2::Digest as _;
 use strum::{Display, EnumString};
@@ -43,6 +45,99 @@ impl DigestAlgorithm {
             digest,
         }
     }
+
+    /// Create digests of many payloads in parallel, returned in the order of the payloads.
+    ///
+    /// The payloads are spread across one worker of the blocking thread pool per available core.
+    /// WebAssembly targets lack threads and hash the payloads one after another.
+    pub async fn digest_all(&self, payloads: Vec<Bytes>) -> miette::Result<Vec<Digest>> {
+        if cfg!(target_family = "wasm") {
+            return Ok(payloads
+                .iter()
+                .map(|payload| self.digest(payload))
+                .collect());
+        }
+
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(payloads.len())
+            .max(1);
+
+        let mut shares = vec![Vec::new(); workers];
+
+        for (index, payload) in payloads.into_iter().enumerate() {
+            shares[index % workers].push((index, payload));
+        }
+
+        let algorithm = *self;
+
+        let tasks: Vec<_> = shares
+            .into_iter()
+            .map(|share| {
+                tokio::task::spawn_blocking(move || {
+                    share
+                        .into_iter()
+                        .map(|(index, payload)| (index, algorithm.digest(&payload)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut digests = Vec::new();
+
+        for task in tasks {
+            digests.extend(task.await.into_diagnostic()?);
+        }
+
+        digests.sort_by_key(|(index, _)| *index);
+
+        Ok(digests.into_iter().map(|(_, digest)| digest).collect())
+    }
+
+    /// Create a hasher computing a digest of data that arrives in pieces, e.g. from a stream.
+    pub fn hasher(&self) -> Hasher {
+        let state = match self {
+            DigestAlgorithm::SHA256 => sha2::Sha256::new(),
+        };
+
+        Hasher {
+            algorithm: *self,
+            state,
+        }
+    }
+}
+
+/// Incremental computation of a [`Digest`], fed by writing to it.
+#[derive(Clone, Debug)]
+pub struct Hasher {
+    algorithm: DigestAlgorithm,
+    state: sha2::Sha256,
+}
+
+impl Hasher {
+    /// Feed data into the digest.
+    pub fn update(&mut self, data: &[u8]) {
+        self.state.update(data);
+    }
+
+    /// The digest of all data fed so far.
+    pub fn finalize(self) -> Digest {
+        Digest {
+            algorithm: self.algorithm,
+            digest: self.state.finalize().to_vec(),
+        }
+    }
+}
+
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 /// Error parsing a [`DigestAlgorithm`].
@@ -64,6 +159,40 @@ fn can_display_digest_algorithm() {
     assert_eq!(DigestAlgorithm::SHA256.to_string(), "sha256");
 }
 
+#[test]
+fn can_digest_incrementally() {
+    let mut hasher = DigestAlgorithm::SHA256.hasher();
+
+    hasher.update(b"hel");
+    hasher.update(b"lo");
+
+    assert_eq!(hasher.finalize(), DigestAlgorithm::SHA256.digest(b"hello"));
+}
+
+#[tokio::test]
+async fn can_digest_in_parallel() {
+    let payloads: Vec<Bytes> = (0..32)
+        .map(|n| Bytes::from(format!("payload {n}")))
+        .collect();
+
+    let digests = DigestAlgorithm::SHA256
+        .digest_all(payloads.clone())
+        .await
+        .unwrap();
+
+    let expected: Vec<_> = payloads
+        .iter()
+        .map(|payload| DigestAlgorithm::SHA256.digest(payload))
+        .collect();
+
+    assert_eq!(digests, expected);
+    assert!(DigestAlgorithm::SHA256
+        .digest_all(Vec::new())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
 /// A representation of a cryptographic digest for data integrity validation
 ///
 /// ```rust
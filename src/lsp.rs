@@ -0,0 +1,151 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Configuration of proto language servers, so imports of installed packages resolve in IDEs
+
+use miette::{miette, Context, IntoDiagnostic};
+use serde_json::{json, Value};
+
+/// Configuration file of the protols language server
+pub const PROTOLS_FILE: &str = "protols.toml";
+
+/// Workspace settings of Visual Studio Code, read by the vscode-proto3 extension
+pub const VSCODE_SETTINGS: &str = ".vscode/settings.json";
+
+/// Sets the include paths of a protols configuration, keeping its other settings
+pub fn protols(existing: Option<&str>, include_paths: &[String]) -> miette::Result<String> {
+    let mut settings: toml::Table = match existing {
+        Some(existing) => toml::from_str(existing)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to parse {PROTOLS_FILE}"))?,
+        None => toml::Table::new(),
+    };
+
+    let config = settings
+        .entry("config")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+        .as_table_mut()
+        .ok_or(miette!("`config` in {PROTOLS_FILE} is not a table"))?;
+
+    config.insert(
+        "include_paths".to_owned(),
+        toml::Value::Array(
+            include_paths
+                .iter()
+                .map(|path| toml::Value::String(path.clone()))
+                .collect(),
+        ),
+    );
+
+    toml::to_string_pretty(&settings).into_diagnostic()
+}
+
+/// Sets the include paths of the vscode-proto3 extension in the workspace settings of Visual
+/// Studio Code, keeping the other settings and protoc options
+///
+/// Settings containing comments can not be parsed and are reported as errors.
+pub fn vscode(existing: Option<&str>, include_paths: &[String]) -> miette::Result<String> {
+    let mut settings: Value = match existing {
+        Some(existing) => serde_json::from_str(existing)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to parse {VSCODE_SETTINGS}"))?,
+        None => json!({}),
+    };
+
+    let settings_object = settings
+        .as_object_mut()
+        .ok_or(miette!("{VSCODE_SETTINGS} does not contain an object"))?;
+
+    let protoc = settings_object
+        .entry("protoc")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .ok_or(miette!("`protoc` in {VSCODE_SETTINGS} is not an object"))?;
+
+    let mut options: Vec<Value> = protoc
+        .get("options")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|option| {
+            !option.as_str().is_some_and(|option| {
+                option.starts_with("--proto_path=") || option.starts_with("-I")
+            })
+        })
+        .cloned()
+        .collect();
+
+    options.extend(
+        include_paths
+            .iter()
+            .map(|path| json!(format!("--proto_path={path}"))),
+    );
+
+    protoc.insert("options".to_owned(), Value::Array(options));
+
+    let mut contents = serde_json::to_string_pretty(&settings).into_diagnostic()?;
+
+    contents.push('\n');
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configures_protols() {
+        let existing =
+            "[config]\ninclude_paths = [\"old\"]\n\n[config.path]\nprotoc = \"/usr/bin/protoc\"\n";
+        let paths = vec!["proto/vendor".to_owned()];
+
+        let settings: toml::Table =
+            toml::from_str(&protols(Some(existing), &paths).unwrap()).unwrap();
+
+        assert_eq!(
+            settings["config"]["include_paths"].as_array().unwrap(),
+            &vec![toml::Value::String("proto/vendor".to_owned())]
+        );
+        assert_eq!(
+            settings["config"]["path"]["protoc"].as_str(),
+            Some("/usr/bin/protoc")
+        );
+
+        assert!(protols(None, &paths).unwrap().contains("include_paths"));
+    }
+
+    #[test]
+    fn configures_vscode() {
+        let existing = r#"{
+            "editor.formatOnSave": true,
+            "protoc": { "options": ["--proto_path=old", "--experimental_allow_proto3_optional"] }
+        }"#;
+        let paths = vec!["proto/vendor".to_owned()];
+
+        let settings: Value =
+            serde_json::from_str(&vscode(Some(existing), &paths).unwrap()).unwrap();
+
+        assert_eq!(settings["editor.formatOnSave"], true);
+        assert_eq!(
+            settings["protoc"]["options"],
+            json!([
+                "--experimental_allow_proto3_optional",
+                "--proto_path=proto/vendor"
+            ])
+        );
+
+        assert!(vscode(Some("// comment\n{}"), &paths).is_err());
+    }
+}
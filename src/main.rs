@@ -12,15 +12,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use buffrs::command::{self, GenerationFlags, InstallMode};
-use buffrs::config::Config;
+use buffrs::cancel::{Cancel, Cancelled};
+use buffrs::command::{
+    self, hooks::GitHook, mirror::Endpoint, BumpLevel, CleanTargets, Emit, FailOn, Forge,
+    GenerateOptions, GenerationFlags, InstallMode, OutputFormat,
+};
+use buffrs::config::{ColorChoice, Config};
+use buffrs::errors::{ErrorCode, ExitCode};
+use buffrs::flock::FileLock;
+use buffrs::hook;
+use buffrs::lock::Digest;
 use buffrs::manifest::Manifest;
+use buffrs::output::MessageFormat;
 use buffrs::package::PackageName;
+use buffrs::registry::bsr::ModuleRef;
+use buffrs::sbom::SbomFormat;
+use buffrs::signing::Signer;
+use buffrs::template::Template;
 use buffrs::{manifest::MANIFEST_FILE, package::PackageType};
 use clap::CommandFactory;
-use clap::{Parser, Subcommand};
+use clap::{FromArgMatches, Parser, Subcommand};
 use miette::{miette, IntoDiagnostic, WrapErr};
-use semver::Version;
+use semver::{Version, VersionReq};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing_subscriber::{
+    filter::LevelFilter, fmt::writer::BoxMakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+    Layer,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about)]
@@ -30,6 +50,14 @@ struct Cli {
     #[clap(long)]
     ignore_defaults: bool,
 
+    /// Format of the output (human or json)
+    #[clap(long, global = true, default_value = "human")]
+    format: OutputFormat,
+
+    /// Only log warnings and errors
+    #[clap(long, short, global = true)]
+    quiet: bool,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -49,6 +77,15 @@ enum Command {
         /// The package name used for initialization
         #[clap(requires = "pkg")]
         package: Option<PackageName>,
+        /// Adopt an existing directory of proto files, moving them into `proto/`
+        ///
+        /// The package name and type are inferred from the files unless given, and dependencies
+        /// are drafted for imports of files outside of the directory.
+        #[clap(long, value_name = "DIR")]
+        from_protos: Option<PathBuf>,
+        /// Symlink the adopted proto files into `proto/` instead of moving them
+        #[clap(long, requires = "from_protos")]
+        link: bool,
     },
 
     /// Creates a new buffrs package in the current directory
@@ -64,10 +101,93 @@ enum Command {
         /// The package name
         #[clap(requires = "pkg")]
         package: PackageName,
+        /// Starter protocol buffers to scaffold (basic or grpc-service)
+        #[clap(long, default_value = "basic")]
+        template: Template,
+        /// Also scaffold a build.rs compiling the protocol buffers with tonic
+        #[clap(long)]
+        build_script: bool,
     },
 
     /// Check rule violations for this package.
-    Lint,
+    Lint {
+        /// Also report messages and enums that are not used by any service
+        #[clap(long)]
+        unused: bool,
+        /// Fail if diagnostics of this or a higher severity are found (warn or error)
+        #[clap(long)]
+        fail_on: Option<FailOn>,
+    },
+
+    /// Checks the package for breaking changes against a published version
+    Breaking {
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Repository the package is published to
+        #[clap(long)]
+        repository: String,
+        /// Version to compare against, defaults to the latest published version
+        #[clap(long)]
+        against: Option<Version>,
+        /// Only report changes of services affecting existing clients
+        #[clap(long)]
+        clients: bool,
+        /// Fail on source incompatible changes (warn) or only on wire incompatible ones (error)
+        #[clap(long, default_value = "warn")]
+        fail_on: FailOn,
+    },
+
+    /// Reserves the fields removed since a published version
+    Fix {
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Repository the package is published to
+        #[clap(long)]
+        repository: String,
+        /// Version to compare against, defaults to the latest published version
+        #[clap(long)]
+        against: Option<Version>,
+    },
+
+    /// Increases the version of the package
+    Version {
+        /// Component to increase (patch, minor or major)
+        ///
+        /// Suggested from the API changes since the latest published version if omitted, which
+        /// requires `--repository`.
+        level: Option<BumpLevel>,
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Repository the package is published to, checks the level against the API changes
+        #[clap(long)]
+        repository: Option<String>,
+        /// Commit the manifest with the new version
+        #[clap(long)]
+        commit: bool,
+        /// Commit the manifest and tag the commit with the new version
+        #[clap(long)]
+        tag: bool,
+        /// Only print the new version without changing the manifest
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Prints the API changes between two versions of a package
+    Diff {
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Repository the package is published to
+        #[clap(long)]
+        repository: String,
+        /// Baseline version (Format <package>@<version>)
+        from: String,
+        /// Version to compare with (Format <package>@<version>), defaults to the local package
+        to: Option<String>,
+    },
 
     /// Adds dependencies to a manifest file
     Add {
@@ -91,6 +211,9 @@ enum Command {
         #[clap(long)]
         #[arg(default_value = ".")]
         output_directory: String,
+        /// Path of the released package, e.g. dist/foo-1.2.0.tgz
+        #[clap(long, conflicts_with = "output_directory")]
+        output: Option<PathBuf>,
         /// Generate package but do not write it to filesystem
         #[clap(long)]
         dry_run: bool,
@@ -112,6 +235,40 @@ enum Command {
         /// Allow a dirty git working tree while publishing
         #[clap(long)]
         allow_dirty: bool,
+        /// Skip compiling the package before publishing
+        #[clap(long)]
+        skip_verify: bool,
+        /// Fail instead of warning if breaking changes are published without a major version bump
+        #[clap(long)]
+        enforce_semver: bool,
+        /// Sign the package with cosign and upload the signature next to it
+        #[clap(long)]
+        sign: bool,
+        /// Private key to sign with, signs keyless via sigstore if omitted
+        #[clap(long, requires = "sign", conflicts_with = "gpg")]
+        signing_key: Option<PathBuf>,
+        /// Sign with GnuPG instead of cosign, for environments without access to sigstore
+        #[clap(long, requires = "sign")]
+        gpg: bool,
+        /// GnuPG key to sign with, uses the default key if omitted
+        #[clap(long, requires = "gpg")]
+        gpg_key: Option<String>,
+        /// Publish a previously packaged archive instead of the current package
+        #[clap(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["allow_dirty", "skip_verify", "enforce_semver"]
+        )]
+        from_file: Option<PathBuf>,
+        /// Digest the archive given by `--from-file` has to match (e.g. sha256:<hex>)
+        #[clap(long, requires = "from_file")]
+        digest: Option<Digest>,
+        /// Upload the release as content defined chunks, skipping chunks the registry already has
+        ///
+        /// Speeds up publishing new versions of large packages, as only the changed chunks are
+        /// transferred. Installing fetches only the chunks missing from the local cache.
+        #[clap(long)]
+        chunked: bool,
         /// Abort right before uploading the release to the registry
         #[clap(long)]
         dry_run: bool,
@@ -120,6 +277,57 @@ enum Command {
         /// Note: This overrides the version in the manifest.
         #[clap(long)]
         set_version: Option<Version>,
+        /// Publish the package and all packages it reaches through local dependencies
+        ///
+        /// Packages are published in dependency order, depending on the versions published
+        /// before them. Versions that are already published are skipped.
+        #[clap(long, conflicts_with_all = ["from_file", "set_version"])]
+        all: bool,
+    },
+
+    /// Bumps the version, then commits, tags, builds and publishes the release
+    Release {
+        /// Component to increase (patch, minor or major)
+        ///
+        /// Suggested from the API changes since the latest published version if omitted.
+        level: Option<BumpLevel>,
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Destination repository for the release
+        #[clap(long)]
+        repository: String,
+        /// Push the release commit and tag to the upstream of the current branch
+        #[clap(long)]
+        push: bool,
+        /// Create a release with the package attached on a forge (github or gitlab)
+        #[clap(long, requires = "push")]
+        forge: Option<Forge>,
+        /// Sign the package with cosign and upload the signature next to it
+        #[clap(long)]
+        sign: bool,
+        /// Private key to sign with, signs keyless via sigstore if omitted
+        #[clap(long, requires = "sign", conflicts_with = "gpg")]
+        signing_key: Option<PathBuf>,
+        /// Sign with GnuPG instead of cosign, for environments without access to sigstore
+        #[clap(long, requires = "sign")]
+        gpg: bool,
+        /// GnuPG key to sign with, uses the default key if omitted
+        #[clap(long, requires = "gpg")]
+        gpg_key: Option<String>,
+        /// Only print the new version and build the package, without changing anything
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Prints the packages affected by changes since a git ref, in publish order
+    ///
+    /// Considers the current package and all packages reachable through local dependencies.
+    /// Packages depending on an affected package are affected as well.
+    Changed {
+        /// Git ref to compare against, e.g. origin/main
+        #[clap(long)]
+        since: String,
     },
 
     /// Installs dependencies
@@ -131,20 +339,149 @@ enum Command {
         /// Generate buf.yaml file matching the installed dependencies
         #[clap(long, default_value = "false")]
         buf_yaml: bool,
+
+        /// Configure proto language servers (protols, vscode-proto3) to resolve installed imports
+        #[clap(long)]
+        lsp: bool,
+
+        /// Only print what would be installed and locked, without changing any files
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Install exclusively from the vendor directory created by `buffrs vendor`
+        #[clap(long)]
+        vendored: bool,
     },
 
+    /// Stores the locked dependencies in the vendor directory for offline installs
+    Vendor,
+
     /// Uninstalls dependencies
     Uninstall,
 
+    /// Removes installed dependencies, build output and cached packages
+    Clean {
+        /// Remove the installed dependencies in proto/vendor
+        #[clap(long)]
+        deps: bool,
+        /// Remove the build output in proto/build
+        #[clap(long)]
+        build: bool,
+        /// Remove the packages cached in the buffrs home
+        #[clap(long)]
+        cache: bool,
+        /// Remove all of the above
+        #[clap(long, conflicts_with_all = ["deps", "build", "cache"])]
+        all: bool,
+
+        /// Only print what would be removed
+        #[clap(long)]
+        dry_run: bool,
+    },
+
     /// Lists all protobuf files managed by Buffrs to stdout
     #[clap(alias = "ls")]
-    List,
+    List {
+        /// Only list the files of these packages (comma separated)
+        #[clap(long, value_delimiter = ',')]
+        only: Vec<PackageName>,
+        /// Leave out the files of these packages (comma separated)
+        #[clap(long, value_delimiter = ',')]
+        exclude: Vec<PackageName>,
+        /// List the installed dependencies and whether they match the lockfile instead
+        #[clap(long)]
+        installed: bool,
+    },
+
+    /// Generates markdown documentation for the package and its dependencies
+    Doc,
+
+    /// Generates files integrating the package and its dependencies into other build systems
+    Generate {
+        /// Kind of files to generate (bazel, maven, npm, kubernetes)
+        #[clap(long)]
+        emit: Emit,
+        /// Maven group of the package and its dependencies
+        #[clap(long, default_value = "buffrs")]
+        group: String,
+        /// npm scope of the package and its dependencies, without the leading @
+        #[clap(long)]
+        scope: Option<String>,
+        /// Directory of the TypeScript bindings to package for npm
+        #[clap(long)]
+        bindings: Option<PathBuf>,
+        /// Name of the Kubernetes resource, defaults to <package>-descriptors
+        #[clap(long)]
+        name: Option<String>,
+        /// Namespace of the Kubernetes resource
+        #[clap(long)]
+        namespace: Option<String>,
+        /// Emit a Kubernetes Secret instead of a ConfigMap
+        #[clap(long)]
+        secret: bool,
+        /// Fail if the generated files on disk are stale instead of writing them
+        #[clap(long)]
+        check: bool,
+    },
+
+    /// Re-runs lint and regenerates the installed protos whenever the package changes
+    Watch {
+        /// Also regenerate the documentation on every change
+        #[clap(long)]
+        doc: bool,
+        /// Milliseconds the files have to stay unchanged before running
+        #[clap(long, default_value = "300")]
+        debounce: u64,
+    },
+
+    /// Summarizes the size of the project, its dependencies and the cache
+    Stats {
+        /// Also query the registries for the publish dates of the locked packages
+        #[clap(long)]
+        published: bool,
+    },
+
+    /// Prints the metadata and contents of a published or local package
+    Info {
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Repository the package is published to
+        #[clap(long)]
+        repository: Option<String>,
+        /// Package to inspect (Format <package>[@<version>]) or path to a local .tgz archive
+        package: String,
+    },
+
+    /// Extracts the files of a package into a directory
+    Extract {
+        /// Artifactory url (e.g. https://<domain>/artifactory)
+        #[clap(long)]
+        registry: Option<String>,
+        /// Repository the package is published to
+        #[clap(long)]
+        repository: Option<String>,
+        /// Package to extract (Format <package>[@<version>]) or path to a local .tgz archive
+        package: String,
+        /// Directory to extract to, has to be empty or not exist yet
+        directory: PathBuf,
+    },
+
+    /// Prints a software bill of materials for the installed dependencies
+    Sbom {
+        /// Standard of the bill of materials (cyclonedx or spdx)
+        #[clap(long, default_value = "cyclonedx")]
+        standard: SbomFormat,
+    },
 
     /// Logs you in for a registry
     Login {
-        /// Artifactory url (e.g. https://<domain>/artifactory)
+        /// Artifactory url (e.g. https://<domain>/artifactory) or registry alias
         #[clap(long)]
         registry: Option<String>,
+        /// Token to store, read from stdin if omitted
+        #[clap(long)]
+        token: Option<String>,
     },
     /// Logs you out from a registry
     Logout {
@@ -158,6 +495,51 @@ enum Command {
         #[command(subcommand)]
         command: LockfileCommand,
     },
+
+    /// Converts between Buf modules and buffrs packages
+    Buf {
+        #[command(subcommand)]
+        command: BufCommand,
+    },
+
+    /// Installs and runs git hooks checking the package before commits and pushes
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommand,
+    },
+
+    /// Synchronizes the package version with a Cargo manifest
+    Cargo {
+        #[command(subcommand)]
+        command: CargoCommand,
+    },
+
+    /// Helpers for continuous integration
+    Ci {
+        #[command(subcommand)]
+        command: CiCommand,
+    },
+
+    /// Copies packages between registries for offline or regional mirrors
+    Mirror {
+        #[command(subcommand)]
+        command: MirrorCommand,
+    },
+
+    /// Reads and writes configuration settings
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Explains an error code in detail (e.g. E0007), lists all codes if omitted
+    Explain {
+        /// The error code printed with the diagnostic
+        code: Option<String>,
+    },
+
+    /// Diagnoses the environment and the current project, suggesting fixes for problems
+    Doctor,
 }
 
 #[derive(Subcommand)]
@@ -168,29 +550,249 @@ enum LockfileCommand {
     PrintFiles,
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> miette::Result<()> {
+#[derive(Subcommand)]
+enum BufCommand {
+    /// Turns a module of the buf.yaml in the current directory into a buffrs package
+    Import {
+        /// Path of the module to import, if buf.yaml declares several
+        #[clap(long)]
+        module: Option<PathBuf>,
+    },
+    /// Exports the package as a Buf module to proto/build/buf
+    Export {
+        /// Name of the module (Format <remote>/<owner>/<module>)
+        module: ModuleRef,
+    },
+    /// Pulls a module from a Buf Schema Registry and adds it as a dependency
+    Add {
+        /// Module to pull (Format <remote>/<owner>/<module>[:<reference>])
+        module: ModuleRef,
+    },
+}
+
+#[derive(Subcommand)]
+enum HooksCommand {
+    /// Installs pre-commit and pre-push hooks running the checks configured in [git-hooks]
+    Install {
+        /// Replace existing hooks that were not installed by buffrs
+        #[clap(long)]
+        force: bool,
+    },
+    /// Runs the checks of a hook, invoked by the installed hooks (pre-commit or pre-push)
+    Run {
+        /// The hook to run the checks of
+        hook: GitHook,
+    },
+}
+
+#[derive(Subcommand)]
+enum CargoCommand {
+    /// Copies the version in the direction configured by sync-version in [cargo]
+    Sync,
+    /// Fails if the versions of the package and the Cargo manifest differ
+    Check,
+}
+
+#[derive(Subcommand)]
+enum CiCommand {
+    /// Prints a Dockerfile pinning buffrs, protoc and the protoc plugins configured in [ci]
+    ImageSpec {
+        /// Write a development container (Dockerfile and devcontainer.json) to .devcontainer
+        #[clap(long)]
+        devcontainer: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MirrorCommand {
+    /// Copies the versions of packages the mirror does not store yet, verifying their digests
+    Sync {
+        /// Registry to copy from, the default registry if omitted
+        #[clap(long)]
+        from: Option<String>,
+        /// Repository to copy from
+        #[clap(long)]
+        repository: String,
+        /// Registry to copy to (alias or url)
+        #[clap(long)]
+        to: String,
+        /// Repository to copy to, the source repository if omitted
+        #[clap(long)]
+        to_repository: Option<String>,
+        /// Only copy these packages (comma separated), all packages if omitted
+        #[clap(long = "package", value_delimiter = ',')]
+        packages: Vec<PackageName>,
+        /// Only copy the versions matching this requirement (e.g. ">=1.0")
+        #[clap(long)]
+        versions: Option<VersionReq>,
+        /// Only print the versions that would be copied
+        #[clap(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Lists the effective settings of all configuration layers
+    List,
+    /// Prints the effective value of a setting
+    Get {
+        /// Dotted key of the setting (e.g. network.retries)
+        key: String,
+    },
+    /// Writes a setting to the project configuration file
+    Set {
+        /// Write to the global configuration file instead
+        #[clap(long)]
+        global: bool,
+        /// Dotted key of the setting (e.g. network.retries)
+        key: String,
+        /// Value of the setting, parsed as TOML if possible (e.g. 3 or ["--buf-yaml"])
+        value: String,
+    },
+}
+
+fn main() {
     human_panic::setup_panic!();
 
-    tracing_subscriber::fmt()
-        .compact()
-        .without_time()
-        .with_level(false)
-        .with_file(false)
-        .with_target(false)
-        .with_line_number(false)
-        .try_init()
-        .unwrap();
+    let result = runtime().and_then(|runtime| runtime.block_on(interruptible()));
+
+    let Err(report) = result else {
+        return;
+    };
+
+    eprintln!("Error: {report:?}");
 
+    // point to the extended explanation, like `rustc --explain`
+    if let Some(code) = report
+        .code()
+        .map(|code| code.to_string())
+        .filter(|code| ErrorCode::lookup(code).is_some())
+    {
+        eprintln!("For more information about this error, run `buffrs explain {code}`");
+    }
+
+    std::process::exit(ExitCode::of(&report) as i32);
+}
+
+/// Builds the async runtime sized by the `[runtime]` section of the configuration
+fn runtime() -> miette::Result<tokio::runtime::Runtime> {
+    let cwd = std::env::current_dir().into_diagnostic()?;
+
+    Config::new(Some(&cwd))?
+        .runtime()
+        .build()
+        .into_diagnostic()
+        .wrap_err("failed to start the async runtime")
+}
+
+/// Runs the command until it completes or is interrupted
+///
+/// Ctrl-C is only intercepted once the command signals that it stops cleanly when cancelled,
+/// all other commands keep being terminated right away.
+async fn interruptible() -> miette::Result<()> {
+    let cancel = Cancel::new();
+    let cancellable = Notify::new();
+
+    // the first ctrl-c asks the command to stop cleanly, the second one abandons it
+    let interrupted = async {
+        cancellable.notified().await;
+
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => cancel.cancel(),
+            // without a handler ctrl-c keeps terminating the process right away
+            Err(_) => std::future::pending().await,
+        }
+
+        tracing::warn!(":: cancelling, press ctrl-c again to abort");
+
+        tokio::signal::ctrl_c().await.ok();
+    };
+
+    tokio::select! {
+        result = run(&cancel, &cancellable) => result,
+        _ = interrupted => Err(Cancelled.into()),
+    }
+}
+
+async fn run(cancel: &Cancel, cancellable: &Notify) -> miette::Result<()> {
     let cwd = std::env::current_dir().into_diagnostic()?;
 
     let config = Config::new(Some(&cwd))?;
 
+    let profile = config.output().profile;
+
+    // only the human profile decorates its output
+    let color = match config.output().color {
+        _ if !profile.is_rich() => Some(false),
+        ColorChoice::Auto => None,
+        ColorChoice::Always => Some(true),
+        ColorChoice::Never => Some(false),
+    };
+
+    if let Some(color) = color {
+        miette::set_hook(Box::new(move |_| {
+            Box::new(
+                miette::MietteHandlerOpts::new()
+                    .color(color)
+                    .unicode(profile.is_rich())
+                    .build(),
+            )
+        }))
+        .into_diagnostic()?;
+    }
+
+    let args = expand_alias(&config, std::env::args().collect())?;
+
+    // subcommands unknown to buffrs are delegated to `buffrs-<command>` executables
+    if let Some(status) = run_external(&args).await? {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+
     // Merge default arguments with user-specified arguments
-    let args = merge_with_default_args(&config);
+    let args = merge_with_default_args(&config, args);
 
     // Parse CLI with merged arguments
-    let cli = Cli::parse_from(args);
+    let matches = Cli::command().get_matches_from(args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    // shell command to run after the subcommand, from the [hooks] section of the config
+    let hook = matches
+        .subcommand_name()
+        .and_then(|name| Some((name.to_owned(), config.hook(name)?.to_owned())));
+
+    // keep stdout free for the document when printing json
+    let writer = match cli.format {
+        OutputFormat::Human => BoxMakeWriter::new(std::io::stdout),
+        OutputFormat::Json => BoxMakeWriter::new(std::io::stderr),
+    };
+
+    // spans are traced for telemetry only, the messages are printed without them
+    let messages = tracing_subscriber::fmt::layer()
+        .with_ansi(color.unwrap_or(true))
+        .with_writer(writer)
+        .event_format(MessageFormat(profile));
+
+    let subscriber = tracing_subscriber::registry().with(messages.with_filter(if cli.quiet {
+        LevelFilter::WARN
+    } else {
+        LevelFilter::INFO
+    }));
+
+    // spans are exported regardless of `--quiet`, and flushed once the command is done
+    #[cfg(feature = "otel")]
+    let telemetry = buffrs::telemetry::Telemetry::from_env()?;
+
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(
+        telemetry
+            .as_ref()
+            .map(|telemetry| telemetry.layer().with_filter(LevelFilter::INFO)),
+    );
+
+    subscriber.try_init().unwrap();
+
+    let format = cli.format;
 
     let manifest = if Manifest::exists().await? {
         Some(Manifest::read().await?)
@@ -210,27 +812,115 @@ async fn main() -> miette::Result<()> {
             .unwrap_or_else(|| name.to_string())
     };
 
-    match cli.command {
-        Command::Init { lib, api, package } => {
+    // JSON result of the command, passed to its hook
+    let mut output = serde_json::Value::Null;
+
+    // commands changing the installed dependencies or the lockfile wait for each other
+    let _lock = if matches!(
+        cli.command,
+        Command::Add { .. }
+            | Command::Remove { .. }
+            | Command::Install { .. }
+            | Command::Buf { .. }
+            | Command::Release { .. }
+            | Command::Vendor
+            | Command::Uninstall
+            | Command::Clean { .. }
+    ) {
+        Some(FileLock::project(&cwd).await?)
+    } else {
+        None
+    };
+
+    // commands taking the cancellation handle remove what they left partially done
+    if matches!(
+        cli.command,
+        Command::Publish { .. }
+            | Command::Release { .. }
+            | Command::Install { .. }
+            | Command::Vendor
+            | Command::Watch { .. }
+            | Command::Mirror {
+                command: MirrorCommand::Sync { .. }
+            }
+    ) {
+        cancellable.notify_one();
+    }
+
+    let result = match cli.command {
+        Command::Init {
+            lib,
+            api,
+            package,
+            from_protos,
+            link,
+        } => {
             let kind = infer_package_type(lib, api);
 
-            command::init(kind, package.to_owned())
-                .await
-                .wrap_err(miette!(
-                    "failed to initialize {}",
-                    package.map(|p| format!("`{p}`")).unwrap_or_default()
-                ))
+            let result = match from_protos {
+                Some(dir) => command::adopt(kind, package.to_owned(), &dir, link).await,
+                None => command::init(kind, package.to_owned()).await,
+            };
+
+            result.wrap_err(miette!(
+                "failed to initialize {}",
+                package.map(|p| format!("`{p}`")).unwrap_or_default()
+            ))
         }
-        Command::New { lib, api, package } => {
+        Command::New {
+            lib,
+            api,
+            package,
+            template,
+            build_script,
+        } => {
             let kind = infer_package_type(lib, api);
 
-            command::new(kind, package.to_owned())
+            command::new(kind, package.to_owned(), template, build_script)
                 .await
                 .wrap_err(miette!("failed to initialize {}", format!("`{package}`")))
         }
-        Command::Login { registry } => {
+        Command::Info {
+            registry,
+            repository,
+            package: spec,
+        } => {
+            // a registry is only needed for published packages, not for local archives
+            let registry = match registry {
+                Some(_) => Some(config.resolve_registry_string(&registry)?),
+                None => config.resolve_registry_string(&None).ok(),
+            };
+
+            command::info(&spec, registry.as_ref(), repository, format, &config)
+                .await
+                .map(|result| output = result)
+                .wrap_err(miette!("failed to inspect `{spec}`"))
+        }
+        Command::Extract {
+            registry,
+            repository,
+            package: spec,
+            directory,
+        } => {
+            // a registry is only needed for published packages, not for local archives
+            let registry = match registry {
+                Some(_) => Some(config.resolve_registry_string(&registry)?),
+                None => config.resolve_registry_string(&None).ok(),
+            };
+
+            command::extract(&spec, &directory, registry.as_ref(), repository, &config)
+                .await
+                .wrap_err(miette!(
+                    "failed to extract `{spec}` to `{}`",
+                    directory.display()
+                ))
+        }
+        Command::Sbom { standard } => command::sbom(standard).await.wrap_err(miette!(
+            "failed to generate a bill of materials for `{package}`"
+        )),
+        Command::Login { registry, token } => {
             let registry = config.resolve_registry_string(&registry)?;
-            command::login(&registry, None)
+            command::login(&registry, token, &config)
                 .await
                 .wrap_err(miette!("failed to login to `{registry}`"))
         }
@@ -246,7 +936,7 @@ async fn main() -> miette::Result<()> {
         } => {
             let registry = config.parse_registry_arg(&registry)?;
             let resolved_registry = config.resolve_registry_uri(&registry)?;
-            command::add(&registry, &resolved_registry, &dependency)
+            command::add(&registry, &resolved_registry, &dependency, &config)
                 .await
                 .wrap_err(miette!(
                     "failed to add `{dependency}` from `{registry}` to `{MANIFEST_FILE}`"
@@ -257,9 +947,10 @@ async fn main() -> miette::Result<()> {
         )),
         Command::Package {
             output_directory,
+            output,
             dry_run,
             set_version,
-        } => command::package(output_directory, dry_run, set_version)
+        } => command::package(output_directory, output.as_deref(), dry_run, set_version)
             .await
             .wrap_err(miette!(
                 "failed to export `{package}` into the buffrs package format"
@@ -268,33 +959,228 @@ async fn main() -> miette::Result<()> {
             registry,
             repository,
             allow_dirty,
+            skip_verify,
+            enforce_semver,
+            sign,
+            signing_key,
+            gpg,
+            gpg_key,
+            from_file,
+            digest,
+            chunked,
             dry_run,
             set_version,
+            all,
         } => {
             let registry = config.resolve_registry_string(&registry)?;
-            command::publish(
+            let signer = match (sign, gpg) {
+                (false, _) => None,
+                (true, false) => Some(Signer::Cosign {
+                    key: signing_key.as_deref(),
+                }),
+                (true, true) => Some(Signer::Gpg {
+                    key: gpg_key.as_deref(),
+                }),
+            };
+
+            if all {
+                command::publish_all(
+                    &registry,
+                    repository.to_owned(),
+                    allow_dirty,
+                    skip_verify,
+                    enforce_semver,
+                    dry_run,
+                    chunked,
+                    signer,
+                    format,
+                    &config,
+                    cancel,
+                )
+                .await
+                .map(|result| output = result)
+                .wrap_err(miette!(
+                    "failed to publish the packages of `{package}` to `{registry}:{repository}`"
+                ))
+            } else if let Some(path) = from_file {
+                command::publish_file(
+                    &registry,
+                    repository.to_owned(),
+                    &path,
+                    digest,
+                    set_version,
+                    dry_run,
+                    chunked,
+                    signer,
+                    format,
+                    &config,
+                    cancel,
+                )
+                .await
+                .map(|result| output = result)
+                .wrap_err(miette!(
+                    "failed to publish `{}` to `{registry}:{repository}`",
+                    path.display()
+                ))
+            } else {
+                command::publish(
+                    &registry,
+                    repository.to_owned(),
+                    allow_dirty,
+                    skip_verify,
+                    enforce_semver,
+                    dry_run,
+                    chunked,
+                    set_version,
+                    signer,
+                    format,
+                    &config,
+                    cancel,
+                )
+                .await
+                .map(|result| output = result)
+                .wrap_err(miette!(
+                    "failed to publish `{package}` to `{registry}:{repository}`",
+                ))
+            }
+        }
+        Command::Release {
+            level,
+            registry,
+            repository,
+            push,
+            forge,
+            sign,
+            signing_key,
+            gpg,
+            gpg_key,
+            dry_run,
+        } => {
+            let registry = config.resolve_registry_string(&registry)?;
+            let signer = match (sign, gpg) {
+                (false, _) => None,
+                (true, false) => Some(Signer::Cosign {
+                    key: signing_key.as_deref(),
+                }),
+                (true, true) => Some(Signer::Gpg {
+                    key: gpg_key.as_deref(),
+                }),
+            };
+
+            command::release(
+                level,
                 &registry,
                 repository.to_owned(),
-                allow_dirty,
+                push,
+                forge,
                 dry_run,
-                set_version,
+                signer,
+                format,
+                &config,
+                cancel,
             )
             .await
+            .map(|result| output = result)
             .wrap_err(miette!(
-                "failed to publish `{package}` to `{registry}:{repository}`",
+                "failed to release `{package}` to `{registry}:{repository}`"
             ))
         }
-        Command::Lint => command::lint()
+        Command::Changed { since } => command::changed(&since, format)
+            .await
+            .map(|result| output = result)
+            .wrap_err(miette!(
+                "failed to detect the packages changed since `{since}`"
+            )),
+        Command::Lint { unused, fail_on } => command::lint(unused, fail_on, format, &config)
             .await
+            .map(|result| output = result)
             .wrap_err(miette!("failed to lint protocol buffers",)),
+        Command::Breaking {
+            registry,
+            repository,
+            against,
+            clients,
+            fail_on,
+        } => {
+            let registry = config.resolve_registry_string(&registry)?;
+            command::breaking(
+                &registry,
+                repository.to_owned(),
+                against,
+                clients,
+                fail_on,
+                &config,
+            )
+            .await
+            .wrap_err(miette!("failed to check `{package}` for breaking changes"))
+        }
+        Command::Version {
+            level,
+            registry,
+            repository,
+            commit,
+            tag,
+            dry_run,
+        } => {
+            // the registry is only needed to compare against the published versions
+            let registry = match repository {
+                Some(_) => Some(config.resolve_registry_string(&registry)?),
+                None => None,
+            };
+
+            let baseline = registry.as_ref().zip(repository);
+
+            command::version(level, baseline, commit, tag, dry_run, &config)
+                .await
+                .map(|_| ())
+                .wrap_err(miette!("failed to increase the version of `{package}`"))
+        }
+        Command::Fix {
+            registry,
+            repository,
+            against,
+        } => {
+            let registry = config.resolve_registry_string(&registry)?;
+            command::fix(&registry, repository.to_owned(), against, &config)
+                .await
+                .wrap_err(miette!("failed to fix `{package}`"))
+        }
+        Command::Diff {
+            registry,
+            repository,
+            from,
+            to,
+        } => {
+            let registry = config.resolve_registry_string(&registry)?;
+            command::diff(
+                &registry,
+                repository.to_owned(),
+                &from,
+                to.as_deref(),
+                &config,
+            )
+            .await
+            .wrap_err(miette!(
+                "failed to compare `{from}` with {}",
+                to.as_ref()
+                    .map(|to| format!("`{to}`"))
+                    .unwrap_or_else(|| format!("the local `{package}`"))
+            ))
+        }
         Command::Install {
             only_dependencies,
             buf_yaml,
+            lsp,
+            dry_run,
+            vendored,
         } => {
             let mut generation_flags = GenerationFlags::empty();
             if buf_yaml {
                 generation_flags |= GenerationFlags::BUF_YAML;
             }
+            if lsp {
+                generation_flags |= GenerationFlags::LSP;
+            }
 
             let install_mode = if only_dependencies {
                 InstallMode::DependenciesOnly
@@ -302,22 +1188,207 @@ async fn main() -> miette::Result<()> {
                 InstallMode::All
             };
 
-            command::install(install_mode, generation_flags, &config)
-                .await
-                .wrap_err(miette!("failed to install dependencies for `{package}`"))
+            command::install(
+                install_mode,
+                generation_flags,
+                dry_run,
+                vendored,
+                format,
+                &config,
+                cancel,
+            )
+            .await
+            .map(|result| output = result)
+            .wrap_err(miette!("failed to install dependencies for `{package}`"))
         }
+        Command::Vendor => command::vendor(&config, cancel)
+            .await
+            .wrap_err(miette!("failed to vendor dependencies for `{package}`")),
         Command::Uninstall => command::uninstall()
             .await
             .wrap_err(miette!("failed to uninstall dependencies for `{package}`")),
-        Command::List => command::list().await.wrap_err(miette!(
-            "failed to list installed protobuf files for `{package}`"
-        )),
+        Command::Clean {
+            deps,
+            build,
+            cache,
+            all,
+            dry_run,
+        } => {
+            let mut targets = CleanTargets::empty();
+
+            if deps {
+                targets |= CleanTargets::DEPS;
+            }
+            if build {
+                targets |= CleanTargets::BUILD;
+            }
+            if cache {
+                targets |= CleanTargets::CACHE;
+            }
+            if all {
+                targets = CleanTargets::all();
+            }
+
+            command::clean(targets, dry_run)
+                .await
+                .wrap_err(miette!("failed to clean `{package}`"))
+        }
+        Command::List {
+            only,
+            exclude,
+            installed: true,
+        } => command::list_installed(&only, &exclude, format)
+            .await
+            .map(|result| output = result)
+            .wrap_err(miette!(
+                "failed to list installed dependencies for `{package}`"
+            )),
+        Command::List {
+            only,
+            exclude,
+            installed: false,
+        } => command::list(&only, &exclude, format)
+            .await
+            .map(|result| output = result)
+            .wrap_err(miette!(
+                "failed to list installed protobuf files for `{package}`"
+            )),
+        Command::Stats { published } => command::stats(published, format, &config)
+            .await
+            .map(|result| output = result)
+            .wrap_err(miette!("failed to collect statistics of `{package}`")),
+        Command::Doc => command::doc()
+            .await
+            .wrap_err(miette!("failed to generate documentation for `{package}`")),
+        Command::Generate {
+            emit,
+            group,
+            scope,
+            bindings,
+            name,
+            namespace,
+            secret,
+            check,
+        } => {
+            let options = GenerateOptions {
+                group,
+                scope,
+                bindings,
+                name,
+                namespace,
+                secret,
+                check,
+            };
+
+            command::generate(emit, &options)
+                .await
+                .wrap_err(miette!("failed to generate {emit} files for `{package}`"))
+        }
+        Command::Watch { doc, debounce } => {
+            command::watch(doc, Duration::from_millis(debounce), &config, cancel)
+                .await
+                .wrap_err(miette!("failed to watch `{package}`"))
+        }
         Command::Lock { command } => match command {
             LockfileCommand::PrintFiles => command::lock::print_files().await.wrap_err(miette!(
                 "failed to print locked file requirements of `{package}`"
             )),
         },
+        Command::Buf { command } => match command {
+            BufCommand::Import { module } => command::buf::import(module.as_deref(), &config)
+                .await
+                .wrap_err(miette!("failed to import the Buf module")),
+            BufCommand::Export { module } => command::buf::export(&module)
+                .await
+                .wrap_err(miette!("failed to export `{package}` as {module}")),
+            BufCommand::Add { module } => command::buf::add(&module, &config)
+                .await
+                .wrap_err(miette!("failed to add {module} to `{package}`")),
+        },
+        Command::Hooks { command } => match command {
+            HooksCommand::Install { force } => command::hooks::install(force)
+                .await
+                .wrap_err(miette!("failed to install the git hooks")),
+            HooksCommand::Run { hook } => command::hooks::run(hook, &config)
+                .await
+                .wrap_err(miette!("the {hook} checks of `{package}` failed")),
+        },
+        Command::Cargo { command } => match command {
+            CargoCommand::Sync => command::cargo::sync(&config)
+                .await
+                .wrap_err(miette!("failed to synchronize the version of `{package}`")),
+            CargoCommand::Check => command::cargo::check(&config)
+                .await
+                .wrap_err(miette!("the version of `{package}` is out of sync")),
+        },
+        Command::Ci { command } => match command {
+            CiCommand::ImageSpec { devcontainer } => command::ci::image_spec(devcontainer, &config)
+                .await
+                .wrap_err(miette!("failed to emit the image spec")),
+        },
+        Command::Mirror { command } => match command {
+            MirrorCommand::Sync {
+                from,
+                repository,
+                to,
+                to_repository,
+                packages,
+                versions,
+                dry_run,
+            } => {
+                let from = config.resolve_registry_string(&from)?;
+                let to = config.resolve_registry_string(&Some(to))?;
+                let to_repository = to_repository.unwrap_or_else(|| repository.clone());
+
+                command::mirror::sync(
+                    Endpoint {
+                        registry: &from,
+                        repository: &repository,
+                    },
+                    Endpoint {
+                        registry: &to,
+                        repository: &to_repository,
+                    },
+                    &packages,
+                    versions.as_ref(),
+                    dry_run,
+                    format,
+                    &config,
+                    cancel,
+                )
+                .await
+                .map(|result| output = result)
+                .wrap_err(miette!(
+                    "failed to mirror `{from}:{repository}` to `{to}:{to_repository}`"
+                ))
+            }
+        },
+        Command::Config { command } => match command {
+            ConfigCommand::List => command::config::list(&config)
+                .await
+                .wrap_err(miette!("failed to list the configuration")),
+            ConfigCommand::Get { key } => command::config::get(&config, &key)
+                .await
+                .wrap_err(miette!("failed to read `{key}` from the configuration")),
+            ConfigCommand::Set { global, key, value } => {
+                command::config::set(&cwd, global, &key, &value)
+                    .await
+                    .wrap_err(miette!("failed to write `{key}` to the configuration"))
+            }
+        },
+        Command::Explain { code } => command::explain(code.as_deref()).await,
+        Command::Doctor => command::doctor(format, &config)
+            .await
+            .map(|result| output = result),
+    };
+
+    result?;
+
+    if let Some((command, hook)) = hook {
+        hook::run(&hook, &command, &output).await?;
     }
+
+    Ok(())
 }
 
 fn infer_package_type(lib: bool, api: bool) -> Option<PackageType> {
@@ -330,16 +1401,101 @@ fn infer_package_type(lib: bool, api: bool) -> Option<PackageType> {
     }
 }
 
+/// Position of the subcommand in the arguments, skipping the global options
+fn subcommand_position(args: &[String]) -> Option<usize> {
+    let mut index = 1;
+
+    while let Some(arg) = args.get(index) {
+        match arg.as_str() {
+            // options taking a value
+            "--format" => index += 2,
+            arg if arg.starts_with('-') => index += 1,
+            _ => return Some(index),
+        }
+    }
+
+    None
+}
+
+/// Whether buffrs itself implements a subcommand, including the aliases of its subcommands
+fn is_builtin(name: &str) -> bool {
+    name == "help" || Cli::command().find_subcommand(name).is_some()
+}
+
+/// Replaces a subcommand by its expansion declared in the `[alias]` section of the config
+///
+/// Aliases can expand into other aliases, but never shadow builtin subcommands.
+fn expand_alias(config: &Config, mut args: Vec<String>) -> miette::Result<Vec<String>> {
+    let mut expanded = Vec::new();
+
+    while let Some(position) = subcommand_position(&args) {
+        let name = args[position].clone();
+
+        if is_builtin(&name) {
+            break;
+        }
+
+        let Some(expansion) = config.alias(&name) else {
+            break;
+        };
+
+        if expanded.contains(&name) {
+            return Err(miette!(
+                "the alias `{name}` expands into itself via {}",
+                expanded.join(" -> ")
+            ));
+        }
+
+        args.splice(position..=position, expansion.iter().cloned());
+        expanded.push(name);
+    }
+
+    Ok(args)
+}
+
+/// Runs `buffrs-<command>` from the `PATH` for subcommands buffrs does not implement itself
+///
+/// The remaining arguments are passed on, returns `None` if there is no such executable.
+async fn run_external(args: &[String]) -> miette::Result<Option<std::process::ExitStatus>> {
+    let Some(position) = subcommand_position(args) else {
+        return Ok(None);
+    };
+
+    let name = &args[position];
+
+    if is_builtin(name) {
+        return Ok(None);
+    }
+
+    let executable = format!("buffrs-{name}{}", std::env::consts::EXE_SUFFIX);
+
+    let Some(path) = std::env::var_os("PATH")
+        .iter()
+        .flat_map(std::env::split_paths)
+        .map(|dir| dir.join(&executable))
+        .find(|path| path.is_file())
+    else {
+        return Ok(None);
+    };
+
+    tokio::process::Command::new(&path)
+        .args(&args[position + 1..])
+        .status()
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to run {}", path.display()))
+        .map(Some)
+}
+
 /// Retrieve and merge default arguments with user-provided arguments
 ///
 /// # Arguments
 /// * `config` - The configuration object
+/// * `args` - The arguments given by the user
 ///
 /// # Returns
 /// A vector of arguments with default arguments merged in
-fn merge_with_default_args(config: &Config) -> Vec<String> {
-    let mut args: Vec<String> = std::env::args().collect();
-
+fn merge_with_default_args(config: &Config, mut args: Vec<String>) -> Vec<String> {
     // Check if --ignore-defaults is in the arguments
     let initial_cli = Cli::try_parse_from(&args);
     if let Ok(cli) = initial_cli {
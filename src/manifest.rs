@@ -12,20 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use miette::{miette, Context, IntoDiagnostic};
+#[cfg(feature = "native")]
+use miette::IntoDiagnostic;
+use miette::{miette, Context};
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Display},
     path::{Path, PathBuf},
     str::FromStr,
 };
+#[cfg(feature = "native")]
+use std::{sync::Mutex, time::SystemTime};
+#[cfg(feature = "native")]
 use tokio::fs;
 
+#[cfg(feature = "native")]
+use crate::errors::{FileExistsError, SerializationError, WriteError};
 use crate::{
-    errors::{DeserializationError, FileExistsError, SerializationError, WriteError},
-    package::{PackageName, PackageType},
+    errors::{DeserializationError, MalformedManifest},
+    package::{PackageName, PackageType, Syntax},
     registry::RegistryUri,
     ManagedFile,
 };
@@ -33,6 +40,14 @@ use crate::{
 /// The name of the manifest file
 pub const MANIFEST_FILE: &str = "Proto.toml";
 
+/// Manifests parsed by this process, keyed by their absolute path
+///
+/// Commands resolving, linting and generating code for a project read the same manifests several
+/// times. Entries are reused as long as the modification time and size of their file are
+/// unchanged, and dropped when this process writes the manifest.
+#[cfg(feature = "native")]
+static PARSED: Mutex<BTreeMap<PathBuf, (SystemTime, u64, Manifest)>> = Mutex::new(BTreeMap::new());
+
 /// The canary edition supported by this version of buffrs
 pub const CANARY_EDITION: &str = concat!("0.", env!("CARGO_PKG_VERSION_MINOR"));
 
@@ -95,10 +110,12 @@ enum RawManifest {
     Canary {
         package: Option<PackageManifest>,
         dependencies: DependencyMap,
+        lint: LintManifest,
     },
     Unknown {
         package: Option<PackageManifest>,
         dependencies: DependencyMap,
+        lint: LintManifest,
     },
 }
 
@@ -117,6 +134,13 @@ impl RawManifest {
         }
     }
 
+    fn lint(&self) -> &LintManifest {
+        match self {
+            Self::Canary { lint, .. } => lint,
+            Self::Unknown { lint, .. } => lint,
+        }
+    }
+
     fn edition(&self) -> Edition {
         match self {
             Self::Canary { .. } => Edition::Canary,
@@ -138,25 +162,37 @@ mod serializer {
                 RawManifest::Canary {
                     ref package,
                     ref dependencies,
+                    ref lint,
                 } => {
-                    let mut s = serializer.serialize_struct("Canary", 3)?;
+                    let mut s = serializer.serialize_struct("Canary", 4)?;
                     s.serialize_field("edition", CANARY_EDITION)?;
                     s.serialize_field("package", package)?;
                     s.serialize_field("dependencies", dependencies)?;
+                    serialize_lint(&mut s, lint)?;
                     s.end()
                 }
                 RawManifest::Unknown {
                     ref package,
                     ref dependencies,
+                    ref lint,
                 } => {
-                    let mut s = serializer.serialize_struct("Unknown", 2)?;
+                    let mut s = serializer.serialize_struct("Unknown", 3)?;
                     s.serialize_field("package", package)?;
                     s.serialize_field("dependencies", dependencies)?;
+                    serialize_lint(&mut s, lint)?;
                     s.end()
                 }
             }
         }
     }
+
+    fn serialize_lint<S: SerializeStruct>(s: &mut S, lint: &LintManifest) -> Result<(), S::Error> {
+        if lint.is_empty() {
+            s.skip_field("lint")
+        } else {
+            s.serialize_field("lint", lint)
+        }
+    }
 }
 
 mod deserializer {
@@ -172,7 +208,7 @@ mod deserializer {
         where
             D: Deserializer<'de>,
         {
-            static FIELDS: &[&str] = &["package", "dependencies"];
+            static FIELDS: &[&str] = &["package", "dependencies", "lint"];
 
             struct ManifestVisitor;
 
@@ -190,6 +226,7 @@ mod deserializer {
                     let mut edition: Option<String> = None;
                     let mut package: Option<PackageManifest> = None;
                     let mut dependencies: Option<HashMap<PackageName, DependencyManifest>> = None;
+                    let mut lint: Option<LintManifest> = None;
 
                     while let Some(key) = map.next_key::<String>()? {
                         match key.as_str() {
@@ -198,16 +235,19 @@ mod deserializer {
                                 dependencies = Some(map.next_value()?);
                             }
                             "edition" => edition = Some(map.next_value()?),
+                            "lint" => lint = Some(map.next_value()?),
                             _ => return Err(de::Error::unknown_field(&key, FIELDS)),
                         }
                     }
 
                     let dependencies = dependencies.unwrap_or_default();
+                    let lint = lint.unwrap_or_default();
 
                     let Some(edition) = edition else {
                         return Ok(RawManifest::Unknown {
                             package,
                             dependencies,
+                            lint,
                         });
                     };
 
@@ -215,6 +255,7 @@ mod deserializer {
                         Edition::Canary | Edition::Canary08 | Edition::Canary07 => Ok(RawManifest::Canary {
                             package,
                             dependencies,
+                            lint,
                         }),
                         Edition::Unknown => Err(de::Error::custom(
                             format!("unsupported manifest edition, supported editions of {} are: {CANARY_EDITION}", env!("CARGO_PKG_VERSION"))
@@ -240,10 +281,12 @@ impl From<Manifest> for RawManifest {
             Edition::Canary | Edition::Canary08 | Edition::Canary07 => RawManifest::Canary {
                 package: manifest.package,
                 dependencies,
+                lint: manifest.lint,
             },
             Edition::Unknown => RawManifest::Unknown {
                 package: manifest.package,
                 dependencies,
+                lint: manifest.lint,
             },
         }
     }
@@ -270,6 +313,8 @@ pub struct Manifest {
     pub package: Option<PackageManifest>,
     /// List of packages the root package depends on
     pub dependencies: Vec<Dependency>,
+    /// Lint configuration of the root package
+    pub lint: LintManifest,
 }
 
 impl Manifest {
@@ -279,10 +324,12 @@ impl Manifest {
             edition: Edition::latest(),
             package,
             dependencies,
+            lint: LintManifest::default(),
         }
     }
 
     /// Checks if the manifest file exists in the filesystem
+    #[cfg(feature = "native")]
     pub async fn exists() -> miette::Result<bool> {
         fs::try_exists(MANIFEST_FILE)
             .await
@@ -291,14 +338,47 @@ impl Manifest {
     }
 
     /// Loads the manifest from the current directory
+    #[cfg(feature = "native")]
     pub async fn read() -> miette::Result<Self> {
         Self::try_read_from(MANIFEST_FILE)
             .await?
             .ok_or(miette!("`{MANIFEST_FILE}` does not exist"))
     }
 
+    /// Parses the contents of a manifest, `path` is only used to report errors
+    pub(crate) fn parse(path: &Path, contents: &str) -> miette::Result<Self> {
+        let raw: RawManifest = toml::from_str(contents)
+            .map_err(|error| MalformedManifest::new(path.to_string_lossy(), contents, error))
+            .wrap_err(DeserializationError(ManagedFile::Manifest))?;
+
+        Ok(Self::from(raw))
+    }
+
     /// Loads the manifest from the given path
+    ///
+    /// Manifests are parsed once per process, unless their file changes in between.
+    #[cfg(feature = "native")]
     pub async fn try_read_from(path: impl AsRef<Path>) -> miette::Result<Option<Self>> {
+        let key = std::path::absolute(path.as_ref()).ok();
+
+        let stamp = fs::metadata(path.as_ref())
+            .await
+            .ok()
+            .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+
+        if let (Some(key), Some((modified, len))) = (&key, stamp) {
+            let parsed = PARSED
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            if let Some((_, _, manifest)) = parsed
+                .get(key)
+                .filter(|(cached, size, _)| *cached == modified && *size == len)
+            {
+                return Ok(Some(manifest.clone()));
+            }
+        }
+
         let contents = match fs::read_to_string(path.as_ref()).await {
             Ok(c) => c,
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -312,27 +392,42 @@ impl Manifest {
             }
         };
 
-        let raw: RawManifest = toml::from_str(&contents)
-            .into_diagnostic()
-            .wrap_err(DeserializationError(ManagedFile::Manifest))?;
+        let manifest = Self::parse(path.as_ref(), &contents)?;
 
-        Ok(Some(raw.into()))
+        if let (Some(key), Some((modified, len))) = (key, stamp) {
+            PARSED
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(key, (modified, len, manifest.clone()));
+        }
+
+        Ok(Some(manifest))
     }
 
     /// Persists the manifest into the current directory
+    #[cfg(feature = "native")]
     pub async fn write(&self) -> miette::Result<()> {
         self.write_at(Path::new(".")).await
     }
 
     /// Persists the manifest into the provided directory, which must exist
+    #[cfg(feature = "native")]
     pub async fn write_at(&self, dir_path: &Path) -> miette::Result<()> {
         // hint: create a canary manifest from the current one
-        let raw = RawManifest::from(Manifest::new(
-            self.package.clone(),
-            self.dependencies.clone(),
-        ));
+        let raw = RawManifest::from(Manifest {
+            lint: self.lint.clone(),
+            ..Manifest::new(self.package.clone(), self.dependencies.clone())
+        });
 
         let manifest_file_path = dir_path.join(MANIFEST_FILE);
+
+        if let Ok(key) = std::path::absolute(&manifest_file_path) {
+            PARSED
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .remove(&key);
+        }
+
         fs::write(
             manifest_file_path,
             toml::to_string(&raw)
@@ -361,6 +456,7 @@ impl From<RawManifest> for Manifest {
             edition: raw.edition(),
             package: raw.package().cloned(),
             dependencies,
+            lint: raw.lint().clone(),
         }
     }
 }
@@ -393,6 +489,71 @@ pub struct PackageManifest {
     pub version: Version,
     /// Description of the api package
     pub description: Option<String>,
+    /// SPDX license expression of the package
+    pub license: Option<String>,
+    /// Upper bound of the size of the package archive in bytes, enforced when publishing
+    #[serde(rename = "max-size", skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+}
+
+/// Lint configuration of a package
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LintManifest {
+    /// Severity overrides keyed by rule name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<String, LintLevel>,
+    /// Convention mapping the package name to its protobuf package
+    #[serde(default, skip_serializing_if = "PackageNaming::is_default")]
+    pub naming: PackageNaming,
+    /// Syntaxes the protos of the package and its dependencies may use, any if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub syntax: Vec<Syntax>,
+}
+
+impl LintManifest {
+    /// Whether this configuration is empty and can be omitted
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.naming.is_default() && self.syntax.is_empty()
+    }
+}
+
+/// Convention for deriving the protobuf package from the name of a buffrs package
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageNaming {
+    /// Dashes become underscores, `physics-units` declares `physics_units.*`
+    #[default]
+    Underscore,
+    /// Dashes separate package components, `physics-units` declares `physics.units.*`
+    Dotted,
+}
+
+impl PackageNaming {
+    /// The protobuf package that all packages declared by `name` have to be nested in
+    pub fn prefix(&self, name: &PackageName) -> String {
+        match self {
+            Self::Underscore => name.replace('-', "_"),
+            Self::Dotted => name.replace('-', "."),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Severity a lint rule is reported with
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// Do not report violations of the rule
+    Allow,
+    /// Report violations as advice
+    Info,
+    /// Report violations as warnings
+    Warning,
+    /// Report violations as errors
+    Error,
 }
 
 /// Represents a single project dependency
@@ -469,6 +630,7 @@ pub enum DependencyManifest {
 }
 
 impl DependencyManifest {
+    #[cfg(feature = "native")]
     pub(crate) fn is_local(&self) -> bool {
         matches!(self, DependencyManifest::Local(_))
     }
@@ -503,3 +665,40 @@ impl From<LocalDependencyManifest> for DependencyManifest {
         Self::Local(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn reparses_changed_manifests() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.join(MANIFEST_FILE);
+
+        std::fs::write(
+            &path,
+            "[package]\ntype = \"lib\"\nname = \"units\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+
+        let manifest = Manifest::try_read_from(&path).await.unwrap().unwrap();
+
+        assert_eq!(
+            Manifest::try_read_from(&path).await.unwrap(),
+            Some(manifest.clone())
+        );
+
+        let mut changed = manifest.clone();
+        changed.package.as_mut().unwrap().version = "1.0.1".parse().unwrap();
+        changed.write_at(dir.path()).await.unwrap();
+
+        let reread = Manifest::try_read_from(&path).await.unwrap().unwrap();
+
+        assert_eq!(reread.package, changed.package);
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Manifest::try_read_from(&path).await.unwrap(), None);
+    }
+}
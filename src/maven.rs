@@ -0,0 +1,157 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Maven projects packaging the protos and descriptor set of a package for JVM consumers
+
+use std::fmt::Write;
+
+use semver::Version;
+
+use crate::{manifest::PackageManifest, package::PackageName};
+
+/// Directory the Maven project is written to
+pub const MAVEN_PATH: &str = "proto/build/maven";
+
+/// Directory of the protos within the Maven project, as expected by the protobuf plugins of
+/// Maven and Gradle
+pub const PROTO_DIR: &str = "src/main/proto";
+
+/// Directory of further resources within the Maven project, e.g. the descriptor set
+pub const RESOURCES_DIR: &str = "src/main/resources";
+
+/// Maven project object model of a buffrs package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pom {
+    /// Group of the package and its dependencies
+    pub group: String,
+    /// The package
+    pub package: PackageManifest,
+    /// Dependencies of the package and their installed versions
+    pub dependencies: Vec<(PackageName, Version)>,
+}
+
+impl Pom {
+    /// Renders the `pom.xml` file
+    ///
+    /// Protos are packaged as resources, so the jar can be consumed without code generation.
+    pub fn render(&self) -> String {
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        let _ = writeln!(
+            out,
+            "<!-- Generated by buffrs {}, do not edit. -->",
+            env!("CARGO_PKG_VERSION")
+        );
+
+        out.push_str(concat!(
+            "<project xmlns=\"http://maven.apache.org/POM/4.0.0\"\n",
+            "         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"\n",
+            "         xsi:schemaLocation=\"http://maven.apache.org/POM/4.0.0 ",
+            "https://maven.apache.org/xsd/maven-4.0.0.xsd\">\n",
+            "  <modelVersion>4.0.0</modelVersion>\n",
+        ));
+
+        let _ = writeln!(out, "  <groupId>{}</groupId>", escape(&self.group));
+        let _ = writeln!(out, "  <artifactId>{}</artifactId>", self.package.name);
+        let _ = writeln!(out, "  <version>{}</version>", self.package.version);
+        out.push_str("  <packaging>jar</packaging>\n");
+
+        if let Some(description) = &self.package.description {
+            let _ = writeln!(out, "  <description>{}</description>", escape(description));
+        }
+
+        if let Some(license) = &self.package.license {
+            out.push_str("  <licenses>\n    <license>\n");
+            let _ = writeln!(out, "      <name>{}</name>", escape(license));
+            out.push_str("    </license>\n  </licenses>\n");
+        }
+
+        out.push_str("  <properties>\n");
+        let _ = writeln!(
+            out,
+            "    <buffrs.version>{}</buffrs.version>",
+            env!("CARGO_PKG_VERSION")
+        );
+        out.push_str("    <project.build.sourceEncoding>UTF-8</project.build.sourceEncoding>\n");
+        out.push_str("  </properties>\n");
+
+        if !self.dependencies.is_empty() {
+            out.push_str("  <dependencies>\n");
+
+            for (name, version) in &self.dependencies {
+                out.push_str("    <dependency>\n");
+                let _ = writeln!(out, "      <groupId>{}</groupId>", escape(&self.group));
+                let _ = writeln!(out, "      <artifactId>{name}</artifactId>");
+                let _ = writeln!(out, "      <version>{version}</version>");
+                out.push_str("    </dependency>\n");
+            }
+
+            out.push_str("  </dependencies>\n");
+        }
+
+        out.push_str("  <build>\n    <resources>\n");
+
+        for dir in [PROTO_DIR, RESOURCES_DIR] {
+            let _ = writeln!(
+                out,
+                "      <resource>\n        <directory>{dir}</directory>\n      </resource>"
+            );
+        }
+
+        out.push_str("    </resources>\n  </build>\n</project>\n");
+        out
+    }
+}
+
+/// Escapes text for XML elements
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::PackageType;
+
+    #[test]
+    fn renders_pom() {
+        let pom = Pom {
+            group: "com.example".to_owned(),
+            package: PackageManifest {
+                kind: PackageType::Api,
+                name: "physics".parse().unwrap(),
+                version: Version::new(1, 2, 0),
+                description: Some("Physics & units".to_owned()),
+                license: None,
+                max_size: None,
+            },
+            dependencies: vec![("units".parse().unwrap(), Version::new(1, 0, 0))],
+        };
+
+        let pom = pom.render();
+
+        assert!(
+            pom.contains("  <groupId>com.example</groupId>\n  <artifactId>physics</artifactId>")
+        );
+        assert!(pom.contains("<version>1.2.0</version>"));
+        assert!(pom.contains("<description>Physics &amp; units</description>"));
+        assert!(pom.contains(concat!("<buffrs.version>", env!("CARGO_PKG_VERSION"))));
+        assert!(
+            pom.contains("      <artifactId>units</artifactId>\n      <version>1.0.0</version>")
+        );
+        assert!(!pom.contains("<licenses>"));
+    }
+}
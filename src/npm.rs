@@ -0,0 +1,215 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! npm packages distributing TypeScript bindings of a package
+
+use std::collections::BTreeSet;
+
+use semver::Version;
+use serde_json::{json, Map, Value};
+
+use crate::{manifest::PackageManifest, package::PackageName};
+
+/// Directory the npm package is written to
+pub const NPM_PATH: &str = "proto/build/npm";
+
+/// Directory of the TypeScript bindings within the npm package
+pub const SOURCE_DIR: &str = "src";
+
+/// Directory of the protos within the npm package
+pub const PROTO_DIR: &str = "proto";
+
+/// Manifest of an npm package wrapping TypeScript bindings of a buffrs package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageJson {
+    /// Scope of the package and its dependencies, without the leading `@`
+    pub scope: Option<String>,
+    /// The package
+    pub package: PackageManifest,
+    /// Dependencies of the package and their installed versions
+    pub dependencies: Vec<(PackageName, Version)>,
+    /// npm packages imported by the bindings, e.g. the protobuf runtime
+    pub runtime: BTreeSet<String>,
+}
+
+impl PackageJson {
+    /// Name of a package on npm
+    pub fn name(&self, package: &PackageName) -> String {
+        match &self.scope {
+            Some(scope) => format!("@{scope}/{package}"),
+            None => package.to_string(),
+        }
+    }
+
+    /// Renders the `package.json` file
+    ///
+    /// The bindings are compiled to ES modules and CommonJS by the `build` script, which runs
+    /// before the package is packed.
+    pub fn render(&self) -> Value {
+        let dependencies: Map<_, _> = self
+            .dependencies
+            .iter()
+            .map(|(name, version)| (self.name(name), json!(version.to_string())))
+            .collect();
+
+        let runtime: Map<_, _> = self
+            .runtime
+            .iter()
+            .map(|name| (name.clone(), json!("*")))
+            .collect();
+
+        let mut manifest = json!({
+            "name": self.name(&self.package.name),
+            "version": self.package.version.to_string(),
+            "exports": {
+                "./*": {
+                    "types": "./dist/types/*.d.ts",
+                    "import": "./dist/esm/*.js",
+                    "require": "./dist/cjs/*.js"
+                }
+            },
+            "files": ["dist", PROTO_DIR],
+            "scripts": {
+                "build": "tsc -p tsconfig.esm.json && tsc -p tsconfig.cjs.json",
+                "prepack": "npm run build"
+            },
+            "dependencies": dependencies,
+            "peerDependencies": runtime,
+            "devDependencies": {
+                "typescript": "^5"
+            },
+            "buffrs": {
+                "version": env!("CARGO_PKG_VERSION")
+            }
+        });
+
+        if let Some(description) = &self.package.description {
+            manifest["description"] = json!(description);
+        }
+
+        if let Some(license) = &self.package.license {
+            manifest["license"] = json!(license);
+        }
+
+        manifest
+    }
+}
+
+/// TypeScript configuration compiling the bindings to ES modules, including type declarations
+pub fn tsconfig_esm() -> Value {
+    tsconfig("ES2020", "dist/esm", true)
+}
+
+/// TypeScript configuration compiling the bindings to CommonJS modules
+pub fn tsconfig_cjs() -> Value {
+    tsconfig("CommonJS", "dist/cjs", false)
+}
+
+fn tsconfig(module: &str, out_dir: &str, declarations: bool) -> Value {
+    let mut options = json!({
+        "target": "ES2020",
+        "module": module,
+        "moduleResolution": "node",
+        "rootDir": SOURCE_DIR,
+        "outDir": out_dir,
+        "esModuleInterop": true,
+        "skipLibCheck": true
+    });
+
+    if declarations {
+        options["declaration"] = json!(true);
+        options["declarationDir"] = json!("dist/types");
+    }
+
+    json!({
+        "compilerOptions": options,
+        "include": [SOURCE_DIR]
+    })
+}
+
+/// npm packages imported by a TypeScript file, ignoring relative imports
+pub fn imports(source: &str) -> BTreeSet<String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("import ") || line.starts_with("export "))
+        .filter_map(|line| {
+            let (_, specifier) = line.rsplit_once(" from ")?;
+            let specifier = specifier.trim_end_matches(';').trim();
+
+            specifier
+                .strip_prefix('"')
+                .and_then(|s| s.strip_suffix('"'))
+                .or_else(|| specifier.strip_prefix('\'')?.strip_suffix('\''))
+        })
+        .filter(|specifier| !specifier.starts_with('.') && !specifier.starts_with('/'))
+        .map(|specifier| {
+            // the package of `@scope/name/path` is `@scope/name`, of `name/path` it is `name`
+            let segments = if specifier.starts_with('@') { 2 } else { 1 };
+
+            specifier
+                .split('/')
+                .take(segments)
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::PackageType;
+
+    #[test]
+    fn renders_package_json() {
+        let manifest = PackageJson {
+            scope: Some("acme".to_owned()),
+            package: PackageManifest {
+                kind: PackageType::Api,
+                name: "physics".parse().unwrap(),
+                version: Version::new(1, 2, 0),
+                description: None,
+                license: Some("MIT".to_owned()),
+                max_size: None,
+            },
+            dependencies: vec![("units".parse().unwrap(), Version::new(1, 0, 0))],
+            runtime: BTreeSet::from(["@bufbuild/protobuf".to_owned()]),
+        }
+        .render();
+
+        assert_eq!(manifest["name"], "@acme/physics");
+        assert_eq!(manifest["version"], "1.2.0");
+        assert_eq!(manifest["license"], "MIT");
+        assert_eq!(manifest["dependencies"]["@acme/units"], "1.0.0");
+        assert_eq!(manifest["peerDependencies"]["@bufbuild/protobuf"], "*");
+        assert!(manifest.get("description").is_none());
+    }
+
+    #[test]
+    fn collects_imports() {
+        let source = r#"
+import { Message, proto3 } from "@bufbuild/protobuf";
+import type { Timestamp } from '@bufbuild/protobuf/wkt';
+import { Unit } from "../units/units_pb.js";
+import Long from "long";
+export { Temperature } from "./temperature_pb.js";
+"#;
+
+        assert_eq!(
+            imports(source),
+            BTreeSet::from(["@bufbuild/protobuf".to_owned(), "long".to_owned()])
+        );
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use serde::Deserialize;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    fmt::{format, FmtContext, FormatEvent, FormatFields},
+    registry::LookupSpan,
+};
+
+/// Decoration of the messages meant for humans, left out by the other profiles
+const DECORATION: &str = ":: ";
+
+/// How log messages are rendered, read from `output.profile`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputProfile {
+    /// Decorated messages, colors and progress bars for terminals
+    #[default]
+    Human,
+    /// Plain messages without colors or progress bars, warnings and errors are prefixed
+    Terse,
+    /// Every line is prefixed with its level, for grepping the logs of CI pipelines
+    Ci,
+}
+
+impl OutputProfile {
+    /// Whether colors, unicode and progress bars may be used
+    pub fn is_rich(self) -> bool {
+        self == Self::Human
+    }
+}
+
+/// Renders log events according to an output profile
+///
+/// Messages are written with the `:: ` decoration of the human profile at their call sites, which
+/// the other profiles strip, so that every message reads the same in every profile apart from
+/// its prefix. Spans are never printed, their names and fields are only meant for telemetry.
+#[derive(Debug, Clone, Copy)]
+pub struct MessageFormat(pub OutputProfile);
+
+impl<S, N> FormatEvent<S, N> for MessageFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut message = Message::default();
+        event.record(&mut message);
+
+        let level = *event.metadata().level();
+
+        for line in message.render().lines() {
+            writer.write_str(&self.render(level, line))?;
+            writer.write_char('\n')?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MessageFormat {
+    /// Renders a single line of a message
+    pub fn render(&self, level: Level, line: &str) -> String {
+        let plain = line.strip_prefix(DECORATION).unwrap_or(line);
+
+        match (self.0, level) {
+            (OutputProfile::Human, _) => line.to_owned(),
+            (OutputProfile::Terse, Level::ERROR) => format!("error: {plain}"),
+            (OutputProfile::Terse, Level::WARN) => format!("warning: {plain}"),
+            (OutputProfile::Terse, _) => plain.to_owned(),
+            (OutputProfile::Ci, level) => {
+                format!("[{}] {plain}", level.as_str().to_ascii_lowercase())
+            }
+        }
+    }
+}
+
+/// The message and the structured fields of an event
+#[derive(Default)]
+struct Message {
+    text: String,
+    fields: Vec<String>,
+}
+
+impl Message {
+    fn render(self) -> String {
+        std::iter::once(self.text)
+            .chain(self.fields)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.text = value.to_owned(),
+            name => self.fields.push(format!("{name}={value}")),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.text = format!("{value:?}"),
+            name => self.fields.push(format!("{name}={value:?}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::Level;
+
+    use super::{MessageFormat, OutputProfile};
+
+    #[test]
+    fn renders_profiles() {
+        let render = |profile, level| MessageFormat(profile).render(level, ":: installed units");
+
+        assert_eq!(
+            render(OutputProfile::Human, Level::INFO),
+            ":: installed units"
+        );
+        assert_eq!(render(OutputProfile::Terse, Level::INFO), "installed units");
+        assert_eq!(
+            render(OutputProfile::Terse, Level::WARN),
+            "warning: installed units"
+        );
+        assert_eq!(
+            render(OutputProfile::Ci, Level::INFO),
+            "[info] installed units"
+        );
+        assert_eq!(
+            render(OutputProfile::Ci, Level::ERROR),
+            "[error] installed units"
+        );
+    }
+}
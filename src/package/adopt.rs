@@ -0,0 +1,147 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use miette::{miette, Context, IntoDiagnostic};
+use tokio::fs;
+use walkdir::WalkDir;
+
+use super::{imports, PackageName, PackageType};
+
+/// Prefix of the imports shipped with protoc, which never require a dependency
+const WELL_KNOWN_IMPORTS: &str = "google/protobuf/";
+
+/// Proto files of an existing directory that is adopted as a buffrs package
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProtoTree {
+    /// Paths of the proto files relative to the scanned directory
+    pub files: Vec<PathBuf>,
+    /// Protobuf packages declared by the files
+    pub packages: BTreeSet<String>,
+    /// Whether any of the files declares a service
+    pub services: bool,
+    /// Imports not provided by the files, keyed by the package expected to provide them
+    pub dependencies: BTreeMap<PackageName, BTreeSet<String>>,
+    /// Imports not provided by the files that no package could be inferred for
+    pub unresolved: BTreeSet<String>,
+}
+
+impl ProtoTree {
+    /// Scans a directory for proto files, skipping vendored ones
+    ///
+    /// Imports are resolved relative to `dir`. Imports of other files are attributed to the
+    /// package named like their top-level directory.
+    pub async fn scan(dir: &Path) -> miette::Result<Self> {
+        let files: Vec<PathBuf> = WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != "vendor")
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.path().extension() == Some("proto".as_ref()))
+            .filter_map(|entry| entry.path().strip_prefix(dir).ok().map(Path::to_path_buf))
+            .collect();
+
+        let provided: BTreeSet<String> = files.iter().map(|file| import_path(file)).collect();
+
+        let mut tree = Self {
+            files,
+            ..Default::default()
+        };
+
+        let mut imports = BTreeSet::new();
+
+        for file in &tree.files {
+            let path = dir.join(file);
+
+            let contents = fs::read_to_string(&path)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", path.display()))?;
+
+            for statement in imports::strip_comments(&contents).split([';', '{', '}']) {
+                let mut words = statement.split_whitespace();
+
+                match words.next() {
+                    Some("package") => tree.packages.extend(words.next().map(str::to_owned)),
+                    Some("service") => tree.services = true,
+                    _ => (),
+                }
+            }
+
+            imports.extend(imports::parse(&contents));
+        }
+
+        for import in imports {
+            if provided.contains(&import) || import.starts_with(WELL_KNOWN_IMPORTS) {
+                continue;
+            }
+
+            match import.split_once('/').and_then(|(dir, _)| name_of(dir)) {
+                Some(package) => {
+                    tree.dependencies.entry(package).or_default().insert(import);
+                }
+                None => {
+                    tree.unresolved.insert(import);
+                }
+            }
+        }
+
+        Ok(tree)
+    }
+
+    /// The package type of the files
+    ///
+    /// Libraries may neither declare services nor have dependencies, everything else is an api.
+    pub fn kind(&self) -> PackageType {
+        if self.services || !self.dependencies.is_empty() {
+            PackageType::Api
+        } else {
+            PackageType::Lib
+        }
+    }
+
+    /// The package name, inferred from the top-level protobuf package shared by all files
+    pub fn name(&self) -> Option<PackageName> {
+        let mut roots = self
+            .packages
+            .iter()
+            .map(|package| package.split('.').next().unwrap_or(package));
+
+        let root = roots.next()?;
+
+        if roots.all(|other| other == root) {
+            name_of(root)
+        } else {
+            None
+        }
+    }
+}
+
+/// Converts an identifier, like a protobuf package or a directory, into a package name
+pub(crate) fn name_of(identifier: &str) -> Option<PackageName> {
+    PackageName::new(identifier.to_ascii_lowercase().replace('_', "-")).ok()
+}
+
+/// The path a file is imported by, which always uses forward slashes
+fn import_path(file: &Path) -> String {
+    file.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
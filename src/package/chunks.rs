@@ -0,0 +1,259 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use miette::{ensure, miette, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::DigestMismatch,
+    lock::{Digest, DigestAlgorithm},
+    package::Package,
+};
+
+/// Chunks are never cut before this many bytes
+const MIN_CHUNK_SIZE: usize = 4 * 1024;
+/// Chunks are always cut after this many bytes
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+/// Cut points are hashes with the top 14 bits unset, resulting in chunks of ~16 KiB on average
+const CUT_MASK: u64 = !(u64::MAX >> 14);
+
+/// Pseudo random values for the gear rolling hash, generated with splitmix64
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+
+    while i < table.len() {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    table
+};
+
+/// Splits data at content defined boundaries
+///
+/// Boundaries only depend on the bytes preceding them, so an edit only changes the chunks
+/// around it while the remaining chunks stay identical between two versions.
+fn split(data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(boundary(rest));
+
+        chunks.push(chunk);
+        rest = tail;
+    }
+
+    chunks
+}
+
+/// Length of the first chunk of the data
+fn boundary(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK_SIZE {
+        return data.len();
+    }
+
+    let mut hash: u64 = 0;
+
+    for (index, byte) in data
+        .iter()
+        .enumerate()
+        .take(MAX_CHUNK_SIZE)
+        .skip(MIN_CHUNK_SIZE)
+    {
+        hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+
+        if hash & CUT_MASK == 0 {
+            return index + 1;
+        }
+    }
+
+    data.len().min(MAX_CHUNK_SIZE)
+}
+
+/// Content defined chunks of the uncompressed archive of a package
+///
+/// Published next to the package, so that a registry only stores and transfers the chunks that
+/// changed between versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkIndex {
+    /// Digest of the package archive the chunks reassemble into
+    pub digest: Digest,
+    /// Digests of the chunks in order
+    pub chunks: Vec<Digest>,
+}
+
+impl ChunkIndex {
+    /// Extension of the index stored next to a published package
+    pub const EXTENSION: &'static str = "chunks.json";
+
+    /// Splits a package into chunks, returning the index together with the chunk contents
+    pub fn split(package: &Package) -> miette::Result<(Self, Vec<(Digest, Bytes)>)> {
+        let tar = Package::decompress(&package.tgz)?;
+
+        let chunks: Vec<(Digest, Bytes)> = split(&tar)
+            .into_iter()
+            .map(|chunk| {
+                (
+                    DigestAlgorithm::SHA256.digest(chunk),
+                    Bytes::copy_from_slice(chunk),
+                )
+            })
+            .collect();
+
+        let index = Self {
+            digest: package.digest(DigestAlgorithm::SHA256),
+            chunks: chunks.iter().map(|(digest, _)| digest.clone()).collect(),
+        };
+
+        Ok((index, chunks))
+    }
+
+    /// Reassembles the package from its chunks, given in the order of the index
+    ///
+    /// Fails if a chunk or the resulting archive does not match its digest.
+    pub fn assemble(&self, chunks: &[Bytes]) -> miette::Result<Package> {
+        ensure!(
+            chunks.len() == self.chunks.len(),
+            "expected {} chunks, got {}",
+            self.chunks.len(),
+            chunks.len()
+        );
+
+        let mut tar = Vec::new();
+
+        for (expected, chunk) in self.chunks.iter().zip(chunks) {
+            ensure!(
+                &expected.algorithm().digest(chunk) == expected,
+                "chunk {expected} is corrupted"
+            );
+
+            tar.extend_from_slice(chunk);
+        }
+
+        let tgz = Package::compress(&tar)?;
+        let digest = self.digest.algorithm().digest(&tgz);
+
+        ensure!(
+            digest == self.digest,
+            DigestMismatch(format!(
+                "the reassembled archive has the digest {digest}, expected {}",
+                self.digest
+            ))
+        );
+
+        Package::try_from(tgz).wrap_err(miette!("the reassembled archive is not a package"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use semver::Version;
+
+    use super::*;
+    use crate::{
+        manifest::{Manifest, PackageManifest},
+        package::PackageType,
+    };
+
+    /// Deterministic pseudo random bytes that do not compress or repeat
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn respects_chunk_size_bounds() {
+        let data = noise(1024 * 1024, 1);
+        let chunks = split(&data);
+
+        assert_eq!(chunks.concat(), data);
+        assert!(chunks.len() > 1);
+
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!((MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk.len()));
+        }
+    }
+
+    #[test]
+    fn reassembles_packages() {
+        let manifest = Manifest::new(
+            Some(PackageManifest {
+                kind: PackageType::Api,
+                name: "physics".parse().unwrap(),
+                version: Version::new(1, 0, 0),
+                description: None,
+                license: None,
+                max_size: None,
+            }),
+            vec![],
+        );
+
+        let files = [
+            ("physics.proto", Bytes::from("package physics;")),
+            ("data.proto", Bytes::from(noise(256 * 1024, 3))),
+        ]
+        .into_iter()
+        .map(|(path, contents)| (PathBuf::from(path), contents))
+        .collect();
+
+        let package = Package::create(manifest, files).unwrap();
+        let (index, chunks) = ChunkIndex::split(&package).unwrap();
+
+        assert!(index.chunks.len() > 1);
+
+        let mut chunks: Vec<Bytes> = chunks.into_iter().map(|(_, chunk)| chunk).collect();
+
+        assert_eq!(index.assemble(&chunks).unwrap().tgz, package.tgz);
+
+        chunks.swap(0, 1);
+
+        assert!(index.assemble(&chunks).is_err());
+    }
+
+    #[test]
+    fn keeps_chunks_stable_across_edits() {
+        let original = noise(512 * 1024, 2);
+
+        let mut edited = original.clone();
+        edited.splice(100_000..100_000, b"message Added {}".iter().copied());
+
+        let before = split(&original);
+        let after = split(&edited);
+
+        let reused = after.iter().filter(|chunk| before.contains(chunk)).count();
+
+        // only the chunks around the edit change
+        assert!(reused + 2 >= after.len(), "{reused} of {}", after.len());
+    }
+}
@@ -12,26 +12,43 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "native")]
+use std::future::Future;
 use std::{
     collections::BTreeMap,
     io::{self, Cursor, Read, Write},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use bytes::{Buf, Bytes};
-use miette::{miette, Context, IntoDiagnostic};
+use miette::{bail, ensure, miette, Context, IntoDiagnostic};
 use semver::Version;
+#[cfg(feature = "native")]
 use tokio::fs;
 
+#[cfg(feature = "native")]
+use crate::{cancel::Cancel, package::PackageStore};
 use crate::{
-    errors::{DeserializationError, SerializationError},
+    errors::{DeserializationError, MalformedManifest, SerializationError},
     lock::{Digest, DigestAlgorithm, LockedPackage},
     manifest::{self, Edition, Manifest, MANIFEST_FILE},
-    package::PackageName,
+    package::{
+        files::{FileList, FileListMismatch, FILE_LIST},
+        license, PackageName, PackageType,
+    },
     registry::RegistryUri,
     ManagedFile,
 };
 
+/// Upper bound of the decompressed size of a package archive in bytes
+///
+/// Guards against archives that are small on the wire but expand to exhaust memory or disk.
+pub const MAX_UNPACKED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Size in bytes from which archives are memory-mapped by [`Package::read`]
+#[cfg(feature = "native")]
+const MMAP_THRESHOLD: u64 = 8 * 1024 * 1024;
+
 /// An in memory representation of a `buffrs` package
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Package {
@@ -42,10 +59,48 @@ pub struct Package {
 }
 
 impl Package {
+    /// Assembles a release from the protos and license texts of a project
+    ///
+    /// `dependencies` are the manifests of the packages the project depends on. This performs the
+    /// checks of [`PackageStore::release`] without touching the file system and returns the
+    /// package together with warnings about its license texts.
+    pub fn release(
+        manifest: Manifest,
+        dependencies: &[Manifest],
+        protos: BTreeMap<PathBuf, Bytes>,
+        licenses: BTreeMap<PathBuf, Bytes>,
+    ) -> miette::Result<(Self, Vec<String>)> {
+        for dependency in dependencies {
+            let Some(ref package) = dependency.package else {
+                bail!("upstream package is invalid, [package] section is missing in manifest");
+            };
+
+            ensure!(
+                package.kind != PackageType::Api,
+                "depending on API packages is not allowed",
+            );
+        }
+
+        let declared = manifest
+            .package
+            .as_ref()
+            .and_then(|package| package.license.as_deref());
+
+        let warnings = license::check(declared, &licenses)?;
+
+        let mut files = protos;
+        files.extend(licenses);
+
+        Ok((Self::create(manifest, files)?, warnings))
+    }
+
     /// Create new [`Package`] from [`Manifest`] and list of files.
     ///
     /// This intentionally uses a [`BTreeMap`] to ensure that the list of files is sorted
-    /// lexicographically. This ensures a reproducible output.
+    /// lexicographically. Together with normalized tar and gzip headers this ensures a
+    /// reproducible output, packaging the same files twice yields a byte-identical archive.
+    /// Only the digests of the file list are computed in parallel, the archive is always written
+    /// in that order.
     pub fn create(mut manifest: Manifest, files: BTreeMap<PathBuf, Bytes>) -> miette::Result<Self> {
         if manifest.edition == Edition::Unknown {
             manifest = Manifest::new(manifest.package, manifest.dependencies);
@@ -69,29 +124,39 @@ impl Package {
             as_str.into_bytes()
         };
 
-        let mut header = tar::Header::new_gnu();
-
-        header.set_size(
-            manifest_bytes
-                .len()
-                .try_into()
-                .into_diagnostic()
-                .wrap_err(miette!(
-                    "serialized manifest was too large to fit in a tarball"
-                ))?,
-        );
+        let size = manifest_bytes
+            .len()
+            .try_into()
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "serialized manifest was too large to fit in a tarball"
+            ))?;
 
-        header.set_mode(0o444);
+        let mut header = Self::header(size);
 
         archive
-            .append_data(&mut header, MANIFEST_FILE, Cursor::new(manifest_bytes))
+            .append_data(&mut header, MANIFEST_FILE, Cursor::new(&manifest_bytes))
             .into_diagnostic()
             .wrap_err(miette!("failed to add manifest to release"))?;
 
+        let list = FileList::new(
+            std::iter::once((Path::new(MANIFEST_FILE), &manifest_bytes[..])).chain(
+                files
+                    .iter()
+                    .map(|(name, contents)| (name.as_path(), &contents[..])),
+            ),
+        )
+        .to_bytes()?;
+
+        let mut header = Self::header(list.len() as u64);
+
+        archive
+            .append_data(&mut header, FILE_LIST, &list[..])
+            .into_diagnostic()
+            .wrap_err(miette!("failed to add file list to release"))?;
+
         for (name, contents) in &files {
-            let mut header = tar::Header::new_gnu();
-            header.set_mode(0o444);
-            header.set_size(contents.len() as u64);
+            let mut header = Self::header(contents.len() as u64);
             archive
                 .append_data(&mut header, name, &contents[..])
                 .into_diagnostic()
@@ -103,32 +168,97 @@ impl Package {
             .into_diagnostic()
             .wrap_err(miette!("failed to assemble tar package"))?;
 
-        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let tgz = Self::compress(&tar)?;
+
+        Ok(Self { manifest, tgz })
+    }
+
+    /// Compresses a tar archive into the reproducible gzip encoding of packages
+    pub(crate) fn compress(tar: &[u8]) -> miette::Result<Bytes> {
+        // the gzip header carries a timestamp and the operating system, both are fixed
+        let mut encoder = flate2::GzBuilder::new()
+            .mtime(0)
+            .operating_system(255)
+            .write(Vec::new(), flate2::Compression::default());
 
         encoder
-            .write_all(&tar)
+            .write_all(tar)
             .into_diagnostic()
             .wrap_err(miette!("failed to compress release"))?;
 
-        let tgz: Bytes = encoder
+        let tgz = encoder
             .finish()
             .into_diagnostic()
-            .wrap_err(miette!("failed to finalize package"))?
-            .into();
+            .wrap_err(miette!("failed to finalize package"))?;
 
-        Ok(Self { manifest, tgz })
+        Ok(tgz.into())
     }
 
-    /// Unpack a package to a specific path.
-    pub async fn unpack(&self, path: &Path) -> miette::Result<()> {
+    /// Tar header of a read-only file without timestamp or ownership
+    fn header(size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+
+        header.set_size(size);
+        header.set_mode(0o444);
+        header.set_mtime(0);
+
+        if let Some(gnu) = header.as_gnu_mut() {
+            gnu.uid = [0; 8];
+            gnu.gid = [0; 8];
+            gnu.uname = [0; 32];
+            gnu.gname = [0; 32];
+        }
+
+        header
+    }
+
+    /// Streams the decompressed tar archive of a package
+    ///
+    /// Reading fails once more than [`MAX_UNPACKED_SIZE`] bytes were decompressed, so that memory
+    /// usage stays bounded regardless of what the archive expands to.
+    fn archive(tgz: &Bytes) -> tar::Archive<impl Read> {
+        tar::Archive::new(Bounded::new(
+            flate2::read::GzDecoder::new(tgz.clone().reader()),
+            MAX_UNPACKED_SIZE,
+        ))
+    }
+
+    /// Decompresses a package archive, refusing archives that exceed [`MAX_UNPACKED_SIZE`]
+    pub(crate) fn decompress(tgz: &Bytes) -> miette::Result<Vec<u8>> {
+        Self::decompress_with_limit(tgz, MAX_UNPACKED_SIZE)
+    }
+
+    /// Decompresses a package archive, refusing archives that exceed `limit` bytes
+    fn decompress_with_limit(tgz: &Bytes, limit: u64) -> miette::Result<Vec<u8>> {
         let mut tar = Vec::new();
-        let mut gz = flate2::read::GzDecoder::new(self.tgz.clone().reader());
 
-        gz.read_to_end(&mut tar)
-            .into_diagnostic()
-            .wrap_err(miette!("failed to decompress package {}", self.name()))?;
+        Bounded::new(flate2::read::GzDecoder::new(tgz.clone().reader()), limit)
+            .read_to_end(&mut tar)
+            .into_diagnostic()?;
+
+        Ok(tar)
+    }
+
+    /// Unpack a package to a specific path.
+    ///
+    /// The archive is streamed from the decompressor into the extraction directory, so that only
+    /// a single buffer is held in memory regardless of the package size. Entries escaping the
+    /// extraction directory, be it through `..` components, absolute paths or links pointing
+    /// outside of it, are rejected before they are written. So are packages whose files do not
+    /// match the digests of their embedded file list, in which case everything extracted so far
+    /// is removed again.
+    #[cfg(feature = "native")]
+    pub async fn unpack(&self, path: &Path) -> miette::Result<()> {
+        self.unpack_cancellable(path, &Cancel::new()).await
+    }
 
-        let mut tar = tar::Archive::new(Bytes::from(tar).reader());
+    /// Unpack a package to a specific path unless the operation is cancelled
+    ///
+    /// Cancellation is checked before every entry of the archive, a cancelled extraction removes
+    /// everything extracted so far like a failed one.
+    #[cfg(feature = "native")]
+    pub async fn unpack_cancellable(&self, path: &Path, cancel: &Cancel) -> miette::Result<()> {
+        cancel.check()?;
 
         fs::remove_dir_all(path).await.ok();
 
@@ -139,28 +269,246 @@ impl Package {
             )
         })?;
 
-        tar.unpack(path).into_diagnostic().wrap_err({
-            miette!(
-                "failed to extract package {} to {}",
+        let tgz = self.tgz.clone();
+        let target = path.to_path_buf();
+        let handle = cancel.clone();
+
+        let extracted = tokio::task::spawn_blocking(move || Self::extract(&tgz, &target, &handle))
+            .await
+            .into_diagnostic()
+            .and_then(|result| result);
+
+        if let Err(error) = extracted {
+            // never leave a partial or unverified package behind
+            fs::remove_dir_all(path).await.ok();
+
+            cancel.check()?;
+
+            return Err(error.wrap_err(miette!(
+                "refusing to extract package {} to {}",
                 self.name(),
                 path.display()
-            )
-        })?;
+            )));
+        }
 
         Ok(())
     }
 
-    /// Load a package from a precompressed archive.
-    pub(crate) fn parse(tgz: Bytes) -> miette::Result<Self> {
-        let mut tar = Vec::new();
+    /// Streams the entries of an archive into a directory, verifying them against the file list
+    ///
+    /// Archives packaged before file lists were introduced carry none and are extracted as is.
+    #[cfg(feature = "native")]
+    fn extract(tgz: &Bytes, path: &Path, cancel: &Cancel) -> miette::Result<()> {
+        let mut archive = Self::archive(tgz);
+
+        let entries = archive
+            .entries()
+            .into_diagnostic()
+            .wrap_err(miette!("corrupted tar package"))?;
+
+        let mut list = None;
+        let mut digests = Vec::new();
+
+        for entry in entries {
+            cancel.check()?;
+
+            let mut entry = entry
+                .into_diagnostic()
+                .wrap_err(miette!("corrupted tar package"))?;
+
+            let relative = Self::check_entry(&entry)?;
+            let kind = entry.header().entry_type();
+
+            // the file list only serves the verification below
+            if relative == Path::new(FILE_LIST) {
+                let mut contents = Vec::new();
+
+                entry
+                    .read_to_end(&mut contents)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to read {FILE_LIST}"))?;
+
+                list = Some(FileList::parse(&contents)?);
+
+                continue;
+            }
+
+            entry
+                .unpack_in(path)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to extract {}", relative.display()))?;
+
+            if kind.is_dir() {
+                continue;
+            }
 
-        let mut gz = flate2::read::GzDecoder::new(tgz.clone().reader());
+            // links carry no contents of their own
+            let digest = if kind.is_file() {
+                let mut hasher = DigestAlgorithm::SHA256.hasher();
 
-        gz.read_to_end(&mut tar)
+                std::fs::File::open(path.join(&relative))
+                    .and_then(|mut file| io::copy(&mut file, &mut hasher))
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to read {}", relative.display()))?;
+
+                hasher.finalize()
+            } else {
+                DigestAlgorithm::SHA256.digest(&[])
+            };
+
+            digests.push((relative, digest));
+        }
+
+        if let Some(list) = list {
+            list.verify(
+                digests
+                    .iter()
+                    .map(|(path, digest)| (path.as_path(), digest.clone())),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the files unpacked into a directory are still exactly the files of this package
+    ///
+    /// Fails if the directory can not be read, and reports files that were modified, added or
+    /// removed since unpacking as a mismatch.
+    ///
+    /// The verification starts right away on the blocking thread pool rather than when the
+    /// returned future is first polled, so that several packages are verified in parallel when
+    /// all verifications are started before awaiting any of them.
+    #[cfg(feature = "native")]
+    pub fn verify_unpacked(
+        &self,
+        path: &Path,
+    ) -> impl Future<Output = miette::Result<Result<(), FileListMismatch>>> {
+        let tgz = self.tgz.clone();
+        let path = path.to_owned();
+
+        let task = tokio::task::spawn_blocking(move || Self::check_unpacked(&tgz, &path));
+
+        async move { task.await.into_diagnostic()? }
+    }
+
+    #[cfg(feature = "native")]
+    fn check_unpacked(tgz: &Bytes, path: &Path) -> miette::Result<Result<(), FileListMismatch>> {
+        let mut archive = Self::archive(tgz);
+        let mut packaged = Vec::new();
+
+        for entry in archive
+            .entries()
             .into_diagnostic()
-            .wrap_err(miette!("failed to decompress package"))?;
+            .wrap_err(miette!("corrupted tar package"))?
+        {
+            let mut entry = entry
+                .into_diagnostic()
+                .wrap_err(miette!("corrupted tar package"))?;
+
+            let entry_path = entry.path().into_diagnostic()?.into_owned();
+
+            if entry.header().entry_type().is_dir() || entry_path == Path::new(FILE_LIST) {
+                continue;
+            }
+
+            let mut hasher = DigestAlgorithm::SHA256.hasher();
+
+            io::copy(&mut entry, &mut hasher)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", entry_path.display()))?;
+
+            packaged.push((entry_path, hasher.finalize()));
+        }
+
+        let list = FileList::from_digests(
+            packaged
+                .iter()
+                .map(|(path, digest)| (path.as_path(), digest.clone())),
+        );
+
+        let mut unpacked = Vec::new();
+
+        for entry in walkdir::WalkDir::new(path) {
+            let entry = entry
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", path.display()))?;
+
+            if !entry.file_type().is_file() || entry.file_name() == PackageStore::DIGEST_FILE {
+                continue;
+            }
+
+            let mut hasher = DigestAlgorithm::SHA256.hasher();
+
+            std::fs::File::open(entry.path())
+                .and_then(|mut file| io::copy(&mut file, &mut hasher))
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", entry.path().display()))?;
+
+            let relative = entry
+                .path()
+                .strip_prefix(path)
+                .into_diagnostic()?
+                .to_owned();
+
+            unpacked.push((relative, hasher.finalize()));
+        }
+
+        Ok(list.verify(
+            unpacked
+                .iter()
+                .map(|(path, digest)| (path.as_path(), digest.clone())),
+        ))
+    }
+
+    /// Validates that an entry of an archive stays within the extraction directory
+    ///
+    /// Returns the normalized path of the entry within the archive.
+    fn check_entry<R: Read>(entry: &tar::Entry<'_, R>) -> miette::Result<PathBuf> {
+        let path = entry.path().into_diagnostic()?.into_owned();
 
-        let mut tar = tar::Archive::new(Bytes::from(tar).reader());
+        let Some(normalized) = contain(Path::new(""), &path) else {
+            return Err(miette!(
+                "entry {} escapes the extraction directory",
+                path.display()
+            ));
+        };
+
+        match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Directory => (),
+            kind @ (tar::EntryType::Symlink | tar::EntryType::Link) => {
+                let target = entry
+                    .link_name()
+                    .into_diagnostic()?
+                    .ok_or(miette!("link {} has no target", path.display()))?;
+
+                // symlinks are relative to their parent, hard links to the archive root
+                let base = match kind {
+                    tar::EntryType::Symlink => normalized.parent().unwrap_or(Path::new("")),
+                    _ => Path::new(""),
+                };
+
+                if contain(base, &target).is_none() {
+                    return Err(miette!(
+                        "link {} points outside of the extraction directory: {}",
+                        path.display(),
+                        target.display()
+                    ));
+                }
+            }
+            kind => {
+                return Err(miette!(
+                    "entry {} has an unsupported type: {kind:?}",
+                    path.display()
+                ))
+            }
+        }
+
+        Ok(normalized)
+    }
+
+    /// Load a package from a precompressed archive.
+    pub(crate) fn parse(tgz: Bytes) -> miette::Result<Self> {
+        let mut tar = Self::archive(&tgz);
 
         let manifest = tar
             .entries()
@@ -188,13 +536,51 @@ impl Package {
 
         let manifest = String::from_utf8(manifest)
             .into_diagnostic()
-            .wrap_err(miette!("manifest has invalid character encoding"))?
-            .parse()
-            .into_diagnostic()?;
+            .wrap_err(miette!("manifest has invalid character encoding"))?;
+
+        let manifest = manifest
+            .parse::<Manifest>()
+            .map_err(|error| MalformedManifest::new(MANIFEST_FILE, &manifest, error))
+            .wrap_err(DeserializationError(ManagedFile::Manifest))?;
 
         Ok(Self { manifest, tgz })
     }
 
+    /// Iterates over the files of the archive without unpacking it to disk
+    ///
+    /// The archive is decompressed once, every file is then read lazily as the iterator advances
+    /// and its contents share the memory of the decompressed archive instead of being copied.
+    /// Directories and the embedded file list are skipped.
+    pub fn entries(&self) -> miette::Result<Entries> {
+        let tar = Self::decompress(&self.tgz)
+            .wrap_err(miette!("failed to decompress package {}", self.name()))?;
+
+        Ok(Entries {
+            tar: Bytes::from(tar),
+            position: 0,
+        })
+    }
+
+    /// List the files of the archive together with their size in bytes
+    pub fn files(&self) -> miette::Result<Vec<(PathBuf, u64)>> {
+        let mut tar = Self::archive(&self.tgz);
+
+        tar.entries()
+            .into_diagnostic()
+            .wrap_err(miette!("corrupted tar package"))?
+            .map(|entry| {
+                let entry = entry.into_diagnostic()?;
+                let path = entry.path().into_diagnostic()?.into_owned();
+
+                Ok((path, entry.size()))
+            })
+            .collect::<miette::Result<_>>()
+            .wrap_err(miette!(
+                "failed to read the contents of package {}",
+                self.name()
+            ))
+    }
+
     /// The name of this package
     #[inline]
     pub fn name(&self) -> &PackageName {
@@ -237,6 +623,78 @@ impl Package {
     }
 }
 
+#[cfg(feature = "native")]
+impl Package {
+    /// Reads a package archive from the file system
+    ///
+    /// Large archives are memory-mapped rather than read into memory, so their pages are loaded
+    /// on demand and can be evicted again instead of adding to the resident memory of buffrs.
+    /// The archive must not be modified while the package is alive.
+    pub async fn read(path: &Path) -> miette::Result<Self> {
+        Self::read_with(path, ()).await
+    }
+
+    /// Reads a package archive from a temporary file like [`Package::read`]
+    ///
+    /// The file is deleted once the archive is no longer referenced, which for memory-mapped
+    /// archives is only after they were unmapped.
+    pub(crate) async fn read_temporary(path: tempfile::TempPath) -> miette::Result<Self> {
+        let file = path.to_path_buf();
+
+        Self::read_with(&file, path).await
+    }
+
+    /// Reads a package archive, keeping `backing` alive as long as the archive is mapped
+    async fn read_with<T: Send + 'static>(path: &Path, backing: T) -> miette::Result<Self> {
+        /// A mapped archive along with what has to outlive the mapping
+        struct Mapped<T> {
+            mmap: memmap2::Mmap,
+            _backing: T,
+        }
+
+        impl<T> AsRef<[u8]> for Mapped<T> {
+            fn as_ref(&self) -> &[u8] {
+                &self.mmap
+            }
+        }
+
+        let file = fs::File::open(path)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to open {}", path.display()))?;
+
+        let size = file
+            .metadata()
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read the metadata of {}", path.display()))?
+            .len();
+
+        let tgz = if size >= MMAP_THRESHOLD {
+            let file = file.into_std().await;
+
+            // SAFETY: archives are read-only inputs, modifying one while it is mapped is documented
+            // to be unsupported
+            let mmap = unsafe { memmap2::Mmap::map(&file) }
+                .into_diagnostic()
+                .wrap_err(miette!("failed to map {}", path.display()))?;
+
+            Bytes::from_owner(Mapped {
+                mmap,
+                _backing: backing,
+            })
+        } else {
+            fs::read(path)
+                .await
+                .map(Bytes::from)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", path.display()))?
+        };
+
+        Self::parse(tgz).wrap_err(miette!("{} is not a buffrs package", path.display()))
+    }
+}
+
 impl TryFrom<Bytes> for Package {
     type Error = miette::Report;
 
@@ -244,3 +702,495 @@ impl TryFrom<Bytes> for Package {
         Package::parse(tgz)
     }
 }
+
+/// A file of a package archive, see [`Package::entries`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path of the file within the package
+    pub path: PathBuf,
+    /// Contents of the file
+    pub contents: Bytes,
+}
+
+impl ArchiveEntry {
+    /// Size of the file in bytes
+    pub fn size(&self) -> u64 {
+        self.contents.len() as u64
+    }
+}
+
+/// Lazy iterator over the files of a package archive, see [`Package::entries`]
+#[derive(Debug, Clone)]
+pub struct Entries {
+    tar: Bytes,
+    position: usize,
+}
+
+impl Entries {
+    /// Reads the entry at the current position and advances past it
+    fn read(&mut self) -> miette::Result<Option<(tar::EntryType, ArchiveEntry)>> {
+        let remaining = &self.tar[self.position..];
+
+        let mut archive = tar::Archive::new(remaining);
+
+        let Some(entry) = archive
+            .entries()
+            .into_diagnostic()
+            .wrap_err(miette!("corrupted tar package"))?
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        let entry = entry
+            .into_diagnostic()
+            .wrap_err(miette!("corrupted tar package"))?;
+
+        let path = Package::check_entry(&entry)?;
+        let kind = entry.header().entry_type();
+
+        let start = usize::try_from(entry.raw_file_position()).into_diagnostic()?;
+        let size = usize::try_from(entry.size()).into_diagnostic()?;
+
+        ensure!(
+            start + size <= remaining.len(),
+            "corrupted tar package: {} is truncated",
+            path.display()
+        );
+
+        let start = self.position + start;
+
+        // contents are padded to whole blocks
+        self.position = start + size.div_ceil(512) * 512;
+
+        let contents = self.tar.slice(start..start + size);
+
+        Ok(Some((kind, ArchiveEntry { path, contents })))
+    }
+}
+
+impl Iterator for Entries {
+    type Item = miette::Result<ArchiveEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.read() {
+                Ok(Some((kind, entry))) => {
+                    if kind.is_dir() || entry.path == Path::new(FILE_LIST) {
+                        continue;
+                    }
+
+                    return Some(Ok(entry));
+                }
+                Ok(None) => return None,
+                Err(error) => {
+                    // a corrupted archive can not be read any further
+                    self.position = self.tar.len();
+
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+/// Reader failing once more than a given number of bytes was read
+///
+/// Unlike [`Read::take`] this does not silently truncate, so a truncated archive can not pass as
+/// a complete one.
+struct Bounded<R> {
+    inner: R,
+    limit: u64,
+    remaining: u64,
+}
+
+impl<R> Bounded<R> {
+    fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            remaining: limit,
+        }
+    }
+}
+
+impl<R: Read> Read for Bounded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+
+        self.remaining = self.remaining.checked_sub(read as u64).ok_or_else(|| {
+            io::Error::other(format!(
+                "the decompressed archive exceeds the limit of {} bytes",
+                self.limit
+            ))
+        })?;
+
+        Ok(read)
+    }
+}
+
+/// Lexically resolves a relative path against a base, if the result stays within the root
+fn contain(base: &Path, path: &Path) -> Option<PathBuf> {
+    let mut resolved = base.to_path_buf();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{manifest::PackageManifest, package::PackageType};
+
+    fn package() -> Package {
+        let manifest = Manifest::new(
+            Some(PackageManifest {
+                kind: PackageType::Lib,
+                name: "units".parse().unwrap(),
+                version: Version::new(1, 0, 0),
+                description: None,
+                license: None,
+                max_size: None,
+            }),
+            vec![],
+        );
+
+        let files = [
+            ("units/mass.proto", "package units;"),
+            ("temperature.proto", "package units;"),
+        ]
+        .into_iter()
+        .map(|(path, contents)| (PathBuf::from(path), Bytes::from(contents)))
+        .collect();
+
+        Package::create(manifest, files).unwrap()
+    }
+
+    #[test]
+    fn assembles_releases_in_memory() {
+        let manifest: Manifest =
+            "[package]\ntype = \"lib\"\nname = \"units\"\nversion = \"1.0.0\"\nlicense = \"MIT\"\n"
+                .parse()
+                .unwrap();
+        let protos =
+            BTreeMap::from([(PathBuf::from("units.proto"), Bytes::from("package units;"))]);
+        let licenses = BTreeMap::from([(PathBuf::from("LICENSE"), Bytes::from("all rights"))]);
+
+        let (package, warnings) =
+            Package::release(manifest.clone(), &[], protos.clone(), licenses).unwrap();
+
+        assert_eq!(package.name().to_string(), "units");
+        assert_eq!(warnings.len(), 1);
+        assert!(Package::release(manifest, &[], protos.clone(), BTreeMap::new()).is_err());
+
+        let api: Manifest = "[package]\ntype = \"api\"\nname = \"service\"\nversion = \"1.0.0\"\n"
+            .parse()
+            .unwrap();
+
+        let lib: Manifest = "[package]\ntype = \"lib\"\nname = \"units\"\nversion = \"1.0.0\"\n"
+            .parse()
+            .unwrap();
+
+        assert!(Package::release(lib.clone(), &[], protos.clone(), BTreeMap::new()).is_ok());
+        assert!(Package::release(lib, &[api], protos, BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn creates_reproducible_archives() {
+        assert_eq!(package().tgz, package().tgz);
+
+        let mut tar = Vec::new();
+
+        flate2::read::GzDecoder::new(package().tgz.reader())
+            .read_to_end(&mut tar)
+            .unwrap();
+
+        let mut archive = tar::Archive::new(&tar[..]);
+
+        let paths: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let header = entry.header();
+
+                let gnu = header.as_gnu().unwrap();
+
+                assert_eq!(header.mtime().unwrap(), 0);
+                assert_eq!((gnu.uid, gnu.gid), ([0; 8], [0; 8]));
+                assert_eq!((gnu.uname, gnu.gname), ([0; 32], [0; 32]));
+
+                entry.path().unwrap().into_owned()
+            })
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from(MANIFEST_FILE),
+                PathBuf::from(FILE_LIST),
+                PathBuf::from("temperature.proto"),
+                PathBuf::from("units/mass.proto"),
+            ]
+        );
+    }
+
+    /// Assembles a tar archive without the path validation of [`tar::Builder`]
+    fn archive(entries: &[(&str, tar::EntryType, Option<&str>)]) -> Vec<u8> {
+        let mut archive = tar::Builder::new(Vec::new());
+
+        for (path, kind, link) in entries {
+            let mut header = tar::Header::new_gnu();
+
+            header.set_entry_type(*kind);
+            header.set_size(0);
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+
+            if let Some(link) = link {
+                header.as_gnu_mut().unwrap().linkname[..link.len()]
+                    .copy_from_slice(link.as_bytes());
+            }
+
+            header.set_cksum();
+            archive.append(&header, io::empty()).unwrap();
+        }
+
+        archive.into_inner().unwrap()
+    }
+
+    fn check_entries(tar: &[u8]) -> miette::Result<()> {
+        for entry in tar::Archive::new(tar).entries().unwrap() {
+            Package::check_entry(&entry.unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_entries_without_unpacking() {
+        let entries: BTreeMap<_, _> = package()
+            .entries()
+            .unwrap()
+            .map(|entry| entry.map(|entry| (entry.path, entry.contents)))
+            .collect::<miette::Result<_>>()
+            .unwrap();
+
+        assert_eq!(
+            entries.keys().collect::<Vec<_>>(),
+            vec![
+                Path::new(MANIFEST_FILE),
+                Path::new("temperature.proto"),
+                Path::new("units/mass.proto")
+            ]
+        );
+        assert_eq!(entries[Path::new("units/mass.proto")], "package units;");
+    }
+
+    #[test]
+    fn reads_entries_with_long_paths() {
+        let path = format!("{}/long.proto", "nested".repeat(30));
+
+        let package = Package::create(
+            package().manifest,
+            BTreeMap::from([(PathBuf::from(&path), Bytes::from("package long;"))]),
+        )
+        .unwrap();
+
+        let entry = package
+            .entries()
+            .unwrap()
+            .map(Result::unwrap)
+            .find(|entry| entry.path == Path::new(&path))
+            .unwrap();
+
+        assert_eq!(entry.size(), 13);
+        assert_eq!(entry.contents, "package long;");
+    }
+
+    #[test]
+    fn resolves_contained_paths() {
+        assert_eq!(
+            contain(Path::new("a"), Path::new("./b/../c")),
+            Some(PathBuf::from("a/c"))
+        );
+        assert_eq!(contain(Path::new("a"), Path::new("../b")), Some("b".into()));
+        assert_eq!(contain(Path::new("a"), Path::new("../../b")), None);
+        assert_eq!(contain(Path::new(""), Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn accepts_regular_entries() {
+        let tar = archive(&[
+            ("units", tar::EntryType::Directory, None),
+            ("units/mass.proto", tar::EntryType::Regular, None),
+            (
+                "units/alias.proto",
+                tar::EntryType::Symlink,
+                Some("mass.proto"),
+            ),
+            (
+                "units/copy.proto",
+                tar::EntryType::Link,
+                Some("units/mass.proto"),
+            ),
+        ]);
+
+        assert!(check_entries(&tar).is_ok());
+    }
+
+    #[test]
+    fn rejects_escaping_entries() {
+        let escaping = [
+            ("../mass.proto", tar::EntryType::Regular, None),
+            ("/etc/mass.proto", tar::EntryType::Regular, None),
+            ("units/link", tar::EntryType::Symlink, Some("../../etc")),
+            ("units/link", tar::EntryType::Symlink, Some("/etc/passwd")),
+            ("units/link", tar::EntryType::Link, Some("../etc/passwd")),
+            ("units/device", tar::EntryType::Char, None),
+        ];
+
+        for entry in escaping {
+            assert!(
+                check_entries(&archive(&[entry])).is_err(),
+                "{entry:?} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_archives() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+
+        encoder.write_all(&[0; 4096]).unwrap();
+
+        let tgz = Bytes::from(encoder.finish().unwrap());
+
+        assert_eq!(
+            Package::decompress_with_limit(&tgz, 4096).unwrap().len(),
+            4096
+        );
+        assert!(Package::decompress_with_limit(&tgz, 4095).is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn preserves_nested_directories() {
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        package().unpack(dir.path()).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.join("units/mass.proto")).unwrap(),
+            "package units;"
+        );
+        assert!(dir.join("temperature.proto").is_file());
+        assert!(!dir.join("mass.proto").exists());
+        assert!(!dir.join(FILE_LIST).exists());
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn reads_small_and_mapped_archives() {
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        // incompressible contents, so the archive exceeds the mapping threshold
+        let mut state = 1u64;
+        let noise: Vec<u8> = std::iter::repeat_with(|| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 56) as u8
+        })
+        .take(MMAP_THRESHOLD as usize + 1)
+        .collect();
+
+        let large = Package::create(
+            package().manifest,
+            BTreeMap::from([(PathBuf::from("noise.proto"), Bytes::from(noise))]),
+        )
+        .unwrap();
+
+        assert!(large.tgz.len() as u64 >= MMAP_THRESHOLD);
+
+        for package in [package(), large] {
+            let path = dir.join(format!("{}.tgz", package.tgz.len()));
+            std::fs::write(&path, &package.tgz).unwrap();
+
+            let read = Package::read(&path).await.unwrap();
+
+            assert_eq!(read.tgz, package.tgz);
+            assert_eq!(read.name(), package.name());
+        }
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn verifies_unpacked_files() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let package = package();
+
+        package.unpack(dir.path()).await.unwrap();
+
+        assert_eq!(package.verify_unpacked(dir.path()).await.unwrap(), Ok(()));
+
+        std::fs::write(dir.join("units/mass.proto"), "package tampered;").unwrap();
+        std::fs::remove_file(dir.join("temperature.proto")).unwrap();
+
+        assert_eq!(
+            package.verify_unpacked(dir.path()).await.unwrap(),
+            Err(FileListMismatch {
+                files: vec![
+                    "units/mass.proto (modified)".into(),
+                    "temperature.proto (missing)".into(),
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "native")]
+    async fn rejects_tampered_files() {
+        let tar = Package::decompress(&package().tgz).unwrap();
+
+        let mut tampered = tar::Builder::new(Vec::new());
+
+        for entry in tar::Archive::new(&tar[..]).entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().into_owned();
+
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+
+            if path == Path::new("units/mass.proto") {
+                contents = b"package tampered;".to_vec();
+            }
+
+            let mut header = Package::header(contents.len() as u64);
+            tampered
+                .append_data(&mut header, path, &contents[..])
+                .unwrap();
+        }
+
+        let tampered = tampered.into_inner().unwrap();
+
+        let package = Package {
+            tgz: Package::compress(&tampered).unwrap(),
+            ..package()
+        };
+
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        assert!(package.unpack(dir.path()).await.is_err());
+        assert!(!dir.join("units/mass.proto").exists());
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+use miette::{miette, Context, Diagnostic, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::lock::{Digest, DigestAlgorithm};
+
+/// File name of the list of files embedded in package archives
+pub const FILE_LIST: &str = "Proto.files";
+
+/// The contents of a package do not match the file list it was packaged with
+#[derive(Error, Diagnostic, Debug, PartialEq)]
+#[error("the contents of the package do not match its file list: {}", .files.join(", "))]
+#[diagnostic(
+    code(E0006),
+    help(
+        "the archive was corrupted or tampered with after it was packaged, try installing it again"
+    )
+)]
+pub struct FileListMismatch {
+    /// The offending files, e.g. `units/mass.proto (modified)`
+    pub files: Vec<String>,
+}
+
+/// Digests of every file of a package, keyed by their path within the archive
+///
+/// Packaged next to the manifest, so that the archive digest and signature cover it as well.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileList {
+    files: BTreeMap<String, Digest>,
+}
+
+impl FileList {
+    /// Records the digests of the given files, which are computed in parallel
+    pub fn new<'a>(files: impl IntoIterator<Item = (&'a Path, &'a [u8])>) -> Self {
+        let files: Vec<_> = files.into_iter().collect();
+
+        let digests = crate::parallel::map(&files, |(_, contents)| {
+            DigestAlgorithm::SHA256.digest(contents)
+        });
+
+        Self::from_digests(files.iter().map(|(path, _)| *path).zip(digests))
+    }
+
+    /// Records the given digests of files, computed with [`DigestAlgorithm::SHA256`]
+    pub fn from_digests<'a>(files: impl IntoIterator<Item = (&'a Path, Digest)>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|(path, digest)| (key(path), digest))
+            .collect();
+
+        Self { files }
+    }
+
+    /// Serializes the list into its packaged representation
+    pub fn to_bytes(&self) -> miette::Result<Vec<u8>> {
+        toml::to_string(self)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to serialize the file list"))
+            .map(String::into_bytes)
+    }
+
+    /// Parses a packaged file list
+    pub fn parse(bytes: &[u8]) -> miette::Result<Self> {
+        let contents = std::str::from_utf8(bytes)
+            .into_diagnostic()
+            .wrap_err(miette!("file list has invalid character encoding"))?;
+
+        toml::from_str(contents)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to parse the file list"))
+    }
+
+    /// Checks that the given files are exactly the listed ones, with matching digests
+    ///
+    /// The digests are compared as they are, so they have to be computed with the algorithms of
+    /// the list, which is [`DigestAlgorithm::SHA256`] for every list created so far.
+    pub fn verify<'a>(
+        &self,
+        files: impl IntoIterator<Item = (&'a Path, Digest)>,
+    ) -> Result<(), FileListMismatch> {
+        let mut missing: BTreeMap<&str, &Digest> = self
+            .files
+            .iter()
+            .map(|(path, digest)| (path.as_str(), digest))
+            .collect();
+
+        let mut mismatches = Vec::new();
+
+        for (path, actual) in files {
+            let key = key(path);
+
+            match missing.remove(key.as_str()) {
+                Some(digest) if actual == *digest => (),
+                Some(_) => mismatches.push(format!("{key} (modified)")),
+                None => mismatches.push(format!("{key} (unlisted)")),
+            }
+        }
+
+        mismatches.extend(missing.into_keys().map(|path| format!("{path} (missing)")));
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(FileListMismatch { files: mismatches })
+        }
+    }
+}
+
+/// Platform independent representation of a path within an archive
+fn key(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list() -> FileList {
+        FileList::new([
+            (Path::new("Proto.toml"), &b"[package]"[..]),
+            (Path::new("units/mass.proto"), &b"package units;"[..]),
+        ])
+    }
+
+    #[test]
+    fn roundtrips() {
+        let bytes = list().to_bytes().unwrap();
+
+        assert_eq!(FileList::parse(&bytes).unwrap(), list());
+    }
+
+    fn digests<'a>(files: &[(&'a str, &[u8])]) -> Vec<(&'a Path, Digest)> {
+        files
+            .iter()
+            .map(|(path, contents)| (Path::new(*path), DigestAlgorithm::SHA256.digest(contents)))
+            .collect()
+    }
+
+    #[test]
+    fn detects_mismatches() {
+        assert_eq!(
+            list().verify(digests(&[
+                ("Proto.toml", b"[package]"),
+                ("units/mass.proto", b"package units;"),
+            ])),
+            Ok(())
+        );
+
+        assert_eq!(
+            list().verify(digests(&[
+                ("units/mass.proto", b"package tampered;"),
+                ("units/extra.proto", b"package units;"),
+            ])),
+            Err(FileListMismatch {
+                files: vec![
+                    "units/mass.proto (modified)".into(),
+                    "units/extra.proto (unlisted)".into(),
+                    "Proto.toml (missing)".into(),
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use bytes::Bytes;
+use walkdir::WalkDir;
+
+/// Kind of an entry of a [`Filesystem`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// A regular file
+    File,
+    /// A directory
+    Directory,
+}
+
+/// File operations of a [`PackageStore`](super::PackageStore)
+///
+/// Operations are synchronous, the store runs them on the blocking thread pool. Besides the
+/// [`NativeFilesystem`], stores can work on a [`MemoryFilesystem`] for hermetic tests or on an
+/// [`OverlayFilesystem`] that keeps all changes in memory, e.g. to simulate an installation or to
+/// run inside of sandboxes forbidding writes.
+pub trait Filesystem: Send + Sync + fmt::Debug {
+    /// Reads the contents of a file
+    fn read(&self, path: &Path) -> io::Result<Bytes>;
+
+    /// Writes a file, replacing its previous contents, the parent directory has to exist
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Creates a directory and all of its missing parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Removes a directory together with all of its contents
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Lists the files and directories directly inside of a directory
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileKind)>>;
+
+    /// Kind of the entry at a path, `None` if there is none
+    fn kind(&self, path: &Path) -> Option<FileKind>;
+
+    /// Copies a file, replacing the target
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write(to, &self.read(from)?)
+    }
+
+    /// Hardlinks a file, filesystems without links fail and callers fall back to copies
+    fn hard_link(&self, _from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Makes a file read-only or writable for its owner, a no-op for filesystems without
+    /// permissions
+    fn set_readonly(&self, _path: &Path, _readonly: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Lists all files below a directory, an empty list if the directory does not exist
+    fn walk(&self, path: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        let mut directories = vec![path.to_path_buf()];
+
+        while let Some(directory) = directories.pop() {
+            for (path, kind) in self.read_dir(&directory).unwrap_or_default() {
+                match kind {
+                    FileKind::File => files.push(path),
+                    FileKind::Directory => directories.push(path),
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Whether paths refer to the file system of the operating system
+    ///
+    /// Packages are extracted with native file operations into native file systems, and through
+    /// [`Filesystem::write`] into all others.
+    fn is_native(&self) -> bool {
+        false
+    }
+}
+
+/// The file system of the operating system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NativeFilesystem;
+
+impl Filesystem for NativeFilesystem {
+    fn read(&self, path: &Path) -> io::Result<Bytes> {
+        std::fs::read(path).map(Bytes::from)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        std::fs::remove_dir_all(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileKind)>> {
+        let mut entries = Vec::new();
+
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+
+            let kind = match entry.file_type()? {
+                kind if kind.is_dir() => FileKind::Directory,
+                kind if kind.is_file() => FileKind::File,
+                // links are neither installed nor packaged
+                _ => continue,
+            };
+
+            entries.push((entry.path(), kind));
+        }
+
+        Ok(entries)
+    }
+
+    fn kind(&self, path: &Path) -> Option<FileKind> {
+        match std::fs::metadata(path).ok()? {
+            metadata if metadata.is_dir() => Some(FileKind::Directory),
+            _ => Some(FileKind::File),
+        }
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::copy(from, to).map(|_| ())
+    }
+
+    fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        std::fs::hard_link(from, to)
+    }
+
+    fn set_readonly(&self, path: &Path, readonly: bool) -> io::Result<()> {
+        let mut permissions = std::fs::metadata(path)?.permissions();
+
+        // only the write permission of the owner is restored, never the one of everybody else
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = permissions.mode();
+            permissions.set_mode(if readonly {
+                mode & !0o222
+            } else {
+                mode | 0o200
+            });
+        }
+
+        #[cfg(not(unix))]
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(readonly);
+
+        std::fs::set_permissions(path, permissions)
+    }
+
+    fn walk(&self, path: &Path) -> Vec<PathBuf> {
+        WalkDir::new(path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .collect()
+    }
+
+    fn is_native(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    File(Bytes),
+    Directory,
+}
+
+/// A file system held entirely in memory
+///
+/// Paths are taken literally, without resolving `.` or `..` components or links.
+#[derive(Debug, Default)]
+pub struct MemoryFilesystem {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+impl MemoryFilesystem {
+    /// Creates an empty file system
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn nodes(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+        self.nodes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Removes the entry at a path and everything below it, returns whether there was one
+    fn remove(&self, path: &Path) -> bool {
+        let mut nodes = self.nodes();
+        let existed = nodes.remove(path).is_some();
+
+        nodes.retain(|node, _| !node.starts_with(path));
+
+        existed
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} does not exist", path.display()),
+    )
+}
+
+impl Filesystem for MemoryFilesystem {
+    fn read(&self, path: &Path) -> io::Result<Bytes> {
+        match self.nodes().get(path) {
+            Some(Node::File(contents)) => Ok(contents.clone()),
+            Some(Node::Directory) => Err(io::Error::other(format!(
+                "{} is a directory",
+                path.display()
+            ))),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut nodes = self.nodes();
+
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if !matches!(nodes.get(parent), Some(Node::Directory)) {
+                return Err(not_found(parent));
+            }
+        }
+
+        if let Some(Node::Directory) = nodes.get(path) {
+            return Err(io::Error::other(format!(
+                "{} is a directory",
+                path.display()
+            )));
+        }
+
+        nodes.insert(
+            path.to_path_buf(),
+            Node::File(Bytes::copy_from_slice(contents)),
+        );
+
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut nodes = self.nodes();
+
+        for ancestor in path.ancestors().filter(|a| !a.as_os_str().is_empty()) {
+            match nodes.get(ancestor) {
+                Some(Node::File(_)) => {
+                    return Err(io::Error::other(format!(
+                        "{} is a file",
+                        ancestor.display()
+                    )))
+                }
+                Some(Node::Directory) => break,
+                None => {
+                    nodes.insert(ancestor.to_path_buf(), Node::Directory);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        match self.remove(path) {
+            true => Ok(()),
+            false => Err(not_found(path)),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileKind)>> {
+        let nodes = self.nodes();
+
+        if !matches!(nodes.get(path), Some(Node::Directory)) {
+            return Err(not_found(path));
+        }
+
+        let entries = nodes
+            .iter()
+            .filter(|(node, _)| node.parent() == Some(path))
+            .map(|(node, kind)| {
+                let kind = match kind {
+                    Node::File(_) => FileKind::File,
+                    Node::Directory => FileKind::Directory,
+                };
+
+                (node.clone(), kind)
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    fn kind(&self, path: &Path) -> Option<FileKind> {
+        match self.nodes().get(path)? {
+            Node::File(_) => Some(FileKind::File),
+            Node::Directory => Some(FileKind::Directory),
+        }
+    }
+}
+
+/// A read-only file system with changes layered on top in memory
+///
+/// Reads see the changes made through the overlay, while the underlying file system is never
+/// written to.
+#[derive(Debug)]
+pub struct OverlayFilesystem {
+    base: Arc<dyn Filesystem>,
+    upper: MemoryFilesystem,
+    /// Directories removed through the overlay, hiding their contents in the base
+    removed: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl OverlayFilesystem {
+    /// Layers an empty set of changes over a file system
+    pub fn new(base: Arc<dyn Filesystem>) -> Self {
+        Self {
+            base,
+            upper: MemoryFilesystem::new(),
+            removed: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Whether an entry of the base is hidden by a removal
+    fn hidden(&self, path: &Path) -> bool {
+        let removed = self
+            .removed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        path.ancestors().any(|ancestor| removed.contains(ancestor))
+    }
+
+    /// Files written through the overlay, e.g. to report what an operation would change
+    pub fn written(&self) -> Vec<PathBuf> {
+        self.upper
+            .nodes()
+            .iter()
+            .filter(|(_, node)| matches!(node, Node::File(_)))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+}
+
+impl Filesystem for OverlayFilesystem {
+    fn read(&self, path: &Path) -> io::Result<Bytes> {
+        if self.upper.kind(path).is_some() {
+            return self.upper.read(path);
+        }
+
+        match self.hidden(path) {
+            true => Err(not_found(path)),
+            false => self.base.read(path),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if self.kind(parent) != Some(FileKind::Directory) {
+                return Err(not_found(parent));
+            }
+
+            self.upper.create_dir_all(parent)?;
+        }
+
+        self.upper.write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.upper.create_dir_all(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        if self.kind(path).is_none() {
+            return Err(not_found(path));
+        }
+
+        self.upper.remove(path);
+
+        self.removed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(path.to_path_buf());
+
+        Ok(())
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<(PathBuf, FileKind)>> {
+        let mut entries = BTreeMap::new();
+        let mut found = false;
+
+        if !self.hidden(path) {
+            if let Ok(base) = self.base.read_dir(path) {
+                found = true;
+
+                entries.extend(base.into_iter().filter(|(path, _)| !self.hidden(path)));
+            }
+        }
+
+        if let Ok(upper) = self.upper.read_dir(path) {
+            found = true;
+
+            entries.extend(upper);
+        }
+
+        match found {
+            true => Ok(entries.into_iter().collect()),
+            false => Err(not_found(path)),
+        }
+    }
+
+    fn kind(&self, path: &Path) -> Option<FileKind> {
+        self.upper.kind(path).or_else(|| match self.hidden(path) {
+            true => None,
+            false => self.base.kind(path),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_files_in_memory() {
+        let fs = MemoryFilesystem::new();
+
+        fs.create_dir_all(Path::new("/project/proto")).unwrap();
+        fs.write(Path::new("/project/proto/units.proto"), b"package units;")
+            .unwrap();
+
+        assert_eq!(
+            fs.read(Path::new("/project/proto/units.proto")).unwrap(),
+            "package units;"
+        );
+        assert_eq!(
+            fs.kind(Path::new("/project/proto")),
+            Some(FileKind::Directory)
+        );
+        assert!(fs.write(Path::new("/missing/units.proto"), b"").is_err());
+        assert_eq!(
+            fs.walk(Path::new("/project")),
+            vec![PathBuf::from("/project/proto/units.proto")]
+        );
+
+        fs.remove_dir_all(Path::new("/project/proto")).unwrap();
+
+        assert_eq!(fs.kind(Path::new("/project/proto/units.proto")), None);
+        assert!(fs.walk(Path::new("/project")).is_empty());
+    }
+
+    #[test]
+    fn overlays_changes_without_writing_through() {
+        let base = Arc::new(MemoryFilesystem::new());
+
+        base.create_dir_all(Path::new("/project/vendor/units"))
+            .unwrap();
+        base.write(Path::new("/project/vendor/units/units.proto"), b"v1")
+            .unwrap();
+
+        let overlay = OverlayFilesystem::new(base.clone());
+
+        overlay
+            .remove_dir_all(Path::new("/project/vendor/units"))
+            .unwrap();
+        overlay
+            .create_dir_all(Path::new("/project/vendor/units"))
+            .unwrap();
+        overlay
+            .write(Path::new("/project/vendor/units/mass.proto"), b"v2")
+            .unwrap();
+
+        assert_eq!(
+            overlay.walk(Path::new("/project")),
+            vec![PathBuf::from("/project/vendor/units/mass.proto")]
+        );
+        assert_eq!(
+            overlay.written(),
+            vec![PathBuf::from("/project/vendor/units/mass.proto")]
+        );
+        assert_eq!(
+            base.walk(Path::new("/project")),
+            vec![PathBuf::from("/project/vendor/units/units.proto")]
+        );
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use miette::Diagnostic;
+use thiserror::Error;
+
+use super::PackageName;
+
+/// An import that is not provided by a declared dependency
+#[derive(Error, Diagnostic, Debug, Clone, PartialEq, Eq)]
+#[error("`{}` imports `{import}` which is not provided by a declared dependency", .file.display())]
+#[diagnostic(help("add the package providing `{import}` to the dependencies of the manifest"))]
+pub struct UndeclaredImport {
+    /// File containing the import
+    pub file: PathBuf,
+    /// The imported path
+    pub import: String,
+}
+
+/// Result of checking the imports of a package against its declared dependencies
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportCheck {
+    /// Imports that are not provided by the package itself or a declared dependency
+    pub undeclared: Vec<UndeclaredImport>,
+    /// Declared dependencies that are never imported
+    pub unused: Vec<PackageName>,
+}
+
+/// A chain of imports leading back to the file it started from
+#[derive(Error, Diagnostic, Debug, Clone, PartialEq, Eq)]
+#[error("circular import: {}", .files.join(" -> "))]
+#[diagnostic(help(
+    "protoc can not compile circular imports, move the shared definitions into a separate file"
+))]
+pub struct ImportCycle {
+    /// Imported paths forming the cycle, starting and ending with the same file
+    pub files: Vec<String>,
+}
+
+/// Finds the import cycles of a graph mapping imported paths to their own imports
+///
+/// Each cycle found is reported once, starting from its lexicographically smallest file.
+pub(crate) fn cycles(graph: &BTreeMap<String, Vec<String>>) -> Vec<ImportCycle> {
+    fn visit<'a>(
+        file: &'a str,
+        graph: &'a BTreeMap<String, Vec<String>>,
+        stack: &mut Vec<&'a str>,
+        done: &mut BTreeSet<&'a str>,
+        found: &mut BTreeSet<Vec<String>>,
+    ) {
+        if let Some(start) = stack.iter().position(|f| *f == file) {
+            let cycle = &stack[start..];
+
+            let first = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap_or(0);
+
+            let mut files: Vec<String> = cycle[first..]
+                .iter()
+                .chain(&cycle[..first])
+                .map(|f| f.to_string())
+                .collect();
+
+            files.push(files[0].clone());
+            found.insert(files);
+
+            return;
+        }
+
+        if done.contains(file) {
+            return;
+        }
+
+        stack.push(file);
+
+        for import in graph.get(file).into_iter().flatten() {
+            visit(import, graph, stack, done, found);
+        }
+
+        stack.pop();
+        done.insert(file);
+    }
+
+    let mut found = BTreeSet::new();
+    let mut done = BTreeSet::new();
+
+    for file in graph.keys() {
+        visit(file, graph, &mut Vec::new(), &mut done, &mut found);
+    }
+
+    found
+        .into_iter()
+        .map(|files| ImportCycle { files })
+        .collect()
+}
+
+/// Extracts the imported paths from the contents of a proto file
+pub(crate) fn parse(contents: &str) -> Vec<String> {
+    strip_comments(contents)
+        .split([';', '{', '}'])
+        .filter_map(|statement| {
+            let rest = statement.trim().strip_prefix("import")?.trim_start();
+
+            let rest = rest
+                .strip_prefix("public")
+                .or_else(|| rest.strip_prefix("weak"))
+                .unwrap_or(rest)
+                .trim_start();
+
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+
+            rest[1..].split(quote).next().map(str::to_string)
+        })
+        .collect()
+}
+
+/// Blanks out line and block comments, leaving string literals and byte offsets intact
+pub(crate) fn strip_comments(contents: &str) -> String {
+    let blank = |c: char| match c {
+        '\n' => "\n".to_string(),
+        c => " ".repeat(c.len_utf8()),
+    };
+
+    let mut stripped = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            stripped.push(c);
+
+            if c == '\\' {
+                stripped.extend(chars.next());
+            } else if c == q {
+                quote = None;
+            }
+
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                stripped.push(' ');
+
+                while let Some(c) = chars.next_if(|c| *c != '\n') {
+                    stripped.push_str(&blank(c));
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                stripped.push_str("  ");
+
+                let mut prev = None;
+
+                for c in chars.by_ref() {
+                    stripped.push_str(&blank(c));
+
+                    if prev == Some('*') && c == '/' {
+                        break;
+                    }
+
+                    prev = Some(c);
+                }
+            }
+            ('"' | '\'', _) => {
+                quote = Some(c);
+                stripped.push(c);
+            }
+            _ => stripped.push(c),
+        }
+    }
+
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_imports() {
+        let proto = r#"
+            syntax = "proto3";
+
+            package physics;
+
+            import "units/temperature.proto";
+            import public 'units/mass.proto';
+            import weak "google/protobuf/timestamp.proto";
+
+            message Sensor {}
+
+            import "units/length.proto";
+        "#;
+
+        assert_eq!(
+            parse(proto),
+            vec![
+                "units/temperature.proto",
+                "units/mass.proto",
+                "google/protobuf/timestamp.proto",
+                "units/length.proto",
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_comments() {
+        let proto = r#"
+            // import "commented/out.proto";
+            /* import "block/comment.proto";
+               import "still/commented.proto"; */
+            option go_package = "https://example.com/physics";
+            import "units/mass.proto"; // trailing comment
+        "#;
+
+        assert_eq!(parse(proto), vec!["units/mass.proto"]);
+    }
+
+    #[test]
+    fn finds_import_cycles() {
+        let graph: BTreeMap<String, Vec<String>> = [
+            ("physics/sensor.proto", vec!["units/mass.proto"]),
+            (
+                "units/mass.proto",
+                vec!["units/base.proto", "google/protobuf/any.proto"],
+            ),
+            ("units/base.proto", vec!["physics/sensor.proto"]),
+            ("physics/self.proto", vec!["physics/self.proto"]),
+            ("physics/acyclic.proto", vec!["units/mass.proto"]),
+        ]
+        .into_iter()
+        .map(|(file, imports)| {
+            (
+                file.to_string(),
+                imports.into_iter().map(str::to_string).collect(),
+            )
+        })
+        .collect();
+
+        let files = |cycle: &ImportCycle| cycle.files.clone();
+
+        assert_eq!(
+            cycles(&graph).iter().map(files).collect::<Vec<_>>(),
+            vec![
+                vec![
+                    "physics/self.proto".to_string(),
+                    "physics/self.proto".to_string()
+                ],
+                vec![
+                    "physics/sensor.proto".to_string(),
+                    "units/mass.proto".to_string(),
+                    "units/base.proto".to_string(),
+                    "physics/sensor.proto".to_string(),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn preserves_offsets_when_stripping_comments() {
+        let proto = "message Mass { // grams\n  /* käse */ int32 value = 1;\n}";
+        let stripped = strip_comments(proto);
+
+        assert_eq!(stripped.len(), proto.len());
+        assert_eq!(stripped.find("int32"), proto.find("int32"));
+        assert!(!stripped.contains("grams"));
+    }
+}
@@ -0,0 +1,166 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bytes::Bytes;
+use miette::Diagnostic;
+use thiserror::Error;
+
+/// A package declares a license but does not bundle its text
+#[derive(Error, Diagnostic, Debug, PartialEq)]
+#[error("the manifest declares the license `{license}`, but the package contains no license text")]
+#[diagnostic(help("add a LICENSE file next to the manifest, it is bundled into the package"))]
+pub struct MissingLicense {
+    /// The declared SPDX license expression
+    pub license: String,
+}
+
+/// Whether a file in the package root holds license or notice texts, e.g. `LICENSE-MIT`
+#[cfg(feature = "native")]
+pub(crate) fn is_license_file(name: &str) -> bool {
+    let name = name.to_uppercase();
+
+    ["LICENSE", "LICENCE", "NOTICE"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Phrase identifying the text of well known licenses
+fn marker(identifier: &str) -> Option<&'static str> {
+    let identifier = identifier.trim_end_matches('+');
+
+    let marker = match identifier {
+        "Apache-2.0" => "apache license",
+        "MIT" => "permission is hereby granted, free of charge",
+        "ISC" => "permission to use, copy, modify, and/or distribute",
+        "BSD-2-Clause" | "BSD-3-Clause" => "redistribution and use in source and binary forms",
+        "MPL-2.0" => "mozilla public license",
+        id if id.starts_with("LGPL-") => "gnu lesser general public license",
+        id if id.starts_with("AGPL-") => "gnu affero general public license",
+        id if id.starts_with("GPL-") => "gnu general public license",
+        _ => return None,
+    };
+
+    Some(marker)
+}
+
+/// Validates the declared license of a package against the license texts it bundles
+///
+/// Declaring a license without bundling its text is an error. Bundled texts without a declared
+/// license, and well known licenses whose text is not among the bundled files, are returned as
+/// warnings.
+pub(crate) fn check(
+    license: Option<&str>,
+    texts: &BTreeMap<PathBuf, Bytes>,
+) -> Result<Vec<String>, MissingLicense> {
+    let Some(license) = license else {
+        return Ok(texts
+            .keys()
+            .map(|file| {
+                format!(
+                    "{} is bundled, but the manifest declares no `license`",
+                    file.display()
+                )
+            })
+            .collect());
+    };
+
+    if texts.is_empty() {
+        return Err(MissingLicense {
+            license: license.to_string(),
+        });
+    }
+
+    let texts: Vec<String> = texts
+        .values()
+        .map(|text| String::from_utf8_lossy(text).to_lowercase())
+        .collect();
+
+    let warnings = license
+        .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+        .filter(|identifier| !["", "AND", "OR", "WITH"].contains(identifier))
+        .filter_map(|identifier| Some((identifier, marker(identifier)?)))
+        .filter(|(_, marker)| !texts.iter().any(|text| text.contains(marker)))
+        .map(|(identifier, _)| {
+            format!("none of the bundled license texts appears to contain `{identifier}`")
+        })
+        .collect();
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(files: &[(&str, &str)]) -> BTreeMap<PathBuf, Bytes> {
+        files
+            .iter()
+            .map(|(name, text)| (PathBuf::from(name), Bytes::from(text.to_string())))
+            .collect()
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn detects_license_files() {
+        assert!(is_license_file("LICENSE"));
+        assert!(is_license_file("LICENSE-APACHE"));
+        assert!(is_license_file("license.md"));
+        assert!(is_license_file("NOTICE"));
+        assert!(!is_license_file("README.md"));
+        assert!(!is_license_file("Proto.toml"));
+    }
+
+    #[test]
+    fn requires_license_texts() {
+        assert_eq!(
+            check(Some("MIT"), &texts(&[])),
+            Err(MissingLicense {
+                license: "MIT".into()
+            })
+        );
+
+        assert_eq!(check(None, &texts(&[])), Ok(vec![]));
+        assert_eq!(
+            check(None, &texts(&[("LICENSE", "MIT License")]))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn matches_declared_licenses() {
+        let bundled = texts(&[
+            (
+                "LICENSE-APACHE",
+                "Apache License\nVersion 2.0, January 2004",
+            ),
+            (
+                "LICENSE-MIT",
+                "Permission is hereby granted, free of charge, to any person",
+            ),
+        ]);
+
+        assert_eq!(check(Some("MIT OR Apache-2.0"), &bundled), Ok(vec![]));
+        assert_eq!(check(Some("LicenseRef-Proprietary"), &bundled), Ok(vec![]));
+        assert_eq!(
+            check(Some("(MIT OR GPL-3.0-only)"), &bundled),
+            Ok(vec![
+                "none of the bundled license texts appears to contain `GPL-3.0-only`".into()
+            ])
+        );
+    }
+}
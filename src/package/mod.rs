@@ -12,9 +12,37 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "native")]
+mod adopt;
+mod chunks;
 mod compressed;
+mod files;
+#[cfg(feature = "native")]
+mod filesystem;
+mod imports;
+mod license;
 mod name;
+#[cfg(feature = "native")]
 mod store;
+mod syntax;
 mod r#type;
 
-pub use self::{compressed::Package, name::PackageName, r#type::PackageType, store::PackageStore};
+#[cfg(feature = "native")]
+pub(crate) use self::adopt::name_of;
+pub(crate) use self::imports::strip_comments;
+#[cfg(feature = "native")]
+pub use self::{
+    adopt::ProtoTree,
+    filesystem::{FileKind, Filesystem, MemoryFilesystem, NativeFilesystem, OverlayFilesystem},
+    store::{LinkMode, PackageStore},
+};
+pub use self::{
+    chunks::ChunkIndex,
+    compressed::{ArchiveEntry, Entries, Package, MAX_UNPACKED_SIZE},
+    files::{FileListMismatch, FILE_LIST},
+    imports::{ImportCheck, ImportCycle, UndeclaredImport},
+    license::MissingLicense,
+    name::PackageName,
+    r#type::PackageType,
+    syntax::{Syntax, SyntaxError, UnsupportedSyntax},
+};
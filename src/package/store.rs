@@ -13,24 +13,53 @@
 // limitations under the License.
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashSet},
     env::current_dir,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
-use miette::{bail, ensure, miette, Context, IntoDiagnostic};
-use tokio::fs;
-use walkdir::WalkDir;
+use bytes::Bytes;
+use miette::{miette, Context, IntoDiagnostic};
+use serde::Deserialize;
 
 use crate::{
+    cancel::Cancel,
+    lock::{Digest, DigestAlgorithm},
     manifest::{Manifest, PackageManifest, MANIFEST_FILE},
-    package::{Package, PackageName, PackageType},
+    package::{
+        imports, license, FileKind, Filesystem, ImportCheck, ImportCycle, NativeFilesystem,
+        Package, PackageName, Syntax, UndeclaredImport, UnsupportedSyntax,
+    },
+    Result,
 };
 
+/// How installed packages are created from their unpacked copies in the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkMode {
+    /// Copy the files, which clones them on filesystems supporting copy-on-write
+    #[default]
+    Copy,
+    /// Hardlink the files, falling back to copies across filesystems
+    ///
+    /// Hardlinked files share their contents with the cache, so editing a file under
+    /// `proto/vendor` would change the cached package for every project linking it. Linked files
+    /// are therefore made read-only, and the cache replaces unpacked copies which no longer match
+    /// their package before linking them again.
+    Hardlink,
+}
+
 /// IO abstraction layer over local `buffrs` package store
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// All file operations go through a [`Filesystem`], the file system of the operating system
+/// unless the store is opened [`with a different one`](PackageStore::with_filesystem). Validation,
+/// compilation and breaking change detection hand paths to the protobuf parser and therefore
+/// always work on the native file system.
+#[derive(Debug, Clone)]
 pub struct PackageStore {
     root: PathBuf,
+    fs: Arc<dyn Filesystem>,
 }
 
 impl PackageStore {
@@ -38,13 +67,17 @@ impl PackageStore {
     pub const PROTO_PATH: &'static str = "proto";
     /// Path to the dependency store
     pub const PROTO_VENDOR_PATH: &'static str = "proto/vendor";
+    /// Path to generated build output, e.g. documentation
+    pub const PROTO_BUILD_PATH: &'static str = "proto/build";
+    /// File recording the digest of an installed package within its directory
+    pub const DIGEST_FILE: &'static str = ".buffrs-digest";
 
-    fn new(root: PathBuf) -> Self {
-        Self { root }
+    fn new(root: PathBuf, fs: Arc<dyn Filesystem>) -> Self {
+        Self { root, fs }
     }
 
     /// Open current directory.
-    pub async fn current() -> miette::Result<Self> {
+    pub async fn current() -> Result<Self> {
         Self::open(&current_dir().into_diagnostic()?).await
     }
 
@@ -58,47 +91,92 @@ impl PackageStore {
         self.root.join(Self::PROTO_VENDOR_PATH)
     }
 
+    /// Path to the build output directory.
+    pub fn proto_build_path(&self) -> PathBuf {
+        self.root.join(Self::PROTO_BUILD_PATH)
+    }
+
+    /// The file system the store operates on
+    pub fn filesystem(&self) -> &Arc<dyn Filesystem> {
+        &self.fs
+    }
+
     /// Path to where the package contents are populated.
     fn populated_path(&self, manifest: &PackageManifest) -> PathBuf {
         self.proto_vendor_path().join(manifest.name.to_string())
     }
 
+    /// Runs file operations on the blocking thread pool, like [`tokio::fs`] does
+    async fn blocking<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&dyn Filesystem) -> T + Send + 'static,
+    ) -> miette::Result<T> {
+        let fs = self.fs.clone();
+
+        tokio::task::spawn_blocking(move || operation(&*fs))
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("file operation of the package store failed"))
+    }
+
     /// Creates the expected directory structure for `buffrs`
-    pub async fn open(path: impl AsRef<Path>) -> miette::Result<Self> {
-        let store = PackageStore::new(path.as_ref().to_path_buf());
-        let create = |dir: PathBuf| async move {
-            fs::create_dir_all(&dir)
-                .await
-                .into_diagnostic()
-                .wrap_err(miette!("failed to create {} directory", dir.display()))
-        };
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_filesystem(path, Arc::new(NativeFilesystem)).await
+    }
 
-        create(store.proto_path()).await?;
-        create(store.proto_vendor_path()).await?;
+    /// Creates the expected directory structure for `buffrs` within the given file system
+    pub async fn with_filesystem(path: impl AsRef<Path>, fs: Arc<dyn Filesystem>) -> Result<Self> {
+        let store = PackageStore::new(path.as_ref().to_path_buf(), fs);
+        let dirs = [store.proto_path(), store.proto_vendor_path()];
+
+        store
+            .blocking(move |fs| -> miette::Result<()> {
+                for dir in dirs {
+                    fs.create_dir_all(&dir)
+                        .into_diagnostic()
+                        .wrap_err(miette!("failed to create {} directory", dir.display()))?;
+                }
+
+                Ok(())
+            })
+            .await??;
 
         Ok(store)
     }
 
     /// Clears all packages from the file system
-    pub async fn clear(&self) -> miette::Result<()> {
+    pub async fn clear(&self) -> Result<()> {
         let path = self.proto_vendor_path();
 
-        match fs::remove_dir_all(&path).await {
-            Ok(()) => {}
-            Err(err) if matches!(err.kind(), std::io::ErrorKind::NotFound) => {}
-            Err(_) => return Err(miette!("failed to clear {path:?} directory",)),
-        }
+        self.blocking(move |fs| -> miette::Result<()> {
+            match fs.remove_dir_all(&path) {
+                Ok(()) => {}
+                Err(err) if matches!(err.kind(), std::io::ErrorKind::NotFound) => {}
+                Err(_) => return Err(miette!("failed to clear {path:?} directory",)),
+            }
 
-        fs::create_dir(&path)
-            .await
-            .map_err(|_| miette!("failed to reinitialize {path:?} directory after cleaning"))
+            fs.create_dir_all(&path)
+                .map_err(|_| miette!("failed to reinitialize {path:?} directory after cleaning"))
+        })
+        .await??;
+
+        Ok(())
     }
 
-    /// Unpacks a package into a local directory
-    pub async fn unpack(&self, package: &Package) -> miette::Result<()> {
+    /// Unpacks a package into a local directory, removing it again if cancelled midway
+    pub async fn unpack(&self, package: &Package, cancel: &Cancel) -> Result<()> {
         let pkg_dir = self.locate(package.name());
 
-        package.unpack(&pkg_dir).await?;
+        if self.fs.is_native() {
+            package.unpack_cancellable(&pkg_dir, cancel).await?;
+        } else {
+            let (package, target, cancel) = (package.clone(), pkg_dir.clone(), cancel.clone());
+
+            self.blocking(move |fs| write_entries(fs, &package, &target, &cancel))
+                .await??;
+        }
+
+        self.record(package).await?;
 
         tracing::debug!(
             ":: unpacked {}@{} into {}",
@@ -110,77 +188,541 @@ impl PackageStore {
         Ok(())
     }
 
+    /// Installs a package from an unpacked copy, e.g. in the cache, instead of its archive
+    ///
+    /// The unpacked copy has to be verified against the package beforehand, as it is installed as
+    /// is. Hardlinked files are made read-only, copied ones writable.
+    pub async fn link(&self, package: &Package, source: &Path, mode: LinkMode) -> Result<()> {
+        let pkg_dir = self.locate(package.name());
+        let (source, target) = (source.to_path_buf(), pkg_dir.clone());
+
+        self.blocking(move |fs| -> miette::Result<()> {
+            fs.remove_dir_all(&target).ok();
+
+            for file in fs.walk(&source) {
+                let path = target.join(file.strip_prefix(&source).into_diagnostic()?);
+
+                if let Some(parent) = path.parent() {
+                    fs.create_dir_all(parent)
+                        .into_diagnostic()
+                        .wrap_err(miette!("failed to create {}", parent.display()))?;
+                }
+
+                let linked = mode == LinkMode::Hardlink && fs.hard_link(&file, &path).is_ok();
+
+                if !linked {
+                    fs.copy(&file, &path).into_diagnostic().wrap_err(miette!(
+                        "failed to install {} from {}",
+                        path.display(),
+                        file.display()
+                    ))?;
+                }
+
+                // copies inherit the permissions of sources made read-only by earlier links
+                fs.set_readonly(&path, linked)
+                    .into_diagnostic()
+                    .wrap_err(miette!(
+                        "failed to set the permissions of {}",
+                        path.display()
+                    ))?;
+            }
+
+            Ok(())
+        })
+        .await??;
+
+        self.record(package).await?;
+
+        tracing::debug!(
+            ":: linked {}@{} into {}",
+            package.name(),
+            package.version(),
+            pkg_dir.display()
+        );
+
+        Ok(())
+    }
+
+    /// Whether a package is installed already, as recorded in its [`PackageStore::DIGEST_FILE`]
+    ///
+    /// Only the recorded digest is compared, files modified after the installation go unnoticed.
+    pub async fn is_installed(&self, package: &Package) -> bool {
+        let marker = self.locate(package.name()).join(Self::DIGEST_FILE);
+
+        let Ok(Ok(recorded)) = self.blocking(move |fs| fs.read(&marker)).await else {
+            return false;
+        };
+
+        std::str::from_utf8(&recorded)
+            .ok()
+            .and_then(|recorded| recorded.trim().parse::<Digest>().ok())
+            .is_some_and(|digest| digest == package.digest(DigestAlgorithm::SHA256))
+    }
+
+    /// Records the digest of an installed package, once all of its files were written
+    async fn record(&self, package: &Package) -> miette::Result<()> {
+        let pkg_dir = self.locate(package.name());
+        let digest = package.digest(DigestAlgorithm::SHA256).to_string();
+
+        self.blocking(move |fs| {
+            fs.create_dir_all(&pkg_dir)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to create {}", pkg_dir.display()))?;
+
+            let marker = pkg_dir.join(Self::DIGEST_FILE);
+
+            fs.write(&marker, digest.as_bytes())
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", marker.display()))
+        })
+        .await?
+    }
+
+    /// Uninstalls every package except for the given ones from the local file system
+    pub async fn retain(&self, packages: &HashSet<PackageName>) -> Result<()> {
+        let vendor = self.proto_vendor_path();
+        let packages = packages.clone();
+
+        self.blocking(move |fs| -> miette::Result<()> {
+            let entries = fs
+                .read_dir(&vendor)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", vendor.display()))?;
+
+            for (path, kind) in entries {
+                let retained = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| PackageName::new(name).ok())
+                    .is_some_and(|name| packages.contains(&name));
+
+                if kind != FileKind::Directory || retained {
+                    continue;
+                }
+
+                fs.remove_dir_all(&path)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to remove {}", path.display()))?;
+            }
+
+            Ok(())
+        })
+        .await??;
+
+        Ok(())
+    }
+
     /// Uninstalls a package from the local file system
-    pub async fn uninstall(&self, package: &PackageName) -> miette::Result<()> {
+    pub async fn uninstall(&self, package: &PackageName) -> Result<()> {
         let pkg_dir = self.proto_vendor_path().join(&**package);
+        let package = package.clone();
 
-        fs::remove_dir_all(&pkg_dir)
-            .await
-            .into_diagnostic()
-            .wrap_err(miette!("failed to uninstall package {package}"))
+        self.blocking(move |fs| {
+            fs.remove_dir_all(&pkg_dir)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to uninstall package {package}"))
+        })
+        .await??;
+
+        Ok(())
     }
 
     /// Resolves a package in the local file system
-    pub async fn resolve(&self, package: &PackageName) -> miette::Result<Manifest> {
+    pub async fn resolve(&self, package: &PackageName) -> Result<Manifest> {
         let manifest = self.locate(package).join(MANIFEST_FILE);
 
-        let manifest = Manifest::try_read_from(&manifest).await?.ok_or(miette!(
-            "the package store is corrupted: `{}` is not present",
-            manifest.display()
-        ))?;
+        if self.fs.is_native() {
+            let resolved = Manifest::try_read_from(&manifest).await?.ok_or(miette!(
+                "the package store is corrupted: `{}` is not present",
+                manifest.display()
+            ))?;
+
+            return Ok(resolved);
+        }
+
+        let resolved = self
+            .blocking(move |fs| -> miette::Result<Manifest> {
+                let contents = fs.read(&manifest).into_diagnostic().wrap_err(miette!(
+                    "the package store is corrupted: `{}` is not present",
+                    manifest.display()
+                ))?;
+
+                let contents =
+                    std::str::from_utf8(&contents)
+                        .into_diagnostic()
+                        .wrap_err(miette!(
+                            "failed to read manifest from `{}`",
+                            manifest.display()
+                        ))?;
+
+                Manifest::parse(&manifest, contents)
+            })
+            .await??;
 
-        Ok(manifest)
+        Ok(resolved)
     }
 
     /// Validate this package
+    ///
+    /// Imports are resolved from the vendor directory and the given additional include paths.
+    /// Rule severities are taken from the lint configuration of the manifest, `custom` rules are
+    /// enforced next to the builtin ones. If `unused` is set, definitions that are not used by any
+    /// service are reported as well.
     #[cfg(feature = "validation")]
     pub async fn validate(
         &self,
-        manifest: &PackageManifest,
-    ) -> miette::Result<crate::validation::Violations> {
+        manifest: &Manifest,
+        includes: &[PathBuf],
+        custom: &crate::validation::CustomRules,
+        unused: bool,
+    ) -> Result<crate::validation::Violations> {
+        let package = manifest
+            .package
+            .as_ref()
+            .ok_or(miette!("a [package] section must be declared to validate"))?;
+
         let root_path = self.proto_vendor_path();
+        let source_files = self.populated_files(package).await;
+
+        let mut parser = crate::validation::Validator::new(&root_path, package);
+
+        parser.configure(&manifest.lint);
+        parser.custom(custom);
+
+        if unused {
+            parser.unused();
+        }
+
+        for include in includes {
+            parser.include(include);
+        }
+
+        for file in &source_files {
+            parser.input(file);
+        }
+
+        Ok(parser.validate()?)
+    }
+
+    /// Verify that the protos of this package compile
+    ///
+    /// Imports are resolved from the vendor directory and the given additional include paths,
+    /// so every dependency that is imported is compiled as well.
+    #[cfg(feature = "validation")]
+    #[tracing::instrument(
+        name = "package.verify",
+        skip_all,
+        fields(package = %manifest.name, version = %manifest.version)
+    )]
+    pub async fn verify(&self, manifest: &PackageManifest, includes: &[PathBuf]) -> Result<()> {
         let source_files = self.populated_files(manifest).await;
 
-        let mut parser = crate::validation::Validator::new(&root_path, manifest);
+        if source_files.is_empty() {
+            return Ok(());
+        }
+
+        let mut parser = crate::validation::Validator::new(&self.proto_vendor_path(), manifest);
+
+        for include in includes {
+            parser.include(include);
+        }
 
         for file in &source_files {
             parser.input(file);
         }
 
-        parser.validate()
+        Ok(parser.compile()?)
     }
 
-    /// Packages a release from the local file system state
-    pub async fn release(&self, manifest: &Manifest) -> miette::Result<Package> {
-        for dependency in manifest.dependencies.iter() {
-            let resolved = self.resolve(&dependency.package).await?;
+    /// Compare this package against a baseline version for breaking changes
+    ///
+    /// The baseline must be unpacked into a directory named after the package inside of
+    /// `baseline`, mirroring the layout of the vendor directory. Imports of both versions are
+    /// resolved from the vendor directory and the given additional include paths.
+    ///
+    /// If `clients` is set, only changes of services affecting existing clients are reported.
+    #[cfg(feature = "validation")]
+    pub async fn breaking(
+        &self,
+        manifest: &PackageManifest,
+        baseline: &Path,
+        includes: &[PathBuf],
+        clients: bool,
+    ) -> Result<Vec<crate::validation::BreakingChange>> {
+        let mut check = crate::validation::BreakingCheck::new(baseline, &self.proto_vendor_path());
+
+        if clients {
+            check.clients();
+        }
+
+        check.include(&self.proto_vendor_path());
+
+        for include in includes {
+            check.include(include);
+        }
+
+        for file in self.collect(&baseline.join(&*manifest.name), true).await {
+            check.baseline(&file);
+        }
+
+        for file in self.populated_files(manifest).await {
+            check.current(&file);
+        }
+
+        Ok(check.check()?)
+    }
+
+    /// Compare the API of two versions of a package
+    ///
+    /// Both versions are expected in a directory named like the package, their parent directories
+    /// are used as roots to resolve imports of the respective version. Remaining imports are
+    /// resolved from the vendor directory and the given additional include paths.
+    #[cfg(feature = "validation")]
+    pub async fn diff(
+        &self,
+        baseline: &Path,
+        current: &Path,
+        includes: &[PathBuf],
+    ) -> Result<crate::validation::ApiDiff> {
+        let root = |path: &Path| path.parent().unwrap_or(path).to_path_buf();
+
+        let mut check = crate::validation::DiffCheck::new(&root(baseline), &root(current));
+
+        check.include(&self.proto_vendor_path());
+
+        for include in includes {
+            check.include(include);
+        }
+
+        for file in self.collect(baseline, true).await {
+            check.baseline(&file);
+        }
+
+        for file in self.collect(current, true).await {
+            check.current(&file);
+        }
+
+        Ok(check.diff()?)
+    }
+
+    /// Check that the given protos only use one of the allowed syntaxes
+    ///
+    /// Every syntax is allowed if `allowed` is empty.
+    pub async fn check_syntax(
+        &self,
+        files: &[PathBuf],
+        allowed: &[Syntax],
+    ) -> Result<Vec<UnsupportedSyntax>> {
+        let mut unsupported = Vec::new();
+
+        if allowed.is_empty() {
+            return Ok(unsupported);
+        }
+
+        for file in files {
+            let contents = self.read_to_string(file).await?;
+
+            let syntax = Syntax::detect(&contents);
+
+            if !allowed.contains(&syntax) {
+                unsupported.push(UnsupportedSyntax {
+                    file: file.strip_prefix(&self.root).unwrap_or(file).to_path_buf(),
+                    syntax,
+                });
+            }
+        }
+
+        Ok(unsupported)
+    }
+
+    /// Check the imports of the local protos against the declared dependencies
+    ///
+    /// Imports are provided by the local package, a declared dependency, the well-known
+    /// types of protobuf or a file in one of the given include paths.
+    pub async fn check_imports(
+        &self,
+        manifest: &Manifest,
+        includes: &[PathBuf],
+    ) -> Result<ImportCheck> {
+        let mut check = ImportCheck::default();
+        let mut used = HashSet::new();
+
+        let is_file = |path: PathBuf| self.fs.kind(&path) == Some(FileKind::File);
+
+        for file in self.collect(&self.proto_path(), false).await {
+            let contents = self.read_to_string(&file).await?;
+
+            for import in imports::parse(&contents) {
+                let package = import.split('/').next().unwrap_or_default();
+
+                if let Some(dependency) = manifest
+                    .dependencies
+                    .iter()
+                    .find(|dependency| &*dependency.package == package)
+                {
+                    used.insert(dependency.package.clone());
+                    continue;
+                }
 
-            let Some(ref resolved_pkg) = resolved.package else {
-                bail!("upstream package is invalid, [package] section is missing in manifest");
-            };
+                let local = manifest
+                    .package
+                    .as_ref()
+                    .is_some_and(|pkg| &*pkg.name == package);
 
-            ensure!(
-                resolved_pkg.kind != PackageType::Api,
-                "depending on API packages is not allowed",
+                let provided = local
+                    || import.starts_with("google/protobuf/")
+                    || is_file(self.proto_path().join(&import))
+                    || includes.iter().any(|path| is_file(path.join(&import)));
+
+                if !provided {
+                    check.undeclared.push(UndeclaredImport {
+                        file: file.strip_prefix(&self.root).unwrap_or(&file).to_path_buf(),
+                        import,
+                    });
+                }
+            }
+        }
+
+        check.unused = manifest
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.package.clone())
+            .filter(|package| !used.contains(package))
+            .collect();
+
+        Ok(check)
+    }
+
+    /// Detect circular imports between the installed packages
+    ///
+    /// Files are identified by their import path relative to the vendor directory, so cycles
+    /// spanning multiple packages are found as well.
+    pub async fn check_cycles(&self) -> Result<Vec<ImportCycle>> {
+        let vendor = self.proto_vendor_path();
+        let mut graph = BTreeMap::new();
+
+        for file in self.collect(&vendor, true).await {
+            let contents = self.read_to_string(&file).await?;
+
+            let path = file.strip_prefix(&vendor).into_diagnostic()?;
+
+            graph.insert(
+                path.to_string_lossy().replace('\\', "/"),
+                imports::parse(&contents),
             );
         }
 
+        Ok(imports::cycles(&graph))
+    }
+
+    /// Packages a release from the local file system state
+    ///
+    /// Reads the inputs of [`Package::release`] from the project and the installed dependencies.
+    pub async fn release(&self, manifest: &Manifest) -> Result<Package> {
+        let mut dependencies = Vec::with_capacity(manifest.dependencies.len());
+
+        for dependency in manifest.dependencies.iter() {
+            dependencies.push(self.resolve(&dependency.package).await?);
+        }
+
         let pkg_path = self.proto_path();
+        let paths = self.collect(&pkg_path, false).await;
+        let contents = self.read_all(paths.clone()).await?;
+
         let mut entries = BTreeMap::new();
 
-        for entry in self.collect(&pkg_path, false).await {
-            let path = entry.strip_prefix(&pkg_path).into_diagnostic()?;
-            let contents = tokio::fs::read(&entry).await.unwrap();
-            entries.insert(path.into(), contents.into());
+        for (entry, contents) in paths.into_iter().zip(contents) {
+            let path = entry
+                .strip_prefix(&pkg_path)
+                .into_diagnostic()?
+                .to_path_buf();
+            entries.insert(path, contents);
         }
 
-        let package = Package::create(manifest.clone(), entries)?;
+        let licenses = self.collect_licenses().await?;
+
+        let (package, warnings) =
+            Package::release(manifest.clone(), &dependencies, entries, licenses)?;
+
+        for warning in warnings {
+            tracing::warn!(":: {warning}");
+        }
 
         tracing::info!(":: packaged {}@{}", package.name(), package.version());
 
         Ok(package)
     }
 
+    /// Reads the license and notice files placed next to the manifest
+    async fn collect_licenses(&self) -> miette::Result<BTreeMap<PathBuf, Bytes>> {
+        let root = self.root.clone();
+
+        self.blocking(move |fs| -> miette::Result<BTreeMap<PathBuf, Bytes>> {
+            let mut licenses = BTreeMap::new();
+
+            let entries = fs
+                .read_dir(&root)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", root.display()))?;
+
+            for (path, kind) in entries {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                if kind != FileKind::File || !license::is_license_file(&name) {
+                    continue;
+                }
+
+                let contents = fs
+                    .read(&path)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to read {name}"))?;
+
+                licenses.insert(PathBuf::from(name), contents);
+            }
+
+            Ok(licenses)
+        })
+        .await?
+    }
+
+    /// Reads a file of the store
+    async fn read(&self, path: &Path) -> miette::Result<Bytes> {
+        let path = path.to_path_buf();
+
+        self.blocking(move |fs| {
+            fs.read(&path)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", path.display()))
+        })
+        .await?
+    }
+
+    /// Reads many files of the store in parallel, returned in the order of the paths
+    async fn read_all(&self, paths: Vec<PathBuf>) -> miette::Result<Vec<Bytes>> {
+        self.blocking(move |fs| {
+            crate::parallel::map(&paths, |path| {
+                fs.read(path)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to read {}", path.display()))
+            })
+            .into_iter()
+            .collect()
+        })
+        .await?
+    }
+
+    /// Reads a text file of the store, e.g. a proto
+    async fn read_to_string(&self, path: &Path) -> miette::Result<String> {
+        let contents = self.read(path).await?;
+
+        String::from_utf8(contents.into())
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {}", path.display()))
+    }
+
     /// Directory for the vendored installation of a package
     pub fn locate(&self, package: &PackageName) -> PathBuf {
         self.proto_vendor_path().join(&**package)
@@ -188,17 +730,14 @@ impl PackageStore {
 
     /// Collect .proto files in a given path
     pub async fn collect(&self, path: &Path, vendored: bool) -> Vec<PathBuf> {
-        let mut paths: Vec<_> = WalkDir::new(path)
+        let (path, vendor) = (path.to_path_buf(), self.proto_vendor_path());
+
+        let mut paths: Vec<_> = self
+            .blocking(move |fs| fs.walk(&path))
+            .await
+            .unwrap_or_default()
             .into_iter()
-            .filter_map(Result::ok)
-            .map(|entry| entry.into_path())
-            .filter(|path| {
-                if vendored {
-                    true
-                } else {
-                    !path.starts_with(self.proto_vendor_path())
-                }
-            })
+            .filter(|path| vendored || !path.starts_with(&vendor))
             .filter(|path| {
                 let ext = path.extension().map(|s| s.to_str());
 
@@ -212,44 +751,79 @@ impl PackageStore {
         paths
     }
 
+    /// Collect .proto files of the installed packages
+    ///
+    /// If `only` is given, the result is restricted to these packages. Packages listed in
+    /// `exclude` are always skipped.
+    pub async fn collect_packages(
+        &self,
+        only: Option<&[PackageName]>,
+        exclude: &[PackageName],
+    ) -> Result<Vec<PathBuf>> {
+        let mut protos = match only {
+            Some(packages) => {
+                let mut protos = Vec::new();
+
+                for package in packages {
+                    let path = self.locate(package);
+
+                    if self.fs.kind(&path) != Some(FileKind::Directory) {
+                        return Err(miette!("package {package} is not installed").into());
+                    }
+
+                    protos.extend(self.collect(&path, true).await);
+                }
+
+                protos.sort();
+                protos.dedup();
+                protos
+            }
+            None => self.collect(&self.proto_vendor_path(), true).await,
+        };
+
+        protos.retain(|proto| {
+            !exclude
+                .iter()
+                .any(|package| proto.starts_with(self.locate(package)))
+        });
+
+        Ok(protos)
+    }
+
     /// Sync this stores proto files to the vendor directory
-    pub async fn populate(&self, manifest: &PackageManifest) -> miette::Result<()> {
+    pub async fn populate(&self, manifest: &PackageManifest) -> Result<()> {
         let source_path = self.proto_path();
         let target_dir = self.proto_vendor_path().join(manifest.name.to_string());
+        let files = self.collect(&source_path, false).await;
 
-        if tokio::fs::try_exists(&target_dir)
-            .await
-            .into_diagnostic()
-            .wrap_err(format!(
-                "failed to check whether directory {} still exists",
-                target_dir.to_str().unwrap()
-            ))?
-        {
-            tokio::fs::remove_dir_all(&target_dir)
-                .await
-                .into_diagnostic()
-                .wrap_err(format!(
-                    "failed to remove directory {} and its contents.",
-                    target_dir.to_str().unwrap()
-                ))?;
-        }
+        self.blocking(move |fs| -> miette::Result<()> {
+            if fs.kind(&target_dir).is_some() {
+                fs.remove_dir_all(&target_dir)
+                    .into_diagnostic()
+                    .wrap_err(format!(
+                        "failed to remove directory {} and its contents.",
+                        target_dir.display()
+                    ))?;
+            }
 
-        for entry in self.collect(&source_path, false).await {
-            let file_name = entry.strip_prefix(&source_path).into_diagnostic()?;
-            let target_path = target_dir.join(file_name);
+            for entry in files {
+                let file_name = entry.strip_prefix(&source_path).into_diagnostic()?;
+                let target_path = target_dir.join(file_name);
+                let parent = target_path.parent().unwrap_or(&target_dir);
 
-            tokio::fs::create_dir_all(target_path.parent().unwrap())
-                .await
-                .into_diagnostic()
-                .wrap_err(format!(
-                    "Failed to create directory {} and its parents.",
-                    target_path.parent().unwrap().to_str().unwrap()
-                ))?;
+                fs.create_dir_all(parent)
+                    .into_diagnostic()
+                    .wrap_err(format!(
+                        "Failed to create directory {} and its parents.",
+                        parent.display()
+                    ))?;
 
-            tokio::fs::copy(entry, target_path)
-                .await
-                .into_diagnostic()?;
-        }
+                fs.copy(&entry, &target_path).into_diagnostic()?;
+            }
+
+            Ok(())
+        })
+        .await??;
 
         Ok(())
     }
@@ -260,14 +834,246 @@ impl PackageStore {
     }
 }
 
+/// Writes the files of a package through a filesystem, removing them again on failure
+fn write_entries(
+    fs: &dyn Filesystem,
+    package: &Package,
+    target: &Path,
+    cancel: &Cancel,
+) -> miette::Result<()> {
+    cancel.check()?;
+
+    fs.remove_dir_all(target).ok();
+
+    let write = || -> miette::Result<()> {
+        fs.create_dir_all(target)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create {}", target.display()))?;
+
+        for entry in package.entries()? {
+            cancel.check()?;
+
+            let entry = entry?;
+            let path = target.join(&entry.path);
+
+            if let Some(parent) = path.parent() {
+                fs.create_dir_all(parent)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to create {}", parent.display()))?;
+            }
+
+            fs.write(&path, &entry.contents)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", path.display()))?;
+        }
+
+        Ok(())
+    };
+
+    let written = write();
+
+    if written.is_err() {
+        // never leave a partial package behind
+        fs.remove_dir_all(target).ok();
+    }
+
+    written
+}
+
 #[test]
 fn can_get_proto_path() {
     assert_eq!(
-        PackageStore::new("/tmp".into()).proto_path(),
+        PackageStore::new("/tmp".into(), Arc::new(NativeFilesystem)).proto_path(),
         PathBuf::from("/tmp/proto")
     );
     assert_eq!(
-        PackageStore::new("/tmp".into()).proto_vendor_path(),
+        PackageStore::new("/tmp".into(), Arc::new(NativeFilesystem)).proto_vendor_path(),
         PathBuf::from("/tmp/proto/vendor")
     );
 }
+
+#[tokio::test]
+async fn preserves_nested_directories() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    std::fs::create_dir_all(dir.join("proto/api/v1")).unwrap();
+    std::fs::write(dir.join("proto/api/v1/service.proto"), "package api.v1;").unwrap();
+    std::fs::write(dir.join("proto/types.proto"), "package api;").unwrap();
+
+    let manifest: Manifest = "[package]\ntype = \"api\"\nname = \"api\"\nversion = \"1.0.0\"\n"
+        .parse()
+        .unwrap();
+
+    let store = PackageStore::open(dir.path()).await.unwrap();
+    let package = store.release(&manifest).await.unwrap();
+
+    let files: Vec<_> = package
+        .files()
+        .unwrap()
+        .into_iter()
+        .map(|(file, _)| file)
+        .collect();
+
+    assert_eq!(
+        files,
+        vec![
+            PathBuf::from(MANIFEST_FILE),
+            PathBuf::from(crate::package::FILE_LIST),
+            PathBuf::from("api/v1/service.proto"),
+            PathBuf::from("types.proto"),
+        ]
+    );
+
+    store
+        .populate(manifest.package.as_ref().unwrap())
+        .await
+        .unwrap();
+
+    assert!(dir.join("proto/vendor/api/api/v1/service.proto").is_file());
+}
+
+#[tokio::test]
+async fn links_unpacked_packages() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    std::fs::create_dir_all(dir.join("proto/api/v1")).unwrap();
+    std::fs::write(dir.join("proto/api/v1/service.proto"), "package api.v1;").unwrap();
+
+    let manifest: Manifest = "[package]\ntype = \"api\"\nname = \"api\"\nversion = \"1.0.0\"\n"
+        .parse()
+        .unwrap();
+
+    let store = PackageStore::open(dir.path()).await.unwrap();
+    let package = store.release(&manifest).await.unwrap();
+
+    let unpacked = dir.join("unpacked");
+    package.unpack(&unpacked).await.unwrap();
+
+    let linked = dir.join("proto/vendor/api/api/v1/service.proto");
+
+    for mode in [LinkMode::Copy, LinkMode::Hardlink, LinkMode::Copy] {
+        store.link(&package, &unpacked, mode).await.unwrap();
+
+        assert_eq!(std::fs::read_to_string(&linked).unwrap(), "package api.v1;");
+        assert!(dir.join("proto/vendor/api").join(MANIFEST_FILE).is_file());
+
+        // edits of hardlinked files would end up in the unpacked copy
+        assert_eq!(
+            std::fs::metadata(&linked).unwrap().permissions().readonly(),
+            mode == LinkMode::Hardlink
+        );
+    }
+}
+
+#[tokio::test]
+async fn records_installed_packages() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    std::fs::create_dir_all(dir.join("proto")).unwrap();
+    std::fs::write(dir.join("proto/units.proto"), "package units;").unwrap();
+
+    let manifest: Manifest = "[package]\ntype = \"api\"\nname = \"units\"\nversion = \"1.0.0\"\n"
+        .parse()
+        .unwrap();
+
+    let store = PackageStore::open(dir.path()).await.unwrap();
+    let package = store.release(&manifest).await.unwrap();
+
+    assert!(!store.is_installed(&package).await);
+
+    store.unpack(&package, &Cancel::new()).await.unwrap();
+
+    assert!(store.is_installed(&package).await);
+
+    std::fs::write(
+        dir.join("proto/vendor/units")
+            .join(PackageStore::DIGEST_FILE),
+        "sha256:00",
+    )
+    .unwrap();
+
+    assert!(!store.is_installed(&package).await);
+
+    std::fs::create_dir_all(dir.join("proto/vendor/stale")).unwrap();
+
+    store
+        .retain(&HashSet::from([package.name().clone()]))
+        .await
+        .unwrap();
+
+    assert!(dir.join("proto/vendor/units").is_dir());
+    assert!(!dir.join("proto/vendor/stale").exists());
+}
+
+#[tokio::test]
+async fn removes_cancelled_packages() {
+    let dir = assert_fs::TempDir::new().unwrap();
+
+    std::fs::create_dir_all(dir.join("proto")).unwrap();
+
+    for unit in ["length", "mass", "time"] {
+        std::fs::write(
+            dir.join(format!("proto/{unit}.proto")),
+            format!("package units.{unit};"),
+        )
+        .unwrap();
+    }
+
+    let manifest: Manifest = "[package]\ntype = \"api\"\nname = \"units\"\nversion = \"1.0.0\"\n"
+        .parse()
+        .unwrap();
+
+    let store = PackageStore::open(dir.path()).await.unwrap();
+    let package = store.release(&manifest).await.unwrap();
+
+    // cancelled after the first entries of the archive were extracted
+    let cancel = Cancel::after(3);
+
+    let error = store.unpack(&package, &cancel).await.unwrap_err();
+
+    assert!(matches!(error, crate::Error::Cancelled(_)));
+    assert!(!store.locate(package.name()).exists());
+    assert!(!store.is_installed(&package).await);
+}
+
+#[tokio::test]
+async fn installs_into_memory() {
+    use crate::package::MemoryFilesystem;
+
+    let fs = Arc::new(MemoryFilesystem::new());
+    let root = Path::new("/project");
+
+    fs.create_dir_all(&root.join("proto")).unwrap();
+    fs.write(&root.join("proto/units.proto"), b"package units;")
+        .unwrap();
+
+    let manifest: Manifest = "[package]\ntype = \"api\"\nname = \"units\"\nversion = \"1.0.0\"\n"
+        .parse()
+        .unwrap();
+
+    let store = PackageStore::with_filesystem(root, fs.clone())
+        .await
+        .unwrap();
+    let package = store.release(&manifest).await.unwrap();
+
+    store.unpack(&package, &Cancel::new()).await.unwrap();
+
+    assert!(store.is_installed(&package).await);
+    assert_eq!(
+        fs.read(&root.join("proto/vendor/units/units.proto"))
+            .unwrap(),
+        "package units;"
+    );
+    assert_eq!(
+        store.resolve(package.name()).await.unwrap().package,
+        manifest.package
+    );
+    assert_eq!(
+        store.collect_packages(None, &[]).await.unwrap(),
+        vec![root.join("proto/vendor/units/units.proto")]
+    );
+
+    store.uninstall(package.name()).await.unwrap();
+
+    assert!(!store.is_installed(&package).await);
+}
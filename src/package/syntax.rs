@@ -0,0 +1,143 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::imports::strip_comments;
+
+/// Syntax level a proto file is written in
+#[derive(Clone, Hash, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub enum Syntax {
+    /// `syntax = "proto2"`, which is also assumed if a file declares no syntax
+    Proto2,
+    /// `syntax = "proto3"`
+    Proto3,
+    /// `edition = "<edition>"`
+    Edition(String),
+}
+
+/// Error parsing a [`Syntax`]
+#[derive(Error, Debug, PartialEq)]
+#[error("unknown syntax `{0}`, expected `proto2`, `proto3` or `edition-<edition>`")]
+pub struct SyntaxError(String);
+
+impl Syntax {
+    /// Detects the syntax declared by the contents of a proto file
+    pub(crate) fn detect(contents: &str) -> Self {
+        let stripped = strip_comments(contents);
+
+        let Some(statement) = stripped.split(';').map(str::trim).find(|s| !s.is_empty()) else {
+            return Self::Proto2;
+        };
+
+        let value = |keyword: &str| {
+            let rest = statement.strip_prefix(keyword)?.trim_start();
+            let rest = rest.strip_prefix('=')?.trim();
+
+            Some(rest.trim_matches(['"', '\'']).to_string())
+        };
+
+        match (value("syntax"), value("edition")) {
+            (Some(syntax), _) if syntax == "proto3" => Self::Proto3,
+            (_, Some(edition)) => Self::Edition(edition),
+            _ => Self::Proto2,
+        }
+    }
+}
+
+impl fmt::Display for Syntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Proto2 => write!(f, "proto2"),
+            Self::Proto3 => write!(f, "proto3"),
+            Self::Edition(edition) => write!(f, "edition-{edition}"),
+        }
+    }
+}
+
+impl FromStr for Syntax {
+    type Err = SyntaxError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "proto2" => Ok(Self::Proto2),
+            "proto3" => Ok(Self::Proto3),
+            _ => match input.strip_prefix("edition-") {
+                Some(edition) if !edition.is_empty() => Ok(Self::Edition(edition.to_string())),
+                _ => Err(SyntaxError(input.to_string())),
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for Syntax {
+    type Error = SyntaxError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Syntax> for String {
+    fn from(syntax: Syntax) -> Self {
+        syntax.to_string()
+    }
+}
+
+/// A proto file written in a syntax that is not allowed by the package
+#[derive(Error, Diagnostic, Debug, Clone, PartialEq, Eq)]
+#[error("`{}` uses {syntax}, which is not allowed for this package", .file.display())]
+#[diagnostic(help(
+    "the `syntax` setting in the [lint] section of the manifest lists the allowed syntaxes"
+))]
+pub struct UnsupportedSyntax {
+    /// File using the syntax
+    pub file: PathBuf,
+    /// The syntax declared by the file
+    pub syntax: Syntax,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_syntax() {
+        assert_eq!(Syntax::detect(r#"syntax = "proto3";"#), Syntax::Proto3);
+        assert_eq!(
+            Syntax::detect("syntax='proto2'; package a;"),
+            Syntax::Proto2
+        );
+        assert_eq!(
+            Syntax::detect("// license\n/* header */\nedition = \"2023\";"),
+            Syntax::Edition("2023".into())
+        );
+        assert_eq!(Syntax::detect("package physics;"), Syntax::Proto2);
+        assert_eq!(Syntax::detect(""), Syntax::Proto2);
+    }
+
+    #[test]
+    fn parses_policy_values() {
+        assert_eq!("proto3".parse(), Ok(Syntax::Proto3));
+        assert_eq!("edition-2023".parse(), Ok(Syntax::Edition("2023".into())));
+        assert!("edition-".parse::<Syntax>().is_err());
+        assert!("proto4".parse::<Syntax>().is_err());
+        assert_eq!(Syntax::Edition("2023".into()).to_string(), "edition-2023");
+    }
+}
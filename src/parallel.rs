@@ -0,0 +1,71 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Order-preserving parallel processing of many small items, e.g. the files of a package
+
+use std::{num::NonZeroUsize, thread};
+
+/// Fewest items worth spreading across threads, smaller batches are processed in place
+const MIN_ITEMS: usize = 64;
+
+/// Applies `operation` to every item, returning the results in the order of the items
+///
+/// The items are split into one contiguous share per available core, each processed by a scoped
+/// thread. WebAssembly targets lack threads and process the items one after another.
+pub(crate) fn map<T, R, F>(items: &[T], operation: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(items.len() / MIN_ITEMS);
+
+    if cfg!(target_family = "wasm") || workers < 2 {
+        return items.iter().map(operation).collect();
+    }
+
+    let share = items.len().div_ceil(workers);
+    let operation = &operation;
+
+    thread::scope(|scope| {
+        let tasks: Vec<_> = items
+            .chunks(share)
+            .map(|share| scope.spawn(move || share.iter().map(operation).collect::<Vec<_>>()))
+            .collect();
+
+        tasks
+            .into_iter()
+            .flat_map(|task| {
+                task.join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn preserves_order() {
+        let items: Vec<usize> = (0..10_000).collect();
+
+        assert_eq!(
+            super::map(&items, |item| item * 2),
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert_eq!(super::map(&items[..3], |item| item + 1), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#[cfg(feature = "native")]
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+#[cfg(feature = "native")]
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use semver::Version;
+
+use crate::package::PackageName;
+
+/// Something that happened while resolving or installing packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// A package was resolved and is going to be installed
+    Resolved {
+        /// Name of the package
+        name: &'a PackageName,
+        /// Version the package was resolved to
+        version: &'a Version,
+    },
+    /// The download of a package from a registry started
+    DownloadStarted {
+        /// Name of the package
+        name: &'a PackageName,
+    },
+    /// Bytes of a package were downloaded
+    DownloadProgress {
+        /// Name of the package
+        name: &'a PackageName,
+        /// Number of bytes downloaded since the previous event
+        bytes: u64,
+    },
+    /// The download of a package finished
+    DownloadFinished {
+        /// Name of the package
+        name: &'a PackageName,
+    },
+    /// A package was installed into the package store
+    Installed {
+        /// Name of the package
+        name: &'a PackageName,
+        /// Version of the package
+        version: &'a Version,
+    },
+}
+
+/// Receiver of the events of an installation
+///
+/// The progress bar of the CLI is one sink, tools embedding buffrs implement their own to show
+/// what is happening in their interface.
+pub trait EventSink: Send + Sync {
+    /// Handles an event, events are delivered in the order they happen
+    fn event(&self, event: Event<'_>);
+}
+
+/// Discards all events
+impl EventSink for () {
+    fn event(&self, _: Event<'_>) {}
+}
+
+/// Layout of the progress bar
+#[cfg(feature = "native")]
+const TEMPLATE: &str = "{spinner} [{elapsed}] {bar:30} {pos}/{len} packages, {msg}";
+
+/// Aggregate progress of an installation, drawn as a bar on terminals
+///
+/// Resolving a package extends the bar and installing it advances the bar, while the downloaded
+/// bytes are shown next to it. The bar is hidden if stderr is not a terminal, e.g. in CI, where
+/// installations log every package instead.
+#[cfg(feature = "native")]
+pub struct Progress {
+    bar: ProgressBar,
+    downloaded: AtomicU64,
+    installed: Mutex<HashSet<PackageName>>,
+}
+
+#[cfg(feature = "native")]
+impl Progress {
+    /// Creates a progress bar drawn to stderr if it is a terminal
+    pub fn new() -> Self {
+        let progress = Self::with_target(ProgressDrawTarget::stderr());
+
+        if progress.is_visible() {
+            progress.bar.enable_steady_tick(Duration::from_millis(100));
+        }
+
+        progress
+    }
+
+    /// Creates a progress bar that is never drawn
+    pub fn hidden() -> Self {
+        Self::with_target(ProgressDrawTarget::hidden())
+    }
+
+    fn with_target(target: ProgressDrawTarget) -> Self {
+        let style = ProgressStyle::with_template(TEMPLATE)
+            .expect("invalid progress bar template")
+            .progress_chars("=> ");
+
+        let bar = ProgressBar::with_draw_target(Some(0), target).with_style(style);
+
+        bar.set_message("0 B downloaded");
+
+        Self {
+            bar,
+            downloaded: AtomicU64::new(0),
+            installed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Whether the bar is drawn, in which case per package logs should be left out
+    pub fn is_visible(&self) -> bool {
+        !self.bar.is_hidden()
+    }
+
+    /// Records a package that was resolved and is going to be installed
+    pub fn resolved(&self) {
+        self.bar.inc_length(1);
+    }
+
+    /// Records bytes downloaded from a registry
+    pub fn downloaded(&self, bytes: u64) {
+        let total = self.downloaded.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        self.bar
+            .set_message(format!("{} downloaded", HumanBytes(total)));
+    }
+
+    /// Records an installed package, packages installed repeatedly are only counted once
+    pub fn installed(&self, name: &PackageName) {
+        let mut installed = self
+            .installed
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if installed.insert(name.clone()) {
+            self.bar.inc(1);
+        }
+    }
+
+    /// Removes the bar and summarizes the installation, e.g. `12 packages (1.5 MiB downloaded) in
+    /// 3 seconds`
+    pub fn finish(&self) -> String {
+        // finishing moves the bar to its end, which counts resolved rather than installed packages
+        let installed = self.bar.position();
+
+        self.bar.finish_and_clear();
+
+        format!(
+            "{} packages ({} downloaded) in {}",
+            installed,
+            HumanBytes(self.downloaded.load(Ordering::Relaxed)),
+            HumanDuration(self.bar.elapsed())
+        )
+    }
+}
+
+#[cfg(feature = "native")]
+impl EventSink for Progress {
+    fn event(&self, event: Event<'_>) {
+        match event {
+            Event::Resolved { .. } => self.resolved(),
+            Event::DownloadProgress { bytes, .. } => self.downloaded(bytes),
+            Event::Installed { name, .. } => self.installed(name),
+            Event::DownloadStarted { .. } | Event::DownloadFinished { .. } => (),
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl Default for Progress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_distinct_packages() {
+        let progress = Progress::hidden();
+        let name: PackageName = "physics".parse().unwrap();
+
+        progress.resolved();
+        progress.resolved();
+        progress.downloaded(1024);
+        progress.installed(&name);
+        progress.installed(&name);
+
+        assert!(!progress.is_visible());
+        assert_eq!(progress.bar.position(), 1);
+        assert_eq!(progress.bar.length(), Some(2));
+        assert!(progress
+            .finish()
+            .starts_with("1 packages (1.00 KiB downloaded) in"));
+    }
+
+    #[test]
+    fn consumes_events() {
+        let progress = Progress::hidden();
+        let name: PackageName = "physics".parse().unwrap();
+        let version = Version::new(1, 0, 0);
+
+        progress.event(Event::Resolved {
+            name: &name,
+            version: &version,
+        });
+        progress.event(Event::DownloadStarted { name: &name });
+        progress.event(Event::DownloadProgress {
+            name: &name,
+            bytes: 2048,
+        });
+        progress.event(Event::DownloadFinished { name: &name });
+        progress.event(Event::Installed {
+            name: &name,
+            version: &version,
+        });
+
+        assert_eq!(progress.bar.position(), 1);
+        assert_eq!(progress.bar.length(), Some(1));
+        assert!(progress
+            .finish()
+            .starts_with("1 packages (2.00 KiB downloaded) in"));
+    }
+}
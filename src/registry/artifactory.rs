@@ -12,24 +12,48 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    time::Duration,
+};
+
 use super::RegistryUri;
 use crate::{
+    config::Network,
     credentials::Credentials,
+    encryption::Encryption,
+    errors::{NotFound, RegistryUnreachable, Unauthorized},
+    lock::{Digest, DigestAlgorithm},
     manifest::{Dependency, DependencyManifest},
-    package::{Package, PackageName},
+    package::{ChunkIndex, Package, PackageName, MAX_UNPACKED_SIZE},
 };
+use bytes::Bytes;
 use miette::{ensure, miette, Context, IntoDiagnostic};
 use reqwest::{Body, Method, Response};
 use semver::Version;
 use serde::Deserialize;
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+use tracing::Instrument;
 use url::Url;
 
+/// Upper bound of the size of downloaded archives
+///
+/// Gzip and encryption add little overhead, so archives beyond this size can not decompress
+/// within [`MAX_UNPACKED_SIZE`] anyway and are aborted before they fill the disk.
+pub const MAX_DOWNLOAD_SIZE: u64 = MAX_UNPACKED_SIZE + 1024 * 1024;
+
 /// The registry implementation for artifactory
 #[derive(Debug, Clone)]
 pub struct Artifactory {
     registry: RegistryUri,
     token: Option<String>,
     client: reqwest::Client,
+    retries: u32,
+    encryption: Option<Encryption>,
 }
 
 impl Artifactory {
@@ -42,11 +66,38 @@ impl Artifactory {
                 .redirect(reqwest::redirect::Policy::none())
                 .build()
                 .into_diagnostic()?,
+            retries: 0,
+            encryption: None,
         })
     }
 
+    /// Sends requests through the configured proxy and retries failed requests
+    pub fn with_network(mut self, network: &Network) -> miette::Result<Self> {
+        let mut client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+
+        if let Some(proxy) = &network.proxy {
+            client = client.proxy(
+                reqwest::Proxy::all(proxy)
+                    .into_diagnostic()
+                    .wrap_err(miette!("invalid proxy: {proxy}"))?,
+            );
+        }
+
+        self.client = client.build().into_diagnostic()?;
+        self.retries = network.retries;
+
+        Ok(self)
+    }
+
+    /// Encrypts published and decrypts downloaded packages with the given keys
+    pub fn with_encryption(mut self, encryption: Option<&Encryption>) -> Self {
+        self.encryption = encryption.cloned();
+        self
+    }
+
     fn new_request(&self, method: Method, url: Url) -> RequestBuilder {
-        let mut request_builder = RequestBuilder::new(self.client.clone(), method, url);
+        let mut request_builder =
+            RequestBuilder::new(self.client.clone(), method, url).retries(self.retries);
 
         if let Some(token) = &self.token {
             request_builder = request_builder.auth(token.clone());
@@ -72,16 +123,156 @@ impl Artifactory {
     }
 
     /// Retrieves the latest version of a package by querying artifactory. Returns an error if no artifact could be found
+    #[tracing::instrument(
+        name = "registry.latest_version",
+        skip_all,
+        fields(registry = %self.registry, repository = %repository, package = %name)
+    )]
     pub async fn get_latest_version(
         &self,
         repository: String,
         name: PackageName,
     ) -> miette::Result<Version> {
-        // First retrieve all packages matching the given name
+        self.latest_version(&repository, &name).await?.ok_or_else(|| {
+            NotFound("no version could be found on artifactory for this artifact name. Does it exist in this registry and repository?".to_owned()).into()
+        })
+    }
+
+    /// Retrieves the highest published version of a package, `None` if it was never published
+    ///
+    /// Unlike [`Artifactory::get_latest_version`], only the absence of the package yields `None`,
+    /// while failing requests are reported as errors. Registries answering the search with "not
+    /// found" have no published version either.
+    pub async fn latest_version(
+        &self,
+        repository: &str,
+        name: &PackageName,
+    ) -> miette::Result<Option<Version>> {
+        let artifacts = match self.search_artifacts(name, repository).await {
+            Ok(artifacts) => artifacts,
+            Err(error) if error.downcast_ref::<NotFound>().is_some() => Vec::new(),
+            Err(error) => return Err(error),
+        };
+
+        // First retrieve all packages matching the given name, then from all package names
+        // retrieved from artifactory, extract the highest version number
+        let highest_version = artifacts
+            .into_iter()
+            .filter_map(|artifact| parse_artifact_name(&artifact))
+            // we double check that the artifact name matches exactly
+            .filter(|(artifact_name, _)| artifact_name == name)
+            .map(|(_, version)| version)
+            .max();
+
+        tracing::debug!("Highest version for artifact: {:?}", highest_version);
+
+        Ok(highest_version)
+    }
+
+    /// Searches a repository for packages whose name contains the query
+    ///
+    /// Returns the published versions of every matching package, the latest version first.
+    pub async fn search(
+        &self,
+        repository: &str,
+        query: &str,
+    ) -> miette::Result<BTreeMap<PackageName, Vec<Version>>> {
+        let mut packages: BTreeMap<PackageName, Vec<Version>> = BTreeMap::new();
+
+        for artifact in self
+            .search_artifacts(&format!("*{query}*"), repository)
+            .await?
+        {
+            if let Some((name, version)) = parse_artifact_name(&artifact) {
+                packages.entry(name).or_default().push(version);
+            }
+        }
+
+        for versions in packages.values_mut() {
+            versions.sort_by(|a, b| b.cmp(a));
+            versions.dedup();
+        }
+
+        Ok(packages)
+    }
+
+    /// Lists the published artifacts of many packages of a repository with a single AQL query
+    ///
+    /// Returns `None` if the registry does not answer AQL queries, e.g. because it is not an
+    /// Artifactory instance or the token lacks the permission, so that callers can fall back to
+    /// querying the packages one by one.
+    #[tracing::instrument(
+        name = "registry.published_artifacts",
+        skip_all,
+        fields(registry = %self.registry, repository = %repository, packages = names.len())
+    )]
+    pub async fn published_artifacts(
+        &self,
+        repository: &str,
+        names: &[PackageName],
+    ) -> miette::Result<Option<PublishedArtifacts>> {
+        let url: Url = format!("{}/api/search/aql", self.registry)
+            .parse()
+            .into_diagnostic()
+            .wrap_err(miette!("unexpected error: failed to construct AQL URL"))?;
+
+        // packages are stored in a directory named after them, chunks in a subdirectory
+        let paths: Vec<_> = names
+            .iter()
+            .map(|name| serde_json::json!({ "path": name.to_string() }))
+            .collect();
+
+        let query = format!(
+            "items.find({}).include(\"path\",\"name\")",
+            serde_json::json!({ "repo": repository, "$or": paths })
+        );
+
+        let response = self
+            .new_request(Method::POST, url)
+            .body(query)
+            .execute()
+            .await?;
+
+        if !response.status().is_success() {
+            tracing::debug!("AQL query failed with {}", response.status());
+            return Ok(None);
+        }
+
+        let body = response.bytes().await.into_diagnostic()?;
+
+        let response: AqlResponse = serde_json::from_slice(&body)
+            .into_diagnostic()
+            .wrap_err(miette!("unexpected error: malformed AQL response"))?;
+
+        let mut artifacts: BTreeMap<PackageName, BTreeSet<String>> = names
+            .iter()
+            .map(|name| (name.clone(), BTreeSet::new()))
+            .collect();
+
+        for item in response.results {
+            let Ok(name) = PackageName::new(&item.path) else {
+                continue;
+            };
+
+            if let Some(files) = artifacts.get_mut(&name) {
+                files.insert(item.name);
+            }
+        }
+
+        Ok(Some(PublishedArtifacts(artifacts)))
+    }
+
+    /// Retrieves the file names of all artifacts in a repository matching a name pattern, without
+    /// their extension
+    async fn search_artifacts(
+        &self,
+        pattern: &str,
+        repository: &str,
+    ) -> miette::Result<Vec<String>> {
         let search_query_url: Url = {
             let mut url = self.registry.clone();
             url.set_path("artifactory/api/search/artifact");
-            url.set_query(Some(&format!("name={}&repos={}", name, repository)));
+            url.set_query(Some(&format!("name={}&repos={}", pattern, repository)));
             url.into()
         };
 
@@ -117,42 +308,78 @@ impl Artifactory {
             parsed_response
         );
 
-        // Then from all package names retrieved from artifactory, extract the highest version number
-        let highest_version = parsed_response
+        let artifacts = parsed_response
             .results
-            .iter()
+            .into_iter()
             .filter_map(|artifact_search_result| {
-                let uri = artifact_search_result.to_owned().uri;
-                let full_artifact_name = uri
+                // chunked packages are only published as their chunk index
+                artifact_search_result
+                    .uri
                     .split('/')
                     .last()
-                    .map(|name_tgz| name_tgz.trim_end_matches(".tgz"));
-                let artifact_version = full_artifact_name
-                    .and_then(|name| name.split('-').last())
-                    .and_then(|version_str| Version::parse(version_str).ok());
-
-                // we double check that the artifact name matches exactly
-                let expected_artifact_name = artifact_version
-                    .clone()
-                    .map(|av| format!("{}-{}", name, av));
-                if full_artifact_name.is_some_and(|actual| {
-                    expected_artifact_name.is_some_and(|expected| expected == actual)
-                }) {
-                    artifact_version
-                } else {
-                    None
-                }
+                    .map(|name_tgz| {
+                        name_tgz
+                            .trim_end_matches(ChunkIndex::EXTENSION)
+                            .trim_end_matches('.')
+                            .trim_end_matches(".tgz")
+                            .to_owned()
+                    })
             })
-            .max();
+            .collect();
 
-        tracing::debug!("Highest version for artifact: {:?}", highest_version);
-        highest_version.ok_or_else(|| {
-            miette!("no version could be found on artifactory for this artifact name. Does it exist in this registry and repository?")
-        })
+        Ok(artifacts)
     }
 
     /// Downloads a package from artifactory
+    ///
+    /// The archive is staged in a temporary file, which is deleted once the package is dropped.
     pub async fn download(&self, dependency: Dependency) -> miette::Result<Package> {
+        let file = tempfile::Builder::new()
+            .prefix("buffrs-")
+            .suffix(".tgz")
+            .tempfile()
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create a temporary file"))?
+            .into_temp_path();
+
+        self.download_to(dependency, &file, |_| ()).await?;
+
+        Package::read_temporary(file).await
+    }
+
+    /// Downloads a package from artifactory into a file, returning it along with its digest
+    ///
+    /// The response body is written to the file as it arrives rather than buffered in memory,
+    /// while its digest is computed on the way and `progress` is called with the size of every
+    /// received chunk. Downloads exceeding [`MAX_DOWNLOAD_SIZE`] are aborted. The returned package
+    /// is read back from the file like [`Package::read`], so large archives are memory-mapped.
+    /// Encrypted packages are decrypted in place, so that the file holds the plain archive of the
+    /// returned package afterwards.
+    pub async fn download_to(
+        &self,
+        dependency: Dependency,
+        file: &Path,
+        mut progress: impl FnMut(u64),
+    ) -> miette::Result<(Package, Digest)> {
+        let span = tracing::info_span!(
+            "registry.download",
+            registry = %self.registry,
+            package = %dependency.package,
+            version = tracing::field::Empty,
+            bytes = tracing::field::Empty,
+        );
+
+        self.fetch_to(dependency, file, &mut progress)
+            .instrument(span)
+            .await
+    }
+
+    async fn fetch_to(
+        &self,
+        dependency: Dependency,
+        file: &Path,
+        mut progress: impl FnMut(u64),
+    ) -> miette::Result<(Package, Digest)> {
         let DependencyManifest::Remote(ref manifest) = dependency.manifest else {
             return Err(miette!(
                 "unable to download local dependency ({}) from artifactory",
@@ -163,6 +390,8 @@ impl Artifactory {
         let artifact_url = {
             let version = super::dependency_version_string(&dependency)?;
 
+            tracing::Span::current().record("version", version.as_str());
+
             let path = manifest.registry.path().to_owned();
 
             let mut url = manifest.registry.clone();
@@ -190,16 +419,432 @@ impl Artifactory {
             "server response has incorrect mime type: {content_type:?}"
         );
 
-        let data = response.bytes().await.into_diagnostic()?;
+        let (size, mut digest) =
+            Self::stream(response, file, &mut progress)
+                .await
+                .wrap_err(miette!(
+                    "failed to download dependency {}",
+                    dependency.package
+                ))?;
+
+        tracing::Span::current().record("bytes", size);
+
+        tracing::debug!(
+            "downloaded {size} bytes of {} ({digest})",
+            dependency.package
+        );
+
+        if Self::is_encrypted(file).await? {
+            let encryption = self.encryption.as_ref().ok_or(miette!(
+                help = "configure the keys of the registry in the `[encryption]` section of the configuration",
+                "{} is encrypted",
+                dependency.package
+            ))?;
 
-        Package::try_from(data).wrap_err(miette!(
+            // decryption needs the whole payload, only encrypted packages are held in memory
+            let data = fs::read(file)
+                .await
+                .map(Bytes::from)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", file.display()))?;
+
+            let data = encryption.decrypt(data).await?;
+            digest = DigestAlgorithm::SHA256.digest(&data);
+
+            fs::write(file, &data)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", file.display()))?;
+        }
+
+        let package = Package::read(file).await.wrap_err(miette!(
             "failed to download dependency {}",
             dependency.package
-        ))
+        ))?;
+
+        Ok((package, digest))
+    }
+
+    /// Whether a downloaded file holds an encrypted package, judging by its header
+    async fn is_encrypted(file: &Path) -> miette::Result<bool> {
+        let mut header = Vec::new();
+
+        fs::File::open(file)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to open {}", file.display()))?
+            .take(64)
+            .read_to_end(&mut header)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read {}", file.display()))?;
+
+        Ok(Encryption::is_encrypted(&header))
+    }
+
+    /// Streams the body of a response into a file, returning its size and digest
+    async fn stream(
+        mut response: Response,
+        file: &Path,
+        progress: &mut impl FnMut(u64),
+    ) -> miette::Result<(u64, Digest)> {
+        if let Some(length) = response.content_length() {
+            ensure!(
+                length <= MAX_DOWNLOAD_SIZE,
+                "the archive has {length} bytes, exceeding the limit of {MAX_DOWNLOAD_SIZE} bytes"
+            );
+        }
+
+        let mut writer = fs::File::create(file)
+            .await
+            .map(tokio::io::BufWriter::new)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create {}", file.display()))?;
+
+        let mut hasher = DigestAlgorithm::SHA256.hasher();
+        let mut size = 0;
+
+        while let Some(chunk) = response.chunk().await.into_diagnostic()? {
+            writer
+                .write_all(&chunk)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write {}", file.display()))?;
+
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            progress(chunk.len() as u64);
+
+            ensure!(
+                size <= MAX_DOWNLOAD_SIZE,
+                "the archive exceeds the limit of {MAX_DOWNLOAD_SIZE} bytes"
+            );
+        }
+
+        writer
+            .flush()
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write {}", file.display()))?;
+
+        Ok((size, hasher.finalize()))
+    }
+
+    /// Retrieves the date a package version was published at
+    pub async fn published(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        version: &Version,
+    ) -> miette::Result<String> {
+        let storage_url: Url = {
+            let mut url = self.registry.clone();
+            let path = url.path().to_owned();
+            url.set_path(&format!(
+                "{path}/api/storage/{repository}/{name}/{name}-{version}.tgz"
+            ));
+            url.into()
+        };
+
+        let response = self.new_request(Method::GET, storage_url).send().await?;
+
+        let response_str = response.0.text().await.into_diagnostic().wrap_err(miette!(
+            "unexpected error: unable to retrieve response payload"
+        ))?;
+
+        let info = serde_json::from_str::<StorageInfo>(&response_str)
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "unexpected error: response could not be deserialized to StorageInfo"
+            ))?;
+
+        Ok(info.created)
+    }
+
+    /// Retrieves the SHA-256 digest the registry stores for the archive of a package version
+    ///
+    /// Returns `None` if the archive is not stored, e.g. because the version was published in
+    /// chunks, or the registry does not report a SHA-256 checksum for it.
+    pub async fn checksum(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        version: &Version,
+    ) -> miette::Result<Option<Digest>> {
+        let url: Url = format!(
+            "{}/api/storage/{repository}/{name}/{name}-{version}.tgz",
+            self.registry
+        )
+        .parse()
+        .into_diagnostic()
+        .wrap_err(miette!("unexpected error: failed to construct storage URL"))?;
+
+        let response = self.new_request(Method::GET, url).execute().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = ValidatedResponse::try_from(response)?;
+        let body = response.0.bytes().await.into_diagnostic()?;
+
+        let info = serde_json::from_slice::<StorageInfo>(&body)
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "unexpected error: response could not be deserialized to StorageInfo"
+            ))?;
+
+        info.checksums
+            .sha256
+            .map(|sha256| Digest::from_parts(DigestAlgorithm::SHA256, &sha256))
+            .transpose()
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "the registry reported an invalid checksum for {name}@{version}"
+            ))
     }
 
     /// Publishes a package to artifactory
+    #[tracing::instrument(
+        name = "registry.publish",
+        skip_all,
+        fields(
+            registry = %self.registry,
+            repository = %repository,
+            package = %package.name(),
+            version = %package.version(),
+        )
+    )]
     pub async fn publish(&self, package: Package, repository: String) -> miette::Result<()> {
+        Self::ensure_remote_dependencies(&package)?;
+
+        let artifact_uri: Url = format!(
+            "{}/{}/{}/{}-{}.tgz",
+            self.registry,
+            repository,
+            package.name(),
+            package.name(),
+            package.version(),
+        )
+        .parse()
+        .into_diagnostic()
+        .wrap_err(miette!(
+            "unexpected error: failed to construct artifact URL"
+        ))?;
+
+        let body = match &self.encryption {
+            Some(encryption) => encryption.encrypt(package.tgz.clone()).await?,
+            None => package.tgz.clone(),
+        };
+
+        let _ = self
+            .new_request(Method::PUT, artifact_uri)
+            .body(body)
+            .send()
+            .await?;
+
+        tracing::info!(
+            ":: published {}/{}@{}",
+            repository,
+            package.name(),
+            package.version()
+        );
+
+        Ok(())
+    }
+
+    /// Publishes a package as content defined chunks of its archive
+    ///
+    /// Only chunks the repository does not store yet are uploaded, followed by the chunk index
+    /// of the package.
+    #[tracing::instrument(
+        name = "registry.publish_chunks",
+        skip_all,
+        fields(
+            registry = %self.registry,
+            repository = %repository,
+            package = %package.name(),
+            version = %package.version(),
+        )
+    )]
+    pub async fn publish_chunks(&self, package: &Package, repository: &str) -> miette::Result<()> {
+        Self::ensure_remote_dependencies(package)?;
+
+        ensure!(
+            self.encryption.is_none(),
+            "unable to publish {} in chunks, chunks of encrypted registries would be stored in plain text",
+            package.name()
+        );
+
+        let (index, chunks) = ChunkIndex::split(package)?;
+
+        let mut seen = BTreeSet::new();
+        let mut uploaded = 0;
+
+        for (digest, chunk) in chunks {
+            if !seen.insert(digest.clone()) {
+                continue;
+            }
+
+            let url = self.chunk_url(repository, package.name(), &digest)?;
+
+            if self.exists(url.clone()).await? {
+                continue;
+            }
+
+            let _ = self
+                .new_request(Method::PUT, url)
+                .body(chunk)
+                .send()
+                .await?;
+
+            uploaded += 1;
+        }
+
+        let url = self.signature_url(
+            repository,
+            package.name(),
+            package.version(),
+            ChunkIndex::EXTENSION,
+        )?;
+
+        let body = serde_json::to_vec(&index)
+            .into_diagnostic()
+            .wrap_err(miette!("failed to serialize the chunk index"))?;
+
+        let _ = self.new_request(Method::PUT, url).body(body).send().await?;
+
+        tracing::info!(
+            ":: published {}/{}@{} ({uploaded} of {} chunks uploaded)",
+            repository,
+            package.name(),
+            package.version(),
+            seen.len()
+        );
+
+        Ok(())
+    }
+
+    /// Downloads the chunk index of a package, if it was published in chunks
+    #[tracing::instrument(
+        name = "registry.download_chunk_index",
+        skip_all,
+        fields(registry = %self.registry, package = %dependency.package)
+    )]
+    pub async fn download_chunk_index(
+        &self,
+        dependency: &Dependency,
+    ) -> miette::Result<Option<ChunkIndex>> {
+        let DependencyManifest::Remote(ref manifest) = dependency.manifest else {
+            return Err(miette!(
+                "unable to download local dependency ({}) from artifactory",
+                dependency.package
+            ));
+        };
+
+        let version = super::dependency_version_string(dependency)?;
+
+        let url: Url = format!(
+            "{}/{}/{}/{}-{}.tgz.{}",
+            self.registry,
+            manifest.repository,
+            dependency.package,
+            dependency.package,
+            version,
+            ChunkIndex::EXTENSION
+        )
+        .parse()
+        .into_diagnostic()
+        .wrap_err(miette!(
+            "unexpected error: failed to construct chunk index URL"
+        ))?;
+
+        let response = self.new_request(Method::GET, url).execute().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = ValidatedResponse::try_from(response)?;
+        let body = response.0.bytes().await.into_diagnostic()?;
+
+        serde_json::from_slice(&body)
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "unexpected error: response could not be deserialized to ChunkIndex"
+            ))
+            .map(Some)
+    }
+
+    /// Downloads a single chunk of a package published in chunks
+    #[tracing::instrument(
+        name = "registry.download_chunk",
+        skip_all,
+        fields(registry = %self.registry, package = %name, chunk = %digest)
+    )]
+    pub async fn download_chunk(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        digest: &Digest,
+    ) -> miette::Result<Bytes> {
+        let url = self.chunk_url(repository, name, digest)?;
+
+        let response = self.new_request(Method::GET, url).send().await?;
+
+        response.0.bytes().await.into_diagnostic()
+    }
+
+    fn chunk_url(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        digest: &Digest,
+    ) -> miette::Result<Url> {
+        format!(
+            "{}/{repository}/{name}/chunks/{}/{}",
+            self.registry,
+            digest.algorithm(),
+            hex::encode(digest.as_bytes())
+        )
+        .parse()
+        .into_diagnostic()
+        .wrap_err(miette!("unexpected error: failed to construct chunk URL"))
+    }
+
+    /// Whether a version of a package is published to a repository
+    #[tracing::instrument(
+        name = "registry.is_published",
+        skip_all,
+        fields(registry = %self.registry, package = %name, version = %version)
+    )]
+    pub async fn is_published(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        version: &Version,
+    ) -> miette::Result<bool> {
+        let url: Url = format!("{}/{repository}/{name}/{name}-{version}.tgz", self.registry)
+            .parse()
+            .into_diagnostic()
+            .wrap_err(miette!("unexpected error: failed to construct package URL"))?;
+
+        self.exists(url).await
+    }
+
+    /// Whether the registry stores an artifact at the given URL
+    async fn exists(&self, url: Url) -> miette::Result<bool> {
+        let response = self.new_request(Method::HEAD, url).execute().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+
+        ValidatedResponse::try_from(response).map(|_| true)
+    }
+
+    /// Aborts publishing packages that depend on local packages
+    fn ensure_remote_dependencies(package: &Package) -> miette::Result<()> {
         let local_deps: Vec<&Dependency> = package
             .manifest
             .dependencies
@@ -218,28 +863,45 @@ impl Artifactory {
             ));
         }
 
-        let artifact_uri: Url = format!(
-            "{}/{}/{}/{}-{}.tgz",
-            self.registry,
-            repository,
-            package.name(),
-            package.name(),
-            package.version(),
+        Ok(())
+    }
+
+    fn signature_url(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        version: &Version,
+        extension: &str,
+    ) -> miette::Result<Url> {
+        format!(
+            "{}/{repository}/{name}/{name}-{version}.tgz.{extension}",
+            self.registry
         )
         .parse()
         .into_diagnostic()
         .wrap_err(miette!(
-            "unexpected error: failed to construct artifact URL"
-        ))?;
+            "unexpected error: failed to construct signature URL"
+        ))
+    }
+
+    /// Uploads the detached signature of a published package
+    pub async fn publish_signature(
+        &self,
+        package: &Package,
+        repository: &str,
+        extension: &str,
+        signature: Bytes,
+    ) -> miette::Result<()> {
+        let url = self.signature_url(repository, package.name(), package.version(), extension)?;
 
         let _ = self
-            .new_request(Method::PUT, artifact_uri)
-            .body(package.tgz.clone())
+            .new_request(Method::PUT, url)
+            .body(signature)
             .send()
             .await?;
 
         tracing::info!(
-            ":: published {}/{}@{}",
+            ":: published signature of {}/{}@{}",
             repository,
             package.name(),
             package.version()
@@ -247,13 +909,39 @@ impl Artifactory {
 
         Ok(())
     }
+
+    /// Downloads the detached signature of a package, if it was signed
+    pub async fn download_signature(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        version: &Version,
+        extension: &str,
+    ) -> miette::Result<Option<Bytes>> {
+        let url = self.signature_url(repository, name, version, extension)?;
+
+        let response = self.new_request(Method::GET, url).execute().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = ValidatedResponse::try_from(response)?;
+
+        response.0.bytes().await.into_diagnostic().map(Some)
+    }
 }
 
-struct RequestBuilder(reqwest::RequestBuilder);
+struct RequestBuilder(reqwest::RequestBuilder, u32);
 
 impl RequestBuilder {
     fn new(client: reqwest::Client, method: reqwest::Method, url: Url) -> Self {
-        Self(client.request(method, url))
+        Self(client.request(method, url), 0)
+    }
+
+    fn retries(mut self, retries: u32) -> Self {
+        self.1 = retries;
+        self
     }
 
     fn auth(mut self, token: String) -> Self {
@@ -267,7 +955,53 @@ impl RequestBuilder {
     }
 
     async fn send(self) -> miette::Result<ValidatedResponse> {
-        self.0.send().await.into_diagnostic()?.try_into()
+        self.execute().await?.try_into()
+    }
+
+    /// Sends the request, retrying connection and server errors with an exponential backoff
+    async fn execute(self) -> miette::Result<Response> {
+        let Self(request, retries) = self;
+
+        for attempt in 0..retries {
+            // requests with streamed bodies can not be repeated
+            let Some(retry) = request.try_clone() else {
+                break;
+            };
+
+            match retry.send().await {
+                Ok(response) if !response.status().is_server_error() => return Ok(response),
+                Ok(response) => tracing::debug!("request failed with {}", response.status()),
+                Err(error) if error.is_connect() || error.is_timeout() => {
+                    tracing::debug!("request failed: {error}")
+                }
+                Err(error) => return Err(RegistryUnreachable(error).into()),
+            }
+
+            tokio::time::sleep(Duration::from_millis(250 << attempt.min(5))).await;
+        }
+
+        Ok(request.send().await.map_err(RegistryUnreachable)?)
+    }
+}
+
+/// File names of the artifacts published for packages of a repository, see
+/// [`Artifactory::published_artifacts`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PublishedArtifacts(BTreeMap<PackageName, BTreeSet<String>>);
+
+impl PublishedArtifacts {
+    /// Whether the pinned version of a dependency was published in chunks
+    ///
+    /// Returns `None` for packages that were not listed.
+    pub fn is_chunked(&self, dependency: &Dependency) -> Option<bool> {
+        let files = self.0.get(&dependency.package)?;
+        let version = super::dependency_version_string(dependency).ok()?;
+
+        Some(files.contains(&format!(
+            "{}-{version}.tgz.{}",
+            dependency.package,
+            ChunkIndex::EXTENSION
+        )))
     }
 }
 
@@ -284,13 +1018,37 @@ impl TryFrom<Response> for ValidatedResponse {
 
         ensure!(
             value.status() != 401,
-            "unauthorized - please provide registry credentials with `buffrs login`"
+            Unauthorized {
+                message: "unauthorized - please provide registry credentials with `buffrs login`"
+                    .to_owned(),
+                help: None,
+            }
+        );
+
+        ensure!(
+            value.status() != 404,
+            NotFound(
+                "not found - please check the registry URL, repository and package name".to_owned()
+            )
         );
 
         value.error_for_status().into_diagnostic().map(Self)
     }
 }
 
+/// Splits the file name of a published package, e.g. `physics-units-1.0.0-rc.1`, into the
+/// package name and version
+fn parse_artifact_name(artifact: &str) -> Option<(PackageName, Version)> {
+    // names and pre-release versions may both contain dashes, the version starts at the first
+    // dash followed by a valid version
+    artifact.match_indices('-').find_map(|(index, _)| {
+        let version = Version::parse(&artifact[index + 1..]).ok()?;
+        let name = PackageName::new(&artifact[..index]).ok()?;
+
+        Some((name, version))
+    })
+}
+
 #[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
 struct ArtifactSearchResponse {
     results: Vec<ArtifactSearchResult>,
@@ -300,3 +1058,99 @@ struct ArtifactSearchResponse {
 struct ArtifactSearchResult {
     uri: String,
 }
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+struct AqlResponse {
+    results: Vec<AqlItem>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+struct AqlItem {
+    path: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+struct StorageInfo {
+    created: String,
+    #[serde(default)]
+    checksums: StorageChecksums,
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+struct StorageChecksums {
+    sha256: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_artifact_names() {
+        let parse = |artifact| {
+            parse_artifact_name(artifact).map(|(name, version)| (name.to_string(), version))
+        };
+
+        assert_eq!(
+            parse("physics-units-1.0.0"),
+            Some(("physics-units".into(), Version::new(1, 0, 0)))
+        );
+        assert_eq!(
+            parse("physics-1.0.0-rc.1"),
+            Some(("physics".into(), Version::parse("1.0.0-rc.1").unwrap()))
+        );
+        assert_eq!(parse("physics"), None);
+        assert_eq!(parse("physics-latest"), None);
+    }
+
+    #[test]
+    fn detects_chunked_artifacts() {
+        let registry: RegistryUri = "https://registry.example.com/artifactory".parse().unwrap();
+        let dependency = |name: &str, version: &str| {
+            Dependency::new(
+                &registry,
+                "protos".to_owned(),
+                name.parse().unwrap(),
+                semver::VersionReq::STAR,
+            )
+            .with_version(&Version::parse(version).unwrap())
+        };
+
+        let artifacts = PublishedArtifacts(BTreeMap::from([(
+            "physics".parse().unwrap(),
+            BTreeSet::from([
+                "physics-1.0.0.tgz".to_owned(),
+                "physics-2.0.0.tgz.chunks.json".to_owned(),
+            ]),
+        )]));
+
+        assert_eq!(
+            artifacts.is_chunked(&dependency("physics", "1.0.0")),
+            Some(false)
+        );
+        assert_eq!(
+            artifacts.is_chunked(&dependency("physics", "2.0.0")),
+            Some(true)
+        );
+        assert_eq!(artifacts.is_chunked(&dependency("units", "1.0.0")), None);
+    }
+
+    #[test]
+    fn parses_storage_checksums() {
+        let info: StorageInfo = serde_json::from_str(
+            r#"{
+                "created": "2024-01-01T00:00:00.000Z",
+                "checksums": { "sha1": "a9993e36", "sha256": "ba7816bf" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(info.checksums.sha256.as_deref(), Some("ba7816bf"));
+
+        let info: StorageInfo =
+            serde_json::from_str(r#"{ "created": "2024-01-01T00:00:00.000Z" }"#).unwrap();
+
+        assert_eq!(info.checksums, StorageChecksums::default());
+    }
+}
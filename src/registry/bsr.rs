@@ -0,0 +1,468 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Client of Buf Schema Registries, for depending on modules hosted there
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::Path,
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use miette::{bail, ensure, miette, Context, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    config::Network,
+    errors::{NotFound, Unauthorized},
+    package::{name_of, PackageName},
+};
+
+/// Environment variable holding the token for Buf Schema Registries, shared with the buf CLI
+pub const BUF_TOKEN_VAR: &str = "BUF_TOKEN";
+
+/// Directory of the project the packages pulled from Buf Schema Registries are stored in
+pub const BSR_DIR: &str = "bsr";
+
+/// Index of the pulled packages, within [`BSR_DIR`]
+pub const MODULES_FILE: &str = "modules.toml";
+
+/// A module hosted on a Buf Schema Registry, e.g. `buf.build/googleapis/googleapis:main`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ModuleRef {
+    /// Host of the registry, e.g. `buf.build`
+    pub remote: String,
+    /// Owner of the module, a user or organization
+    pub owner: String,
+    /// Name of the module
+    pub module: String,
+    /// Commit or label of the module, its default label if omitted
+    pub reference: Option<String>,
+}
+
+impl ModuleRef {
+    /// Full name of the module, without the reference
+    pub fn name(&self) -> String {
+        format!("{}/{}/{}", self.remote, self.owner, self.module)
+    }
+
+    /// The same module at another reference
+    pub fn at(&self, reference: impl Into<String>) -> Self {
+        Self {
+            reference: Some(reference.into()),
+            ..self.clone()
+        }
+    }
+}
+
+impl FromStr for ModuleRef {
+    type Err = miette::Report;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, reference) = match value.split_once(':') {
+            Some((name, reference)) => (name, Some(reference.to_owned())),
+            None => (value, None),
+        };
+
+        let segments: Vec<_> = name.split('/').collect();
+
+        ensure!(
+            segments.len() == 3 && segments.iter().all(|segment| !segment.is_empty()),
+            help = "modules are referenced as <remote>/<owner>/<module>[:<reference>]",
+            "invalid module reference `{value}`"
+        );
+
+        ensure!(
+            reference.as_deref() != Some(""),
+            "invalid module reference `{value}`: the reference is empty"
+        );
+
+        Ok(Self {
+            remote: segments[0].to_owned(),
+            owner: segments[1].to_owned(),
+            module: segments[2].to_owned(),
+            reference,
+        })
+    }
+}
+
+impl Display for ModuleRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())?;
+
+        if let Some(reference) = &self.reference {
+            write!(f, ":{reference}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Paths of the protos of a package, relative to the package, and their contents
+pub type PackageFiles<'a> = Vec<(&'a str, &'a [u8])>;
+
+/// Protos of a module at a commit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleContents {
+    /// Commit the protos were taken from
+    pub commit: String,
+    /// Paths of the protos within the module, and their contents
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl ModuleContents {
+    /// Splits the protos into packages named like their top-level directories
+    ///
+    /// Paths of the protos are relative to the top-level directory, so the imports of the module
+    /// resolve once the packages are installed.
+    pub fn packages(&self) -> miette::Result<BTreeMap<PackageName, PackageFiles<'_>>> {
+        let mut packages: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+        for (path, contents) in &self.files {
+            let Some((dir, file)) = path.split_once('/') else {
+                bail!(
+                    help = "buffrs packages are imported by their name, which is taken from the \
+                            top-level directories of modules",
+                    "{path} is not within a directory"
+                );
+            };
+
+            let name =
+                name_of(dir).ok_or(miette!("the directory `{dir}` is not a valid package name"))?;
+
+            packages
+                .entry(name)
+                .or_default()
+                .push((file, contents.as_slice()));
+        }
+
+        Ok(packages)
+    }
+}
+
+/// A package created from a module of a Buf Schema Registry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PulledPackage {
+    /// Name of the package
+    pub package: PackageName,
+    /// Name of the module, e.g. `buf.build/googleapis/googleapis`
+    pub module: String,
+    /// Commit the package was pulled at
+    pub commit: String,
+}
+
+/// Index of the packages pulled from Buf Schema Registries
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PulledPackages {
+    /// The pulled packages
+    #[serde(default)]
+    pub packages: Vec<PulledPackage>,
+}
+
+impl PulledPackages {
+    /// Reads the index of a directory, which is empty if it does not exist yet
+    pub async fn read(dir: &Path) -> miette::Result<Self> {
+        let path = dir.join(MODULES_FILE);
+
+        match fs::read_to_string(&path).await {
+            Ok(contents) => toml::from_str(&contents)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to parse {}", path.display())),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to read {}", path.display())),
+        }
+    }
+
+    /// Writes the index into a directory
+    pub async fn write(&self, dir: &Path) -> miette::Result<()> {
+        let path = dir.join(MODULES_FILE);
+        let contents = toml::to_string_pretty(self).into_diagnostic()?;
+
+        fs::write(&path, contents)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write {}", path.display()))
+    }
+
+    /// The pulled package of the given name
+    pub fn get(&self, package: &PackageName) -> Option<&PulledPackage> {
+        self.packages
+            .iter()
+            .find(|pulled| &pulled.package == package)
+    }
+
+    /// Records a pulled package, replacing a previous pull
+    pub fn insert(&mut self, pulled: PulledPackage) {
+        self.packages
+            .retain(|other| other.package != pulled.package);
+        self.packages.push(pulled);
+        self.packages.sort_by(|a, b| a.package.cmp(&b.package));
+    }
+}
+
+/// Client of a Buf Schema Registry
+///
+/// Requests are authenticated with the token in [`BUF_TOKEN_VAR`], if set.
+#[derive(Debug, Clone)]
+pub struct Bsr {
+    client: reqwest::Client,
+    token: Option<String>,
+}
+
+impl Bsr {
+    /// Creates a client sending its requests through the configured proxy
+    pub fn new(network: &Network) -> miette::Result<Self> {
+        let mut client = reqwest::Client::builder();
+
+        if let Some(proxy) = &network.proxy {
+            client = client.proxy(
+                reqwest::Proxy::all(proxy)
+                    .into_diagnostic()
+                    .wrap_err(miette!("invalid proxy: {proxy}"))?,
+            );
+        }
+
+        Ok(Self {
+            client: client.build().into_diagnostic()?,
+            token: std::env::var(BUF_TOKEN_VAR).ok(),
+        })
+    }
+
+    /// Downloads the protos of a module
+    #[tracing::instrument(name = "registry.bsr.download", skip_all, fields(module = %module))]
+    pub async fn download(&self, module: &ModuleRef) -> miette::Result<ModuleContents> {
+        let url = format!(
+            "https://{}/buf.registry.module.v1.DownloadService/Download",
+            module.remote
+        );
+
+        let request = DownloadRequest {
+            values: vec![DownloadValue {
+                resource_ref: ResourceRef {
+                    name: ResourceName {
+                        owner: module.owner.clone(),
+                        module: module.module.clone(),
+                        reference: module.reference.clone(),
+                    },
+                },
+            }],
+        };
+
+        let mut builder = self
+            .client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("Connect-Protocol-Version", "1")
+            .body(serde_json::to_vec(&request).into_diagnostic()?);
+
+        if let Some(token) = &self.token {
+            builder = builder.bearer_auth(token);
+        }
+
+        let response = builder
+            .send()
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to reach {}", module.remote))?;
+
+        ensure!(
+            response.status() != 401,
+            Unauthorized {
+                message: format!("unauthorized to download {module}"),
+                help: Some(format!(
+                    "provide a token for the registry in {BUF_TOKEN_VAR}"
+                )),
+            }
+        );
+
+        ensure!(
+            response.status() != 404,
+            NotFound(format!("{module} was not found"))
+        );
+
+        let body = response
+            .error_for_status()
+            .into_diagnostic()?
+            .bytes()
+            .await
+            .into_diagnostic()?;
+
+        let response: DownloadResponse = serde_json::from_slice(&body)
+            .into_diagnostic()
+            .wrap_err(miette!("unexpected response for {module}"))?;
+
+        response.into_contents()
+    }
+}
+
+#[derive(Serialize)]
+struct DownloadRequest {
+    values: Vec<DownloadValue>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadValue {
+    resource_ref: ResourceRef,
+}
+
+#[derive(Serialize)]
+struct ResourceRef {
+    name: ResourceName,
+}
+
+#[derive(Serialize)]
+struct ResourceName {
+    owner: String,
+    module: String,
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    reference: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DownloadResponse {
+    contents: Vec<DownloadContent>,
+}
+
+#[derive(Deserialize)]
+struct DownloadContent {
+    commit: Commit,
+    #[serde(default)]
+    files: Vec<File>,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct File {
+    path: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl DownloadResponse {
+    fn into_contents(self) -> miette::Result<ModuleContents> {
+        let content = self
+            .contents
+            .into_iter()
+            .next()
+            .ok_or(miette!("the registry returned no contents"))?;
+
+        let files = content
+            .files
+            .into_iter()
+            .filter(|file| file.path.ends_with(".proto"))
+            .map(|file| {
+                let contents = STANDARD
+                    .decode(&file.content)
+                    .into_diagnostic()
+                    .wrap_err(miette!("invalid contents of {}", file.path))?;
+
+                Ok((file.path, contents))
+            })
+            .collect::<miette::Result<_>>()?;
+
+        Ok(ModuleContents {
+            commit: content.commit.id,
+            files,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_module_refs() {
+        let module: ModuleRef = "buf.build/googleapis/googleapis:main".parse().unwrap();
+
+        assert_eq!(module.name(), "buf.build/googleapis/googleapis");
+        assert_eq!(module.reference.as_deref(), Some("main"));
+        assert_eq!(module.to_string(), "buf.build/googleapis/googleapis:main");
+
+        let module: ModuleRef = "buf.build/acme/units".parse().unwrap();
+
+        assert_eq!(module.reference, None);
+        assert_eq!(
+            module.at("0123abcd").to_string(),
+            "buf.build/acme/units:0123abcd"
+        );
+
+        assert!("buf.build/acme".parse::<ModuleRef>().is_err());
+        assert!("buf.build//units".parse::<ModuleRef>().is_err());
+        assert!("buf.build/acme/units:".parse::<ModuleRef>().is_err());
+    }
+
+    #[test]
+    fn splits_packages() {
+        let contents = ModuleContents {
+            commit: "0123abcd".to_owned(),
+            files: vec![
+                ("google/api/http.proto".to_owned(), b"a".to_vec()),
+                ("google/type/date.proto".to_owned(), b"b".to_vec()),
+                ("grafeas/v1/grafeas.proto".to_owned(), b"c".to_vec()),
+            ],
+        };
+
+        let packages = contents.packages().unwrap();
+        let google: PackageName = "google".parse().unwrap();
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(
+            packages[&google],
+            vec![
+                ("api/http.proto", &b"a"[..]),
+                ("type/date.proto", &b"b"[..])
+            ]
+        );
+
+        let contents = ModuleContents {
+            commit: "0123abcd".to_owned(),
+            files: vec![("units.proto".to_owned(), b"a".to_vec())],
+        };
+
+        assert!(contents.packages().is_err());
+    }
+
+    #[test]
+    fn decodes_downloads() {
+        let response: DownloadResponse = serde_json::from_str(
+            r#"{"contents":[{"commit":{"id":"0123abcd","ownerId":"1"},"files":[
+                {"path":"google/type/date.proto","content":"c3ludGF4ID0gInByb3RvMyI7"},
+                {"path":"LICENSE","content":"TUlU"}
+            ]}]}"#,
+        )
+        .unwrap();
+
+        let contents = response.into_contents().unwrap();
+
+        assert_eq!(contents.commit, "0123abcd");
+        assert_eq!(
+            contents.files,
+            vec![(
+                "google/type/date.proto".to_owned(),
+                b"syntax = \"proto3\";".to_vec()
+            )]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use miette::Diagnostic;
+use semver::{Version, VersionReq};
+use thiserror::Error;
+
+use super::{Artifactory, RegistryUri};
+use crate::{
+    config::Config,
+    credentials::Credentials,
+    manifest::Dependency,
+    package::{Package, PackageName},
+    signing::TrustPolicy,
+    Result,
+};
+
+/// Errors that can occur while talking to a registry
+#[derive(Error, Diagnostic, Debug)]
+pub enum RegistryError {
+    /// The client could not be set up, e.g. because of unreadable credentials or an invalid proxy
+    #[error("failed to set up the client of registry {0}")]
+    Setup(RegistryUri, #[diagnostic_source] miette::Report),
+    /// A package could not be downloaded
+    #[error("failed to download {0}@{1}")]
+    Download(PackageName, Version, #[diagnostic_source] miette::Report),
+    /// The signature of a downloaded package does not satisfy the trust policy of the registry
+    #[error("failed to verify the signature of {0}@{1}")]
+    Signature(PackageName, Version, #[diagnostic_source] miette::Report),
+    /// A package could not be published
+    #[error("failed to publish {0}@{1}")]
+    Publish(PackageName, Version, #[diagnostic_source] miette::Report),
+    /// The published versions of a package could not be retrieved
+    #[error("failed to retrieve the versions of {0}")]
+    Versions(PackageName, #[diagnostic_source] miette::Report),
+}
+
+/// Client of a package registry, for embedding buffrs operations into other tools
+///
+/// Registry aliases, credentials, the network and encryption settings and the signature
+/// requirements of the configuration are applied the same way the CLI applies them.
+///
+/// ```rust,no_run
+/// # async fn example() -> miette::Result<()> {
+/// use buffrs::{config::Config, registry::Registry};
+///
+/// let config = Config::new(None)?;
+/// let registry = Registry::open(&"https://example.com/artifactory".parse()?, &config).await?;
+///
+/// let name = "units".parse()?;
+/// let latest = registry.latest_version("protos", &name).await?;
+/// let package = registry.download("protos", &name, &latest).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Registry {
+    uri: RegistryUri,
+    client: Artifactory,
+    policy: Option<TrustPolicy>,
+}
+
+impl Registry {
+    /// Opens a registry, resolving aliases and loading credentials
+    pub async fn open(uri: &RegistryUri, config: &Config) -> Result<Self> {
+        let setup = |error| RegistryError::Setup(uri.clone(), error);
+
+        let uri = config.resolve_registry_uri(uri).map_err(setup)?;
+        let credentials = Credentials::load().await.map_err(setup)?;
+
+        let client = Artifactory::new(&uri, &credentials)
+            .and_then(|client| client.with_network(config.network()))
+            .map(|client| client.with_encryption(config.encryption(&uri)))
+            .map_err(setup)?;
+
+        let policy = config.trust_policy(&uri).cloned();
+
+        Ok(Self {
+            uri,
+            client,
+            policy,
+        })
+    }
+
+    /// The URI of the registry, with aliases resolved
+    pub fn uri(&self) -> &RegistryUri {
+        &self.uri
+    }
+
+    /// Downloads a published version of a package
+    ///
+    /// If the configuration declares signature requirements for the registry, the signature of
+    /// the package is verified against them like on installation.
+    pub async fn download(
+        &self,
+        repository: &str,
+        name: &PackageName,
+        version: &Version,
+    ) -> Result<Package> {
+        let dependency = Dependency::new(
+            &self.uri,
+            repository.to_owned(),
+            name.clone(),
+            VersionReq::STAR,
+        )
+        .with_version(version);
+
+        let package = self
+            .client
+            .download(dependency)
+            .await
+            .map_err(|error| RegistryError::Download(name.clone(), version.clone(), error))?;
+
+        if let Some(policy) = &self.policy {
+            let verified = async {
+                let signature = self
+                    .client
+                    .download_signature(repository, name, version, policy.extension())
+                    .await?;
+
+                policy.verify(&package, signature).await
+            };
+
+            verified
+                .await
+                .map_err(|error| RegistryError::Signature(name.clone(), version.clone(), error))?;
+        }
+
+        Ok(package)
+    }
+
+    /// Retrieves the highest published version of a package
+    pub async fn latest_version(&self, repository: &str, name: &PackageName) -> Result<Version> {
+        let version = self
+            .client
+            .get_latest_version(repository.to_owned(), name.clone())
+            .await
+            .map_err(|error| RegistryError::Versions(name.clone(), error))?;
+
+        Ok(version)
+    }
+
+    /// Publishes a package to a repository of the registry
+    pub async fn publish(&self, package: Package, repository: &str) -> Result<()> {
+        let (name, version) = (package.name().clone(), package.version().clone());
+
+        self.client
+            .publish(package, repository.to_owned())
+            .await
+            .map_err(|error| RegistryError::Publish(name, version, error))?;
+
+        Ok(())
+    }
+}
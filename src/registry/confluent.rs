@@ -0,0 +1,352 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Client of Confluent schema registries, for registering the messages of published packages
+
+use std::collections::BTreeMap;
+
+use miette::{bail, ensure, miette, Context, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{config::Network, errors::Unauthorized, package::PackageName};
+
+/// Environment variable holding the credentials for the schema registry, as `<key>:<secret>`
+pub const SCHEMA_REGISTRY_AUTH_VAR: &str = "SCHEMA_REGISTRY_AUTH";
+
+/// Content type of requests to and responses from schema registries
+const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+/// Directory of the well-known types, which schema registries provide without references
+const WELL_KNOWN: &str = "google/protobuf/";
+
+/// A proto file registered as a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaFile {
+    /// Contents of the file
+    pub contents: String,
+    /// Protobuf package declared by the file
+    pub package: String,
+    /// Names of the top-level messages declared by the file
+    pub messages: Vec<String>,
+    /// Import paths of the files imported by the file
+    pub imports: Vec<String>,
+}
+
+impl SchemaFile {
+    /// Whether the file declares a message, given by its fully qualified name
+    pub fn declares(&self, message: &str) -> bool {
+        let name = match self.package.as_str() {
+            "" => Some(message),
+            package => message
+                .strip_prefix(package)
+                .and_then(|name| name.strip_prefix('.')),
+        };
+
+        // nested messages are declared by the file declaring their top-level message
+        name.and_then(|name| name.split('.').next())
+            .is_some_and(|name| self.messages.iter().any(|message| message == name))
+    }
+}
+
+/// Names the subject of a message from a template
+///
+/// `{message}` is replaced by the fully qualified name of the message and `{package}` by the
+/// name of the buffrs package declaring it, e.g. `{message}-value`.
+pub fn subject(template: &str, message: &str, package: &PackageName) -> String {
+    template
+        .replace("{message}", message)
+        .replace("{package}", package)
+}
+
+/// Orders the files imported by a file, directly or transitively, so that every file comes after
+/// the files it imports
+///
+/// Well-known types and files not contained in `files` are left out.
+pub fn import_order(files: &BTreeMap<String, SchemaFile>, file: &str) -> Vec<String> {
+    fn visit(files: &BTreeMap<String, SchemaFile>, file: &str, order: &mut Vec<String>) {
+        let Some(schema) = files.get(file) else {
+            return;
+        };
+
+        for import in &schema.imports {
+            if import.starts_with(WELL_KNOWN) || !files.contains_key(import) {
+                continue;
+            }
+
+            if !order.contains(import) {
+                visit(files, import, order);
+                order.push(import.clone());
+            }
+        }
+    }
+
+    let mut order = Vec::new();
+
+    visit(files, file, &mut order);
+
+    order
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct SchemaReference {
+    name: String,
+    subject: String,
+    version: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterRequest<'a> {
+    schema_type: &'static str,
+    schema: &'a str,
+    references: Vec<SchemaReference>,
+}
+
+#[derive(Deserialize)]
+struct LookupResponse {
+    version: u32,
+}
+
+/// Client of a Confluent schema registry
+///
+/// Requests are authenticated with the credentials in [`SCHEMA_REGISTRY_AUTH_VAR`], if set.
+#[derive(Debug, Clone)]
+pub struct SchemaRegistry {
+    client: reqwest::Client,
+    url: Url,
+    auth: Option<(String, String)>,
+}
+
+impl SchemaRegistry {
+    /// Creates a client sending its requests through the configured proxy
+    pub fn new(url: &Url, network: &Network) -> miette::Result<Self> {
+        let mut client = reqwest::Client::builder();
+
+        if let Some(proxy) = &network.proxy {
+            client = client.proxy(
+                reqwest::Proxy::all(proxy)
+                    .into_diagnostic()
+                    .wrap_err(miette!("invalid proxy: {proxy}"))?,
+            );
+        }
+
+        let auth = match std::env::var(SCHEMA_REGISTRY_AUTH_VAR) {
+            Ok(auth) => {
+                let (key, secret) = auth.split_once(':').ok_or(miette!(
+                    "{SCHEMA_REGISTRY_AUTH_VAR} must have the format <key>:<secret>"
+                ))?;
+
+                Some((key.to_owned(), secret.to_owned()))
+            }
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            client: client.build().into_diagnostic()?,
+            url: url.clone(),
+            auth,
+        })
+    }
+
+    /// Registers the file declaring a message under a subject and returns the registered version
+    ///
+    /// The files it imports are registered first, each under its import path as subject, and
+    /// referenced by the files importing them.
+    #[tracing::instrument(name = "registry.confluent.register", skip_all, fields(subject = %subject))]
+    pub async fn register(
+        &self,
+        files: &BTreeMap<String, SchemaFile>,
+        message: &str,
+        subject: &str,
+    ) -> miette::Result<u32> {
+        let Some((file, _)) = files.iter().find(|(_, file)| file.declares(message)) else {
+            bail!("no file of the package declares the message {message}");
+        };
+
+        let mut versions = BTreeMap::new();
+
+        for import in import_order(files, file) {
+            let version = self
+                .register_schema(&import, &files[&import], &versions)
+                .await?;
+
+            versions.insert(import, version);
+        }
+
+        self.register_schema(subject, &files[file], &versions).await
+    }
+
+    /// Registers a single file, referencing the versions its imports were registered at
+    async fn register_schema(
+        &self,
+        subject: &str,
+        file: &SchemaFile,
+        versions: &BTreeMap<String, u32>,
+    ) -> miette::Result<u32> {
+        let references = file
+            .imports
+            .iter()
+            .filter_map(|import| {
+                versions.get(import).map(|version| SchemaReference {
+                    name: import.clone(),
+                    subject: import.clone(),
+                    version: *version,
+                })
+            })
+            .collect();
+
+        let request = RegisterRequest {
+            schema_type: "PROTOBUF",
+            schema: &file.contents,
+            references,
+        };
+
+        let body = serde_json::to_vec(&request).into_diagnostic()?;
+
+        // registering an already registered schema is a no-op, the lookup yields its version
+        self.post(subject, &["versions"], &body)
+            .await
+            .wrap_err(miette!("failed to register {subject}"))?;
+
+        let response = self.post(subject, &[], &body).await.wrap_err(miette!(
+            "failed to look up the registered version of {subject}"
+        ))?;
+
+        let response: LookupResponse = serde_json::from_slice(&response)
+            .into_diagnostic()
+            .wrap_err(miette!("unexpected response for {subject}"))?;
+
+        Ok(response.version)
+    }
+
+    /// Posts a request to an endpoint of a subject and returns the response body
+    async fn post(&self, subject: &str, path: &[&str], body: &[u8]) -> miette::Result<Vec<u8>> {
+        let mut url = self.url.clone();
+
+        url.path_segments_mut()
+            .map_err(|_| miette!("{} can not be used as a base URL", self.url))?
+            .pop_if_empty()
+            .extend(["subjects", subject])
+            .extend(path);
+
+        let mut builder = self
+            .client
+            .post(url)
+            .header("Content-Type", CONTENT_TYPE)
+            .header("Accept", CONTENT_TYPE)
+            .body(body.to_vec());
+
+        if let Some((key, secret)) = &self.auth {
+            builder = builder.basic_auth(key, Some(secret));
+        }
+
+        let response = builder
+            .send()
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to reach {}", self.url))?;
+
+        ensure!(
+            response.status() != 401 && response.status() != 403,
+            Unauthorized {
+                message: format!("unauthorized to register {subject}"),
+                help: Some(format!(
+                    "provide credentials for the schema registry in {SCHEMA_REGISTRY_AUTH_VAR}"
+                )),
+            }
+        );
+
+        ensure!(
+            response.status() != 409,
+            "the schema is incompatible with the versions registered for {subject}"
+        );
+
+        let body = response
+            .error_for_status()
+            .into_diagnostic()?
+            .bytes()
+            .await
+            .into_diagnostic()?;
+
+        Ok(body.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(package: &str, messages: &[&str], imports: &[&str]) -> SchemaFile {
+        SchemaFile {
+            contents: String::new(),
+            package: package.to_owned(),
+            messages: messages.iter().map(|name| name.to_string()).collect(),
+            imports: imports.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn finds_declared_messages() {
+        let file = file("physics.units", &["Mass"], &[]);
+
+        assert!(file.declares("physics.units.Mass"));
+        assert!(file.declares("physics.units.Mass.Unit"));
+        assert!(!file.declares("physics.units.Length"));
+        assert!(!file.declares("physics.Mass"));
+        assert!(!file.declares("physics.unitsMass"));
+    }
+
+    #[test]
+    fn names_subjects() {
+        let package: PackageName = "physics".parse().unwrap();
+
+        assert_eq!(
+            subject("{message}-value", "physics.units.Mass", &package),
+            "physics.units.Mass-value"
+        );
+        assert_eq!(
+            subject("{package}.{message}", "physics.units.Mass", &package),
+            "physics.physics.units.Mass"
+        );
+    }
+
+    #[test]
+    fn orders_imports() {
+        let files = BTreeMap::from([
+            (
+                "physics/mass.proto".to_owned(),
+                file(
+                    "physics",
+                    &["Mass"],
+                    &["units/units.proto", "google/protobuf/timestamp.proto"],
+                ),
+            ),
+            (
+                "units/units.proto".to_owned(),
+                file("units", &["Unit"], &["units/prefix.proto"]),
+            ),
+            (
+                "units/prefix.proto".to_owned(),
+                file("units", &["Prefix"], &[]),
+            ),
+        ]);
+
+        assert_eq!(
+            import_order(&files, "physics/mass.proto"),
+            vec!["units/prefix.proto", "units/units.proto"]
+        );
+        assert!(import_order(&files, "units/prefix.proto").is_empty());
+    }
+}
@@ -18,16 +18,28 @@ use std::{
     str::FromStr,
 };
 
+#[cfg(feature = "native")]
 mod artifactory;
-#[cfg(test)]
+#[cfg(feature = "native")]
+pub mod bsr;
+#[cfg(all(test, feature = "native"))]
 mod cache;
-
-use crate::manifest::Dependency;
-use crate::manifest::DependencyManifest;
-pub use artifactory::Artifactory;
+#[cfg(feature = "native")]
+mod client;
+#[cfg(feature = "native")]
+pub mod confluent;
+
+#[cfg(feature = "native")]
+use crate::manifest::{Dependency, DependencyManifest};
+#[cfg(feature = "native")]
+pub use artifactory::{Artifactory, PublishedArtifacts, MAX_DOWNLOAD_SIZE};
+#[cfg(feature = "native")]
+pub use client::{Registry, RegistryError};
 use miette::{ensure, miette, Context, IntoDiagnostic};
+#[cfg(feature = "native")]
 use semver::VersionReq;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "native")]
 use thiserror::Error;
 use url::Url;
 
@@ -125,14 +137,17 @@ fn sanity_check_url(url: &Url) -> miette::Result<()> {
     }
 }
 
+#[cfg(feature = "native")]
 #[derive(Error, Debug)]
 #[error("{0} is not a supported version requirement")]
 struct UnsupportedVersionRequirement(VersionReq);
 
+#[cfg(feature = "native")]
 #[derive(Error, Debug)]
 #[error("{0} is not supported yet. Pin the exact version you want to use with '='. For example: '=1.0.4' instead of '^1.0.0'")]
 struct VersionNotPinned(VersionReq);
 
+#[cfg(feature = "native")]
 fn dependency_version_string(dependency: &Dependency) -> miette::Result<String> {
     let DependencyManifest::Remote(ref manifest) = dependency.manifest else {
         return Err(miette!(
@@ -174,7 +189,7 @@ fn dependency_version_string(dependency: &Dependency) -> miette::Result<String>
     ))
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 mod tests {
     use std::str::FromStr;
 
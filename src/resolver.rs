@@ -1,23 +1,38 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+#[cfg(feature = "native")]
+use std::{collections::BTreeSet, sync::Arc};
+use std::{collections::HashMap, path::PathBuf};
 
-use async_recursion::async_recursion;
-use miette::{bail, ensure, Context, Diagnostic};
+use miette::{bail, ensure};
+#[cfg(feature = "native")]
+use miette::{Context, Diagnostic};
 use semver::VersionReq;
+#[cfg(feature = "native")]
 use thiserror::Error;
 
+#[cfg(feature = "native")]
 use crate::{
     cache::{Cache, Entry},
+    cancel::{Cancel, Cancelled},
+    config::Config,
     credentials::Credentials,
-    lock::{FileRequirement, Lockfile},
+    manifest::MANIFEST_FILE,
+    package::PackageStore,
+    progress::{Event, EventSink},
+    registry::{Artifactory, PublishedArtifacts},
+    vendor::Vendor,
+};
+use crate::{
+    errors::Conflict,
+    lock::Lockfile,
     manifest::{
-        Dependency, DependencyManifest, LocalDependencyManifest, Manifest,
-        RemoteDependencyManifest, MANIFEST_FILE,
+        Dependency, DependencyManifest, LocalDependencyManifest, Manifest, RemoteDependencyManifest,
     },
-    package::{Package, PackageName, PackageStore},
-    registry::{Artifactory, RegistryUri},
+    package::{Package, PackageName},
+    registry::RegistryUri,
 };
 
 /// Represents a dependency contextualized by the current dependency graph
+#[derive(Debug)]
 pub enum ResolvedDependency {
     /// A resolved dependency that is located on a remote registry
     Remote {
@@ -62,6 +77,7 @@ impl ResolvedDependency {
 }
 
 /// Represents a requester of the associated dependency
+#[derive(Debug)]
 pub struct Dependant {
     /// Package that requested the dependency
     pub name: PackageName,
@@ -70,16 +86,19 @@ pub struct Dependant {
 }
 
 /// Represents direct and transitive dependencies of the root package
+#[derive(Debug)]
 pub struct DependencyGraph {
     entries: HashMap<PackageName, ResolvedDependency>,
 }
 
+#[cfg(feature = "native")]
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct RemoteDependency {
     package: PackageName,
     manifest: RemoteDependencyManifest,
 }
 
+#[cfg(feature = "native")]
 impl From<RemoteDependency> for Dependency {
     fn from(value: RemoteDependency) -> Self {
         Dependency {
@@ -89,12 +108,7 @@ impl From<RemoteDependency> for Dependency {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
-struct LocalDependency {
-    package: PackageName,
-    manifest: LocalDependencyManifest,
-}
-
+#[cfg(feature = "native")]
 #[derive(Error, Diagnostic, Debug)]
 #[error("failed to download dependency {name}@{version} from the registry")]
 struct DownloadError {
@@ -102,300 +116,634 @@ struct DownloadError {
     version: VersionReq,
 }
 
-impl DependencyGraph {
-    /// Recursively resolves dependencies from the manifest to build a dependency graph
-    pub async fn from_manifest(
-        manifest: &Manifest,
-        lockfile: &Lockfile,
-        credentials: &Arc<Credentials>,
-        cache: &Cache,
-    ) -> miette::Result<Self> {
+/// Package that has to be fetched before a [`Resolution`] can continue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// A package published to a registry, at a version matching the requirement
+    Remote {
+        /// Name of the package
+        package: PackageName,
+        /// Registry, repository and version requirement of the dependency
+        manifest: RemoteDependencyManifest,
+    },
+    /// A package on the local file system
+    Local {
+        /// Name of the package
+        package: PackageName,
+        /// Location of the package
+        manifest: LocalDependencyManifest,
+    },
+}
+
+/// Dependency waiting to be processed, together with the package depending on it
+struct Pending {
+    dependant: PackageName,
+    dependency: Dependency,
+    is_root: bool,
+}
+
+/// Dependency resolution without any IO
+///
+/// The resolution walks the dependencies of a manifest depth first, ordered by name, and asks for
+/// every package it needs through [`Resolution::next_request`]. Callers fetch the package however
+/// they like, e.g. from a registry, a cache or an in memory fixture, and hand it back through
+/// [`Resolution::provide`]. Conflicting requirements are detected as the packages arrive, so
+/// that the walk itself is a pure function of the manifest, the lockfile and the packages.
+///
+/// ```rust
+/// # use buffrs::{manifest::Manifest, package::Package, resolver::Request};
+/// # fn example(manifest: &Manifest, fetch: impl Fn(&Request) -> Package) -> miette::Result<()> {
+/// use buffrs::{lock::Lockfile, resolver::Resolution};
+///
+/// let lockfile = Lockfile::default();
+/// let mut resolution = Resolution::new(manifest, &lockfile);
+///
+/// while let Some(request) = resolution.next_request()? {
+///     resolution.provide(fetch(&request))?;
+/// }
+///
+/// let graph = resolution.finish();
+/// # Ok(())
+/// # }
+/// ```
+pub struct Resolution<'a> {
+    lockfile: &'a Lockfile,
+    entries: HashMap<PackageName, ResolvedDependency>,
+    /// Dependencies left to process, the last one is processed next
+    pending: Vec<Pending>,
+    /// The last request and the package that caused it
+    requested: Option<(PackageName, Request)>,
+}
+
+impl<'a> Resolution<'a> {
+    /// Starts resolving the dependencies of a manifest
+    pub fn new(manifest: &Manifest, lockfile: &'a Lockfile) -> Self {
         let name = manifest
             .package
             .as_ref()
             .map(|p| p.name.clone())
             .unwrap_or_else(|| PackageName::unchecked("."));
 
-        let mut entries = HashMap::new();
-
-        for dependency in &manifest.dependencies {
-            Self::process_dependency(
-                name.clone(),
-                dependency.clone(),
-                true,
-                lockfile,
-                credentials,
-                cache,
-                &mut entries,
-            )
-            .await?;
+        let pending = Self::sorted(manifest.dependencies.clone())
+            .map(|dependency| Pending {
+                dependant: name.clone(),
+                dependency,
+                is_root: true,
+            })
+            .collect();
+
+        Self {
+            lockfile,
+            entries: HashMap::new(),
+            pending,
+            requested: None,
         }
+    }
 
-        Ok(Self { entries })
+    /// Dependencies in the order they are pushed onto the pending stack, so that they are
+    /// processed by name regardless of the order of the manifest
+    fn sorted(mut dependencies: Vec<Dependency>) -> impl Iterator<Item = Dependency> {
+        dependencies.sort_by(|a, b| a.package.cmp(&b.package));
+        dependencies.into_iter().rev()
     }
 
-    async fn process_dependency(
-        name: PackageName,
-        dependency: Dependency,
-        is_root: bool,
-        lockfile: &Lockfile,
-        credentials: &Arc<Credentials>,
-        cache: &Cache,
-        entries: &mut HashMap<PackageName, ResolvedDependency>,
-    ) -> miette::Result<()> {
-        match dependency.manifest {
-            DependencyManifest::Remote(manifest) => {
-                Self::process_remote_dependency(
-                    name.clone(),
-                    RemoteDependency {
-                        package: dependency.package,
-                        manifest,
-                    },
-                    is_root,
-                    lockfile,
-                    credentials,
-                    cache,
-                    entries,
-                )
-                .await?;
-            }
-            DependencyManifest::Local(manifest) => {
-                Self::process_local_dependency(
-                    name.clone(),
-                    LocalDependency {
+    /// Advances the resolution to the next package that has to be fetched
+    ///
+    /// Returns `None` once all dependencies are resolved. Fails if requirements conflict with
+    /// each other or with the lockfile.
+    pub fn next_request(&mut self) -> miette::Result<Option<Request>> {
+        ensure!(
+            self.requested.is_none(),
+            "the previously requested package was not provided"
+        );
+
+        while let Some(Pending {
+            dependant,
+            dependency,
+            is_root,
+        }) = self.pending.pop()
+        {
+            let request = match dependency.manifest {
+                DependencyManifest::Local(manifest) => Request::Local {
+                    package: dependency.package,
+                    manifest,
+                },
+                DependencyManifest::Remote(manifest) => {
+                    if self.depend(&dependant, &dependency.package, &manifest)? {
+                        continue;
+                    }
+
+                    if let Some(locked) = self.lockfile.get(&dependency.package) {
+                        ensure!(
+                            is_root || manifest.registry == locked.registry,
+                            Conflict(format!(
+                                "mismatched registry detected for dependency {} - requested {} but lockfile requires {}",
+                                dependency.package,
+                                manifest.registry,
+                                locked.registry,
+                            ))
+                        );
+                    }
+
+                    Request::Remote {
                         package: dependency.package,
                         manifest,
-                    },
-                    is_root,
-                    lockfile,
-                    credentials,
-                    cache,
-                    entries,
-                )
-                .await?;
-            }
+                    }
+                }
+            };
+
+            self.requested = Some((dependant, request.clone()));
+
+            return Ok(Some(request));
         }
 
-        Ok(())
+        Ok(None)
     }
 
-    #[async_recursion]
-    async fn process_local_dependency(
-        name: PackageName,
-        dependency: LocalDependency,
-        _: bool,
-        lockfile: &Lockfile,
-        credentials: &Arc<Credentials>,
-        cache: &Cache,
-        entries: &mut HashMap<PackageName, ResolvedDependency>,
-    ) -> miette::Result<()> {
-        let manifest = Manifest::try_read_from(&dependency.manifest.path.join(MANIFEST_FILE))
-            .await?
-            .ok_or_else(|| {
-                miette::miette!(
-                    "no `{}` for package {} found at path {}",
-                    MANIFEST_FILE,
-                    dependency.package,
-                    dependency.manifest.path.join(MANIFEST_FILE).display()
-                )
-            })?;
+    /// Adds a dependant to a package resolved before, returns whether the package was resolved
+    fn depend(
+        &mut self,
+        dependant: &PackageName,
+        package: &PackageName,
+        manifest: &RemoteDependencyManifest,
+    ) -> miette::Result<bool> {
+        let version_req = manifest.version.clone();
+
+        match self.entries.get_mut(package) {
+            None => Ok(false),
+            Some(ResolvedDependency::Local {
+                path, dependants, ..
+            }) => {
+                bail!(Conflict(format!(
+                    "a dependency of your project requires {}@{} which collides with a local dependency for {}@{} required by {:?}",
+                    package,
+                    manifest.version,
+                    package,
+                    path.display(),
+                    dependants[0].name.clone(),
+                )));
+            }
+            Some(ResolvedDependency::Remote {
+                package: resolved,
+                dependants,
+                ..
+            }) => {
+                ensure!(
+                    version_req.matches(resolved.version()),
+                    Conflict(format!(
+                        "a dependency of your project requires {}@{} which collides with {}@{} required by {:?}",
+                        package,
+                        manifest.version,
+                        resolved.name(),
+                        resolved.version(),
+                        dependants[0].name.clone(),
+                    ))
+                );
+
+                dependants.push(Dependant {
+                    name: dependant.clone(),
+                    version_req,
+                });
+
+                Ok(true)
+            }
+        }
+    }
 
-        let store = PackageStore::open(&dependency.manifest.path).await?;
-        let package = store.release(&manifest).await?;
+    /// Continues the resolution with the package fetched for the last request
+    pub fn provide(&mut self, package: Package) -> miette::Result<()> {
+        let Some((dependant, request)) = self.requested.take() else {
+            bail!("no package was requested");
+        };
 
-        let dependency_name = package.name().clone();
+        let name = package.name().clone();
         let sub_dependencies = package.manifest.dependencies.clone();
-        let sub_dependency_names: Vec<_> = sub_dependencies
+        let depends_on = sub_dependencies
             .iter()
             .map(|sub_dependency| sub_dependency.package.clone())
             .collect();
 
-        entries.insert(
-            dependency_name.clone(),
-            ResolvedDependency::Local {
+        if let Request::Remote { manifest, .. } = &request {
+            ensure!(
+                manifest.version.matches(package.version()),
+                Conflict(format!(
+                    "{} requires {}@{} but {}@{} was provided",
+                    dependant,
+                    name,
+                    manifest.version,
+                    name,
+                    package.version(),
+                ))
+            );
+        }
+
+        let resolved = match request {
+            Request::Remote { manifest, .. } => ResolvedDependency::Remote {
                 package,
-                path: dependency.manifest.path,
+                registry: manifest.registry,
+                repository: manifest.repository,
                 dependants: vec![Dependant {
-                    name,
+                    name: dependant,
+                    version_req: manifest.version,
+                }],
+                depends_on,
+            },
+            Request::Local { manifest, .. } => ResolvedDependency::Local {
+                package,
+                path: manifest.path,
+                dependants: vec![Dependant {
+                    name: dependant,
                     version_req: VersionReq::STAR,
                 }],
-                depends_on: sub_dependency_names,
+                depends_on,
             },
-        );
+        };
 
-        for sub_dependency in sub_dependencies {
-            Self::process_dependency(
-                dependency_name.clone(),
-                sub_dependency,
-                false,
-                lockfile,
-                credentials,
-                cache,
-                entries,
-            )
-            .await?;
-        }
+        self.entries.insert(name.clone(), resolved);
+
+        self.pending
+            .extend(Self::sorted(sub_dependencies).map(|dependency| Pending {
+                dependant: name.clone(),
+                dependency,
+                is_root: false,
+            }));
 
         Ok(())
     }
 
-    #[async_recursion]
-    async fn process_remote_dependency(
-        name: PackageName,
-        dependency: RemoteDependency,
-        is_root: bool,
+    /// The dependency graph, once [`Resolution::next_request`] returned `None`
+    pub fn finish(self) -> DependencyGraph {
+        DependencyGraph {
+            entries: self.entries,
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+impl DependencyGraph {
+    /// Recursively resolves dependencies from the manifest to build a dependency graph
+    ///
+    /// Fetches the packages requested by a [`Resolution`] from the cache, the registries and the
+    /// file system.
+    #[tracing::instrument(name = "resolve", skip_all)]
+    pub async fn from_manifest(
+        manifest: &Manifest,
         lockfile: &Lockfile,
         credentials: &Arc<Credentials>,
+        config: &Config,
         cache: &Cache,
-        entries: &mut HashMap<PackageName, ResolvedDependency>,
-    ) -> miette::Result<()> {
-        let version_req = dependency.manifest.version.clone();
-
-        if let Some(entry) = entries.get_mut(&dependency.package) {
-            match entry {
-                ResolvedDependency::Local {
-                    path, dependants, ..
+        events: &dyn EventSink,
+    ) -> miette::Result<Self> {
+        let mut resolution = Resolution::new(manifest, lockfile);
+        let mut catalog = Catalog::new(manifest, lockfile);
+
+        while let Some(request) = resolution.next_request()? {
+            let package = match request {
+                Request::Remote {
+                    ref package,
+                    ref manifest,
                 } => {
-                    bail!(
-                        "a dependency of your project requires {}@{} which collides with a local dependency for {}@{} required by {:?}", 
-                        dependency.package,
-                        dependency.manifest.version,
-                        dependency.package,
-                        path.display(),
-                        dependants[0].name.clone(),
-                    );
+                    let dependency = RemoteDependency {
+                        package: package.clone(),
+                        manifest: manifest.clone(),
+                    };
+
+                    Self::resolve(
+                        dependency,
+                        lockfile,
+                        credentials,
+                        config,
+                        cache,
+                        &mut catalog,
+                        events,
+                    )
+                    .await?
                 }
-                ResolvedDependency::Remote {
-                    package,
-                    dependants,
-                    ..
-                } => {
-                    ensure!(
-                        version_req.matches(package.version()),
-                        "a dependency of your project requires {}@{} which collides with {}@{} required by {:?}",
-                        dependency.package,
-                        dependency.manifest.version,
-                        package.name(),
-                        package.version(),
-                        dependants[0].name.clone(),
-                    );
+                Request::Local {
+                    ref package,
+                    ref manifest,
+                } => Self::release(package, manifest).await?,
+            };
+
+            events.event(Event::Resolved {
+                name: package.name(),
+                version: package.version(),
+            });
+
+            resolution.provide(package)?;
+        }
 
-                    dependants.push(Dependant { name, version_req });
-                }
-            }
-        } else {
-            let dependency_pkg =
-                Self::resolve(dependency.clone(), is_root, lockfile, credentials, cache).await?;
+        Ok(resolution.finish())
+    }
 
-            let dependency_name = dependency_pkg.name().clone();
-            let sub_dependencies = dependency_pkg.manifest.dependencies.clone();
-            let sub_dependency_names: Vec<_> = sub_dependencies
-                .iter()
-                .map(|sub_dependency| sub_dependency.package.clone())
-                .collect();
-
-            entries.insert(
-                dependency_name.clone(),
-                ResolvedDependency::Remote {
-                    package: dependency_pkg,
-                    registry: dependency.manifest.registry,
-                    repository: dependency.manifest.repository,
-                    dependants: vec![Dependant { name, version_req }],
-                    depends_on: sub_dependency_names,
-                },
-            );
+    /// Resolves dependencies exclusively from a vendor directory, without touching the network
+    ///
+    /// Remote dependencies have to be locked at a version matching their requirement and
+    /// vendored at that version.
+    pub async fn from_vendor(
+        manifest: &Manifest,
+        lockfile: &Lockfile,
+        vendor: &Vendor,
+        events: &dyn EventSink,
+    ) -> miette::Result<Self> {
+        let mut resolution = Resolution::new(manifest, lockfile);
 
-            for sub_dependency in sub_dependencies {
-                Self::process_dependency(
-                    dependency_name.clone(),
-                    sub_dependency,
-                    false,
-                    lockfile,
-                    credentials,
-                    cache,
-                    entries,
-                )
-                .await?;
-            }
+        while let Some(request) = resolution.next_request()? {
+            let package = match request {
+                Request::Remote {
+                    ref package,
+                    ref manifest,
+                } => {
+                    let locked = lockfile
+                        .get(package)
+                        .filter(|locked| manifest.version.matches(&locked.version))
+                        .ok_or_else(|| {
+                            miette::miette!(
+                                help = "lock and vendor the dependency with network access",
+                                "{package} is not locked at a version matching {}",
+                                manifest.version
+                            )
+                        })?;
+
+                    vendor.get(locked).await?
+                }
+                Request::Local {
+                    ref package,
+                    ref manifest,
+                } => Self::release(package, manifest).await?,
+            };
+
+            events.event(Event::Resolved {
+                name: package.name(),
+                version: package.version(),
+            });
+
+            resolution.provide(package)?;
         }
 
-        Ok(())
+        Ok(resolution.finish())
+    }
+
+    /// Packages a local dependency from its current state on the file system
+    #[tracing::instrument(name = "resolve.local", skip_all, fields(package = %package))]
+    async fn release(
+        package: &PackageName,
+        manifest: &LocalDependencyManifest,
+    ) -> miette::Result<Package> {
+        let path = manifest.path.join(MANIFEST_FILE);
+
+        let manifest_file = Manifest::try_read_from(&path).await?.ok_or_else(|| {
+            miette::miette!(
+                "no `{}` for package {} found at path {}",
+                MANIFEST_FILE,
+                package,
+                path.display()
+            )
+        })?;
+
+        let store = PackageStore::open(&manifest.path).await?;
+
+        Ok(store.release(&manifest_file).await?)
     }
 
+    #[tracing::instrument(
+        name = "resolve.remote",
+        skip_all,
+        fields(package = %dependency.package, version = %dependency.manifest.version)
+    )]
     async fn resolve(
         dependency: RemoteDependency,
-        is_root: bool,
         lockfile: &Lockfile,
         credentials: &Arc<Credentials>,
+        config: &Config,
         cache: &Cache,
+        catalog: &mut Catalog,
+        events: &dyn EventSink,
     ) -> miette::Result<Package> {
-        if let Some(local_locked) = lockfile.get(&dependency.package) {
-            ensure!(
-                is_root || dependency.manifest.registry == local_locked.registry,
-                "mismatched registry detected for dependency {} - requested {} but lockfile requires {}",
-                    dependency.package,
-                    dependency.manifest.registry,
-                    local_locked.registry,
-            );
-
-            // For now we should only check cache if locked package matches manifest,
-            // but theoretically we should be able to still look into cache when freshly installing
-            // a dependency.
-            if dependency.manifest.version.matches(&local_locked.version) {
-                if let Some(cached) = cache.get(local_locked.into()).await? {
-                    local_locked.validate(&cached)?;
+        // For now we should only check cache if locked package matches manifest, but
+        // theoretically we should be able to still look into cache when freshly installing a
+        // dependency.
+        if let Some(locked) = lockfile.get(&dependency.package) {
+            if dependency.manifest.version.matches(&locked.version) {
+                // validated against the lockfile together with all other packages afterwards
+                if let Some(cached) = cache.get(locked.into()).await? {
                     return Ok(cached);
                 }
             }
+        }
 
-            let registry = Artifactory::new(&dependency.manifest.registry, credentials).wrap_err(
-                DownloadError {
-                    name: dependency.package.clone(),
-                    version: dependency.manifest.version.clone(),
-                },
-            )?;
+        let registry = Artifactory::new(&dependency.manifest.registry, credentials)
+            .and_then(|registry| registry.with_network(config.network()))
+            .map(|registry| {
+                registry.with_encryption(config.encryption(&dependency.manifest.registry))
+            })
+            .wrap_err(DownloadError {
+                name: dependency.package.clone(),
+                version: dependency.manifest.version.clone(),
+            })?;
 
-            let package = registry
-                // TODO(#205): This works now because buffrs only supports pinned versions.
-                // This logic has to change once we implement dynamic version resolution.
-                .download(dependency.clone().into())
-                .await
-                .wrap_err(DownloadError {
-                    name: dependency.package,
-                    version: dependency.manifest.version,
-                })?;
-
-            let file_requirement = FileRequirement::from(local_locked);
-            cache
-                .put(file_requirement.into(), package.tgz.clone())
-                .await
-                .ok();
-
-            Ok(package)
-        } else {
-            let registry = Artifactory::new(&dependency.manifest.registry, credentials).wrap_err(
-                DownloadError {
-                    name: dependency.package.clone(),
-                    version: dependency.manifest.version.clone(),
-                },
-            )?;
+        // TODO(#205): This works now because buffrs only supports pinned versions.
+        // This logic has to change once we implement dynamic version resolution.
+        let package = Self::download(&registry, &dependency, cache, catalog, events)
+            .await
+            .wrap_err(DownloadError {
+                name: dependency.package,
+                version: dependency.manifest.version,
+            })?;
+
+        Ok(package)
+    }
 
-            let package = registry
-                .download(dependency.clone().into())
+    /// Downloads a package and puts it in the cache, fetching only the chunks missing from the
+    /// cache if it was published in chunks
+    ///
+    /// Whole archives are streamed into the cache directory and moved into place once complete,
+    /// so that they are never held in memory more than once.
+    async fn download(
+        registry: &Artifactory,
+        dependency: &RemoteDependency,
+        cache: &Cache,
+        catalog: &mut Catalog,
+        events: &dyn EventSink,
+    ) -> miette::Result<Package> {
+        let name = &dependency.package;
+
+        events.event(Event::DownloadStarted { name });
+
+        let package = Self::fetch(registry, dependency, cache, catalog, events).await?;
+
+        events.event(Event::DownloadFinished { name });
+
+        Ok(package)
+    }
+
+    async fn fetch(
+        registry: &Artifactory,
+        dependency: &RemoteDependency,
+        cache: &Cache,
+        catalog: &mut Catalog,
+        events: &dyn EventSink,
+    ) -> miette::Result<Package> {
+        let name = &dependency.package;
+
+        // spare probing for a chunk index if the listing of the repository shows there is none
+        let index = match catalog.is_chunked(registry, dependency).await {
+            Some(false) => None,
+            _ => {
+                registry
+                    .download_chunk_index(&dependency.clone().into())
+                    .await?
+            }
+        };
+
+        let Some(index) = index else {
+            let file = cache.download().await?;
+
+            let downloaded = registry
+                .download_to(dependency.clone().into(), &file, |bytes| {
+                    events.event(Event::DownloadProgress { name, bytes })
+                })
+                .await;
+
+            let (package, digest) = match downloaded {
+                Ok(downloaded) => downloaded,
+                Err(error) => {
+                    tokio::fs::remove_file(&file).await.ok();
+                    return Err(error);
+                }
+            };
+
+            if cache
+                .adopt(Entry::new(package.name(), &digest), &file)
                 .await
-                .wrap_err(DownloadError {
-                    name: dependency.package,
-                    version: dependency.manifest.version,
-                })?;
+                .is_err()
+            {
+                tokio::fs::remove_file(&file).await.ok();
+            }
+
+            return Ok(package);
+        };
+
+        let mut chunks = Vec::with_capacity(index.chunks.len());
+
+        for digest in &index.chunks {
+            if let Some(chunk) = cache.get_chunk(digest).await {
+                chunks.push(chunk);
+                continue;
+            }
+
+            let chunk = registry
+                .download_chunk(&dependency.manifest.repository, &dependency.package, digest)
+                .await?;
+
+            events.event(Event::DownloadProgress {
+                name,
+                bytes: chunk.len() as u64,
+            });
 
-            let key = Entry::from(&package);
-            let content = package.tgz.clone();
-            cache.put(key, content).await.ok();
+            cache.put_chunk(digest, chunk.clone()).await.ok();
+            chunks.push(chunk);
+        }
+
+        let package = index.assemble(&chunks)?;
+
+        cache
+            .put(Entry::from(&package), package.tgz.clone())
+            .await
+            .ok();
+
+        Ok(package)
+    }
+}
+
+/// Published artifacts of the remote packages a resolution may download
+///
+/// Instead of probing every package for a chunk index, the packages of a repository are listed
+/// with a single query the first time one of them is downloaded.
+#[cfg(feature = "native")]
+#[derive(Debug, Default)]
+struct Catalog {
+    /// Packages of the manifest and lockfile by the registry and repository they come from
+    expected: HashMap<(RegistryUri, String), BTreeSet<PackageName>>,
+    /// Listings of the repositories queried so far, `None` if the registry can not list packages
+    listings: HashMap<(RegistryUri, String), Option<PublishedArtifacts>>,
+}
 
-            Ok(package)
+#[cfg(feature = "native")]
+impl Catalog {
+    fn new(manifest: &Manifest, lockfile: &Lockfile) -> Self {
+        let mut expected: HashMap<_, BTreeSet<_>> = HashMap::new();
+
+        let remote = manifest.dependencies.iter().filter_map(|dependency| {
+            let DependencyManifest::Remote(ref manifest) = dependency.manifest else {
+                return None;
+            };
+
+            Some((
+                &manifest.registry,
+                &manifest.repository,
+                &dependency.package,
+            ))
+        });
+
+        let locked = lockfile
+            .packages()
+            .map(|locked| (&locked.registry, &locked.repository, &locked.name));
+
+        for (registry, repository, name) in remote.chain(locked) {
+            expected
+                .entry((registry.clone(), repository.clone()))
+                .or_default()
+                .insert(name.clone());
+        }
+
+        Self {
+            expected,
+            listings: HashMap::new(),
         }
     }
 
+    /// Whether a dependency was published in chunks, `None` if the listing can not tell
+    async fn is_chunked(
+        &mut self,
+        registry: &Artifactory,
+        dependency: &RemoteDependency,
+    ) -> Option<bool> {
+        let key = (
+            dependency.manifest.registry.clone(),
+            dependency.manifest.repository.clone(),
+        );
+
+        if !self.listings.contains_key(&key) {
+            let mut names = self.expected.remove(&key).unwrap_or_default();
+            names.insert(dependency.package.clone());
+
+            // a single package takes as many requests without the listing
+            let listing = if names.len() > 1 {
+                let names: Vec<_> = names.into_iter().collect();
+
+                registry
+                    .published_artifacts(&key.1, &names)
+                    .await
+                    .unwrap_or_else(|error| {
+                        tracing::debug!("failed to list the packages of {}: {error}", key.1);
+                        None
+                    })
+            } else {
+                None
+            };
+
+            self.listings.insert(key.clone(), listing);
+        }
+
+        self.listings
+            .get(&key)?
+            .as_ref()?
+            .is_chunked(&dependency.clone().into())
+    }
+}
+
+impl DependencyGraph {
     /// Locates and returns a reference to a resolved dependency package by its name
     pub fn get(&self, name: &PackageName) -> Option<&ResolvedDependency> {
         self.entries.get(name)
@@ -415,3 +763,254 @@ impl IntoIterator for DependencyGraph {
         self.entries.into_values()
     }
 }
+
+/// Errors that can occur while resolving the dependencies of a manifest
+#[cfg(feature = "native")]
+#[derive(Error, Diagnostic, Debug)]
+pub enum ResolveError {
+    /// A registry alias of the manifest is not configured
+    #[error("failed to resolve the registry of {0}")]
+    Registry(PackageName, #[diagnostic_source] miette::Report),
+    /// The credentials could not be loaded
+    #[error("failed to load the credentials")]
+    Credentials(#[diagnostic_source] miette::Report),
+    /// The package cache could not be opened
+    #[error("failed to open the package cache")]
+    Cache(#[diagnostic_source] miette::Report),
+    /// The dependency graph could not be built
+    #[error("dependency resolution failed")]
+    Resolution(#[diagnostic_source] miette::Report),
+    /// The resolution was cancelled through [`Resolver::with_cancel`]
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Cancelled(Cancelled),
+}
+
+/// Resolves the dependency graph of a manifest, for embedding buffrs operations into other tools
+///
+/// This performs the resolution step of `buffrs install` without installing anything, so the
+/// graph can be inspected or installed into a [`PackageStore`] by the caller.
+#[cfg(feature = "native")]
+#[derive(Clone)]
+pub struct Resolver {
+    config: Config,
+    events: Arc<dyn EventSink>,
+    cancel: Cancel,
+}
+
+#[cfg(feature = "native")]
+impl Resolver {
+    /// Creates a resolver using the registries and settings of a configuration
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            events: Arc::new(()),
+            cancel: Cancel::new(),
+        }
+    }
+
+    /// Reports resolved packages and downloads to a sink, events are discarded by default
+    pub fn with_events(mut self, events: Arc<dyn EventSink>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Stops the resolution once `cancel` is cancelled, abandoning downloads in progress
+    pub fn with_cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Resolves the direct and transitive dependencies of a manifest
+    ///
+    /// Locked packages are resolved to their locked versions and downloads go through the shared
+    /// package cache.
+    pub async fn resolve(
+        &self,
+        manifest: &Manifest,
+        lockfile: &Lockfile,
+    ) -> crate::Result<DependencyGraph> {
+        let mut manifest = manifest.clone();
+
+        for dependency in manifest.dependencies.iter_mut() {
+            if let DependencyManifest::Remote(ref mut remote) = dependency.manifest {
+                remote.registry = self
+                    .config
+                    .resolve_registry_uri(&remote.registry)
+                    .map_err(|error| ResolveError::Registry(dependency.package.clone(), error))?;
+            }
+        }
+
+        let credentials = Credentials::load()
+            .await
+            .map(Arc::new)
+            .map_err(ResolveError::Credentials)?;
+        let cache = Cache::open().await.map_err(ResolveError::Cache)?;
+
+        let resolution = DependencyGraph::from_manifest(
+            &manifest,
+            lockfile,
+            &credentials,
+            &self.config,
+            &cache,
+            self.events.as_ref(),
+        );
+
+        let graph = self
+            .cancel
+            .run(resolution)
+            .await
+            .map_err(ResolveError::Cancelled)?
+            .map_err(ResolveError::Resolution)?;
+
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::lock::LockedPackage;
+
+    const REGISTRY: &str = "https://registry.example.com/artifactory";
+
+    fn remote(name: &str, version: &str) -> String {
+        format!(
+            "{name} = {{ version = \"{version}\", registry = \"{REGISTRY}\", repository = \"protos\" }}\n"
+        )
+    }
+
+    fn manifest(dependencies: &[String]) -> Manifest {
+        format!("[dependencies]\n{}", dependencies.concat())
+            .parse()
+            .unwrap()
+    }
+
+    fn package(name: &str, version: &str, dependencies: &[String]) -> Package {
+        let manifest = format!(
+            "[package]\ntype = \"lib\"\nname = \"{name}\"\nversion = \"{version}\"\n\n[dependencies]\n{}",
+            dependencies.concat()
+        );
+
+        Package::create(
+            manifest.parse().unwrap(),
+            BTreeMap::from([(
+                PathBuf::from(format!("{name}.proto")),
+                Bytes::from(format!("package {name};")),
+            )]),
+        )
+        .unwrap()
+    }
+
+    /// Resolves a manifest against in memory packages, returning the requested package names
+    fn resolve(
+        manifest: &Manifest,
+        lockfile: &Lockfile,
+        packages: &[Package],
+    ) -> miette::Result<(Vec<String>, DependencyGraph)> {
+        let mut resolution = Resolution::new(manifest, lockfile);
+        let mut requested = Vec::new();
+
+        while let Some(request) = resolution.next_request()? {
+            let (Request::Remote { package, .. } | Request::Local { package, .. }) = request;
+
+            requested.push(package.to_string());
+
+            let package = packages
+                .iter()
+                .find(|candidate| *candidate.name() == package)
+                .expect("unknown package requested")
+                .clone();
+
+            resolution.provide(package)?;
+        }
+
+        Ok((requested, resolution.finish()))
+    }
+
+    #[test]
+    fn resolves_depth_first() {
+        let packages = [
+            package("physics", "1.0.0", &[remote("units", "=1.0.0")]),
+            package("units", "1.0.0", &[]),
+            package("chemistry", "2.0.0", &[remote("units", "=1.0.0")]),
+        ];
+        let manifest = manifest(&[remote("physics", "=1.0.0"), remote("chemistry", "=2.0.0")]);
+
+        let (requested, graph) = resolve(&manifest, &Lockfile::default(), &packages).unwrap();
+
+        assert_eq!(requested, ["chemistry", "units", "physics"]);
+
+        let Some(ResolvedDependency::Remote { dependants, .. }) =
+            graph.get(&"units".parse().unwrap())
+        else {
+            panic!("units was not resolved from the registry");
+        };
+
+        let dependants: Vec<_> = dependants.iter().map(|d| d.name.to_string()).collect();
+
+        assert_eq!(dependants, ["chemistry", "physics"]);
+        assert_eq!(
+            graph.get(&"physics".parse().unwrap()).unwrap().depends_on(),
+            ["units".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_versions() {
+        let packages = [
+            package("physics", "1.0.0", &[remote("units", "=1.0.0")]),
+            package("units", "1.0.0", &[]),
+        ];
+        let manifest = manifest(&[remote("physics", "=1.0.0"), remote("units", "=2.0.0")]);
+
+        let error = resolve(&manifest, &Lockfile::default(), &packages).unwrap_err();
+
+        assert_eq!(error.code().unwrap().to_string(), "E0002");
+    }
+
+    #[test]
+    fn rejects_packages_not_matching_the_request() {
+        let packages = [package("units", "1.0.0", &[])];
+        let manifest = manifest(&[remote("units", "=2.0.0")]);
+
+        let error = resolve(&manifest, &Lockfile::default(), &packages).unwrap_err();
+
+        assert_eq!(error.code().unwrap().to_string(), "E0002");
+    }
+
+    #[test]
+    fn rejects_registries_not_matching_the_lockfile() {
+        let units = package("units", "1.0.0", &[]);
+        let packages = [
+            package("physics", "1.0.0", &[remote("units", "=1.0.0")]),
+            units.clone(),
+        ];
+        let manifest = manifest(&[remote("physics", "=1.0.0")]);
+        let lockfile = Lockfile::from_iter([LockedPackage::lock(
+            &units,
+            "https://mirror.example.com/artifactory".parse().unwrap(),
+            "protos".to_owned(),
+            1,
+        )]);
+
+        let error = resolve(&manifest, &lockfile, &packages).unwrap_err();
+
+        assert_eq!(error.code().unwrap().to_string(), "E0002");
+    }
+
+    #[test]
+    fn requires_requested_packages() {
+        let manifest = manifest(&[remote("units", "=1.0.0")]);
+        let lockfile = Lockfile::default();
+        let mut resolution = Resolution::new(&manifest, &lockfile);
+
+        assert!(resolution.provide(package("units", "1.0.0", &[])).is_err());
+        assert!(resolution.next_request().unwrap().is_some());
+        assert!(resolution.next_request().is_err());
+    }
+}
@@ -0,0 +1,420 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    env,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use semver::Version;
+use serde_json::{json, Value};
+use sha2::{Digest as _, Sha256};
+use strum::{Display, EnumString};
+
+use crate::{
+    lock::{Digest, DigestAlgorithm, FileRequirement, LockedPackage},
+    manifest::PackageManifest,
+    package::PackageName,
+};
+
+/// Format of a software bill of materials
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 JSON
+    CycloneDx,
+    /// SPDX 2.3 JSON
+    Spdx,
+}
+
+/// Package listed in a software bill of materials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    /// Name of the package
+    pub name: PackageName,
+    /// Exact version of the package
+    pub version: Version,
+    /// Digest of the package archive, if it was downloaded from a registry
+    pub digest: Option<Digest>,
+    /// Location the package archive was downloaded from
+    pub url: Option<String>,
+    /// SPDX license expression declared by the package
+    pub license: Option<String>,
+    /// Names of the packages this package depends on
+    pub dependencies: Vec<PackageName>,
+}
+
+impl Component {
+    /// Describes an installed package from its lockfile entry and manifest
+    pub fn locked(locked: &LockedPackage, manifest: Option<&PackageManifest>) -> Self {
+        let mut dependencies = locked.dependencies.clone();
+        dependencies.sort();
+
+        Self {
+            name: locked.name.clone(),
+            version: locked.version.clone(),
+            digest: Some(locked.digest.clone()),
+            url: Some(FileRequirement::from(locked).url().to_string()),
+            license: manifest.and_then(|manifest| manifest.license.clone()),
+            dependencies,
+        }
+    }
+
+    fn reference(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+
+    fn purl(&self) -> String {
+        let mut purl = format!("pkg:generic/{}@{}", self.name, self.version);
+
+        if let Some(url) = &self.url {
+            purl.push_str(&format!("?download_url={}", encode(url)));
+        }
+
+        purl
+    }
+
+    fn spdx_id(&self) -> String {
+        let id: String = self
+            .reference()
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+                _ => '-',
+            })
+            .collect();
+
+        format!("SPDXRef-Package-{id}")
+    }
+
+    fn hex(&self) -> Option<(DigestAlgorithm, String)> {
+        self.digest
+            .as_ref()
+            .map(|digest| (digest.algorithm(), hex::encode(digest.as_bytes())))
+    }
+}
+
+/// Software bill of materials of a package and its resolved dependency tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sbom {
+    /// The package the bill of materials describes
+    pub root: Component,
+    /// Every installed dependency, direct and transitive
+    pub components: Vec<Component>,
+    /// Creation time in seconds since the unix epoch
+    pub created: u64,
+}
+
+impl Sbom {
+    /// Creates a bill of materials
+    ///
+    /// The creation time honours `SOURCE_DATE_EPOCH` to allow reproducible documents.
+    pub fn new(root: Component, mut components: Vec<Component>) -> Self {
+        components.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let created = env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.parse().ok())
+            .unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_secs())
+            });
+
+        Self {
+            root,
+            components,
+            created,
+        }
+    }
+
+    /// Renders the bill of materials as JSON in the given format
+    pub fn render(&self, format: SbomFormat) -> Value {
+        match format {
+            SbomFormat::CycloneDx => self.cyclonedx(),
+            SbomFormat::Spdx => self.spdx(),
+        }
+    }
+
+    fn cyclonedx(&self) -> Value {
+        let component = |component: &Component, kind: &str| {
+            let mut value = json!({
+                "type": kind,
+                "bom-ref": component.reference(),
+                "name": component.name.to_string(),
+                "version": component.version.to_string(),
+                "purl": component.purl(),
+            });
+
+            if let Some((algorithm, hex)) = component.hex() {
+                let algorithm = match algorithm {
+                    DigestAlgorithm::SHA256 => "SHA-256",
+                };
+
+                value["hashes"] = json!([{ "alg": algorithm, "content": hex }]);
+            }
+
+            if let Some(license) = &component.license {
+                value["licenses"] = json!([{ "expression": license }]);
+            }
+
+            if let Some(url) = &component.url {
+                value["externalReferences"] = json!([{ "type": "distribution", "url": url }]);
+            }
+
+            value
+        };
+
+        let dependencies: Vec<Value> = std::iter::once(&self.root)
+            .chain(&self.components)
+            .map(|component| {
+                json!({
+                    "ref": component.reference(),
+                    "dependsOn": self.references(component),
+                })
+            })
+            .collect();
+
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "version": 1,
+            "metadata": {
+                "timestamp": timestamp(self.created),
+                "tools": [{ "name": "buffrs", "version": env!("CARGO_PKG_VERSION") }],
+                "component": component(&self.root, "application"),
+            },
+            "components": self
+                .components
+                .iter()
+                .map(|dependency| component(dependency, "library"))
+                .collect::<Vec<_>>(),
+            "dependencies": dependencies,
+        })
+    }
+
+    fn spdx(&self) -> Value {
+        let package = |component: &Component| {
+            let mut value = json!({
+                "SPDXID": component.spdx_id(),
+                "name": component.name.to_string(),
+                "versionInfo": component.version.to_string(),
+                "downloadLocation": component.url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": component.license.as_deref().unwrap_or("NOASSERTION"),
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": component.purl(),
+                }],
+            });
+
+            if let Some((algorithm, hex)) = component.hex() {
+                let algorithm = match algorithm {
+                    DigestAlgorithm::SHA256 => "SHA256",
+                };
+
+                value["checksums"] = json!([{ "algorithm": algorithm, "checksumValue": hex }]);
+            }
+
+            value
+        };
+
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": self.root.spdx_id(),
+        })];
+
+        for component in std::iter::once(&self.root).chain(&self.components) {
+            for dependency in self.dependencies(component) {
+                relationships.push(json!({
+                    "spdxElementId": component.spdx_id(),
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": dependency.spdx_id(),
+                }));
+            }
+        }
+
+        // the namespace has to be unique per document, so it is derived from its contents
+        let mut hasher = Sha256::new();
+
+        for component in std::iter::once(&self.root).chain(&self.components) {
+            hasher.update(component.reference());
+            hasher.update(component.hex().map(|(_, hex)| hex).unwrap_or_default());
+        }
+
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": self.root.reference(),
+            "documentNamespace": format!(
+                "https://spdx.org/spdxdocs/{}-{}",
+                self.root.reference().replace('@', "-"),
+                hex::encode(hasher.finalize())
+            ),
+            "creationInfo": {
+                "created": timestamp(self.created),
+                "creators": [format!("Tool: buffrs-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": std::iter::once(&self.root)
+                .chain(&self.components)
+                .map(package)
+                .collect::<Vec<_>>(),
+            "relationships": relationships,
+        })
+    }
+
+    fn dependencies<'a>(&'a self, component: &'a Component) -> impl Iterator<Item = &'a Component> {
+        component.dependencies.iter().filter_map(|name| {
+            self.components
+                .iter()
+                .find(|candidate| &candidate.name == name)
+        })
+    }
+
+    fn references(&self, component: &Component) -> Vec<String> {
+        self.dependencies(component)
+            .map(Component::reference)
+            .collect()
+    }
+}
+
+/// Percent-encodes the characters a purl qualifier value may not contain
+fn encode(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '%' => "%25".to_string(),
+            '&' => "%26".to_string(),
+            '+' => "%2B".to_string(),
+            '#' => "%23".to_string(),
+            '?' => "%3F".to_string(),
+            ' ' => "%20".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Formats seconds since the unix epoch as an RFC 3339 UTC timestamp
+fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(name: &str, dependencies: &[&str]) -> Component {
+        Component {
+            name: name.parse().unwrap(),
+            version: "1.0.0".parse().unwrap(),
+            digest: Some(
+                "sha256:9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+                    .parse()
+                    .unwrap(),
+            ),
+            url: Some(format!(
+                "https://example.com/artifactory/protos/{name}/{name}-1.0.0.tgz"
+            )),
+            license: Some("Apache-2.0".into()),
+            dependencies: dependencies
+                .iter()
+                .map(|name| name.parse().unwrap())
+                .collect(),
+        }
+    }
+
+    fn sbom() -> Sbom {
+        let root = Component {
+            digest: None,
+            url: None,
+            ..component("app", &["physics"])
+        };
+
+        Sbom {
+            root,
+            components: vec![component("physics", &["units"]), component("units", &[])],
+            created: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn formats_timestamps() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(timestamp(951_827_696), "2000-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn renders_cyclonedx() {
+        let bom = sbom().render(SbomFormat::CycloneDx);
+
+        assert_eq!(bom["metadata"]["component"]["bom-ref"], "app@1.0.0");
+        assert_eq!(bom["components"][0]["hashes"][0]["alg"], "SHA-256");
+        assert_eq!(
+            bom["components"][0]["licenses"][0]["expression"],
+            "Apache-2.0"
+        );
+        assert_eq!(
+            bom["dependencies"],
+            json!([
+                { "ref": "app@1.0.0", "dependsOn": ["physics@1.0.0"] },
+                { "ref": "physics@1.0.0", "dependsOn": ["units@1.0.0"] },
+                { "ref": "units@1.0.0", "dependsOn": [] },
+            ])
+        );
+    }
+
+    #[test]
+    fn renders_spdx() {
+        let bom = sbom().render(SbomFormat::Spdx);
+
+        assert_eq!(bom["creationInfo"]["created"], "2023-11-14T22:13:20Z");
+        assert_eq!(bom["packages"][0]["downloadLocation"], "NOASSERTION");
+        assert_eq!(
+            bom["packages"][1]["SPDXID"],
+            "SPDXRef-Package-physics-1.0.0"
+        );
+        assert_eq!(bom["packages"][1]["checksums"][0]["algorithm"], "SHA256");
+        assert_eq!(bom["relationships"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!("cyclonedx".parse(), Ok(SbomFormat::CycloneDx));
+        assert_eq!("spdx".parse(), Ok(SbomFormat::Spdx));
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use bytes::Bytes;
+use miette::{ensure, miette, Context, IntoDiagnostic};
+use serde::Deserialize;
+use tokio::{fs, process::Command};
+
+use crate::{errors::SignatureRejected, package::Package};
+
+/// Tool producing the detached signature of a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signer<'a> {
+    /// Sign with cosign, keyless via sigstore if no private key is given
+    Cosign {
+        /// Path to the private key
+        key: Option<&'a Path>,
+    },
+    /// Sign with GnuPG, using the default key if none is given
+    Gpg {
+        /// Id of the signing key
+        key: Option<&'a str>,
+    },
+}
+
+impl Signer<'_> {
+    /// Extension of the signature stored next to a published package
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Cosign { .. } => SIGSTORE_EXTENSION,
+            Self::Gpg { .. } => GPG_EXTENSION,
+        }
+    }
+
+    /// Signs a package, producing a detached signature
+    ///
+    /// Keyless cosign signatures require an OIDC identity that cosign obtains interactively or
+    /// from the CI environment.
+    pub async fn sign(&self, package: &Package) -> miette::Result<Bytes> {
+        let workspace = Workspace::new(package).await?;
+        let signature = workspace.path("signature");
+
+        let mut command = match self {
+            Self::Cosign { key } => {
+                let mut command = Command::new("cosign");
+
+                command
+                    .args(["sign-blob", "--yes", "--bundle"])
+                    .arg(&signature);
+
+                if let Some(key) = key {
+                    command.arg("--key").arg(key);
+                }
+
+                command
+            }
+            Self::Gpg { key } => {
+                let mut command = Command::new("gpg");
+
+                command
+                    .args(["--batch", "--armor", "--detach-sign", "--output"])
+                    .arg(&signature);
+
+                if let Some(key) = key {
+                    command.arg("--local-user").arg(key);
+                }
+
+                command
+            }
+        };
+
+        command.arg(workspace.path("tgz"));
+
+        run(&mut command).await?;
+
+        fs::read(&signature)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to read the produced signature"))
+            .map(Bytes::from)
+    }
+}
+
+/// Extension of the sigstore bundle stored next to a published package
+pub const SIGSTORE_EXTENSION: &str = "sigstore.json";
+
+/// Extension of the armored GnuPG signature stored next to a published package
+pub const GPG_EXTENSION: &str = "asc";
+
+/// Requirements published packages of a registry have to fulfil to be installed
+///
+/// Configured per registry alias in the `[signing.<alias>]` section of the configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TrustPolicy {
+    /// Fail instead of warning if a package is not signed
+    #[serde(default)]
+    pub require_signatures: bool,
+    /// Public key verifying key-based signatures
+    pub key: Option<PathBuf>,
+    /// Identity expected in the certificate of keyless signatures, e.g. an email address
+    pub certificate_identity: Option<String>,
+    /// OIDC issuer expected in the certificate of keyless signatures
+    pub certificate_oidc_issuer: Option<String>,
+    /// Keyring with the public keys trusted for GnuPG signatures, replaces cosign if set
+    pub gpg_keyring: Option<PathBuf>,
+}
+
+impl TrustPolicy {
+    /// Extension of the signatures accepted by this policy
+    pub fn extension(&self) -> &'static str {
+        match self.gpg_keyring {
+            Some(_) => GPG_EXTENSION,
+            None => SIGSTORE_EXTENSION,
+        }
+    }
+
+    /// Verifies the signature of a package against this policy
+    ///
+    /// Invalid signatures are always rejected. Missing signatures are rejected if signatures
+    /// are required, and reported as a warning otherwise.
+    pub async fn verify(&self, package: &Package, signature: Option<Bytes>) -> miette::Result<()> {
+        let Some(signature) = signature else {
+            ensure!(
+                !self.require_signatures,
+                SignatureRejected(format!(
+                    "{}@{} is not signed, but the registry requires signatures",
+                    package.name(),
+                    package.version()
+                ))
+            );
+
+            tracing::warn!(":: {}@{} is not signed", package.name(), package.version());
+
+            return Ok(());
+        };
+
+        let workspace = Workspace::new(package).await?;
+        let path = workspace.path("signature");
+
+        let mut command = match &self.gpg_keyring {
+            Some(keyring) => {
+                let mut command = Command::new("gpgv");
+
+                command.arg("--keyring").arg(keyring).arg(&path);
+                command
+            }
+            None => {
+                let mut command = Command::new("cosign");
+
+                command.arg("verify-blob").arg("--bundle").arg(&path);
+                command
+            }
+        };
+
+        match (
+            &self.gpg_keyring,
+            &self.key,
+            &self.certificate_identity,
+            &self.certificate_oidc_issuer,
+        ) {
+            (Some(_), _, _, _) => {}
+            (None, Some(key), _, _) => {
+                command.arg("--key").arg(key);
+            }
+            (None, None, Some(identity), Some(issuer)) => {
+                command
+                    .arg("--certificate-identity")
+                    .arg(identity)
+                    .arg("--certificate-oidc-issuer")
+                    .arg(issuer);
+            }
+            _ => {
+                return Err(miette!(
+                    help = "configure either `key`, `certificate-identity` and `certificate-oidc-issuer`, or `gpg-keyring`",
+                    "the trust policy does not specify whom to trust"
+                ));
+            }
+        }
+
+        command.arg(workspace.path("tgz"));
+
+        let result = async {
+            fs::write(&path, &signature)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to write the signature"))?;
+
+            run(&mut command).await
+        }
+        .await;
+
+        // wrapping would report the code of the underlying failure, which has none
+        result.map_err(|error| {
+            SignatureRejected(format!(
+                "invalid signature for {}@{}: {error}",
+                package.name(),
+                package.version()
+            ))
+        })?;
+
+        tracing::debug!(
+            "verified the signature of {}@{}",
+            package.name(),
+            package.version()
+        );
+
+        Ok(())
+    }
+}
+
+/// Temporary directory holding a package archive and its signature, removed when dropped
+struct Workspace(tempfile::TempDir);
+
+impl Workspace {
+    async fn new(package: &Package) -> miette::Result<Self> {
+        let root = tempfile::Builder::new()
+            .prefix("buffrs-signing-")
+            .tempdir()
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create a temporary directory"))?;
+
+        let workspace = Self(root);
+
+        fs::write(workspace.path("tgz"), &package.tgz)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write the package archive"))?;
+
+        Ok(workspace)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.path().join(name)
+    }
+}
+
+/// Runs a signing tool, surfacing its output if it fails
+async fn run(command: &mut Command) -> miette::Result<()> {
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!(
+            help = format!("make sure `{program}` is installed and on the PATH"),
+            "failed to run {program}"
+        ))?;
+
+    ensure!(
+        output.status.success(),
+        "{program} failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+
+    Ok(())
+}
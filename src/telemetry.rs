@@ -0,0 +1,79 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the tracing spans of buffrs to an OpenTelemetry collector
+
+use miette::{Context, IntoDiagnostic};
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use tracing::Subscriber;
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+/// Variables pointing to the collector, the export is disabled if neither is set
+const ENDPOINT_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+];
+
+/// Exports spans over OTLP/HTTP, flushing the pending spans when dropped
+pub struct Telemetry {
+    provider: TracerProvider,
+}
+
+impl Telemetry {
+    /// Sets up the export to the collector configured by the `OTEL_EXPORTER_OTLP_*` variables
+    ///
+    /// Returns `None` if no endpoint is configured.
+    pub fn from_env() -> miette::Result<Option<Self>> {
+        if ENDPOINT_VARS
+            .iter()
+            .all(|var| std::env::var_os(var).is_none())
+        {
+            return Ok(None);
+        }
+
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .build()
+            .into_diagnostic()
+            .wrap_err("failed to set up the OTLP span exporter")?;
+
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::TokioCurrentThread)
+            .with_resource(Resource::new([
+                KeyValue::new("service.name", env!("CARGO_PKG_NAME")),
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+            ]))
+            .build();
+
+        Ok(Some(Self { provider }))
+    }
+
+    /// A layer recording the spans of a subscriber into the export
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.provider.tracer(env!("CARGO_PKG_NAME")))
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(error) = self.provider.shutdown() {
+            eprintln!("failed to export the remaining spans: {error}");
+        }
+    }
+}
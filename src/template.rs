@@ -0,0 +1,167 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use miette::ensure;
+use strum::{Display, EnumString};
+
+use crate::{
+    manifest::PackageNaming,
+    package::{PackageName, PackageType},
+};
+
+/// Build script compiling the installed protocol buffers with tonic
+const BUILD_SCRIPT: &str = r#"fn main() {
+    let inputs = buffrs::build::BuildConfig::new()
+        .emit_rerun_if_changed(true)
+        .prepare_blocking()
+        .expect("failed to prepare protocol buffers");
+
+    tonic_build::configure()
+        .compile(&inputs.protos, &inputs.includes)
+        .unwrap();
+}
+"#;
+
+/// Starter contents of packages created by `buffrs new`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Template {
+    /// A single message
+    #[default]
+    Basic,
+    /// A service with a request and response message, only for api packages
+    GrpcService,
+}
+
+impl Template {
+    /// The files scaffolded for a package, relative to the package directory
+    ///
+    /// Implementation packages start out without protocol buffers, libraries and apis with a
+    /// starter file declaring the protobuf package derived from their name.
+    pub fn files(
+        &self,
+        kind: Option<PackageType>,
+        name: &PackageName,
+        build_script: bool,
+    ) -> miette::Result<Vec<(PathBuf, String)>> {
+        ensure!(
+            *self != Self::GrpcService || kind == Some(PackageType::Api),
+            "the {self} template requires an api package (--api)"
+        );
+
+        let mut files = Vec::new();
+
+        if kind.is_some() {
+            let package = PackageNaming::default().prefix(name);
+
+            files.push((
+                PathBuf::from("proto").join(format!("{package}.proto")),
+                self.proto(&package, &pascal_case(name)),
+            ));
+        }
+
+        if build_script {
+            files.push((PathBuf::from("build.rs"), BUILD_SCRIPT.to_owned()));
+        }
+
+        Ok(files)
+    }
+
+    fn proto(&self, package: &str, ident: &str) -> String {
+        let definitions = match self {
+            Self::Basic => format!(
+                "message {ident} {{\n  \
+                   string name = 1;\n\
+                 }}\n"
+            ),
+            Self::GrpcService => format!(
+                "service {ident}Service {{\n  \
+                   rpc Get{ident}(Get{ident}Request) returns (Get{ident}Response);\n\
+                 }}\n\
+                 \n\
+                 message Get{ident}Request {{\n  \
+                   string name = 1;\n\
+                 }}\n\
+                 \n\
+                 message Get{ident}Response {{\n  \
+                   string name = 1;\n\
+                 }}\n"
+            ),
+        };
+
+        format!("syntax = \"proto3\";\n\npackage {package};\n\n{definitions}")
+    }
+}
+
+/// Converts a kebab-case package name into a protobuf identifier, e.g. `physics-units` into
+/// `PhysicsUnits`
+fn pascal_case(name: &PackageName) -> String {
+    name.split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffolds_starter_protos() {
+        let name: PackageName = "physics-units".parse().unwrap();
+
+        let files = Template::Basic
+            .files(Some(PackageType::Lib), &name, false)
+            .unwrap();
+
+        assert_eq!(
+            files,
+            vec![(
+                PathBuf::from("proto/physics_units.proto"),
+                "syntax = \"proto3\";\n\npackage physics_units;\n\nmessage PhysicsUnits {\n  string name = 1;\n}\n".to_owned()
+            )]
+        );
+
+        assert!(Template::Basic
+            .files(None, &name, false)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn scaffolds_services_for_apis() {
+        let name: PackageName = "physics".parse().unwrap();
+
+        let files = Template::GrpcService
+            .files(Some(PackageType::Api), &name, true)
+            .unwrap();
+
+        assert!(files[0]
+            .1
+            .contains("rpc GetPhysics(GetPhysicsRequest) returns (GetPhysicsResponse);"));
+        assert_eq!(files[1].0, PathBuf::from("build.rs"));
+
+        assert!(Template::GrpcService
+            .files(Some(PackageType::Lib), &name, false)
+            .is_err());
+    }
+}
@@ -12,22 +12,39 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// Breaking change detection.
+mod breaking;
+/// Semantic differences between API versions.
+mod changes;
+/// User-defined lint rules.
+mod custom;
 /// Parsed protocol buffer definitions.
 mod data;
 mod parse;
+/// Validation of projects held in memory.
+#[cfg(feature = "native")]
+mod project;
+/// Reservation of removed fields.
+mod reserve;
 /// Rules for protocol buffer definitions.
 mod rules;
 /// Serde utilities.
 pub(crate) mod serde;
 mod violation;
 
+pub use breaking::*;
+pub use changes::*;
+pub use custom::*;
+#[cfg(feature = "native")]
+pub use project::*;
+pub use reserve::*;
 pub use violation::*;
 
 use miette::IntoDiagnostic;
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path};
 
-use self::parse::*;
-use crate::manifest::PackageManifest;
+use self::{parse::*, rules::Rule};
+use crate::manifest::{LintLevel, LintManifest, PackageManifest, MANIFEST_FILE};
 
 /// Validates buffrs packages.
 ///
@@ -35,6 +52,9 @@ use crate::manifest::PackageManifest;
 pub struct Validator {
     parser: parse::Parser,
     manifest: PackageManifest,
+    lint: LintManifest,
+    unused: bool,
+    custom: Vec<CustomRule>,
 }
 
 impl Validator {
@@ -43,14 +63,74 @@ impl Validator {
         Self {
             parser: Parser::new(root),
             manifest: manifest.clone(),
+            lint: LintManifest::default(),
+            unused: false,
+            custom: Vec::new(),
         }
     }
 
+    /// Create new parser validating protos held in memory, keyed by their import path.
+    #[cfg(feature = "native")]
+    pub fn memory(protos: BTreeMap<String, String>, manifest: &PackageManifest) -> Self {
+        Self {
+            parser: Parser::memory(protos),
+            manifest: manifest.clone(),
+            lint: LintManifest::default(),
+            unused: false,
+            custom: Vec::new(),
+        }
+    }
+
+    /// Override the severity of rules.
+    pub fn configure(&mut self, lint: &LintManifest) {
+        self.lint = lint.clone();
+    }
+
+    /// Enforce user-defined rules in addition to the builtin ones.
+    pub fn custom(&mut self, rules: &CustomRules) {
+        self.custom = rules.enabled().cloned().collect();
+    }
+
+    /// Also report definitions that are not used by any service.
+    ///
+    /// This is enabled as well if a level other than `allow` is configured for the
+    /// `UnusedDefinition` rule.
+    pub fn unused(&mut self) {
+        self.unused = true;
+    }
+
     /// Add file to be validated.
     pub fn input(&mut self, file: &Path) {
         self.parser.input(file);
     }
 
+    /// Add an additional include path to resolve imports from.
+    pub fn include(&mut self, path: &Path) {
+        self.parser.include(path);
+    }
+
+    /// Check that the inputs and their imports compile, without running any rules.
+    pub fn compile(self) -> miette::Result<()> {
+        self.parser.parse().into_diagnostic().map(|_| ())
+    }
+
+    /// Compile the inputs into the descriptors of the inputs and their imports.
+    pub fn descriptors(self) -> miette::Result<Vec<protobuf::descriptor::FileDescriptorProto>> {
+        self.parser.descriptors().into_diagnostic()
+    }
+
+    /// Compile the inputs into a serialized `FileDescriptorSet`, including their imports.
+    pub fn descriptor_set(self) -> miette::Result<Vec<u8>> {
+        use protobuf::{descriptor::FileDescriptorSet, Message};
+
+        let set = FileDescriptorSet {
+            file: self.descriptors()?,
+            ..Default::default()
+        };
+
+        set.write_to_bytes().into_diagnostic()
+    }
+
     /// Run validation.
     ///
     /// This produces a list of [`Violation`]. These implement the
@@ -58,8 +138,43 @@ impl Validator {
     /// severity.
     pub fn validate(self) -> miette::Result<Violations> {
         let parsed = self.parser.parse().into_diagnostic()?;
-        let mut rule_set = rules::all(&self.manifest);
-        Ok(parsed.check(&mut rule_set))
+        let mut rule_set = rules::all(&self.manifest, self.lint.naming);
+
+        let unused = rules::UnusedDefinition;
+        let configured = self.lint.rules.get(unused.rule_name());
+
+        if self.unused || configured.is_some_and(|level| *level != LintLevel::Allow) {
+            rule_set.push(Box::new(unused.clone()));
+        }
+
+        for rule in self.custom {
+            rule_set.push(Box::new(rule));
+        }
+
+        for rule in self.lint.rules.keys() {
+            let known =
+                rule_set.iter().any(|r| r.rule_name() == rule) || rule == unused.rule_name();
+
+            if !known {
+                tracing::warn!(":: unknown lint rule `{rule}` in `{MANIFEST_FILE}`");
+            }
+        }
+
+        Ok(parsed
+            .check(&mut rule_set)
+            .into_iter()
+            .filter_map(|mut violation| {
+                violation.level = match self.lint.rules.get(&violation.rule) {
+                    None => violation.level,
+                    Some(LintLevel::Allow) => return None,
+                    Some(LintLevel::Info) => Level::Info,
+                    Some(LintLevel::Warning) => Level::Warning,
+                    Some(LintLevel::Error) => Level::Error,
+                };
+
+                Some(violation)
+            })
+            .collect())
     }
 }
 
@@ -86,4 +201,16 @@ mod tests {
 
     parse_test!(books);
     parse_test!(addressbook);
+
+    #[test]
+    fn collects_descriptors() {
+        use std::path::Path;
+
+        let mut parser = super::Parser::new(Path::new("tests/data/parsing"));
+        parser.input(Path::new("tests/data/parsing/books.proto"));
+
+        let descriptors = parser.descriptors().unwrap();
+
+        assert!(descriptors.iter().any(|file| file.name() == "books.proto"));
+    }
 }
@@ -0,0 +1,679 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+use miette::{Diagnostic, IntoDiagnostic, Severity};
+
+use super::{
+    data::{Entity, Enum, Field, Message, Packages, Service},
+    parse::Parser,
+    reserve::Reservation,
+};
+
+/// Kind of compatibility that a change breaks.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BreakingKind {
+    /// Generated code of consumers no longer compiles, the wire format is unaffected.
+    Source,
+    /// Messages or calls of existing consumers can no longer be decoded.
+    Wire,
+}
+
+impl Display for BreakingKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Source => write!(f, "source"),
+            Self::Wire => write!(f, "wire"),
+        }
+    }
+}
+
+/// Change that breaks existing consumers of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakingChange {
+    /// Kind of compatibility this change breaks
+    pub kind: BreakingKind,
+    /// Fully qualified name of the changed definition
+    pub path: String,
+    /// Description of the change
+    pub message: String,
+    /// Field that should be reserved because it was removed
+    pub reservation: Option<Reservation>,
+}
+
+impl BreakingChange {
+    fn new(kind: BreakingKind, path: &str, message: String) -> Self {
+        Self {
+            kind,
+            path: path.to_string(),
+            message,
+            reservation: None,
+        }
+    }
+
+    fn with_reservation(self, reservation: Reservation) -> Self {
+        Self {
+            reservation: Some(reservation),
+            ..self
+        }
+    }
+}
+
+impl std::error::Error for BreakingChange {}
+
+impl Display for BreakingChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl Diagnostic for BreakingChange {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(format!("breaking::{}", self.kind)))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        // generated code has to be adapted, but existing clients keep working
+        match self.kind {
+            BreakingKind::Source => Some(Severity::Warning),
+            BreakingKind::Wire => Some(Severity::Error),
+        }
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        if let Some(reservation) = &self.reservation {
+            return Some(Box::new(format!(
+                "prevent reusing the field by adding `{}` to the message, or run `buffrs fix`",
+                reservation.statement()
+            )));
+        }
+
+        let help = match self.kind {
+            BreakingKind::Source => "consumers need to update their code, release this as a new major version",
+            BreakingKind::Wire => "existing clients and stored messages become incompatible, add a new definition instead of changing this one",
+        };
+
+        Some(Box::new(help))
+    }
+}
+
+/// Detects breaking changes between two versions of a buffrs package.
+pub struct BreakingCheck {
+    baseline: Parser,
+    current: Parser,
+    clients: bool,
+}
+
+impl BreakingCheck {
+    /// Create a new check with the roots of the baseline and the current protos.
+    pub fn new(baseline: &Path, current: &Path) -> Self {
+        Self {
+            baseline: Parser::new(baseline),
+            current: Parser::new(current),
+            clients: false,
+        }
+    }
+
+    /// Create a new check of protos held in memory, keyed by their import path.
+    #[cfg(feature = "native")]
+    pub fn memory(baseline: BTreeMap<String, String>, current: BTreeMap<String, String>) -> Self {
+        Self {
+            baseline: Parser::memory(baseline),
+            current: Parser::memory(current),
+            clients: false,
+        }
+    }
+
+    /// Only check the services, reporting changes that affect existing gRPC clients.
+    pub fn clients(&mut self) {
+        self.clients = true;
+    }
+
+    /// Add file of the baseline version.
+    pub fn baseline(&mut self, file: &Path) {
+        self.baseline.input(file);
+    }
+
+    /// Add file of the current version.
+    pub fn current(&mut self, file: &Path) {
+        self.current.input(file);
+    }
+
+    /// Add an additional include path to resolve imports of both versions from.
+    pub fn include(&mut self, path: &Path) {
+        self.baseline.include(path);
+        self.current.include(path);
+    }
+
+    /// Run the check.
+    pub fn check(self) -> miette::Result<Vec<BreakingChange>> {
+        let baseline = self.baseline.parse().into_diagnostic()?;
+        let current = self.current.parse().into_diagnostic()?;
+
+        if self.clients {
+            return Ok(compare_clients(&baseline, &current));
+        }
+
+        Ok(compare(&baseline, &current))
+    }
+}
+
+/// Compares two sets of packages and returns the changes breaking consumers of `baseline`.
+fn compare(baseline: &Packages, current: &Packages) -> Vec<BreakingChange> {
+    let mut changes = Vec::new();
+
+    for (name, old) in &baseline.packages {
+        let Some(new) = current.packages.get(name) else {
+            changes.push(BreakingChange::new(
+                BreakingKind::Source,
+                name,
+                "package was removed".into(),
+            ));
+            continue;
+        };
+
+        for (entity, old) in &old.entities {
+            let path = format!("{name}.{entity}");
+
+            match (old, new.entities.get(entity)) {
+                (old, None) => changes.push(BreakingChange::new(
+                    match old {
+                        Entity::Service(_) => BreakingKind::Wire,
+                        _ => BreakingKind::Source,
+                    },
+                    &path,
+                    format!("{} was removed", kind(old)),
+                )),
+                (Entity::Message(old), Some(Entity::Message(new))) => {
+                    compare_messages(&path, old, new, &mut changes)
+                }
+                (Entity::Enum(old), Some(Entity::Enum(new))) => {
+                    compare_enums(&path, old, new, &mut changes)
+                }
+                (Entity::Service(old), Some(Entity::Service(new))) => {
+                    compare_services(&path, old, new, &mut changes)
+                }
+                (old, Some(new)) => changes.push(BreakingChange::new(
+                    BreakingKind::Wire,
+                    &path,
+                    format!("changed from {} to {}", kind(old), kind(new)),
+                )),
+            }
+        }
+    }
+
+    changes
+}
+
+/// Compares the services of two sets of packages.
+///
+/// Removed services are reported as renamed if a new service declares exactly the same methods.
+fn compare_clients(baseline: &Packages, current: &Packages) -> Vec<BreakingChange> {
+    let services = |packages: &Packages| -> BTreeMap<String, Service> {
+        packages
+            .packages
+            .iter()
+            .flat_map(|(name, package)| {
+                package
+                    .entities
+                    .iter()
+                    .filter_map(move |(entity, value)| match value {
+                        Entity::Service(service) => {
+                            Some((format!("{name}.{entity}"), service.clone()))
+                        }
+                        _ => None,
+                    })
+            })
+            .collect()
+    };
+
+    let old = services(baseline);
+    let new = services(current);
+
+    let mut changes = Vec::new();
+
+    for (path, service) in &old {
+        if let Some(current) = new.get(path) {
+            compare_services(path, service, current, &mut changes);
+            continue;
+        }
+
+        let renamed = new
+            .iter()
+            .find(|(name, current)| !old.contains_key(*name) && current.methods == service.methods);
+
+        changes.push(match renamed {
+            Some((name, _)) => BreakingChange::new(
+                BreakingKind::Wire,
+                path,
+                format!("service was renamed to {name}"),
+            ),
+            None => BreakingChange::new(BreakingKind::Wire, path, "service was removed".into()),
+        });
+    }
+
+    changes
+}
+
+fn compare_messages(path: &str, old: &Message, new: &Message, changes: &mut Vec<BreakingChange>) {
+    for (number, field) in &old.fields {
+        let Some(current) = new.fields.get(number) else {
+            let renumbered = new
+                .fields
+                .iter()
+                .find(|(_, current)| current.name == field.name);
+
+            changes.push(match renumbered {
+                Some((to, _)) => BreakingChange::new(
+                    BreakingKind::Wire,
+                    path,
+                    format!("field {} was renumbered from {number} to {to}", field.name),
+                ),
+                None => {
+                    let change = BreakingChange::new(
+                        BreakingKind::Source,
+                        path,
+                        format!("field {} ({number}) was removed", field.name),
+                    );
+
+                    let reserved = new
+                        .reserved
+                        .iter()
+                        .any(|range| (range.start..range.end).contains(number));
+
+                    if reserved {
+                        change
+                    } else {
+                        change.with_reservation(Reservation {
+                            message: path.to_string(),
+                            number: *number,
+                            name: field.name.clone(),
+                        })
+                    }
+                }
+            });
+
+            continue;
+        };
+
+        if current.name != field.name {
+            changes.push(BreakingChange::new(
+                BreakingKind::Source,
+                path,
+                format!(
+                    "field {number} was renamed from {} to {}",
+                    field.name, current.name
+                ),
+            ));
+        }
+
+        if type_of(current) != type_of(field) {
+            changes.push(BreakingChange::new(
+                BreakingKind::Wire,
+                path,
+                format!(
+                    "field {} changed type from {} to {}",
+                    field.name,
+                    type_of(field),
+                    type_of(current)
+                ),
+            ));
+        }
+
+        if current.label != field.label {
+            changes.push(BreakingChange::new(
+                BreakingKind::Wire,
+                path,
+                format!("field {} changed its label", field.name),
+            ));
+        }
+    }
+}
+
+fn compare_enums(path: &str, old: &Enum, new: &Enum, changes: &mut Vec<BreakingChange>) {
+    for (number, value) in &old.values {
+        match new.values.get(number) {
+            None => changes.push(BreakingChange::new(
+                BreakingKind::Source,
+                path,
+                format!("enum value {} ({number}) was removed", value.name),
+            )),
+            Some(current) if current.name != value.name => changes.push(BreakingChange::new(
+                BreakingKind::Source,
+                path,
+                format!(
+                    "enum value {number} was renamed from {} to {}",
+                    value.name, current.name
+                ),
+            )),
+            Some(_) => {}
+        }
+    }
+}
+
+fn compare_services(path: &str, old: &Service, new: &Service, changes: &mut Vec<BreakingChange>) {
+    for (name, method) in &old.methods {
+        let Some(current) = new.methods.get(name) else {
+            changes.push(BreakingChange::new(
+                BreakingKind::Wire,
+                path,
+                format!("method {name} was removed"),
+            ));
+            continue;
+        };
+
+        if current.input_type != method.input_type || current.output_type != method.output_type {
+            changes.push(BreakingChange::new(
+                BreakingKind::Wire,
+                path,
+                format!(
+                    "method {name} changed from ({}) -> ({}) to ({}) -> ({})",
+                    method.input_type.trim_start_matches('.'),
+                    method.output_type.trim_start_matches('.'),
+                    current.input_type.trim_start_matches('.'),
+                    current.output_type.trim_start_matches('.'),
+                ),
+            ));
+        }
+
+        if current.client_streaming != method.client_streaming
+            || current.server_streaming != method.server_streaming
+        {
+            changes.push(BreakingChange::new(
+                BreakingKind::Wire,
+                path,
+                format!("method {name} changed its streaming mode"),
+            ));
+        }
+    }
+}
+
+/// Keyword declaring the entity in protobuf.
+pub(super) fn kind(entity: &Entity) -> &'static str {
+    match entity {
+        Entity::Enum(_) => "enum",
+        Entity::Service(_) => "service",
+        Entity::Message(_) => "message",
+    }
+}
+
+/// Type of a field as written in protobuf, without the leading dot of qualified names.
+pub(super) fn type_of(field: &Field) -> String {
+    match &field.type_name {
+        Some(name) => name.trim_start_matches('.').to_string(),
+        None => format!("{:?}", field.type_).to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::data::{
+        EnumValue, FieldLabel, FieldType, Method, Package, ReservedRange,
+    };
+
+    fn field(name: &str, type_: FieldType) -> Field {
+        Field {
+            name: name.into(),
+            type_,
+            type_name: None,
+            label: Some(FieldLabel::Optional),
+            default: None,
+        }
+    }
+
+    fn packages(entities: Vec<(&str, Entity)>) -> Packages {
+        let package = Package {
+            name: "physics".into(),
+            files: vec!["physics/units.proto".into()],
+            entities: entities
+                .into_iter()
+                .map(|(name, entity)| (name.to_string(), entity))
+                .collect(),
+            options: Default::default(),
+        };
+
+        Packages {
+            packages: [(package.name.clone(), package)].into(),
+        }
+    }
+
+    fn message(fields: Vec<(i32, Field)>) -> Entity {
+        Entity::Message(Message {
+            fields: fields.into_iter().collect(),
+            reserved: vec![],
+            nested: Default::default(),
+        })
+    }
+
+    #[test]
+    fn identical_packages_are_compatible() {
+        let packages = packages(vec![(
+            "Mass",
+            message(vec![(1, field("value", FieldType::Double))]),
+        )]);
+
+        assert!(compare(&packages, &packages).is_empty());
+    }
+
+    #[test]
+    fn detects_field_changes() {
+        let baseline = packages(vec![(
+            "Mass",
+            message(vec![
+                (1, field("value", FieldType::Double)),
+                (2, field("unit", FieldType::String)),
+                (3, field("label", FieldType::String)),
+            ]),
+        )]);
+
+        let current = packages(vec![(
+            "Mass",
+            message(vec![
+                (1, field("value", FieldType::Float)),
+                (4, field("unit", FieldType::String)),
+            ]),
+        )]);
+
+        assert_eq!(
+            compare(&baseline, &current),
+            vec![
+                BreakingChange::new(
+                    BreakingKind::Wire,
+                    "physics.Mass",
+                    "field value changed type from double to float".into()
+                ),
+                BreakingChange::new(
+                    BreakingKind::Wire,
+                    "physics.Mass",
+                    "field unit was renumbered from 2 to 4".into()
+                ),
+                BreakingChange::new(
+                    BreakingKind::Source,
+                    "physics.Mass",
+                    "field label (3) was removed".into()
+                )
+                .with_reservation(Reservation {
+                    message: "physics.Mass".into(),
+                    number: 3,
+                    name: "label".into(),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_reserved_fields() {
+        let baseline = packages(vec![(
+            "Mass",
+            message(vec![(1, field("label", FieldType::String))]),
+        )]);
+
+        let current = packages(vec![(
+            "Mass",
+            Entity::Message(Message {
+                fields: Default::default(),
+                reserved: vec![ReservedRange { start: 1, end: 2 }],
+                nested: Default::default(),
+            }),
+        )]);
+
+        let changes = compare(&baseline, &current);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].reservation, None);
+    }
+
+    #[test]
+    fn detects_removed_definitions() {
+        let service = Service {
+            methods: [(
+                "Convert".to_string(),
+                Method {
+                    input_type: ".physics.Mass".into(),
+                    output_type: ".physics.Mass".into(),
+                    client_streaming: false,
+                    server_streaming: false,
+                },
+            )]
+            .into(),
+        };
+
+        let unit = Enum {
+            values: [
+                (
+                    0,
+                    EnumValue {
+                        name: "UNIT_UNSPECIFIED".into(),
+                    },
+                ),
+                (
+                    1,
+                    EnumValue {
+                        name: "UNIT_KG".into(),
+                    },
+                ),
+            ]
+            .into(),
+        };
+
+        let baseline = packages(vec![
+            ("Converter", Entity::Service(service)),
+            ("Unit", Entity::Enum(unit)),
+            ("Mass", message(vec![])),
+        ]);
+
+        let current = packages(vec![
+            ("Converter", Entity::Service(Service::default())),
+            (
+                "Unit",
+                Entity::Enum(Enum {
+                    values: [(
+                        0,
+                        EnumValue {
+                            name: "UNIT_UNSPECIFIED".into(),
+                        },
+                    )]
+                    .into(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            compare(&baseline, &current),
+            vec![
+                BreakingChange::new(
+                    BreakingKind::Wire,
+                    "physics.Converter",
+                    "method Convert was removed".into()
+                ),
+                BreakingChange::new(
+                    BreakingKind::Source,
+                    "physics.Mass",
+                    "message was removed".into()
+                ),
+                BreakingChange::new(
+                    BreakingKind::Source,
+                    "physics.Unit",
+                    "enum value UNIT_KG (1) was removed".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_changes_affecting_clients() {
+        let method = |input_type: &str| Method {
+            input_type: input_type.into(),
+            output_type: ".physics.Mass".into(),
+            client_streaming: false,
+            server_streaming: false,
+        };
+
+        let service = |methods: Vec<(&str, Method)>| {
+            Entity::Service(Service {
+                methods: methods
+                    .into_iter()
+                    .map(|(name, method)| (name.to_string(), method))
+                    .collect(),
+            })
+        };
+
+        let baseline = packages(vec![
+            (
+                "Converter",
+                service(vec![
+                    ("Convert", method(".physics.Mass")),
+                    ("Scale", method(".physics.Mass")),
+                ]),
+            ),
+            ("Scale", service(vec![("Weigh", method(".physics.Mass"))])),
+            ("Mass", message(vec![])),
+        ]);
+
+        let current = packages(vec![
+            (
+                "Converter",
+                service(vec![("Convert", method(".physics.Weight"))]),
+            ),
+            ("Balance", service(vec![("Weigh", method(".physics.Mass"))])),
+        ]);
+
+        assert_eq!(
+            compare_clients(&baseline, &current),
+            vec![
+                BreakingChange::new(
+                    BreakingKind::Wire,
+                    "physics.Converter",
+                    "method Convert changed from (physics.Mass) -> (physics.Mass) to (physics.Weight) -> (physics.Mass)".into()
+                ),
+                BreakingChange::new(
+                    BreakingKind::Wire,
+                    "physics.Converter",
+                    "method Scale was removed".into()
+                ),
+                BreakingChange::new(
+                    BreakingKind::Wire,
+                    "physics.Scale",
+                    "service was renamed to physics.Balance".into()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,434 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+    path::Path,
+};
+
+use miette::IntoDiagnostic;
+
+use super::{
+    breaking::{kind, type_of},
+    data::{Entity, Enum, Field, Message, Method, Packages, Service},
+    parse::Parser,
+};
+
+/// Kind of an API change.
+#[derive(Clone, Debug, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ChangeKind {
+    /// A definition was added.
+    Added,
+    /// A definition was removed.
+    Removed,
+    /// A definition was modified.
+    Changed,
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added => write!(f, "+"),
+            Self::Removed => write!(f, "-"),
+            Self::Changed => write!(f, "~"),
+        }
+    }
+}
+
+/// Single change between two versions of an API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiChange {
+    /// Kind of this change
+    pub kind: ChangeKind,
+    /// Description of the change
+    pub description: String,
+}
+
+/// Semantic difference between two versions of an API.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiDiff {
+    /// Changes keyed by the fully qualified name of the changed definition
+    pub changes: BTreeMap<String, Vec<ApiChange>>,
+}
+
+impl ApiDiff {
+    /// Whether both versions have the same API.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn push(&mut self, path: &str, kind: ChangeKind, description: String) {
+        self.changes
+            .entry(path.to_string())
+            .or_default()
+            .push(ApiChange { kind, description });
+    }
+}
+
+impl Display for ApiDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (path, changes) in &self.changes {
+            writeln!(f, "{path}")?;
+
+            for change in changes {
+                writeln!(f, "  {} {}", change.kind, change.description)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Computes the API changes between two versions of a buffrs package.
+pub struct DiffCheck {
+    baseline: Parser,
+    current: Parser,
+}
+
+impl DiffCheck {
+    /// Create a new check with the roots of the baseline and the current protos.
+    pub fn new(baseline: &Path, current: &Path) -> Self {
+        Self {
+            baseline: Parser::new(baseline),
+            current: Parser::new(current),
+        }
+    }
+
+    /// Add file of the baseline version.
+    pub fn baseline(&mut self, file: &Path) {
+        self.baseline.input(file);
+    }
+
+    /// Add file of the current version.
+    pub fn current(&mut self, file: &Path) {
+        self.current.input(file);
+    }
+
+    /// Add an additional include path to resolve imports of both versions from.
+    pub fn include(&mut self, path: &Path) {
+        self.baseline.include(path);
+        self.current.include(path);
+    }
+
+    /// Compute the changes.
+    pub fn diff(self) -> miette::Result<ApiDiff> {
+        let baseline = self.baseline.parse().into_diagnostic()?;
+        let current = self.current.parse().into_diagnostic()?;
+
+        Ok(compare(&baseline, &current))
+    }
+}
+
+/// Union of the keys of two maps, in order.
+fn keys<'a, K: Ord, V>(old: &'a BTreeMap<K, V>, new: &'a BTreeMap<K, V>) -> BTreeSet<&'a K> {
+    old.keys().chain(new.keys()).collect()
+}
+
+fn compare(baseline: &Packages, current: &Packages) -> ApiDiff {
+    let mut diff = ApiDiff::default();
+
+    for name in keys(&baseline.packages, &current.packages) {
+        let (old, new) = match (baseline.packages.get(name), current.packages.get(name)) {
+            (Some(old), Some(new)) => (old, new),
+            (None, _) => {
+                diff.push(name, ChangeKind::Added, "package".into());
+                continue;
+            }
+            (_, None) => {
+                diff.push(name, ChangeKind::Removed, "package".into());
+                continue;
+            }
+        };
+
+        for entity in keys(&old.entities, &new.entities) {
+            let path = format!("{name}.{entity}");
+
+            match (old.entities.get(entity), new.entities.get(entity)) {
+                (None, Some(new)) => diff.push(&path, ChangeKind::Added, kind(new).into()),
+                (Some(old), None) => diff.push(&path, ChangeKind::Removed, kind(old).into()),
+                (Some(Entity::Message(old)), Some(Entity::Message(new))) => {
+                    compare_messages(&path, old, new, &mut diff)
+                }
+                (Some(Entity::Enum(old)), Some(Entity::Enum(new))) => {
+                    compare_enums(&path, old, new, &mut diff)
+                }
+                (Some(Entity::Service(old)), Some(Entity::Service(new))) => {
+                    compare_services(&path, old, new, &mut diff)
+                }
+                (Some(old), Some(new)) => diff.push(
+                    &path,
+                    ChangeKind::Changed,
+                    format!("changed from {} to {}", kind(old), kind(new)),
+                ),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+
+    diff
+}
+
+fn describe(number: i32, field: &Field) -> String {
+    format!("field {} = {number} ({})", field.name, type_of(field))
+}
+
+fn compare_messages(path: &str, old: &Message, new: &Message, diff: &mut ApiDiff) {
+    for number in keys(&old.fields, &new.fields) {
+        let (old, new) = match (old.fields.get(number), new.fields.get(number)) {
+            (Some(old), Some(new)) => (old, new),
+            (None, Some(new)) => {
+                diff.push(path, ChangeKind::Added, describe(*number, new));
+                continue;
+            }
+            (Some(old), None) => {
+                diff.push(path, ChangeKind::Removed, describe(*number, old));
+                continue;
+            }
+            (None, None) => unreachable!(),
+        };
+
+        if old.name != new.name {
+            diff.push(
+                path,
+                ChangeKind::Changed,
+                format!("field {number} renamed from {} to {}", old.name, new.name),
+            );
+        }
+
+        if type_of(old) != type_of(new) {
+            diff.push(
+                path,
+                ChangeKind::Changed,
+                format!(
+                    "field {} = {number} changed type from {} to {}",
+                    new.name,
+                    type_of(old),
+                    type_of(new)
+                ),
+            );
+        }
+
+        if old.label != new.label {
+            diff.push(
+                path,
+                ChangeKind::Changed,
+                format!(
+                    "field {} = {number} changed label from {} to {}",
+                    new.name,
+                    label(old),
+                    label(new)
+                ),
+            );
+        }
+    }
+
+    for name in keys(&old.nested, &new.nested) {
+        let nested = format!("{path}.{name}");
+
+        match (old.nested.get(name), new.nested.get(name)) {
+            (Some(old), Some(new)) => compare_messages(&nested, old, new, diff),
+            (None, _) => diff.push(&nested, ChangeKind::Added, "message".into()),
+            (_, None) => diff.push(&nested, ChangeKind::Removed, "message".into()),
+        }
+    }
+}
+
+fn label(field: &Field) -> String {
+    field
+        .label
+        .as_ref()
+        .map(|label| format!("{label:?}").to_lowercase())
+        .unwrap_or_else(|| "none".into())
+}
+
+fn compare_enums(path: &str, old: &Enum, new: &Enum, diff: &mut ApiDiff) {
+    for number in keys(&old.values, &new.values) {
+        match (old.values.get(number), new.values.get(number)) {
+            (None, Some(new)) => diff.push(
+                path,
+                ChangeKind::Added,
+                format!("value {} = {number}", new.name),
+            ),
+            (Some(old), None) => diff.push(
+                path,
+                ChangeKind::Removed,
+                format!("value {} = {number}", old.name),
+            ),
+            (Some(old), Some(new)) if old.name != new.name => diff.push(
+                path,
+                ChangeKind::Changed,
+                format!("value {number} renamed from {} to {}", old.name, new.name),
+            ),
+            _ => {}
+        }
+    }
+}
+
+fn compare_services(path: &str, old: &Service, new: &Service, diff: &mut ApiDiff) {
+    let signature = |method: &Method| {
+        let stream = |streaming: bool| if streaming { "stream " } else { "" };
+
+        format!(
+            "({}{}) returns ({}{})",
+            stream(method.client_streaming),
+            method.input_type.trim_start_matches('.'),
+            stream(method.server_streaming),
+            method.output_type.trim_start_matches('.'),
+        )
+    };
+
+    for name in keys(&old.methods, &new.methods) {
+        match (old.methods.get(name), new.methods.get(name)) {
+            (None, Some(new)) => diff.push(
+                path,
+                ChangeKind::Added,
+                format!("rpc {name}{}", signature(new)),
+            ),
+            (Some(old), None) => diff.push(
+                path,
+                ChangeKind::Removed,
+                format!("rpc {name}{}", signature(old)),
+            ),
+            (Some(old), Some(new)) if signature(old) != signature(new) => diff.push(
+                path,
+                ChangeKind::Changed,
+                format!(
+                    "rpc {name} changed from {} to {}",
+                    signature(old),
+                    signature(new)
+                ),
+            ),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::data::{EnumValue, FieldLabel, FieldType, Package};
+
+    fn field(name: &str, type_: FieldType) -> Field {
+        Field {
+            name: name.into(),
+            type_,
+            type_name: None,
+            label: Some(FieldLabel::Optional),
+            default: None,
+        }
+    }
+
+    fn packages(entities: Vec<(&str, Entity)>) -> Packages {
+        let package = Package {
+            name: "physics".into(),
+            files: vec!["physics/units.proto".into()],
+            entities: entities
+                .into_iter()
+                .map(|(name, entity)| (name.to_string(), entity))
+                .collect(),
+            options: Default::default(),
+        };
+
+        Packages {
+            packages: [(package.name.clone(), package)].into(),
+        }
+    }
+
+    fn message(fields: Vec<(i32, Field)>) -> Entity {
+        Entity::Message(Message {
+            fields: fields.into_iter().collect(),
+            reserved: vec![],
+            nested: Default::default(),
+        })
+    }
+
+    #[test]
+    fn identical_packages_have_no_changes() {
+        let packages = packages(vec![(
+            "Mass",
+            message(vec![(1, field("value", FieldType::Double))]),
+        )]);
+
+        assert!(compare(&packages, &packages).is_empty());
+    }
+
+    #[test]
+    fn describes_changes() {
+        let service = |output_type: &str| {
+            Entity::Service(Service {
+                methods: [(
+                    "Convert".to_string(),
+                    Method {
+                        input_type: ".physics.Mass".into(),
+                        output_type: output_type.into(),
+                        client_streaming: false,
+                        server_streaming: false,
+                    },
+                )]
+                .into(),
+            })
+        };
+
+        let baseline = packages(vec![
+            (
+                "Mass",
+                message(vec![
+                    (1, field("value", FieldType::Double)),
+                    (2, field("unit", FieldType::String)),
+                ]),
+            ),
+            ("Converter", service(".physics.Mass")),
+            ("Legacy", message(vec![])),
+        ]);
+
+        let current = packages(vec![
+            (
+                "Mass",
+                message(vec![
+                    (1, field("value", FieldType::Float)),
+                    (3, field("label", FieldType::String)),
+                ]),
+            ),
+            ("Converter", service(".physics.Weight")),
+            (
+                "Unit",
+                Entity::Enum(Enum {
+                    values: [(
+                        0,
+                        EnumValue {
+                            name: "UNIT_UNSPECIFIED".into(),
+                        },
+                    )]
+                    .into(),
+                }),
+            ),
+        ]);
+
+        assert_eq!(
+            compare(&baseline, &current).to_string(),
+            "physics.Converter
+  ~ rpc Convert changed from (physics.Mass) returns (physics.Mass) to (physics.Mass) returns (physics.Weight)
+physics.Legacy
+  - message
+physics.Mass
+  ~ field value = 1 changed type from double to float
+  - field unit = 2 (string)
+  + field label = 3 (string)
+physics.Unit
+  + enum
+"
+        );
+    }
+}
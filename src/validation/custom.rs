@@ -0,0 +1,464 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "native")]
+use std::path::Path;
+
+#[cfg(feature = "native")]
+use miette::{miette, Context, IntoDiagnostic};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "native")]
+use tokio::fs;
+
+use super::{
+    data::{Entity, FieldLabel, FieldType, Message, Package},
+    rules::Rule,
+    violation::{self, Level, Location, Violation, Violations},
+};
+use crate::manifest::LintLevel;
+
+/// Name of the file declaring custom lint rules
+pub const LINT_FILE: &str = "lint.toml";
+
+/// User-defined lint rules declared in the `lint.toml` of a project
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRules {
+    #[serde(default, rename = "rule")]
+    rules: Vec<CustomRule>,
+}
+
+impl CustomRules {
+    /// Loads the custom rules of the current project, if it declares any
+    #[cfg(feature = "native")]
+    pub async fn read() -> miette::Result<Self> {
+        Self::try_read_from(LINT_FILE)
+            .await
+            .map(Option::unwrap_or_default)
+    }
+
+    /// Loads custom rules from the given path
+    #[cfg(feature = "native")]
+    pub async fn try_read_from(path: impl AsRef<Path>) -> miette::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path.as_ref()).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).into_diagnostic().wrap_err(miette!(
+                    "failed to read custom lint rules from `{}`",
+                    path.as_ref().display()
+                ))
+            }
+        };
+
+        toml::from_str(&contents)
+            .into_diagnostic()
+            .wrap_err(miette!(
+                "failed to parse custom lint rules in `{}`",
+                path.as_ref().display()
+            ))
+            .map(Some)
+    }
+
+    /// Rules that are not disabled
+    pub(crate) fn enabled(&self) -> impl Iterator<Item = &CustomRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.level != LintLevel::Allow)
+    }
+}
+
+/// Regular expression in a custom rule
+#[derive(Debug, Clone)]
+struct Pattern(Regex);
+
+impl Pattern {
+    fn matches(pattern: &Option<Self>, value: &str) -> bool {
+        match pattern {
+            Some(Pattern(regex)) => regex.is_match(value),
+            None => true,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Lint rule declared by the user
+///
+/// A rule applies to every protobuf package matching `packages` that is declared in a file
+/// matching `files`. Names are checked against the regular expressions for their kind of
+/// definition, fields must not use any of the banned labels or types, and every file has to set
+/// the listed file options to exactly the given values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRule {
+    name: String,
+    message: String,
+    #[serde(default = "default_level")]
+    level: LintLevel,
+    packages: Option<Pattern>,
+    files: Option<Pattern>,
+    message_names: Option<Pattern>,
+    field_names: Option<Pattern>,
+    enum_names: Option<Pattern>,
+    enum_value_names: Option<Pattern>,
+    service_names: Option<Pattern>,
+    method_names: Option<Pattern>,
+    #[serde(default)]
+    banned_labels: Vec<FieldLabel>,
+    #[serde(default)]
+    banned_types: Vec<FieldType>,
+    #[serde(default)]
+    options: BTreeMap<String, String>,
+}
+
+fn default_level() -> LintLevel {
+    LintLevel::Warning
+}
+
+impl CustomRule {
+    fn violation(&self, entity: Option<&str>, message: String) -> Violation {
+        let mut violation = self.to_violation(violation::Message {
+            message,
+            help: self.message.clone(),
+        });
+
+        violation.location = Location {
+            entity: entity.map(str::to_string),
+            ..Default::default()
+        };
+
+        violation
+    }
+
+    fn check_name(
+        &self,
+        pattern: &Option<Pattern>,
+        kind: &str,
+        name: &str,
+        entity: &str,
+        violations: &mut Violations,
+    ) {
+        let Some(Pattern(regex)) = pattern else {
+            return;
+        };
+
+        if !regex.is_match(name) {
+            violations.push(self.violation(
+                Some(entity),
+                format!("{kind} name {name} does not match `{regex}`"),
+            ));
+        }
+    }
+
+    fn check_message(
+        &self,
+        name: &str,
+        message: &Message,
+        entity: &str,
+        violations: &mut Violations,
+    ) {
+        self.check_name(&self.message_names, "message", name, entity, violations);
+
+        for field in message.fields.values() {
+            self.check_name(&self.field_names, "field", &field.name, entity, violations);
+
+            if let Some(label) = field
+                .label
+                .as_ref()
+                .filter(|l| self.banned_labels.contains(l))
+            {
+                violations.push(self.violation(
+                    Some(entity),
+                    format!("field {} of {name} is {}", field.name, describe(label)),
+                ));
+            }
+
+            if self.banned_types.contains(&field.type_) {
+                violations.push(self.violation(
+                    Some(entity),
+                    format!(
+                        "field {} of {name} is of type {}",
+                        field.name,
+                        describe(&field.type_)
+                    ),
+                ));
+            }
+        }
+
+        for (nested, message) in &message.nested {
+            self.check_message(nested, message, entity, violations);
+        }
+    }
+}
+
+/// Formats a value the way it is written in the rule configuration
+fn describe<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl Rule for CustomRule {
+    fn rule_name(&self) -> &str {
+        &self.name
+    }
+
+    fn rule_info(&self) -> &str {
+        &self.message
+    }
+
+    fn rule_level(&self) -> Level {
+        match self.level {
+            LintLevel::Allow | LintLevel::Info => Level::Info,
+            LintLevel::Warning => Level::Warning,
+            LintLevel::Error => Level::Error,
+        }
+    }
+
+    fn check_package(&mut self, package: &Package) -> Violations {
+        let in_scope = Pattern::matches(&self.packages, &package.name)
+            && package
+                .files
+                .iter()
+                .any(|file| Pattern::matches(&self.files, &file.to_string_lossy()));
+
+        if !in_scope {
+            return vec![];
+        }
+
+        let mut violations = Violations::new();
+
+        for (name, entity) in &package.entities {
+            match entity {
+                Entity::Message(message) => {
+                    self.check_message(name, message, name, &mut violations)
+                }
+                Entity::Enum(entity) => {
+                    self.check_name(&self.enum_names, "enum", name, name, &mut violations);
+
+                    for value in entity.values.values() {
+                        self.check_name(
+                            &self.enum_value_names,
+                            "enum value",
+                            &value.name,
+                            name,
+                            &mut violations,
+                        );
+                    }
+                }
+                Entity::Service(service) => {
+                    self.check_name(&self.service_names, "service", name, name, &mut violations);
+
+                    for method in service.methods.keys() {
+                        self.check_name(
+                            &self.method_names,
+                            "method",
+                            method,
+                            name,
+                            &mut violations,
+                        );
+                    }
+                }
+            }
+        }
+
+        for file in &package.files {
+            let options = package.options.get(file);
+
+            for (option, expected) in &self.options {
+                let actual = options.and_then(|options| options.get(option));
+
+                if actual != Some(expected) {
+                    let mut violation = self.violation(
+                        None,
+                        format!(
+                            "option {option} of {} is not set to `{expected}`",
+                            file.display()
+                        ),
+                    );
+
+                    violation.location.file = Some(file.display().to_string());
+                    violations.push(violation);
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::data::{Enum, EnumValue, Field};
+
+    fn rule(config: &str) -> CustomRule {
+        let rules: CustomRules = toml::from_str(config).unwrap();
+        rules.rules.into_iter().next().unwrap()
+    }
+
+    fn package() -> Package {
+        let message = Message {
+            fields: [(
+                1,
+                Field {
+                    name: "massKg".into(),
+                    type_: FieldType::Float,
+                    type_name: None,
+                    label: Some(FieldLabel::Required),
+                    default: None,
+                },
+            )]
+            .into(),
+            reserved: vec![],
+            nested: Default::default(),
+        };
+
+        let unit = Enum {
+            values: [(
+                0,
+                EnumValue {
+                    name: "KILOGRAM".into(),
+                },
+            )]
+            .into(),
+        };
+
+        Package {
+            name: "physics.units".into(),
+            files: vec!["physics/units.proto".into()],
+            entities: [
+                ("Mass".to_string(), Entity::Message(message)),
+                ("Unit".to_string(), Entity::Enum(unit)),
+            ]
+            .into(),
+            options: [(
+                "physics/units.proto".into(),
+                [("java_package".to_string(), "com.example.units".to_string())].into(),
+            )]
+            .into(),
+        }
+    }
+
+    fn messages(violations: Violations) -> Vec<String> {
+        violations
+            .into_iter()
+            .map(|violation| violation.message.message)
+            .collect()
+    }
+
+    #[test]
+    fn checks_names_against_patterns() {
+        let mut rule = rule(
+            r#"
+            [[rule]]
+            name = "ApiGuidelines"
+            message = "Follow the API design guidelines."
+            field_names = "^[a-z][a-z0-9_]*$"
+            enum_value_names = "^UNIT_"
+            "#,
+        );
+
+        assert_eq!(
+            messages(rule.check_package(&package())),
+            vec![
+                "field name massKg does not match `^[a-z][a-z0-9_]*$`",
+                "enum value name KILOGRAM does not match `^UNIT_`",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_banned_labels_and_types() {
+        let mut rule = rule(
+            r#"
+            [[rule]]
+            name = "NoRequired"
+            message = "Required fields can never be removed."
+            level = "error"
+            banned_labels = ["required"]
+            banned_types = ["group", "float"]
+            "#,
+        );
+
+        let violations = rule.check_package(&package());
+
+        assert!(violations.iter().all(|v| v.level == Level::Error));
+        assert_eq!(
+            messages(violations),
+            vec![
+                "field massKg of Mass is required",
+                "field massKg of Mass is of type float",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_file_options() {
+        let mut rule = rule(
+            r#"
+            [[rule]]
+            name = "JavaOptions"
+            message = "Java code is generated into separate files."
+            options = { java_package = "com.example.units", java_multiple_files = "true" }
+            "#,
+        );
+
+        assert_eq!(
+            messages(rule.check_package(&package())),
+            vec!["option java_multiple_files of physics/units.proto is not set to `true`"]
+        );
+    }
+
+    #[test]
+    fn respects_scope() {
+        let mut rule = rule(
+            r#"
+            [[rule]]
+            name = "Internal"
+            message = "Internal packages don't use floats."
+            packages = "\\.internal$"
+            banned_types = ["float"]
+            "#,
+        );
+
+        assert!(rule.check_package(&package()).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let rules = toml::from_str::<CustomRules>(
+            r#"
+            [[rule]]
+            name = "Broken"
+            message = "Broken pattern."
+            field_names = "("
+            "#,
+        );
+
+        assert!(rules.is_err());
+    }
+}
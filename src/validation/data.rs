@@ -19,9 +19,13 @@ use std::{
 
 use diff::Diff;
 use miette::Diagnostic;
-use protobuf::descriptor::{
-    field_descriptor_proto::{Label as FieldDescriptorLabel, Type as FieldDescriptorType},
-    *,
+use protobuf::{
+    descriptor::{
+        field_descriptor_proto::{Label as FieldDescriptorLabel, Type as FieldDescriptorType},
+        *,
+    },
+    reflect::ReflectValueRef,
+    MessageFull,
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -36,6 +36,12 @@ pub struct Message {
     /// Fields defined in this message.
     #[serde(deserialize_with = "crate::validation::serde::de_int_key")]
     pub fields: BTreeMap<i32, Field>,
+    /// Reserved field number ranges.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reserved: Vec<ReservedRange>,
+    /// Messages nested in this message, including the entries of map fields.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub nested: BTreeMap<String, Message>,
 }
 
 impl Message {
@@ -50,10 +56,36 @@ impl Message {
             );
         }
 
+        for range in &descriptor.reserved_range {
+            message.reserved.push(ReservedRange {
+                start: range.start(),
+                end: range.end(),
+            });
+        }
+
+        for nested in &descriptor.nested_type {
+            message
+                .nested
+                .insert(nested.name().to_string(), Message::new(nested)?);
+        }
+
         Ok(message)
     }
 }
 
+/// Range of reserved field numbers.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Diff)]
+#[diff(attr(
+    #[derive(Debug)]
+    #[allow(missing_docs)]
+))]
+pub struct ReservedRange {
+    /// First reserved field number.
+    pub start: i32,
+    /// End of the range, exclusive.
+    pub end: i32,
+}
+
 /// Field defined in this message.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Diff)]
 #[diff(attr(
@@ -65,6 +97,9 @@ pub struct Field {
     pub name: String,
     /// Type of field.
     pub type_: FieldType,
+    /// Fully qualified name of the message or enum type of the field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_name: Option<String>,
     /// Label of field.
     pub label: Option<FieldLabel>,
     /// Default value.
@@ -87,6 +122,7 @@ impl Field {
                 Ok(value) => value.into(),
                 Err(number) => FieldType::Unknown(number),
             },
+            type_name: descriptor.type_name.clone(),
             label: match descriptor.label.map(|label| label.enum_value()) {
                 None => None,
                 Some(Ok(label)) => Some(label.into()),
@@ -27,6 +27,9 @@ pub struct Package {
     pub files: Vec<PathBuf>,
     /// Entities defined in this package.
     pub entities: BTreeMap<String, Entity>,
+    /// File options that are set, keyed by the file declaring them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<PathBuf, BTreeMap<String, String>>,
 }
 
 /// Error parsing package.
@@ -70,6 +73,7 @@ impl Package {
             files: vec![descriptor.name().into()],
             name: descriptor.package().to_string(),
             entities: Default::default(),
+            options: Default::default(),
         };
 
         package.parse(descriptor)?;
@@ -91,6 +95,12 @@ impl Package {
     }
 
     fn parse(&mut self, descriptor: &FileDescriptorProto) -> Result<&Self, PackageError> {
+        let options = file_options(descriptor);
+
+        if !options.is_empty() {
+            self.options.insert(descriptor.name().into(), options);
+        }
+
         for message in &descriptor.message_type {
             self.add_entity(
                 message.name(),
@@ -112,7 +122,7 @@ impl Package {
         }
 
         for entity in &descriptor.service {
-            self.add_entity(entity.name(), Service {})?;
+            self.add_entity(entity.name(), Service::new(entity))?;
         }
 
         Ok(self)
@@ -137,16 +147,48 @@ impl Package {
         rules
             .check_package(self)
             .into_iter()
-            .chain(
-                self.entities
-                    .iter()
-                    .flat_map(|(name, entity)| rules.check_entity(name, entity).into_iter()),
-            )
+            .chain(self.entities.iter().flat_map(|(name, entity)| {
+                rules
+                    .check_entity(name, entity)
+                    .into_iter()
+                    .map(|mut violation| {
+                        violation.location.entity = Some(name.clone());
+                        violation
+                    })
+            }))
             .map(|mut violation| {
-                violation.location.file = Some(self.files.last().unwrap().display().to_string());
+                violation
+                    .location
+                    .file
+                    .get_or_insert_with(|| self.files.last().unwrap().display().to_string());
                 violation.location.package = Some(self.name.clone());
                 violation
             })
             .collect()
     }
 }
+
+/// Collects the standard file options that are set, formatted as they are written in protobuf.
+fn file_options(descriptor: &FileDescriptorProto) -> BTreeMap<String, String> {
+    let Some(options) = descriptor.options.as_ref() else {
+        return Default::default();
+    };
+
+    FileOptions::descriptor()
+        .fields()
+        .filter(|field| field.is_singular())
+        .filter_map(|field| {
+            let value = match field.get_singular(options)? {
+                ReflectValueRef::String(value) => value.to_string(),
+                ReflectValueRef::Bool(value) => value.to_string(),
+                ReflectValueRef::Enum(descriptor, number) => descriptor
+                    .value_by_number(number)
+                    .map(|value| value.name().to_string())
+                    .unwrap_or_else(|| number.to_string()),
+                _ => return None,
+            };
+
+            Some((field.name().to_string(), value))
+        })
+        .collect()
+}
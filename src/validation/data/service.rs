@@ -20,4 +20,51 @@ use super::*;
     #[derive(Debug)]
     #[allow(missing_docs)]
 ))]
-pub struct Service {}
+pub struct Service {
+    /// Methods of this service.
+    #[serde(default)]
+    pub methods: BTreeMap<String, Method>,
+}
+
+impl Service {
+    /// Create new from [`ServiceDescriptorProto`].
+    pub fn new(descriptor: &ServiceDescriptorProto) -> Self {
+        let methods = descriptor
+            .method
+            .iter()
+            .map(|method| (method.name().to_string(), Method::new(method)))
+            .collect();
+
+        Self { methods }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq, Diff)]
+#[diff(attr(
+    #[derive(Debug)]
+    #[allow(missing_docs)]
+))]
+pub struct Method {
+    /// Fully qualified name of the request message.
+    pub input_type: String,
+    /// Fully qualified name of the response message.
+    pub output_type: String,
+    /// Whether the client streams requests.
+    #[serde(default)]
+    pub client_streaming: bool,
+    /// Whether the server streams responses.
+    #[serde(default)]
+    pub server_streaming: bool,
+}
+
+impl Method {
+    /// Create new from [`MethodDescriptorProto`].
+    pub fn new(descriptor: &MethodDescriptorProto) -> Self {
+        Self {
+            input_type: descriptor.input_type().to_string(),
+            output_type: descriptor.output_type().to_string(),
+            client_streaming: descriptor.client_streaming(),
+            server_streaming: descriptor.server_streaming(),
+        }
+    }
+}
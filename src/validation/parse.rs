@@ -12,8 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(feature = "native")]
+use std::collections::BTreeMap;
 use std::path::Path;
 
+use protobuf::descriptor::FileDescriptorProto;
+
+#[cfg(feature = "native")]
+use crate::package::{Filesystem, NativeFilesystem};
 use crate::validation::data::{Packages, PackagesError};
 
 /// Errors parsing `buffrs` packages.
@@ -28,7 +34,18 @@ pub enum ParseError {
 
 /// Parser for `buffrs` packages.
 pub struct Parser {
-    parser: protobuf_parse::Parser,
+    source: Source,
+}
+
+enum Source {
+    /// Protos are read from include paths of the file system
+    Native(protobuf_parse::Parser),
+    /// Protos are looked up by their import path
+    #[cfg(feature = "native")]
+    Memory {
+        protos: BTreeMap<String, String>,
+        inputs: Vec<String>,
+    },
 }
 
 impl Parser {
@@ -38,20 +55,109 @@ impl Parser {
         parser.pure();
         parser.include(root);
 
-        Self { parser }
+        Self {
+            source: Source::Native(parser),
+        }
+    }
+
+    /// Create new parser resolving imports from protos held in memory, keyed by import path.
+    #[cfg(feature = "native")]
+    pub fn memory(protos: BTreeMap<String, String>) -> Self {
+        Self {
+            source: Source::Memory {
+                protos,
+                inputs: Vec::new(),
+            },
+        }
+    }
+
+    /// Add an include path to resolve imports from.
+    ///
+    /// Parsers working in memory never read from the file system and ignore include paths.
+    pub fn include(&mut self, path: &Path) {
+        if let Source::Native(parser) = &mut self.source {
+            parser.include(path);
+        }
     }
 
     /// Add file to be processed by this parser.
+    ///
+    /// Parsers working in memory expect the import path of the file.
     pub fn input(&mut self, file: &Path) {
-        self.parser.input(file);
+        match &mut self.source {
+            Source::Native(parser) => {
+                parser.input(file);
+            }
+            #[cfg(feature = "native")]
+            Source::Memory { inputs, .. } => {
+                inputs.push(file.to_string_lossy().replace('\\', "/"));
+            }
+        }
     }
 
     /// Parse into [`Packages`].
     pub fn parse(self) -> Result<Packages, ParseError> {
-        let fds = self.parser.file_descriptor_set()?;
+        let files = self.descriptors()?;
+
+        Self::collect(&files)
+    }
+
+    /// Parse into the descriptors of the inputs and the files they import.
+    pub fn descriptors(self) -> Result<Vec<FileDescriptorProto>, ParseError> {
+        let files = match self.source {
+            Source::Native(parser) => parser.file_descriptor_set()?.file,
+            #[cfg(feature = "native")]
+            Source::Memory { protos, inputs } => Self::parse_memory(&protos, &inputs)?,
+        };
+
+        Ok(files)
+    }
+
+    /// Parses protos held in memory from a private temporary directory
+    ///
+    /// The parser only resolves imports from include paths, so the protos are written out first.
+    /// The directory is removed once the descriptors are parsed.
+    #[cfg(feature = "native")]
+    fn parse_memory(
+        protos: &BTreeMap<String, String>,
+        inputs: &[String],
+    ) -> anyhow::Result<Vec<FileDescriptorProto>> {
+        use anyhow::Context;
+
+        let dir = tempfile::Builder::new()
+            .prefix("buffrs-parse-")
+            .tempdir()
+            .context("failed to create a temporary directory")?;
+
+        let fs = NativeFilesystem;
+
+        for (import, contents) in protos {
+            // rejects absolute paths and `..`, nothing may be written outside of the directory
+            protobuf_parse::ProtoPathBuf::new(import.clone())?;
+
+            let path = dir.path().join(import);
+
+            if let Some(parent) = path.parent() {
+                fs.create_dir_all(parent)?;
+            }
+
+            fs.write(&path, contents.as_bytes())
+                .with_context(|| format!("failed to write {import}"))?;
+        }
+
+        let mut parser = protobuf_parse::Parser::new();
+        parser.pure();
+        parser.include(dir.path());
+
+        for input in inputs {
+            parser.input(dir.path().join(input));
+        }
+
+        Ok(parser.file_descriptor_set()?.file)
+    }
 
-        let packages = fds
-            .file
+    fn collect(files: &[FileDescriptorProto]) -> Result<Packages, ParseError> {
+        let packages = files
             .iter()
             .try_fold(Packages::default(), |mut packages, item| {
                 packages.add(item).map(|_| packages)
@@ -0,0 +1,258 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use miette::miette;
+
+use super::{BreakingChange, BreakingCheck, CustomRules, Validator, Violations};
+use crate::manifest::{Manifest, PackageManifest, MANIFEST_FILE};
+
+/// A project held in memory, to lint and check protos without touching any project files
+///
+/// Protos of the project are given relative to its `proto` directory, protos of dependencies
+/// relative to the vendor directory, i.e. starting with the name of their package. This allows
+/// services to validate the protos of a pull request from the contents of its diff.
+///
+/// ```rust
+/// use buffrs::validation::{CustomRules, VirtualProject};
+///
+/// let manifest = "[package]\ntype = \"api\"\nname = \"units\"\nversion = \"1.0.0\"\n";
+///
+/// let project = VirtualProject::new(manifest)
+///     .unwrap()
+///     .with_proto("units.proto", "syntax = \"proto3\";\npackage units;\n");
+///
+/// project.verify().unwrap();
+///
+/// assert!(project.lint(&CustomRules::default(), false).unwrap().is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct VirtualProject {
+    manifest: Manifest,
+    protos: BTreeMap<PathBuf, String>,
+    vendored: BTreeMap<PathBuf, String>,
+}
+
+impl VirtualProject {
+    /// Creates a project without protos from the contents of its manifest
+    pub fn new(manifest: &str) -> miette::Result<Self> {
+        Ok(Self {
+            manifest: Manifest::parse(Path::new(MANIFEST_FILE), manifest)?,
+            protos: BTreeMap::new(),
+            vendored: BTreeMap::new(),
+        })
+    }
+
+    /// Adds a proto of the project, relative to its `proto` directory
+    pub fn with_proto(mut self, path: impl Into<PathBuf>, contents: impl Into<String>) -> Self {
+        self.protos.insert(path.into(), contents.into());
+        self
+    }
+
+    /// Adds a proto of a dependency, relative to the vendor directory
+    pub fn with_dependency_proto(
+        mut self,
+        path: impl Into<PathBuf>,
+        contents: impl Into<String>,
+    ) -> Self {
+        self.vendored.insert(path.into(), contents.into());
+        self
+    }
+
+    /// The manifest of the project
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    fn package(&self) -> miette::Result<&PackageManifest> {
+        self.manifest
+            .package
+            .as_ref()
+            .ok_or(miette!("a [package] section must be declared to validate"))
+    }
+
+    /// Import paths of the protos of the project, as if they were populated into the vendor
+    /// directory
+    fn inputs(&self, package: &PackageManifest) -> Vec<PathBuf> {
+        self.protos
+            .keys()
+            .map(|path| Path::new(&*package.name).join(path))
+            .collect()
+    }
+
+    /// Protos of the project and its dependencies, keyed by import path
+    fn sources(&self, package: &PackageManifest) -> BTreeMap<String, String> {
+        let own = self
+            .protos
+            .iter()
+            .map(|(path, contents)| (Path::new(&*package.name).join(path), contents));
+
+        self.vendored
+            .iter()
+            .map(|(path, contents)| (path.clone(), contents))
+            .chain(own)
+            .map(|(path, contents)| (path.to_string_lossy().replace('\\', "/"), contents.clone()))
+            .collect()
+    }
+
+    /// Lints the protos of the project, like `buffrs lint`
+    ///
+    /// Rule severities are taken from the lint configuration of the manifest, `custom` rules are
+    /// enforced next to the builtin ones. If `unused` is set, definitions that are not used by any
+    /// service are reported as well.
+    pub fn lint(&self, custom: &CustomRules, unused: bool) -> miette::Result<Violations> {
+        let package = self.package()?;
+
+        let mut validator = Validator::memory(self.sources(package), package);
+
+        validator.configure(&self.manifest.lint);
+        validator.custom(custom);
+
+        if unused {
+            validator.unused();
+        }
+
+        for input in self.inputs(package) {
+            validator.input(&input);
+        }
+
+        validator.validate()
+    }
+
+    /// Verifies that the protos of the project and their imports compile
+    pub fn verify(&self) -> miette::Result<()> {
+        let package = self.package()?;
+
+        if self.protos.is_empty() {
+            return Ok(());
+        }
+
+        let mut validator = Validator::memory(self.sources(package), package);
+
+        for input in self.inputs(package) {
+            validator.input(&input);
+        }
+
+        validator.compile()
+    }
+
+    /// Compares the protos of the project against a baseline version for breaking changes
+    ///
+    /// Imports of both versions are resolved from the dependencies of this project. If `clients`
+    /// is set, only changes of services affecting existing clients are reported.
+    pub fn breaking(
+        &self,
+        baseline: &VirtualProject,
+        clients: bool,
+    ) -> miette::Result<Vec<BreakingChange>> {
+        let package = self.package()?;
+
+        let baseline = Self {
+            manifest: self.manifest.clone(),
+            protos: baseline.protos.clone(),
+            vendored: self.vendored.clone(),
+        };
+
+        let mut check = BreakingCheck::memory(baseline.sources(package), self.sources(package));
+
+        if clients {
+            check.clients();
+        }
+
+        for input in baseline.inputs(package) {
+            check.baseline(&input);
+        }
+
+        for input in self.inputs(package) {
+            check.current(&input);
+        }
+
+        check.check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "[package]\ntype = \"api\"\nname = \"physics\"\nversion = \"1.0.0\"\n\n[dependencies]\nunits = { version = \"=1.0.0\", registry = \"https://example.com\", repository = \"protos\" }\n";
+
+    const UNITS: &str =
+        "syntax = \"proto3\";\npackage units;\nmessage Mass { double value = 1; }\n";
+
+    fn project(body: &str) -> VirtualProject {
+        VirtualProject::new(MANIFEST)
+            .unwrap()
+            .with_dependency_proto("units/units.proto", UNITS)
+            .with_proto(
+                "physics.proto",
+                format!("syntax = \"proto3\";\npackage physics;\nimport \"units/units.proto\";\n{body}\n"),
+            )
+    }
+
+    #[test]
+    fn lints_protos_in_memory() {
+        let project = project("message Body { units.Mass mass = 1; }");
+
+        project.verify().unwrap();
+
+        assert!(project
+            .lint(&CustomRules::default(), false)
+            .unwrap()
+            .is_empty());
+
+        let misnamed = VirtualProject::new(MANIFEST).unwrap().with_proto(
+            "physics.proto",
+            "syntax = \"proto3\";\npackage chemistry;\n",
+        );
+
+        let violations = misnamed.lint(&CustomRules::default(), false).unwrap();
+
+        assert!(violations
+            .iter()
+            .any(|violation| violation.rule == "PackageName"));
+    }
+
+    #[test]
+    fn reports_unresolved_imports() {
+        let project = VirtualProject::new(MANIFEST).unwrap().with_proto(
+            "physics.proto",
+            "syntax = \"proto3\";\npackage physics;\nimport \"units/units.proto\";\n",
+        );
+
+        assert!(project.verify().is_err());
+    }
+
+    #[test]
+    fn detects_breaking_changes_in_memory() {
+        let baseline = project("message Body { units.Mass mass = 1; string name = 2; }");
+        let current = project("message Body { units.Mass mass = 1; }");
+
+        let changes = current.breaking(&baseline, false).unwrap();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "physics.Body");
+
+        assert!(baseline.breaking(&baseline, false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        assert!(VirtualProject::new("[package\n").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt::{self, Display, Formatter};
+
+use regex::Regex;
+
+use crate::package::strip_comments;
+
+/// Field number and name freed by removing a field, which should be reserved to prevent reuse.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Reservation {
+    /// Fully qualified name of the message the field was removed from
+    pub message: String,
+    /// Number of the removed field
+    pub number: i32,
+    /// Name of the removed field
+    pub name: String,
+}
+
+impl Reservation {
+    /// Statements reserving the number and name of the removed field.
+    pub fn statement(&self) -> String {
+        format!("reserved {}; reserved \"{}\";", self.number, self.name)
+    }
+
+    /// Inserts the reservation into the contents of a proto file.
+    ///
+    /// Returns `None` if the file does not declare the message at its top level.
+    pub fn apply(&self, contents: &str) -> Option<String> {
+        let code = strip_comments(contents);
+
+        let package = code.split(';').find_map(|statement| {
+            let rest = statement.trim().strip_prefix("package")?;
+
+            rest.starts_with(char::is_whitespace)
+                .then(|| rest.trim().to_string())
+        })?;
+
+        let message = self.message.strip_prefix(&format!("{package}."))?;
+
+        let pattern = Regex::new(&format!(r"\bmessage\s+{}\s*\{{", regex::escape(message))).ok()?;
+
+        let declaration = pattern.find_iter(&code).find(|m| {
+            let prefix = &code[..m.start()];
+            prefix.matches('{').count() == prefix.matches('}').count()
+        })?;
+
+        let line = contents[..declaration.start()]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+
+        let indent: String = contents[line..]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+
+        let (head, body) = contents.split_at(declaration.end());
+
+        Some(format!(
+            "{head}\n{indent}  reserved {};\n{indent}  reserved \"{}\";\n{body}",
+            self.number, self.name
+        ))
+    }
+}
+
+impl Display for Reservation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "field {} ({}) of {}",
+            self.name, self.number, self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reservation() -> Reservation {
+        Reservation {
+            message: "physics.units.Mass".into(),
+            number: 2,
+            name: "unit".into(),
+        }
+    }
+
+    #[test]
+    fn inserts_reserved_statements() {
+        let proto = r#"syntax = "proto3";
+
+package physics.units;
+
+// message Mass { is only mentioned here
+message Sensor {
+  message Mass {
+    double value = 1;
+  }
+}
+
+message Mass {
+  double value = 1;
+}
+"#;
+
+        let expected = r#"syntax = "proto3";
+
+package physics.units;
+
+// message Mass { is only mentioned here
+message Sensor {
+  message Mass {
+    double value = 1;
+  }
+}
+
+message Mass {
+  reserved 2;
+  reserved "unit";
+
+  double value = 1;
+}
+"#;
+
+        assert_eq!(reservation().apply(proto).as_deref(), Some(expected));
+    }
+
+    #[test]
+    fn skips_other_packages() {
+        let proto = r#"
+            syntax = "proto3";
+
+            package physics;
+
+            message Mass {}
+        "#;
+
+        assert_eq!(reservation().apply(proto), None);
+    }
+}
@@ -14,11 +14,13 @@
 
 use std::fmt::Debug;
 
+use enum_zero_value::EnumZeroValue;
+use field_numbering::FieldNumbering;
 use lib_package::LibPackage;
 use package_hierarchy::PackageHierarchy;
 
 use crate::{
-    manifest::PackageManifest,
+    manifest::{PackageManifest, PackageNaming},
     package::PackageType,
     validation::{
         data::*,
@@ -26,12 +28,15 @@ use crate::{
     },
 };
 
+mod enum_zero_value;
+mod field_numbering;
 mod ident_casing;
 mod lib_package;
 mod package_hierarchy;
 mod package_name;
+mod unused_definition;
 
-pub use self::{ident_casing::*, package_name::*};
+pub use self::{ident_casing::*, package_name::*, unused_definition::*};
 
 /// Collection of rules.
 pub type RuleSet = Vec<Box<dyn Rule>>;
@@ -41,12 +46,12 @@ pub trait Rule: Debug {
     /// Name of this rule.
     ///
     /// Defaults to the name of the type of this rule.
-    fn rule_name(&self) -> &'static str {
+    fn rule_name(&self) -> &str {
         std::any::type_name::<Self>().split("::").last().unwrap()
     }
 
     /// Help text for rule.
-    fn rule_info(&self) -> &'static str;
+    fn rule_info(&self) -> &str;
 
     /// Default severity [`Level`] of the rule.
     fn rule_level(&self) -> Level {
@@ -109,11 +114,17 @@ impl Rule for RuleSet {
 }
 
 /// Get default rules for a given `buffrs` package name.
-pub fn all(manifest: &PackageManifest) -> RuleSet {
+///
+/// The `naming` convention determines the protobuf package the package has to declare.
+pub fn all(manifest: &PackageManifest, naming: PackageNaming) -> RuleSet {
+    let prefix = naming.prefix(&manifest.name);
+
     let mut ret: Vec<Box<dyn Rule>> = vec![
-        Box::new(PackageName::new(manifest.name.clone())),
+        Box::new(PackageName::new(manifest.name.clone()).with_naming(naming)),
         Box::new(IdentCasing),
-        Box::new(PackageHierarchy),
+        Box::new(PackageHierarchy::new(&prefix)),
+        Box::new(EnumZeroValue),
+        Box::new(FieldNumbering),
     ];
 
     if manifest.kind == PackageType::Lib {
@@ -136,19 +147,21 @@ mod tests {
             name: crate::package::PackageName::new("package")?,
             version: Version::new(0, 1, 0),
             description: Default::default(),
+            license: Default::default(),
+            max_size: Default::default(),
         };
 
-        let all = all(&manifest)
-            .iter()
-            .map(|r| r.rule_name())
-            .collect::<Vec<_>>();
+        let rules = all(&manifest, PackageNaming::default());
+        let all = rules.iter().map(|r| r.rule_name()).collect::<Vec<_>>();
 
         assert_eq!(
             all,
             &[
                 PackageName::new(manifest.name.clone()).rule_name(),
                 IdentCasing.rule_name(),
-                PackageHierarchy.rule_name(),
+                PackageHierarchy::default().rule_name(),
+                EnumZeroValue.rule_name(),
+                FieldNumbering.rule_name(),
             ],
         );
 
@@ -162,19 +175,21 @@ mod tests {
             name: crate::package::PackageName::new("package")?,
             version: Version::new(0, 1, 0),
             description: Default::default(),
+            license: Default::default(),
+            max_size: Default::default(),
         };
 
-        let all = all(&manifest)
-            .iter()
-            .map(|r| r.rule_name())
-            .collect::<Vec<_>>();
+        let rules = all(&manifest, PackageNaming::default());
+        let all = rules.iter().map(|r| r.rule_name()).collect::<Vec<_>>();
 
         assert_eq!(
             all,
             &[
                 PackageName::new(manifest.name.clone()).rule_name(),
                 IdentCasing.rule_name(),
-                PackageHierarchy.rule_name(),
+                PackageHierarchy::default().rule_name(),
+                EnumZeroValue.rule_name(),
+                FieldNumbering.rule_name(),
                 LibPackage.rule_name(),
             ],
         );
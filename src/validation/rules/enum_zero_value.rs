@@ -0,0 +1,164 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+
+/// Ensure that enums have an unspecified zero value.
+#[derive(Debug, Clone, Default)]
+pub struct EnumZeroValue;
+
+impl Rule for EnumZeroValue {
+    fn rule_info(&self) -> &'static str {
+        "Make sure that the zero value of enums does not carry meaning."
+    }
+
+    fn rule_level(&self) -> Level {
+        Level::Warning
+    }
+
+    fn check_entity(&mut self, name: &str, entity: &Entity) -> Violations {
+        let Entity::Enum(entity) = entity else {
+            return vec![];
+        };
+
+        let expected = format!("{}_UNSPECIFIED", upper_snake_case(name));
+
+        let message = match entity.values.get(&0) {
+            None => format!("enum {name} has no zero value"),
+            Some(value) if value.name.ends_with("_UNSPECIFIED") => return vec![],
+            Some(value) => format!(
+                "zero value {} of enum {name} is not unspecified",
+                value.name
+            ),
+        };
+
+        vec![self.to_violation(violation::Message {
+            message,
+            help: format!("Unset enum fields decode as zero, so the zero value should be named `{expected}` instead of carrying meaning."),
+        })]
+    }
+}
+
+/// Converts a PascalCase identifier into UPPER_SNAKE_CASE.
+///
+/// A run of capitals is an acronym and stays one word, e.g. `HTTPStatus` becomes `HTTP_STATUS`.
+fn upper_snake_case(ident: &str) -> String {
+    let chars: Vec<char> = ident.chars().collect();
+    let mut ret = String::with_capacity(ident.len() + 4);
+
+    for (index, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next = chars.get(index + 1);
+
+            let starts_word = !previous.is_ascii_uppercase()
+                || next.is_some_and(|next| next.is_ascii_lowercase());
+
+            if starts_word {
+                ret.push('_');
+            }
+        }
+
+        ret.push(c.to_ascii_uppercase());
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(values: &[(i32, &str)]) -> Entity {
+        Entity::Enum(Enum {
+            values: values
+                .iter()
+                .map(|(number, name)| {
+                    (
+                        *number,
+                        EnumValue {
+                            name: name.to_string(),
+                        },
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    #[test]
+    fn can_convert_to_upper_snake_case() {
+        assert_eq!(upper_snake_case("PhoneType"), "PHONE_TYPE");
+        assert_eq!(upper_snake_case("Kind"), "KIND");
+        assert_eq!(upper_snake_case("HTTPStatus"), "HTTP_STATUS");
+        assert_eq!(upper_snake_case("StatusHTTP"), "STATUS_HTTP");
+    }
+
+    #[test]
+    fn acronym_prefixed_enum() {
+        let mut rule = EnumZeroValue;
+        let entity = entity(&[(0, "OK"), (1, "NOT_FOUND")]);
+        let violations = rule.check_entity("HTTPStatus", &entity);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].message.help,
+            "Unset enum fields decode as zero, so the zero value should be named `HTTP_STATUS_UNSPECIFIED` instead of carrying meaning."
+        );
+    }
+
+    #[test]
+    fn correct_zero_value() {
+        let mut rule = EnumZeroValue;
+        let entity = entity(&[(0, "PHONE_TYPE_UNSPECIFIED"), (1, "PHONE_TYPE_MOBILE")]);
+        assert!(rule.check_entity("PhoneType", &entity).is_empty());
+    }
+
+    #[test]
+    fn meaningful_zero_value() {
+        let mut rule = EnumZeroValue;
+        let entity = entity(&[(0, "MOBILE"), (1, "HOME")]);
+        assert_eq!(
+            rule.check_entity("PhoneType", &entity),
+            vec![Violation {
+                rule: "EnumZeroValue".into(),
+                level: Level::Warning,
+                location: Default::default(),
+                info: rule.rule_info().into(),
+                message: violation::Message {
+                    message: "zero value MOBILE of enum PhoneType is not unspecified".into(),
+                    help: "Unset enum fields decode as zero, so the zero value should be named `PHONE_TYPE_UNSPECIFIED` instead of carrying meaning.".into(),
+                }
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_zero_value() {
+        let mut rule = EnumZeroValue;
+        let entity = entity(&[(1, "HOME")]);
+        let violations = rule.check_entity("PhoneType", &entity);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].message.message,
+            "enum PhoneType has no zero value"
+        );
+    }
+
+    #[test]
+    fn ignores_messages() {
+        let mut rule = EnumZeroValue;
+        assert!(rule
+            .check_entity("Person", &Entity::Message(Default::default()))
+            .is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::*;
+use crate::validation::data::Message;
+
+/// Ensure that gaps in field numbers are reserved.
+#[derive(Debug, Clone, Default)]
+pub struct FieldNumbering;
+
+impl Rule for FieldNumbering {
+    fn rule_info(&self) -> &'static str {
+        "Make sure that unused field numbers are reserved."
+    }
+
+    fn rule_level(&self) -> Level {
+        Level::Info
+    }
+
+    fn check_entity(&mut self, name: &str, entity: &Entity) -> Violations {
+        let Entity::Message(message) = entity else {
+            return vec![];
+        };
+
+        let gaps = gaps(message);
+
+        if gaps.is_empty() {
+            return vec![];
+        }
+
+        let gaps = gaps
+            .iter()
+            .map(|(start, end)| match start == end {
+                true => start.to_string(),
+                false => format!("{start}-{end}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        vec![self.to_violation(violation::Message {
+            message: format!("message {name} skips field numbers {gaps}"),
+            help: "Mark the numbers of removed fields as `reserved` so they are not reused with a different meaning.".into(),
+        })]
+    }
+}
+
+/// Inclusive ranges of field numbers that are neither used nor reserved.
+fn gaps(message: &Message) -> Vec<(i32, i32)> {
+    let mut gaps = Vec::new();
+    let mut next = 1;
+
+    for &number in message.fields.keys() {
+        if number > next {
+            gaps.extend(unreserved(message, next, number - 1));
+        }
+
+        next = number + 1;
+    }
+
+    gaps
+}
+
+/// Inclusive ranges within `start..=end` that are not covered by a reserved range.
+fn unreserved(message: &Message, mut start: i32, end: i32) -> Vec<(i32, i32)> {
+    let mut reserved: Vec<_> = message
+        .reserved
+        .iter()
+        .map(|range| (range.start, range.end - 1))
+        .collect();
+
+    reserved.sort();
+
+    let mut ranges = Vec::new();
+
+    for (first, last) in reserved {
+        if last < start {
+            continue;
+        }
+
+        if first > end {
+            break;
+        }
+
+        if first > start {
+            ranges.push((start, first - 1));
+        }
+
+        start = last + 1;
+    }
+
+    if start <= end {
+        ranges.push((start, end));
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(numbers: &[i32], reserved: Vec<ReservedRange>) -> Message {
+        Message {
+            fields: numbers
+                .iter()
+                .map(|number| {
+                    (
+                        *number,
+                        Field {
+                            name: format!("field_{number}"),
+                            type_: FieldType::String,
+                            type_name: None,
+                            label: None,
+                            default: None,
+                        },
+                    )
+                })
+                .collect(),
+            reserved,
+            nested: Default::default(),
+        }
+    }
+
+    #[test]
+    fn sequential_fields() {
+        assert!(gaps(&message(&[1, 2, 3], vec![])).is_empty());
+        assert!(gaps(&message(&[], vec![])).is_empty());
+    }
+
+    #[test]
+    fn can_find_gaps() {
+        assert_eq!(gaps(&message(&[1, 3, 7], vec![])), vec![(2, 2), (4, 6)]);
+        assert_eq!(gaps(&message(&[5], vec![])), vec![(1, 4)]);
+    }
+
+    #[test]
+    fn reserved_numbers_are_not_gaps() {
+        let reserved = vec![ReservedRange { start: 2, end: 4 }];
+        assert!(gaps(&message(&[1, 4], reserved)).is_empty());
+
+        let reserved = vec![ReservedRange { start: 3, end: 5 }];
+        assert_eq!(gaps(&message(&[1, 8], reserved)), vec![(2, 2), (5, 7)]);
+    }
+
+    #[test]
+    fn reports_gaps() {
+        let mut rule = FieldNumbering;
+        let violations = rule.check_entity("Person", &Entity::Message(message(&[1, 3, 7], vec![])));
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].message.message,
+            "message Person skips field numbers 2, 4-6"
+        );
+        assert_eq!(violations[0].level, Level::Info);
+    }
+}
@@ -14,10 +14,19 @@
 
 use super::*;
 
-/// Ensure that file names match package names.
+/// Ensure that entity names follow the protocol buffer style guide.
 #[derive(Debug, Clone, Default)]
 pub struct IdentCasing;
 
+impl IdentCasing {
+    fn violation(&self, kind: &str, name: &str, casing: &str) -> Violation {
+        self.to_violation(violation::Message {
+            message: format!("{kind} {name} should be {casing}"),
+            help: "Messages, enums and services use PascalCase, fields use snake_case and enum values use UPPER_SNAKE_CASE.".into(),
+        })
+    }
+}
+
 impl Rule for IdentCasing {
     fn rule_info(&self) -> &'static str {
         "making sure entity names are correct"
@@ -29,7 +38,164 @@ impl Rule for IdentCasing {
     }
 
     /// Check [`Entity`] for violations.
-    fn check_entity(&mut self, _name: &str, _entity: &Entity) -> Violations {
-        vec![]
+    fn check_entity(&mut self, name: &str, entity: &Entity) -> Violations {
+        let mut violations = Violations::default();
+
+        let kind = match entity {
+            Entity::Enum(_) => "enum",
+            Entity::Service(_) => "service",
+            Entity::Message(_) => "message",
+        };
+
+        if !is_pascal_case(name) {
+            violations.push(self.violation(kind, name, "PascalCase"));
+        }
+
+        match entity {
+            Entity::Message(message) => {
+                for field in message.fields.values() {
+                    if !is_snake_case(&field.name) {
+                        violations.push(self.violation("field", &field.name, "snake_case"));
+                    }
+                }
+            }
+            Entity::Enum(entity) => {
+                for value in entity.values.values() {
+                    if !is_upper_snake_case(&value.name) {
+                        violations.push(self.violation(
+                            "enum value",
+                            &value.name,
+                            "UPPER_SNAKE_CASE",
+                        ));
+                    }
+                }
+            }
+            Entity::Service(_) => {}
+        }
+
+        violations
+    }
+}
+
+fn is_pascal_case(ident: &str) -> bool {
+    ident.starts_with(|c: char| c.is_ascii_uppercase())
+        && ident.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_snake_case(ident: &str) -> bool {
+    ident.starts_with(|c: char| c.is_ascii_lowercase())
+        && ident
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !ident.contains("__")
+}
+
+fn is_upper_snake_case(ident: &str) -> bool {
+    ident.starts_with(|c: char| c.is_ascii_uppercase())
+        && ident
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        && !ident.contains("__")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::data::Message;
+
+    #[test]
+    fn can_check_casing() {
+        assert!(is_pascal_case("AddressBook"));
+        assert!(is_pascal_case("V1"));
+        assert!(!is_pascal_case("addressBook"));
+        assert!(!is_pascal_case("Address_Book"));
+
+        assert!(is_snake_case("last_updated"));
+        assert!(is_snake_case("field2"));
+        assert!(!is_snake_case("lastUpdated"));
+        assert!(!is_snake_case("last__updated"));
+
+        assert!(is_upper_snake_case("PHONE_TYPE_MOBILE"));
+        assert!(!is_upper_snake_case("Mobile"));
+    }
+
+    #[test]
+    fn correct_message_names() {
+        let message = Message {
+            fields: [(
+                1,
+                Field {
+                    name: "last_updated".into(),
+                    type_: FieldType::String,
+                    type_name: None,
+                    label: None,
+                    default: None,
+                },
+            )]
+            .into(),
+            reserved: vec![],
+            nested: Default::default(),
+        };
+
+        let mut rule = IdentCasing;
+        assert!(rule
+            .check_entity("AddressBook", &Entity::Message(message))
+            .is_empty());
+    }
+
+    #[test]
+    fn incorrect_names() {
+        let message = Message {
+            fields: [(
+                1,
+                Field {
+                    name: "lastUpdated".into(),
+                    type_: FieldType::String,
+                    type_name: None,
+                    label: None,
+                    default: None,
+                },
+            )]
+            .into(),
+            reserved: vec![],
+            nested: Default::default(),
+        };
+
+        let mut rule = IdentCasing;
+        let violations = rule.check_entity("address_book", &Entity::Message(message));
+
+        assert_eq!(
+            violations
+                .iter()
+                .map(|v| v.message.message.as_str())
+                .collect::<Vec<_>>(),
+            &[
+                "message address_book should be PascalCase",
+                "field lastUpdated should be snake_case",
+            ]
+        );
+        assert!(violations.iter().all(|v| v.level == Level::Info));
+    }
+
+    #[test]
+    fn incorrect_enum_values() {
+        let entity = Enum {
+            values: [(
+                0,
+                EnumValue {
+                    name: "mobile".into(),
+                },
+            )]
+            .into(),
+        };
+
+        let mut rule = IdentCasing;
+        let violations = rule.check_entity("PhoneType", &Entity::Enum(entity));
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].message.message,
+            "enum value mobile should be UPPER_SNAKE_CASE"
+        );
     }
 }
@@ -38,8 +38,8 @@ mod tests {
     fn should_complain_about_service_defs() {
         let entities = {
             let mut ret: BTreeMap<String, Entity> = BTreeMap::new();
-            ret.insert("service A".into(), Entity::Service(Service {}));
-            ret.insert("service B".into(), Entity::Service(Service {}));
+            ret.insert("service A".into(), Entity::Service(Service::default()));
+            ret.insert("service B".into(), Entity::Service(Service::default()));
 
             ret
         };
@@ -48,6 +48,7 @@ mod tests {
             name: "my package".into(),
             files: vec!["ignored.proto".into()],
             entities,
+            options: Default::default(),
         };
 
         let violations = LibPackage.check_package(&package);
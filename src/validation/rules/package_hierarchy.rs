@@ -1,7 +1,25 @@
 use super::*;
 
 #[derive(Debug, Clone, Copy)]
-pub struct PackageHierarchy;
+pub struct PackageHierarchy {
+    /// Number of leading components of the package that are not mirrored in folders.
+    depth: usize,
+}
+
+impl PackageHierarchy {
+    /// Create new checker for packages nested in the protobuf package `prefix`.
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            depth: prefix.split('.').count(),
+        }
+    }
+}
+
+impl Default for PackageHierarchy {
+    fn default() -> Self {
+        Self { depth: 1 }
+    }
+}
 
 impl Rule for PackageHierarchy {
     fn rule_info(&self) -> &'static str {
@@ -12,7 +30,7 @@ impl Rule for PackageHierarchy {
         let expected_components = package
             .name
             .split('.')
-            .skip(1) // first part is the root package name, which is not expected to be a subfolder of proto
+            .skip(self.depth) // the root package name is not expected to be a subfolder of proto
             .collect::<Vec<_>>();
 
         let expected_path = {
@@ -77,9 +95,10 @@ mod tests {
             name: "root".into(),
             files: vec!["proto/file_not_called_root.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
 
-        let result = PackageHierarchy.check_package(&package);
+        let result = PackageHierarchy::default().check_package(&package);
         assert!(result.is_empty());
     }
 
@@ -89,9 +108,10 @@ mod tests {
             name: "mypackage.sub1.sub2".into(),
             files: vec!["proto/sub1/sub2/file.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
 
-        let result = PackageHierarchy.check_package(&package);
+        let result = PackageHierarchy::default().check_package(&package);
         assert!(result.is_empty());
     }
 
@@ -101,9 +121,10 @@ mod tests {
             name: "mypackage.subpackage".into(),
             files: vec!["proto/file.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
         assert_eq!(
-            PackageHierarchy.check_package(&package),
+            PackageHierarchy::default().check_package(&package),
             &[Violation {
                 rule: "PackageHierarchy".into(),
                 level: Level::Error,
@@ -112,7 +133,7 @@ mod tests {
                     help: EXPECTED_HELP.into(),
                 },
                 location: Default::default(),
-                info: PackageHierarchy.rule_info().into(),
+                info: PackageHierarchy::default().rule_info().into(),
             }],
         );
     }
@@ -123,9 +144,10 @@ mod tests {
             name: "mypackage.subpackage".into(),
             files: vec!["proto/not_subpackage/file.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
         assert_eq!(
-            PackageHierarchy.check_package(&package),
+            PackageHierarchy::default().check_package(&package),
             &[Violation {
                 rule: "PackageHierarchy".into(),
                 level: Level::Error,
@@ -136,7 +158,7 @@ mod tests {
                     help: EXPECTED_HELP.into(),
                 },
                 location: Default::default(),
-                info: PackageHierarchy.rule_info().into(),
+                info: PackageHierarchy::default().rule_info().into(),
             }],
         );
     }
@@ -151,9 +173,10 @@ mod tests {
                 "proto/foo/bar/file.proto".into(),
             ],
             entities: Default::default(),
+            options: Default::default(),
         };
         assert_eq!(
-            PackageHierarchy.check_package(&package),
+            PackageHierarchy::default().check_package(&package),
             &[
                 Violation {
                     rule: "PackageHierarchy".into(),
@@ -165,7 +188,7 @@ mod tests {
                         help: EXPECTED_HELP.into(),
                     },
                     location: Default::default(),
-                    info: PackageHierarchy.rule_info().into(),
+                    info: PackageHierarchy::default().rule_info().into(),
                 },
                 Violation {
                     rule: "PackageHierarchy".into(),
@@ -176,9 +199,30 @@ mod tests {
                         help: EXPECTED_HELP.into(),
                     },
                     location: Default::default(),
-                    info: PackageHierarchy.rule_info().into(),
+                    info: PackageHierarchy::default().rule_info().into(),
                 }
             ],
         );
     }
+
+    #[test]
+    fn should_skip_all_components_of_the_prefix() {
+        let package = Package {
+            name: "physics.units.v1".into(),
+            files: vec!["physics-units/v1/mass.proto".into()],
+            entities: Default::default(),
+            options: Default::default(),
+        };
+        assert!(PackageHierarchy::new("physics.units")
+            .check_package(&package)
+            .is_empty());
+
+        let package = Package {
+            files: vec!["physics-units/units/v1/mass.proto".into()],
+            ..package
+        };
+        assert!(PackageHierarchy::default()
+            .check_package(&package)
+            .is_empty());
+    }
 }
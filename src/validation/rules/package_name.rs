@@ -19,12 +19,22 @@ use super::*;
 pub struct PackageName {
     /// Package name to enforce.
     name: crate::package::PackageName,
+    /// Convention deriving the protobuf package from the package name.
+    naming: PackageNaming,
 }
 
 impl PackageName {
     /// Create new checker for this rule.
     pub fn new(name: crate::package::PackageName) -> Self {
-        Self { name }
+        Self {
+            name,
+            naming: PackageNaming::default(),
+        }
+    }
+
+    /// Use a different naming convention than underscores.
+    pub fn with_naming(self, naming: PackageNaming) -> Self {
+        Self { naming, ..self }
     }
 }
 
@@ -34,7 +44,7 @@ impl Rule for PackageName {
     }
 
     fn check_package(&mut self, package: &Package) -> Violations {
-        let transposed = self.name.to_string().replace('-', "_");
+        let transposed = self.naming.prefix(&self.name);
 
         if !is_prefix(&transposed, &package.name) {
             let message = violation::Message {
@@ -84,6 +94,7 @@ mod tests {
             name: "my_package".into(),
             files: vec!["ignored.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
         let mut rule = PackageName::new("my-package".parse().unwrap());
         assert!(rule.check_package(&package).is_empty());
@@ -95,6 +106,7 @@ mod tests {
             name: "my_package.submodule".into(),
             files: vec!["ignored.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
         let mut rule = PackageName::new("my-package".parse().unwrap());
         assert!(rule.check_package(&package).is_empty());
@@ -106,17 +118,41 @@ mod tests {
             name: "my_package.submodule".into(),
             files: vec!["ignored.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
         let mut rule = PackageName::new("my-package".parse().unwrap());
         assert!(rule.check_package(&package).is_empty());
     }
 
+    #[test]
+    fn dotted_naming_convention() {
+        let package = Package {
+            name: "physics.units.v1".into(),
+            files: vec!["ignored.proto".into()],
+            entities: Default::default(),
+            options: Default::default(),
+        };
+        let mut rule =
+            PackageName::new("physics-units".parse().unwrap()).with_naming(PackageNaming::Dotted);
+        assert!(rule.check_package(&package).is_empty());
+
+        let package = Package {
+            name: "physics.sensors.v1".into(),
+            ..package
+        };
+        assert_eq!(
+            rule.check_package(&package)[0].message.message,
+            "package name is physics.sensors.v1 but should have physics.units prefix"
+        );
+    }
+
     #[test]
     fn incorrect_package_name() {
         let package = Package {
             name: "my_package_other".into(),
             files: vec!["ignored.proto".into()],
             entities: Default::default(),
+            options: Default::default(),
         };
         let mut rule = PackageName::new("my-package".parse().unwrap());
         assert_eq!(
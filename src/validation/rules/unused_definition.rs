@@ -0,0 +1,249 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::*;
+use crate::validation::data::Message;
+
+/// Report messages and enums that no service depends on.
+///
+/// Every service is considered part of the public interface of a package, and so is every
+/// message or enum that it references, directly or through other definitions. Packages without
+/// any service export all of their definitions and are not checked.
+#[derive(Debug, Clone, Default)]
+pub struct UnusedDefinition;
+
+impl Rule for UnusedDefinition {
+    fn rule_info(&self) -> &'static str {
+        "Make sure that all definitions are reachable from a service."
+    }
+
+    fn rule_level(&self) -> Level {
+        Level::Info
+    }
+
+    fn check_packages(&mut self, packages: &Packages) -> Violations {
+        let definitions: BTreeMap<String, (&Package, &str, &Entity)> = packages
+            .packages
+            .values()
+            .flat_map(|package| {
+                package.entities.iter().map(move |(name, entity)| {
+                    (
+                        format!(".{}.{name}", package.name),
+                        (package, name.as_str(), entity),
+                    )
+                })
+            })
+            .collect();
+
+        let mut pending: Vec<String> = definitions
+            .iter()
+            .filter(|(_, (_, _, entity))| matches!(entity, Entity::Service(_)))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        if pending.is_empty() {
+            return vec![];
+        }
+
+        let mut reachable = BTreeSet::new();
+
+        while let Some(type_name) = pending.pop() {
+            let Some(path) = resolve(&definitions, &type_name) else {
+                continue; // defined in a dependency
+            };
+
+            if !reachable.insert(path.to_string()) {
+                continue;
+            }
+
+            match definitions[path].2 {
+                Entity::Service(service) => {
+                    for method in service.methods.values() {
+                        pending.push(method.input_type.clone());
+                        pending.push(method.output_type.clone());
+                    }
+                }
+                Entity::Message(message) => references(message, &mut pending),
+                Entity::Enum(_) => {}
+            }
+        }
+
+        definitions
+            .iter()
+            .filter(|(path, _)| !reachable.contains(*path))
+            .map(|(_, (package, name, entity))| {
+                let kind = match entity {
+                    Entity::Message(_) => "message",
+                    Entity::Enum(_) => "enum",
+                    Entity::Service(_) => "service",
+                };
+
+                let mut violation = self.to_violation(violation::Message {
+                    message: format!("{kind} {name} is not used by any service"),
+                    help: "Remove definitions that are no longer used, or move them into a lib package if they are meant to be shared.".into(),
+                });
+
+                violation.location = Location {
+                    file: package.files.last().map(|file| file.display().to_string()),
+                    package: Some(package.name.clone()),
+                    entity: Some(name.to_string()),
+                };
+
+                violation
+            })
+            .collect()
+    }
+}
+
+/// Collects the type names referenced by the fields of a message and its nested messages.
+fn references(message: &Message, pending: &mut Vec<String>) {
+    pending.extend(
+        message
+            .fields
+            .values()
+            .filter_map(|field| field.type_name.clone()),
+    );
+
+    for nested in message.nested.values() {
+        references(nested, pending);
+    }
+}
+
+/// Resolves a fully qualified type name to the top-level definition containing it.
+fn resolve<'a, T>(definitions: &'a BTreeMap<String, T>, type_name: &str) -> Option<&'a str> {
+    let mut path = type_name;
+
+    loop {
+        if let Some((path, _)) = definitions.get_key_value(path) {
+            return Some(path);
+        }
+
+        path = &path[..path.rfind('.')?];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(type_name: &str) -> Field {
+        Field {
+            name: "field".into(),
+            type_: FieldType::Message,
+            type_name: Some(type_name.into()),
+            label: None,
+            default: None,
+        }
+    }
+
+    fn message(type_names: &[&str]) -> Entity {
+        Entity::Message(Message {
+            fields: type_names
+                .iter()
+                .enumerate()
+                .map(|(index, type_name)| (index as i32 + 1, field(type_name)))
+                .collect(),
+            reserved: vec![],
+            nested: Default::default(),
+        })
+    }
+
+    fn service(input_type: &str, output_type: &str) -> Entity {
+        Entity::Service(Service {
+            methods: [(
+                "Call".to_string(),
+                Method {
+                    input_type: input_type.into(),
+                    output_type: output_type.into(),
+                    client_streaming: false,
+                    server_streaming: false,
+                },
+            )]
+            .into(),
+        })
+    }
+
+    fn packages(entities: Vec<(&str, Entity)>) -> Packages {
+        let package = Package {
+            name: "physics".into(),
+            files: vec!["physics.proto".into()],
+            entities: entities
+                .into_iter()
+                .map(|(name, entity)| (name.to_string(), entity))
+                .collect(),
+            options: Default::default(),
+        };
+
+        Packages {
+            packages: [(package.name.clone(), package)].into(),
+        }
+    }
+
+    fn unused(packages: &Packages) -> Vec<String> {
+        UnusedDefinition
+            .check_packages(packages)
+            .into_iter()
+            .filter_map(|violation| violation.location.entity)
+            .collect()
+    }
+
+    #[test]
+    fn packages_without_services_are_not_checked() {
+        let packages = packages(vec![("Mass", message(&[])), ("Unit", message(&[]))]);
+
+        assert!(unused(&packages).is_empty());
+    }
+
+    #[test]
+    fn follows_references_transitively() {
+        let mut measurement = Message {
+            fields: [(1, field(".physics.Mass"))].into(),
+            reserved: vec![],
+            nested: Default::default(),
+        };
+
+        let nested = Message {
+            fields: [(1, field(".physics.Unit"))].into(),
+            reserved: vec![],
+            nested: Default::default(),
+        };
+
+        measurement.nested.insert("Reading".into(), nested);
+
+        let packages = packages(vec![
+            ("Scale", service(".physics.Measurement", ".physics.Empty")),
+            ("Measurement", Entity::Message(measurement)),
+            ("Mass", message(&[])),
+            ("Unit", Entity::Enum(Enum::default())),
+            ("Empty", message(&[".google.protobuf.Empty"])),
+            ("Legacy", message(&[".physics.Orphan"])),
+            ("Orphan", message(&[".physics.Legacy"])),
+        ]);
+
+        assert_eq!(unused(&packages), vec!["Legacy", "Orphan"]);
+    }
+
+    #[test]
+    fn resolves_nested_type_names() {
+        let definitions: BTreeMap<String, ()> = [(".physics.Measurement".to_string(), ())].into();
+
+        assert_eq!(
+            resolve(&definitions, ".physics.Measurement.Reading"),
+            Some(".physics.Measurement")
+        );
+        assert_eq!(resolve(&definitions, ".physics.Mass"), None);
+    }
+}
@@ -0,0 +1,303 @@
+// Copyright 2023 Helsing GmbH
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Vendored copies of the locked dependencies, for hermetic builds and source archival
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use miette::{bail, ensure, miette, Context, IntoDiagnostic};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::DigestMismatch,
+    lock::{Digest, DigestAlgorithm, LockedPackage},
+    package::{Package, PackageName},
+};
+
+/// Directory of the project the dependencies are vendored into
+pub const VENDOR_DIR: &str = "vendor";
+
+/// Index of the vendored archives and their checksums, within the vendor directory
+pub const CHECKSUMS_FILE: &str = "checksums.toml";
+
+/// A vendored package, as recorded in the checksum index
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VendoredPackage {
+    /// Name of the package
+    pub name: PackageName,
+    /// Version of the package
+    pub version: Version,
+    /// File name of the archive within the vendor directory
+    pub archive: String,
+    /// Digest of the archive
+    pub digest: Digest,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RawChecksums {
+    packages: Vec<VendoredPackage>,
+}
+
+/// A directory holding the archives of the locked dependencies, their extracted protos and an
+/// index of the checksums of the archives
+///
+/// ```text
+/// vendor/
+/// ├── checksums.toml
+/// ├── units-1.0.0.tgz
+/// └── units/
+///     └── units.proto
+/// ```
+pub struct Vendor {
+    root: PathBuf,
+    packages: BTreeMap<PackageName, VendoredPackage>,
+}
+
+impl Vendor {
+    /// Opens a vendor directory, which is empty if it does not exist yet
+    pub async fn open(root: impl Into<PathBuf>) -> miette::Result<Self> {
+        let root = root.into();
+        let path = root.join(CHECKSUMS_FILE);
+
+        let raw: RawChecksums = match tokio::fs::read_to_string(&path).await {
+            Ok(contents) => toml::from_str(&contents)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to parse {}", path.display()))?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => RawChecksums::default(),
+            Err(error) => {
+                return Err(error)
+                    .into_diagnostic()
+                    .wrap_err(miette!("failed to read {}", path.display()))
+            }
+        };
+
+        let packages = raw
+            .packages
+            .into_iter()
+            .map(|package| (package.name.clone(), package))
+            .collect();
+
+        Ok(Self { root, packages })
+    }
+
+    /// The vendored packages, sorted by name
+    pub fn packages(&self) -> impl Iterator<Item = &VendoredPackage> {
+        self.packages.values()
+    }
+
+    /// Directory the protos of a vendored package are extracted to
+    pub fn unpacked(&self, name: &PackageName) -> PathBuf {
+        self.root.join(name.to_string())
+    }
+
+    /// Vendors a package, replacing any other vendored version of it
+    pub async fn add(&mut self, package: &Package) -> miette::Result<()> {
+        if let Some(previous) = self.packages.remove(package.name()) {
+            self.remove_files(&previous).await?;
+        }
+
+        let archive = format!("{}-{}.tgz", package.name(), package.version());
+        let path = self.root.join(&archive);
+
+        tokio::fs::create_dir_all(&self.root)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to create {}", self.root.display()))?;
+
+        tokio::fs::write(&path, &package.tgz)
+            .await
+            .into_diagnostic()
+            .wrap_err(miette!("failed to write {}", path.display()))?;
+
+        package.unpack(&self.unpacked(package.name())).await?;
+
+        self.packages.insert(
+            package.name().clone(),
+            VendoredPackage {
+                name: package.name().clone(),
+                version: package.version().clone(),
+                archive,
+                digest: package.digest(DigestAlgorithm::SHA256),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Removes the vendored packages for which `keep` returns false
+    pub async fn retain(&mut self, keep: impl Fn(&VendoredPackage) -> bool) -> miette::Result<()> {
+        let removed: Vec<_> = self
+            .packages
+            .values()
+            .filter(|package| !keep(package))
+            .cloned()
+            .collect();
+
+        for package in removed {
+            self.remove_files(&package).await?;
+            self.packages.remove(&package.name);
+        }
+
+        Ok(())
+    }
+
+    async fn remove_files(&self, package: &VendoredPackage) -> miette::Result<()> {
+        let unpacked = self.unpacked(&package.name);
+
+        if tokio::fs::try_exists(&unpacked).await.unwrap_or(false) {
+            tokio::fs::remove_dir_all(&unpacked)
+                .await
+                .into_diagnostic()
+                .wrap_err(miette!("failed to remove {}", unpacked.display()))?;
+        }
+
+        tokio::fs::remove_file(self.root.join(&package.archive))
+            .await
+            .ok();
+
+        Ok(())
+    }
+
+    /// Persists the checksum index
+    pub async fn write(&self) -> miette::Result<()> {
+        let raw = RawChecksums {
+            packages: self.packages.values().cloned().collect(),
+        };
+
+        let path = self.root.join(CHECKSUMS_FILE);
+
+        tokio::fs::write(
+            &path,
+            toml::to_string(&raw)
+                .into_diagnostic()
+                .wrap_err(miette!("failed to serialize {CHECKSUMS_FILE}"))?,
+        )
+        .await
+        .into_diagnostic()
+        .wrap_err(miette!("failed to write {}", path.display()))
+    }
+
+    /// Reads the vendored copy of a locked package
+    ///
+    /// The archive has to match both the checksum index and the lockfile, and the extracted
+    /// protos have to match the archive.
+    pub async fn get(&self, locked: &LockedPackage) -> miette::Result<Package> {
+        let Some(vendored) = self.packages.get(&locked.name) else {
+            bail!(
+                help = "run `buffrs vendor` to vendor the locked dependencies",
+                "{} is not vendored",
+                locked.name
+            );
+        };
+
+        ensure!(
+            vendored.version == locked.version,
+            help = "run `buffrs vendor` to vendor the locked dependencies",
+            "{} is vendored at version {}, but locked at {}",
+            locked.name,
+            vendored.version,
+            locked.version
+        );
+
+        let package = Package::read(&self.root.join(&vendored.archive)).await?;
+
+        let digest = package.digest(vendored.digest.algorithm());
+
+        ensure!(
+            digest == vendored.digest,
+            DigestMismatch(format!(
+                "digest mismatch of the vendored archive of {} - expected {}, actual {digest}",
+                locked.name, vendored.digest
+            ))
+        );
+
+        locked.validate(&package)?;
+
+        package
+            .verify_unpacked(&self.unpacked(&locked.name))
+            .await?
+            .wrap_err(miette!(
+                "the extracted protos of {} were modified",
+                locked.name
+            ))?;
+
+        Ok(package)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::Manifest;
+
+    fn package(version: &str) -> Package {
+        let manifest: Manifest =
+            format!("[package]\ntype = \"lib\"\nname = \"units\"\nversion = \"{version}\"\n")
+                .parse()
+                .unwrap();
+
+        Package::create(
+            manifest,
+            BTreeMap::from([(PathBuf::from("units.proto"), "package units;".into())]),
+        )
+        .unwrap()
+    }
+
+    fn lock(package: &Package) -> LockedPackage {
+        package.lock(
+            "https://registry.example.com/artifactory".parse().unwrap(),
+            "protos".to_owned(),
+            1,
+        )
+    }
+
+    #[tokio::test]
+    async fn vendors_packages() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let (old, new) = (package("1.0.0"), package("1.1.0"));
+
+        let mut vendor = Vendor::open(dir.path()).await.unwrap();
+        vendor.add(&old).await.unwrap();
+        vendor.add(&new).await.unwrap();
+        vendor.write().await.unwrap();
+
+        assert!(!dir.join("units-1.0.0.tgz").exists());
+        assert!(dir.join("units/units.proto").is_file());
+
+        let vendor = Vendor::open(dir.path()).await.unwrap();
+
+        assert_eq!(vendor.packages().count(), 1);
+        assert_eq!(vendor.get(&lock(&new)).await.unwrap().tgz, new.tgz);
+        assert!(vendor.get(&lock(&old)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_modified_files() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let package = package("1.0.0");
+
+        let mut vendor = Vendor::open(dir.path()).await.unwrap();
+        vendor.add(&package).await.unwrap();
+
+        std::fs::write(dir.join("units/units.proto"), "package tampered;").unwrap();
+
+        assert!(vendor.get(&lock(&package)).await.is_err());
+
+        std::fs::write(dir.join("units/units.proto"), "package units;").unwrap();
+        std::fs::write(dir.join("units-1.0.0.tgz"), "tampered").unwrap();
+
+        assert!(vendor.get(&lock(&package)).await.is_err());
+    }
+}
@@ -0,0 +1,17 @@
+use crate::VirtualFileSystem;
+
+#[test]
+fn fixture() {
+    let vfs = VirtualFileSystem::copy(crate::parent_directory!().join("in"));
+
+    crate::cli!()
+        .arg("doctor")
+        .env("PROTOC", "./missing-protoc")
+        .current_dir(vfs.root())
+        .assert()
+        .success()
+        .stdout(include_str!("stdout.log"))
+        .stderr(include_str!("stderr.log"));
+
+    vfs.verify_against(crate::parent_directory!().join("out"));
+}
@@ -0,0 +1,18 @@
+use crate::VirtualFileSystem;
+
+#[test]
+fn fixture() {
+    let vfs = VirtualFileSystem::copy(crate::parent_directory!().join("in"));
+
+    crate::cli!()
+        .arg("extract")
+        .arg("lib-0.0.1.tgz")
+        .arg("lib")
+        .current_dir(vfs.root())
+        .assert()
+        .success()
+        .stdout(include_str!("stdout.log"))
+        .stderr(include_str!("stderr.log"));
+
+    vfs.verify_against(crate::parent_directory!().join("out"));
+}
@@ -1,3 +1,4 @@
 mod api;
 mod default;
+mod from_protos;
 mod lib;
@@ -1,5 +1,7 @@
 use crate::VirtualFileSystem;
 
+mod installed;
+
 #[test]
 fn fixture() {
     let vfs = VirtualFileSystem::copy(crate::parent_directory!().join("in"));
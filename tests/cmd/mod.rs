@@ -1,4 +1,7 @@
 mod add;
+mod clean;
+mod doctor;
+mod extract;
 mod init;
 mod install;
 mod lint;
@@ -8,4 +11,6 @@ mod ls;
 mod package;
 mod publish;
 mod remove;
+mod stats;
 mod tuto;
+mod version;
@@ -1,3 +1,8 @@
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
 use crate::VirtualFileSystem;
 
 #[test]
@@ -14,3 +19,57 @@ fn fixture() {
 
     vfs.verify_against(crate::parent_directory!().join("out"));
 }
+
+#[test]
+fn output_file() {
+    let vfs = VirtualFileSystem::copy(crate::parent_directory!().join("in"));
+
+    crate::cli!()
+        .arg("package")
+        .arg("--output")
+        .arg("dist/lib-0.0.1.tgz")
+        .current_dir(vfs.root())
+        .assert()
+        .success()
+        .stdout(include_str!("stdout.log"))
+        .stderr(include_str!("stderr.log"));
+
+    vfs.verify_against(crate::parent_directory!().join("out-file"));
+}
+
+#[test]
+fn reproducible() {
+    let first = VirtualFileSystem::copy(crate::parent_directory!().join("in"));
+    let second = VirtualFileSystem::copy(crate::parent_directory!().join("in"));
+
+    // recreate the protos of one copy in another order and with another modification time
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+    for proto in ["proto/hello.proto", "proto/foo/bar.proto"].iter().rev() {
+        let path = second.root().join(proto);
+        let contents = fs::read(&path).unwrap();
+
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, contents).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    for vfs in [&first, &second] {
+        crate::cli!()
+            .arg("package")
+            .current_dir(vfs.root())
+            .assert()
+            .success();
+    }
+
+    assert_eq!(
+        fs::read(first.root().join("lib-0.0.1.tgz")).unwrap(),
+        fs::read(second.root().join("lib-0.0.1.tgz")).unwrap(),
+        "packaging the same files twice yields different archives"
+    );
+}
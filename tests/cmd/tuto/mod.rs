@@ -153,13 +153,14 @@ fn fixture() {
                 .unwrap()
                 .success());
 
-            // cargo add --build buffrs --features=build
+            // cargo add --build buffrs --no-default-features --features=native
             assert!(Command::new("cargo")
                 .args([
                     "add",
                     "buffrs",
                     "--build",
                     "--no-default-features",
+                    "--features=native",
                     "--path",
                     git_root
                 ])
@@ -107,7 +107,9 @@ impl VirtualFileSystem {
                 }
             };
 
-            let filter_gitkeep = |f: &PathBuf| !f.ends_with(".gitkeep");
+            // installation markers only record the digests of installed packages
+            let filter_gitkeep =
+                |f: &PathBuf| !f.ends_with(".gitkeep") && !f.ends_with(".buffrs-digest");
 
             let mut actual_files: Vec<PathBuf> = vfs
                 .files